@@ -10,4 +10,24 @@ pub enum EscrowError{
     InvalidMintA,
     #[msg("Invalid mint b")]
     InvalidMintB,
+    #[msg("Invalid mint")]
+    InvalidMint,
+    #[msg("Mint a and mint b are the same")]
+    SameMint,
+    #[msg("Invalid deadline")]
+    InvalidDeadline,
+    #[msg("Escrow expired")]
+    EscrowExpired,
+    #[msg("Minimum receive not met")]
+    MinReceiveNotMet,
+    #[msg("Overflow")]
+    Overflow,
+    #[msg("Invalid native side")]
+    InvalidNativeSide,
+    #[msg("Missing token account")]
+    MissingTokenAccount,
+    #[msg("Invalid arbiter")]
+    InvalidArbiter,
+    #[msg("Escrow already migrated")]
+    AlreadyMigrated,
 }
\ No newline at end of file
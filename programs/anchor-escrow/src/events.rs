@@ -0,0 +1,33 @@
+use anchor_lang::prelude::*;
+
+// Mints on the native side of a SOL escrow are the default pubkey
+
+#[event]
+pub struct EscrowMade {
+    pub escrow: Pubkey,
+    pub maker: Pubkey,
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    pub amount_deposited: u64,
+    pub amount_expected: u64,
+}
+
+#[event]
+pub struct EscrowTaken {
+    pub escrow: Pubkey,
+    pub maker: Pubkey,
+    pub taker: Pubkey,
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    pub amount_paid: u64,     // Paid by the taker to the maker
+    pub amount_received: u64, // Released from the escrow to the taker
+}
+
+#[event]
+pub struct EscrowRefunded {
+    pub escrow: Pubkey,
+    pub maker: Pubkey,
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    pub amount_refunded: u64,
+}
@@ -1,6 +1,6 @@
 use anchor_lang::{prelude::*, Discriminator};
 use anchor_spl::{associated_token::AssociatedToken, token_interface::{Mint, TokenAccount, TokenInterface, transfer_checked, TransferChecked}};
-use crate::state::Escrow;
+use crate::state::{Escrow, NativeSide};
 
 #[derive(Accounts)]
 #[instruction(seed:u64)]
@@ -53,7 +53,7 @@ pub struct Make<'info> {
 
 impl<'info> Make<'info>  {
     
-pub fn populate_escrow(&mut self, seed:u64, amount_expected:u64, bump:u8){
+pub fn populate_escrow(&mut self, seed:u64, amount_deposited:u64, amount_expected:u64, deadline:i64, arbiter:Option<Pubkey>, bump:u8){
     self.escrow.set_inner(
         Escrow { 
             seed, 
@@ -61,6 +61,10 @@ pub fn populate_escrow(&mut self, seed:u64, amount_expected:u64, bump:u8){
             mint_a: self.mint_a.key(), 
             mint_b: self.mint_b.key(), 
             receive: amount_expected, 
+            deposited_remaining: amount_deposited,
+            deadline,
+            native: NativeSide::None,
+            arbiter: arbiter.unwrap_or_default(),
             bump
         }
     );
@@ -0,0 +1,135 @@
+use anchor_lang::{
+    prelude::*,
+    system_program::{transfer, Transfer},
+    Discriminator
+};
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{
+        Mint,
+        TokenAccount,
+        TokenInterface,
+        transfer_checked,
+        TransferChecked
+    }
+};
+use crate::{state::{Escrow, NativeSide}, EscrowError};
+
+#[derive(Accounts)]
+#[instruction(seed:u64)]
+pub struct MakeSol<'info> {
+    #[account(
+        mut
+    )]
+    pub maker:Signer<'info>,
+
+    #[account(
+        init,
+        payer = maker,
+        space = Escrow::DISCRIMINATOR.len() + Escrow::INIT_SPACE,
+        seeds = [b"escrow", maker.key().as_ref(), seed.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+
+    // The mint of the token side of the escrow
+    #[account(
+        owner = token_program.key()
+    )]
+    pub mint:InterfaceAccount<'info, Mint>,
+
+    // Only required when the maker deposits tokens
+    #[account(
+        mut,
+        associated_token::authority = maker,
+        associated_token::mint = mint,
+        associated_token::token_program = token_program
+    )]
+    pub maker_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    // Only required when the maker deposits tokens
+    #[account(
+        init,
+        payer = maker,
+        associated_token::authority = escrow,
+        associated_token::mint = mint,
+        associated_token::token_program = token_program
+    )]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> MakeSol<'info>  {
+
+pub fn populate_escrow(&mut self, seed:u64, amount_deposited:u64, amount_expected:u64, deadline:i64, native:NativeSide, bump:u8)->Result<()>{
+    // The native side has no mint
+    let (mint_a, mint_b) = match native {
+        NativeSide::A => (Pubkey::default(), self.mint.key()),
+        NativeSide::B => (self.mint.key(), Pubkey::default()),
+        NativeSide::None => return err!(EscrowError::InvalidNativeSide),
+    };
+
+    self.escrow.set_inner(
+        Escrow {
+            seed,
+            maker: *self.maker.key,
+            mint_a,
+            mint_b,
+            receive: amount_expected,
+            deposited_remaining: amount_deposited,
+            deadline,
+            native,
+            arbiter: Pubkey::default(),
+            bump
+        }
+    );
+
+    Ok(())
+}
+
+pub fn deposit(&mut self, amount_deposited:u64) ->Result<()>{
+    match self.escrow.native {
+        // The lamports are held by the escrow account itself
+        NativeSide::A => {
+            let accounts = Transfer{
+                from: self.maker.to_account_info(),
+                to: self.escrow.to_account_info(),
+            };
+
+            let context = CpiContext::new(
+                self.system_program.to_account_info(),
+                accounts
+            );
+
+            transfer(context, amount_deposited)
+        }
+        NativeSide::B => {
+            let (Some(maker_ata), Some(vault)) = (&self.maker_ata, &self.vault) else {
+                return err!(EscrowError::MissingTokenAccount);
+            };
+
+            let accounts = TransferChecked{
+                authority:self.maker.to_account_info(),
+                from: maker_ata.to_account_info(),
+                mint: self.mint.to_account_info(),
+                to: vault.to_account_info(),
+            };
+
+            let context = CpiContext::new(
+                self.token_program.to_account_info(),
+                accounts
+            );
+
+            transfer_checked(
+                context,
+                amount_deposited,
+                self.mint.decimals
+            )
+        }
+        NativeSide::None => err!(EscrowError::InvalidNativeSide),
+    }
+}
+}
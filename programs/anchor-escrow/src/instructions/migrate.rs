@@ -0,0 +1,89 @@
+use anchor_lang::{
+    prelude::*,
+    system_program::{transfer, Transfer},
+    Discriminator
+};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+use crate::{state::{Escrow, LegacyEscrow, NativeSide}, EscrowError};
+
+#[derive(Accounts)]
+pub struct Migrate<'info> {
+    #[account(
+        mut
+    )]
+    pub payer:Signer<'info>,
+
+    #[account(
+        mut,
+        owner = crate::ID
+    )]
+    /// CHECK: An escrow in the legacy layout, which `Account` can not load, it is read in `migrate`
+    pub escrow:UncheckedAccount<'info>,
+
+    pub mint_a:InterfaceAccount<'info, Mint>,
+
+    #[account(
+        associated_token::authority = escrow,
+        associated_token::mint = mint_a,
+        associated_token::token_program = token_program
+    )]
+    pub vault:InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>
+}
+
+impl<'info> Migrate<'info> {
+
+    pub fn migrate(&mut self) -> Result<()> {
+        let legacy = {
+            let data = self.escrow.try_borrow_data()?;
+
+            require!(data.starts_with(Escrow::DISCRIMINATOR), ErrorCode::AccountDiscriminatorMismatch);
+            require_eq!(data.len(), Escrow::DISCRIMINATOR.len() + LegacyEscrow::INIT_SPACE, EscrowError::AlreadyMigrated);
+
+            LegacyEscrow::deserialize(&mut &data[Escrow::DISCRIMINATOR.len()..])?
+        };
+
+        require_keys_eq!(legacy.mint_a, self.mint_a.key(), EscrowError::InvalidMintA);
+
+        let space = Escrow::DISCRIMINATOR.len() + Escrow::INIT_SPACE;
+
+        // Keep the escrow rent exempt at its new size
+        let shortfall = Rent::get()?.minimum_balance(space).saturating_sub(self.escrow.lamports());
+
+        if shortfall != 0 {
+            let accounts = Transfer{
+                from: self.payer.to_account_info(),
+                to: self.escrow.to_account_info(),
+            };
+
+            let context = CpiContext::new(
+                self.system_program.to_account_info(),
+                accounts
+            );
+
+            transfer(context, shortfall)?;
+        }
+
+        self.escrow.resize(space)?;
+
+        // Legacy escrows were token for token, all or nothing and without a deadline or arbiter
+        let escrow = Escrow {
+            seed: legacy.seed,
+            maker: legacy.maker,
+            mint_a: legacy.mint_a,
+            mint_b: legacy.mint_b,
+            receive: legacy.receive,
+            bump: legacy.bump,
+            deposited_remaining: self.vault.amount,
+            deadline: 0,
+            native: NativeSide::None,
+            arbiter: Pubkey::default(),
+        };
+
+        let mut data = self.escrow.try_borrow_mut_data()?;
+
+        escrow.try_serialize(&mut &mut data[..])
+    }
+}
@@ -5,4 +5,22 @@ pub mod take;
 pub use take::*;
 
 pub mod refund;
-pub use refund::*;
\ No newline at end of file
+pub use refund::*;
+
+pub mod make_sol;
+pub use make_sol::*;
+
+pub mod take_sol;
+pub use take_sol::*;
+
+pub mod take_native;
+pub use take_native::*;
+
+pub mod refund_sol;
+pub use refund_sol::*;
+
+pub mod relist;
+pub use relist::*;
+
+pub mod migrate;
+pub use migrate::*;
\ No newline at end of file
@@ -0,0 +1,22 @@
+use anchor_lang::prelude::*;
+use crate::{state::{Escrow, NativeSide}, EscrowError};
+
+// Escrows holding tokens are refunded with `Refund`, this only covers
+// escrows holding lamports, closing the escrow returns them to the maker
+#[derive(Accounts)]
+pub struct RefundSol<'info> {
+    #[account(
+        mut
+    )]
+    pub maker:Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow", maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+        has_one = maker @ EscrowError::InvalidMaker, // This check is not necessary since the escrow is derived from the maker
+        constraint = escrow.native == NativeSide::A @ EscrowError::InvalidNativeSide,
+        close = maker
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
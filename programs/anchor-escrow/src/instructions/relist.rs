@@ -0,0 +1,27 @@
+use anchor_lang::prelude::*;
+use crate::{state::Escrow, EscrowError};
+
+#[derive(Accounts)]
+pub struct Relist<'info> {
+    pub maker:Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow", maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+        has_one = maker @ EscrowError::InvalidMaker // This check is not necessary since the escrow is derived from the maker
+    )]
+    pub escrow: Account<'info, Escrow>,
+}
+
+impl<'info> Relist<'info>  {
+
+    // The deposit is left in place, only the price changes
+    pub fn update_receive(&mut self, amount_expected:u64) ->Result<()>{
+        require_gt!(amount_expected, 0, EscrowError::InvalidAmount);
+
+        self.escrow.receive = amount_expected;
+
+        Ok(())
+    }
+}
@@ -11,7 +11,7 @@ use anchor_spl::{
         TransferChecked
     }
 };
-use crate::{state::Escrow, EscrowError};
+use crate::{state::{Escrow, NativeSide}, EscrowError};
 
 #[derive(Accounts)]
 pub struct Take<'info> {
@@ -34,7 +34,7 @@ pub struct Take<'info> {
         has_one = maker @ EscrowError::InvalidMaker, // This check is not necessary since the escrow is derived from the maker
         has_one = mint_a @ EscrowError::InvalidMintA,
         has_one = mint_b @ EscrowError::InvalidMintB,
-        close = maker
+        constraint = escrow.native == NativeSide::None @ EscrowError::InvalidNativeSide
     )]
     pub escrow: Box<Account<'info, Escrow>>,
 
@@ -77,12 +77,19 @@ pub struct Take<'info> {
 
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub token_program: Interface<'info, TokenInterface>,
-    pub system_program: Program<'info, System>
+    pub system_program: Program<'info, System>,
+
+    // Only required for escrows made with an arbiter
+    pub arbiter: Option<Signer<'info>>
 }
 
 
 impl<'info> Take<'info>{
-    pub fn transfer_tokens(&mut self)->Result<()>{
+    pub fn transfer_tokens(&mut self, amount:u64)->Result<()>{
+
+        // Defense in depth, the decimals come from the passed mint so the paying account
+        // has to hold the mint recorded in the escrow regardless of the account constraints
+        require_keys_eq!(self.taker_ata_b.mint, self.escrow.mint_b, EscrowError::InvalidMintB);
 
         let transfer_b_accounts = TransferChecked{
             authority:self.taker.to_account_info(),
@@ -96,44 +103,79 @@ impl<'info> Take<'info>{
             transfer_b_accounts
         );
 
-        transfer_checked(transfer_b_context, self.escrow.receive, self.mint_b.decimals)
+        transfer_checked(transfer_b_context, amount, self.mint_b.decimals)
 
     }
 
-    pub fn withdraw_and_close_vault(&mut self)->Result<()>{
-        let transfer_a_accounts = TransferChecked{
-            authority:self.escrow.to_account_info(),
-            from: self.vault.to_account_info(),
-            mint: self.mint_a.to_account_info(),
-            to: self.taker_ata_a.to_account_info()
-        };
-
-        let seed_bytes = self.escrow.seed.to_le_bytes();
-
-        let bump_seed = &[self.escrow.bump];
-
-        let signer_seeds = &[&[b"escrow", self.maker.key.as_ref(), &seed_bytes, bump_seed][..]];
+    pub fn withdraw_and_close_vault(&mut self, amount:u64, amount_out:u64)->Result<()>{
+        withdraw_and_close_vault(
+            &mut self.escrow,
+            &self.maker,
+            &self.mint_a,
+            &self.vault,
+            &self.taker_ata_a,
+            &self.token_program,
+            amount,
+            amount_out
+        )
+    }
+}
 
-        let transfer_a_context = CpiContext::new_with_signer(
-            self.token_program.to_account_info(),
-            transfer_a_accounts,
-            signer_seeds
-        );
+// Releases `amount_out` of the deposit to the taker for `amount` of the expected amount,
+// closing the vault and the escrow once it is fully filled, shared with `TakeNative`
+#[allow(clippy::too_many_arguments)]
+pub fn withdraw_and_close_vault<'info>(
+    escrow:&mut Account<'info, Escrow>,
+    maker:&AccountInfo<'info>,
+    mint_a:&InterfaceAccount<'info, Mint>,
+    vault:&InterfaceAccount<'info, TokenAccount>,
+    taker_ata_a:&InterfaceAccount<'info, TokenAccount>,
+    token_program:&Interface<'info, TokenInterface>,
+    amount:u64,
+    amount_out:u64
+)->Result<()>{
+    let transfer_a_accounts = TransferChecked{
+        authority:escrow.to_account_info(),
+        from: vault.to_account_info(),
+        mint: mint_a.to_account_info(),
+        to: taker_ata_a.to_account_info()
+    };
+
+    let seed_bytes = escrow.seed.to_le_bytes();
+
+    let bump_seed = &[escrow.bump];
+
+    let signer_seeds = &[&[b"escrow", maker.key.as_ref(), &seed_bytes, bump_seed][..]];
+
+    let transfer_a_context = CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        transfer_a_accounts,
+        signer_seeds
+    );
+
+    transfer_checked(transfer_a_context, amount_out, mint_a.decimals)?;
+
+    escrow.receive = escrow.receive.checked_sub(amount).ok_or(EscrowError::Overflow)?;
+    escrow.deposited_remaining = escrow.deposited_remaining.saturating_sub(amount_out);
+
+    // Only close once the escrow is fully filled
+    if escrow.receive != 0 {
+        return Ok(());
+    }
 
-        transfer_checked(transfer_a_context, self.vault.amount, self.mint_a.decimals)?;
+    let close_accounts = CloseAccount{
+        account:vault.to_account_info(),
+        authority:escrow.to_account_info(),
+        destination:maker.to_account_info()
+    };
 
-        let close_accounts = CloseAccount{
-            account:self.vault.to_account_info(),
-            authority:self.escrow.to_account_info(),
-            destination:self.maker.to_account_info()
-        };
+    let close_context = CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        close_accounts,
+        signer_seeds
+    );
 
-        let close_context = CpiContext::new_with_signer(
-            self.token_program.to_account_info(), 
-            close_accounts, 
-            signer_seeds
-        );
+    close_account(close_context)?;
 
-        close_account(close_context)
-    }
-} 
\ No newline at end of file
+    escrow.close(maker.to_account_info())
+}
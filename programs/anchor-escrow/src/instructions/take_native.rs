@@ -0,0 +1,204 @@
+use anchor_lang::{
+    prelude::*,
+    system_program::{transfer, Transfer}
+};
+use anchor_spl::{
+    associated_token::{
+        create,
+        get_associated_token_address_with_program_id,
+        AssociatedToken,
+        Create
+    },
+    token::spl_token::native_mint,
+    token_interface::{
+        Mint,
+        TokenAccount,
+        TokenInterface,
+        close_account,
+        sync_native,
+        transfer_checked,
+        CloseAccount,
+        SyncNative,
+        TransferChecked
+    }
+};
+use crate::{instructions::withdraw_and_close_vault, state::{Escrow, NativeSide}, EscrowError};
+
+// Same as `Take` for escrows expecting wrapped SOL, the taker pays from native SOL
+// through its wSOL account, which is only left open if the taker already held one
+#[derive(Accounts)]
+pub struct TakeNative<'info> {
+
+    #[account(
+        mut
+    )]
+    pub taker:Signer<'info>,
+
+    #[account(
+        mut
+    )]
+    /// CHECK: This account is checked with the has_one constraint
+    pub maker:UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow", maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+        has_one = maker @ EscrowError::InvalidMaker, // This check is not necessary since the escrow is derived from the maker
+        has_one = mint_a @ EscrowError::InvalidMintA,
+        has_one = mint_b @ EscrowError::InvalidMintB,
+        constraint = escrow.native == NativeSide::None @ EscrowError::InvalidNativeSide
+    )]
+    pub escrow: Box<Account<'info, Escrow>>,
+
+    pub mint_a:Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        address = native_mint::ID @ EscrowError::InvalidMintB
+    )]
+    pub mint_b:Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        associated_token::authority = escrow,
+        associated_token::mint = mint_a
+    )]
+    pub vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::authority = taker,
+        associated_token::mint = mint_a
+    )]
+    pub taker_ata_a: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // The taker's canonical wSOL account the payment is wrapped into, it is created and
+    // closed within the instruction unless the taker already holds one
+    #[account(
+        mut,
+        address = get_associated_token_address_with_program_id(taker.key, &mint_b.key(), token_program.key)
+    )]
+    /// CHECK: The address is the taker's wSOL ATA, it is created in `wrap` when empty
+    pub taker_ata_b: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::authority = maker,
+        associated_token::mint = mint_b,
+        associated_token::token_program = token_program
+    )]
+    pub maker_ata_b: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+
+    // Only required for escrows made with an arbiter
+    pub arbiter: Option<Signer<'info>>
+}
+
+
+impl<'info> TakeNative<'info>{
+    // Returns whether the wSOL account was created here, so that `unwrap` leaves an account
+    // the taker already held in place
+    pub fn wrap(&mut self, amount:u64)->Result<bool>{
+        let created = self.taker_ata_b.data_is_empty();
+
+        if created {
+            let create_accounts = Create{
+                payer: self.taker.to_account_info(),
+                associated_token: self.taker_ata_b.to_account_info(),
+                authority: self.taker.to_account_info(),
+                mint: self.mint_b.to_account_info(),
+                system_program: self.system_program.to_account_info(),
+                token_program: self.token_program.to_account_info()
+            };
+
+            let create_context = CpiContext::new(
+                self.associated_token_program.to_account_info(),
+                create_accounts
+            );
+
+            create(create_context)?;
+        }
+
+        let transfer_accounts = Transfer{
+            from: self.taker.to_account_info(),
+            to: self.taker_ata_b.to_account_info()
+        };
+
+        let transfer_context = CpiContext::new(
+            self.system_program.to_account_info(),
+            transfer_accounts
+        );
+
+        transfer(transfer_context, amount)?;
+
+        let sync_accounts = SyncNative{
+            account: self.taker_ata_b.to_account_info()
+        };
+
+        let sync_context = CpiContext::new(
+            self.token_program.to_account_info(),
+            sync_accounts
+        );
+
+        sync_native(sync_context)?;
+
+        Ok(created)
+    }
+
+    pub fn transfer_tokens(&mut self, amount:u64)->Result<()>{
+
+        let transfer_b_accounts = TransferChecked{
+            authority:self.taker.to_account_info(),
+            from: self.taker_ata_b.to_account_info(),
+            mint: self.mint_b.to_account_info(),
+            to: self.maker_ata_b.to_account_info()
+        };
+
+        let transfer_b_context = CpiContext::new(
+            self.token_program.to_account_info(),
+            transfer_b_accounts
+        );
+
+        transfer_checked(transfer_b_context, amount, self.mint_b.decimals)
+    }
+
+    // Closing the wSOL account returns its rent to the taker as SOL, the instruction is atomic
+    // so a failure before this point also undoes the account creation
+    pub fn unwrap(&mut self, created:bool)->Result<()>{
+        // A pre-existing account only held the wrapped payment, which has been paid out
+        if !created {
+            return Ok(());
+        }
+
+        let close_accounts = CloseAccount{
+            account:self.taker_ata_b.to_account_info(),
+            authority:self.taker.to_account_info(),
+            destination:self.taker.to_account_info()
+        };
+
+        let close_context = CpiContext::new(
+            self.token_program.to_account_info(),
+            close_accounts
+        );
+
+        close_account(close_context)
+    }
+
+    pub fn withdraw_and_close_vault(&mut self, amount:u64, amount_out:u64)->Result<()>{
+        withdraw_and_close_vault(
+            &mut self.escrow,
+            &self.maker,
+            &self.mint_a,
+            &self.vault,
+            &self.taker_ata_a,
+            &self.token_program,
+            amount,
+            amount_out
+        )
+    }
+}
@@ -0,0 +1,181 @@
+use anchor_lang::{
+    prelude::*,
+    system_program::{transfer, Transfer}
+};
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{
+        Mint,
+        TokenAccount,
+        TokenInterface,
+        close_account,
+        transfer_checked,
+        CloseAccount,
+        TransferChecked
+    }
+};
+use crate::{state::{Escrow, NativeSide}, EscrowError};
+
+#[derive(Accounts)]
+pub struct TakeSol<'info> {
+
+    #[account(
+        mut
+    )]
+    pub taker:Signer<'info>,
+
+    #[account(
+        mut
+    )]
+    /// CHECK: This account is checked with the has_one constraint
+    pub maker:UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"escrow", maker.key().as_ref(), escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+        has_one = maker @ EscrowError::InvalidMaker, // This check is not necessary since the escrow is derived from the maker
+        constraint = escrow.native != NativeSide::None @ EscrowError::InvalidNativeSide
+    )]
+    pub escrow: Box<Account<'info, Escrow>>,
+
+    // The mint of the token side of the escrow
+    #[account(
+        constraint = mint.key() == escrow.token_mint() @ EscrowError::InvalidMint
+    )]
+    pub mint:Box<InterfaceAccount<'info, Mint>>,
+
+    // Only required when the maker deposited tokens
+    #[account(
+        mut,
+        associated_token::authority = escrow,
+        associated_token::mint = mint,
+        associated_token::token_program = token_program
+    )]
+    pub vault: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::authority = taker,
+        associated_token::mint = mint,
+        associated_token::token_program = token_program
+    )]
+    pub taker_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Only required when the maker expects tokens
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::authority = maker,
+        associated_token::mint = mint,
+        associated_token::token_program = token_program
+    )]
+    pub maker_ata: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>
+}
+
+
+impl<'info> TakeSol<'info>{
+    // Returns the deposit released to the taker, escrows with a native side are filled at once
+    pub fn fill_amount(&self)->Result<u64>{
+        match self.escrow.native {
+            NativeSide::A => Ok(self.escrow.deposited_remaining),
+            NativeSide::B => {
+                let vault = self.vault.as_ref().ok_or(EscrowError::MissingTokenAccount)?;
+                Ok(vault.amount)
+            }
+            NativeSide::None => err!(EscrowError::InvalidNativeSide),
+        }
+    }
+
+    pub fn pay_maker(&mut self)->Result<()>{
+        match self.escrow.native {
+            NativeSide::A => {
+                let maker_ata = self.maker_ata.as_ref().ok_or(EscrowError::MissingTokenAccount)?;
+
+                let accounts = TransferChecked{
+                    authority:self.taker.to_account_info(),
+                    from: self.taker_ata.to_account_info(),
+                    mint: self.mint.to_account_info(),
+                    to: maker_ata.to_account_info()
+                };
+
+                let context = CpiContext::new(
+                    self.token_program.to_account_info(),
+                    accounts
+                );
+
+                transfer_checked(context, self.escrow.receive, self.mint.decimals)
+            }
+            NativeSide::B => {
+                let accounts = Transfer{
+                    from: self.taker.to_account_info(),
+                    to: self.maker.to_account_info()
+                };
+
+                let context = CpiContext::new(
+                    self.system_program.to_account_info(),
+                    accounts
+                );
+
+                transfer(context, self.escrow.receive)
+            }
+            NativeSide::None => err!(EscrowError::InvalidNativeSide),
+        }
+    }
+
+    pub fn withdraw_and_close(&mut self, amount_out:u64)->Result<()>{
+        match self.escrow.native {
+            // The escrow is owned by this program so its lamports are moved directly
+            NativeSide::A => {
+                self.escrow.sub_lamports(amount_out)?;
+                self.taker.add_lamports(amount_out)?;
+            }
+            NativeSide::B => {
+                let vault = self.vault.as_ref().ok_or(EscrowError::MissingTokenAccount)?;
+
+                let seed_bytes = self.escrow.seed.to_le_bytes();
+
+                let bump_seed = &[self.escrow.bump];
+
+                let signer_seeds = &[&[b"escrow", self.maker.key.as_ref(), &seed_bytes, bump_seed][..]];
+
+                let transfer_accounts = TransferChecked{
+                    authority:self.escrow.to_account_info(),
+                    from: vault.to_account_info(),
+                    mint: self.mint.to_account_info(),
+                    to: self.taker_ata.to_account_info()
+                };
+
+                let transfer_context = CpiContext::new_with_signer(
+                    self.token_program.to_account_info(),
+                    transfer_accounts,
+                    signer_seeds
+                );
+
+                transfer_checked(transfer_context, amount_out, self.mint.decimals)?;
+
+                let close_accounts = CloseAccount{
+                    account:vault.to_account_info(),
+                    authority:self.escrow.to_account_info(),
+                    destination:self.maker.to_account_info()
+                };
+
+                let close_context = CpiContext::new_with_signer(
+                    self.token_program.to_account_info(),
+                    close_accounts,
+                    signer_seeds
+                );
+
+                close_account(close_context)?;
+            }
+            NativeSide::None => return err!(EscrowError::InvalidNativeSide),
+        }
+
+        self.escrow.close(self.maker.to_account_info())
+    }
+}
@@ -11,25 +11,207 @@ pub use errors::*;
 pub mod instructions;
 pub use instructions::*;
 
+pub mod events;
+pub use events::*;
+
+pub mod pda;
+pub use pda::*;
+
 #[program]
 pub mod anchor_escrow {
     use super::*;
-    pub fn make(ctx: Context<Make>, seed:u64, amount_deposited:u64, amount_expected:u64) -> Result<()> {
+    // An arbiter, when given, has to co-sign every take of the escrow
+    pub fn make(ctx: Context<Make>, seed:u64, amount_deposited:u64, amount_expected:u64, deadline:i64, arbiter:Option<Pubkey>) -> Result<()> {
         require_gt!(amount_deposited, 0, EscrowError::InvalidAmount);
         require_gt!(amount_expected, 0, EscrowError::InvalidAmount);
+        require_keys_neq!(ctx.accounts.mint_a.key(), ctx.accounts.mint_b.key(), EscrowError::SameMint);
+
+        // A zero deadline means the escrow does not expire
+        if deadline != 0 {
+            require_gt!(deadline, Clock::get()?.unix_timestamp, EscrowError::InvalidDeadline);
+        }
+
+        ctx.accounts.populate_escrow(seed, amount_deposited, amount_expected, deadline, arbiter, ctx.bumps.escrow);
 
-        ctx.accounts.populate_escrow(seed, amount_expected, ctx.bumps.escrow);
+        ctx.accounts.transfer_tokens(amount_deposited)?;
 
-        ctx.accounts.transfer_tokens(amount_deposited)
+        emit!(EscrowMade {
+            escrow: ctx.accounts.escrow.key(),
+            maker: ctx.accounts.maker.key(),
+            mint_a: ctx.accounts.escrow.mint_a,
+            mint_b: ctx.accounts.escrow.mint_b,
+            amount_deposited,
+            amount_expected,
+        });
+
+        Ok(())
     }
 
-    pub fn take(ctx: Context<Take>) -> Result<()> {
-        ctx.accounts.transfer_tokens()?;
+    pub fn take(ctx: Context<Take>, amount:u64, min_receive:u64) -> Result<()> {
+        if ctx.accounts.escrow.deadline != 0 {
+            require_gt!(ctx.accounts.escrow.deadline, Clock::get()?.unix_timestamp, EscrowError::EscrowExpired);
+        }
+
+        ctx.accounts.escrow.check_arbiter(ctx.accounts.arbiter.as_ref())?;
+
+        // The taker can fill any part of the expected amount
+        require_gt!(amount, 0, EscrowError::InvalidAmount);
+        require_gte!(ctx.accounts.escrow.receive, amount, EscrowError::InvalidAmount);
 
-        ctx.accounts.withdraw_and_close_vault()
+        let amount_out = ctx.accounts.escrow.fill_amount(amount, ctx.accounts.vault.amount)?;
+        require_gt!(amount_out, 0, EscrowError::InvalidAmount);
+
+        // Protect the taker against an escrow holding less than they expect
+        require_gte!(amount_out, min_receive, EscrowError::MinReceiveNotMet);
+
+        ctx.accounts.transfer_tokens(amount)?;
+
+        ctx.accounts.withdraw_and_close_vault(amount, amount_out)?;
+
+        emit!(EscrowTaken {
+            escrow: ctx.accounts.escrow.key(),
+            maker: ctx.accounts.maker.key(),
+            taker: ctx.accounts.taker.key(),
+            mint_a: ctx.accounts.escrow.mint_a,
+            mint_b: ctx.accounts.escrow.mint_b,
+            amount_paid: amount,
+            amount_received: amount_out,
+        });
+
+        Ok(())
     }
+
     pub fn refund(ctx: Context<Refund>) -> Result<()> {
-        ctx.accounts.withdraw_and_close_vault()
+        let amount_refunded = ctx.accounts.vault.amount;
+
+        ctx.accounts.withdraw_and_close_vault()?;
+
+        emit!(EscrowRefunded {
+            escrow: ctx.accounts.escrow.key(),
+            maker: ctx.accounts.maker.key(),
+            mint_a: ctx.accounts.escrow.mint_a,
+            mint_b: ctx.accounts.escrow.mint_b,
+            amount_refunded,
+        });
+
+        Ok(())
+    }
+
+    pub fn relist(ctx: Context<Relist>, amount_expected:u64) -> Result<()> {
+        ctx.accounts.update_receive(amount_expected)
+    }
+
+    // Brings an escrow made before the fields after `bump` were added up to the current
+    // layout, anyone can run it and pays for the added space
+    pub fn migrate(ctx: Context<Migrate>) -> Result<()> {
+        ctx.accounts.migrate()
+    }
+
+    // Same as `make` with one side of the trade settled in lamports
+    pub fn make_sol(ctx: Context<MakeSol>, seed:u64, amount_deposited:u64, amount_expected:u64, deadline:i64, native:NativeSide) -> Result<()> {
+        require_gt!(amount_deposited, 0, EscrowError::InvalidAmount);
+        require_gt!(amount_expected, 0, EscrowError::InvalidAmount);
+
+        // A zero deadline means the escrow does not expire
+        if deadline != 0 {
+            require_gt!(deadline, Clock::get()?.unix_timestamp, EscrowError::InvalidDeadline);
+        }
+
+        ctx.accounts.populate_escrow(seed, amount_deposited, amount_expected, deadline, native, ctx.bumps.escrow)?;
+
+        ctx.accounts.deposit(amount_deposited)?;
+
+        emit!(EscrowMade {
+            escrow: ctx.accounts.escrow.key(),
+            maker: ctx.accounts.maker.key(),
+            mint_a: ctx.accounts.escrow.mint_a,
+            mint_b: ctx.accounts.escrow.mint_b,
+            amount_deposited,
+            amount_expected,
+        });
+
+        Ok(())
+    }
+
+    pub fn take_sol(ctx: Context<TakeSol>, min_receive:u64) -> Result<()> {
+        if ctx.accounts.escrow.deadline != 0 {
+            require_gt!(ctx.accounts.escrow.deadline, Clock::get()?.unix_timestamp, EscrowError::EscrowExpired);
+        }
+
+        let amount_out = ctx.accounts.fill_amount()?;
+        require_gt!(amount_out, 0, EscrowError::InvalidAmount);
+
+        // Protect the taker against an escrow holding less than they expect
+        require_gte!(amount_out, min_receive, EscrowError::MinReceiveNotMet);
+
+        let amount_paid = ctx.accounts.escrow.receive;
+
+        ctx.accounts.pay_maker()?;
+
+        ctx.accounts.withdraw_and_close(amount_out)?;
+
+        emit!(EscrowTaken {
+            escrow: ctx.accounts.escrow.key(),
+            maker: ctx.accounts.maker.key(),
+            taker: ctx.accounts.taker.key(),
+            mint_a: ctx.accounts.escrow.mint_a,
+            mint_b: ctx.accounts.escrow.mint_b,
+            amount_paid,
+            amount_received: amount_out,
+        });
+
+        Ok(())
+    }
+
+    pub fn refund_sol(ctx: Context<RefundSol>) -> Result<()> {
+        emit!(EscrowRefunded {
+            escrow: ctx.accounts.escrow.key(),
+            maker: ctx.accounts.maker.key(),
+            mint_a: ctx.accounts.escrow.mint_a,
+            mint_b: ctx.accounts.escrow.mint_b,
+            amount_refunded: ctx.accounts.escrow.deposited_remaining,
+        });
+
+        Ok(())
+    }
+
+    // Same as `take` for escrows expecting wrapped SOL, paid from the taker's native SOL
+    pub fn take_native(ctx: Context<TakeNative>, amount:u64, min_receive:u64) -> Result<()> {
+        if ctx.accounts.escrow.deadline != 0 {
+            require_gt!(ctx.accounts.escrow.deadline, Clock::get()?.unix_timestamp, EscrowError::EscrowExpired);
+        }
+
+        ctx.accounts.escrow.check_arbiter(ctx.accounts.arbiter.as_ref())?;
+
+        // The taker can fill any part of the expected amount
+        require_gt!(amount, 0, EscrowError::InvalidAmount);
+        require_gte!(ctx.accounts.escrow.receive, amount, EscrowError::InvalidAmount);
+
+        let amount_out = ctx.accounts.escrow.fill_amount(amount, ctx.accounts.vault.amount)?;
+        require_gt!(amount_out, 0, EscrowError::InvalidAmount);
+
+        // Protect the taker against an escrow holding less than they expect
+        require_gte!(amount_out, min_receive, EscrowError::MinReceiveNotMet);
+
+        let created = ctx.accounts.wrap(amount)?;
+
+        ctx.accounts.transfer_tokens(amount)?;
+
+        ctx.accounts.unwrap(created)?;
+
+        ctx.accounts.withdraw_and_close_vault(amount, amount_out)?;
+
+        emit!(EscrowTaken {
+            escrow: ctx.accounts.escrow.key(),
+            maker: ctx.accounts.maker.key(),
+            taker: ctx.accounts.taker.key(),
+            mint_a: ctx.accounts.escrow.mint_a,
+            mint_b: ctx.accounts.escrow.mint_b,
+            amount_paid: amount,
+            amount_received: amount_out,
+        });
+
+        Ok(())
     }
 }
 
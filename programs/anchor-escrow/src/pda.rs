@@ -0,0 +1,13 @@
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+
+// Client side derivations, the seeds have to stay in step with the `Make` and `MakeSol` constraints
+
+pub fn find_escrow_address(maker: &Pubkey, seed: u64) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"escrow", maker.as_ref(), seed.to_le_bytes().as_ref()], &crate::ID)
+}
+
+// The vault is the escrow's associated token account for the deposited mint
+pub fn find_vault_address(escrow: &Pubkey, mint_a: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    get_associated_token_address_with_program_id(escrow, mint_a, token_program)
+}
@@ -1,5 +1,7 @@
 use anchor_lang::prelude::*;
 
+use crate::EscrowError;
+
 #[derive(InitSpace)]
 #[account]
 pub struct Escrow{
@@ -9,5 +11,70 @@ pub struct Escrow{
     pub mint_b: Pubkey,
     pub receive: u64,
     pub bump: u8,
+    // The fields below were appended to the original layout, `migrate` brings the escrows
+    // made before them up to date
+    pub deposited_remaining: u64,
+    pub deadline: i64, // Zero when the escrow does not expire
+    pub native: NativeSide,
+    pub arbiter: Pubkey, // The default pubkey when takes need no arbiter
+}
+
+// The layout escrows were made with before any field was appended
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace)]
+pub struct LegacyEscrow{
+    pub seed: u64,
+    pub maker: Pubkey,
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    pub receive: u64,
+    pub bump: u8,
+}
+
+// The side of the escrow settled in lamports instead of tokens, the native
+// side's mint is left as the default pubkey
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum NativeSide {
+    None,
+    A, // The maker deposits lamports, held by the escrow account itself
+    B, // The maker expects lamports
+}
+
+impl Escrow {
+    // Requires the arbiter, when the escrow has one, to have signed the take
+    pub fn check_arbiter(&self, arbiter:Option<&Signer>) -> Result<()> {
+        if self.arbiter == Pubkey::default() {
+            return Ok(());
+        }
+
+        let arbiter = arbiter.ok_or(EscrowError::InvalidArbiter)?;
+
+        require_keys_eq!(arbiter.key(), self.arbiter, EscrowError::InvalidArbiter);
+
+        Ok(())
+    }
+
+    // Returns the part of the deposit released for filling `amount` of the expected amount,
+    // `vault_amount` is what the vault currently holds
+    pub fn fill_amount(&self, amount:u64, vault_amount:u64) -> Result<u64> {
+        // The last fill takes whatever is left in the vault
+        if amount == self.receive {
+            return Ok(vault_amount);
+        }
+
+        let amount_out = (self.deposited_remaining as u128)
+            .checked_mul(amount as u128)
+            .ok_or(EscrowError::Overflow)?
+            .checked_div(self.receive as u128)
+            .ok_or(EscrowError::Overflow)?;
+
+        u64::try_from(amount_out).map_err(|_| EscrowError::Overflow.into())
+    }
+
+    pub fn token_mint(&self) -> Pubkey {
+        match self.native {
+            NativeSide::A => self.mint_b,
+            _ => self.mint_a,
+        }
+    }
 }
 
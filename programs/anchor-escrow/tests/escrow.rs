@@ -0,0 +1,624 @@
+use anchor_escrow::{find_escrow_address, find_vault_address, EscrowError};
+use anchor_lang::{
+    prelude::Pubkey, solana_program::instruction::Instruction, system_program, AnchorSerialize,
+    Discriminator, InstructionData, Space, ToAccountMetas,
+};
+use anchor_spl::{
+    associated_token::{get_associated_token_address, ID as ASSOCIATED_TOKEN_PROGRAM_ID},
+    token::spl_token::{self, native_mint},
+};
+use anchor_test_runtime::{program_error, Account, Runtime, TransactionError};
+
+const DEPOSIT: u64 = 1_000_000;
+const EXPECTED: u64 = 400_000;
+const SEED: u64 = 7;
+const LAMPORTS: u64 = 10_000_000_000;
+
+struct Test {
+    runtime: Runtime,
+    maker: Pubkey,
+    taker: Pubkey,
+    mint_a: Pubkey,
+    mint_b: Pubkey,
+}
+
+impl Test {
+    fn new() -> Self {
+        let mut runtime = Runtime::new();
+        let maker = Pubkey::new_unique();
+        let taker = Pubkey::new_unique();
+        let mint_a = Pubkey::new_unique();
+        let mint_b = Pubkey::new_unique();
+
+        runtime.add_program(anchor_escrow::ID, anchor_escrow::entry);
+        runtime.airdrop(&maker, LAMPORTS);
+        runtime.airdrop(&taker, LAMPORTS);
+
+        runtime.create_mint(&mint_a, None, 6);
+        runtime.create_mint(&mint_b, None, 6);
+        runtime.create_associated_token_account(&maker, &mint_a, DEPOSIT);
+        runtime.create_associated_token_account(&taker, &mint_b, EXPECTED);
+
+        Self { runtime, maker, taker, mint_a, mint_b }
+    }
+
+    fn escrow(&self) -> Pubkey {
+        find_escrow_address(&self.maker, SEED).0
+    }
+
+    fn vault(&self) -> Pubkey {
+        find_vault_address(&self.escrow(), &self.mint_a, &spl_token::ID)
+    }
+
+    fn make_with(&self, deadline: i64, arbiter: Option<Pubkey>) -> Instruction {
+        Instruction {
+            program_id: anchor_escrow::ID,
+            accounts: anchor_escrow::accounts::Make {
+                maker: self.maker,
+                escrow: self.escrow(),
+                mint_a: self.mint_a,
+                mint_b: self.mint_b,
+                maker_ata_a: get_associated_token_address(&self.maker, &self.mint_a),
+                vault: self.vault(),
+                associated_token_program: ASSOCIATED_TOKEN_PROGRAM_ID,
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+            }.to_account_metas(None),
+            data: anchor_escrow::instruction::Make {
+                seed: SEED,
+                amount_deposited: DEPOSIT,
+                amount_expected: EXPECTED,
+                deadline,
+                arbiter,
+            }.data(),
+        }
+    }
+
+    fn make(&self) -> Instruction {
+        self.make_with(0, None)
+    }
+
+    fn take_with(&self, amount: u64, min_receive: u64, arbiter: Option<Pubkey>) -> Instruction {
+        Instruction {
+            program_id: anchor_escrow::ID,
+            accounts: anchor_escrow::accounts::Take {
+                taker: self.taker,
+                maker: self.maker,
+                escrow: self.escrow(),
+                mint_a: self.mint_a,
+                mint_b: self.mint_b,
+                vault: self.vault(),
+                taker_ata_a: get_associated_token_address(&self.taker, &self.mint_a),
+                taker_ata_b: get_associated_token_address(&self.taker, &self.mint_b),
+                maker_ata_b: get_associated_token_address(&self.maker, &self.mint_b),
+                associated_token_program: ASSOCIATED_TOKEN_PROGRAM_ID,
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+                arbiter,
+            }.to_account_metas(None),
+            data: anchor_escrow::instruction::Take { amount, min_receive }.data(),
+        }
+    }
+
+    fn take(&self, amount: u64) -> Instruction {
+        self.take_with(amount, 0, None)
+    }
+
+    fn send(&mut self, instruction: Instruction, signer: Pubkey) -> Result<(), TransactionError> {
+        self.runtime.process_transaction(&[instruction], &[signer])
+    }
+
+    fn balance(&self, owner: &Pubkey, mint: &Pubkey) -> u64 {
+        self.runtime.token_balance(&get_associated_token_address(owner, mint))
+    }
+}
+
+fn failed(error: EscrowError) -> Result<(), TransactionError> {
+    Err(TransactionError { index: 0, error: program_error(error) })
+}
+
+#[test]
+fn two_partial_fills_settle_the_escrow() {
+    let mut test = Test::new();
+    test.send(test.make(), test.maker).unwrap();
+
+    // A quarter of the expected amount releases a quarter of the deposit
+    test.send(test.take(100_000), test.taker).unwrap();
+
+    let escrow = test.runtime.anchor_account::<anchor_escrow::Escrow>(&test.escrow()).unwrap();
+    assert_eq!((escrow.receive, escrow.deposited_remaining), (300_000, 750_000));
+    assert_eq!(test.balance(&test.taker, &test.mint_a), 250_000);
+    assert_eq!(test.balance(&test.maker, &test.mint_b), 100_000);
+
+    // The rest closes it
+    test.send(test.take(300_000), test.taker).unwrap();
+
+    assert_eq!(test.balance(&test.taker, &test.mint_a), DEPOSIT);
+    assert_eq!(test.balance(&test.taker, &test.mint_b), 0);
+    assert_eq!(test.balance(&test.maker, &test.mint_b), EXPECTED);
+    assert!(test.runtime.account(&test.escrow()).is_none());
+    assert!(test.runtime.account(&test.vault()).is_none());
+}
+
+#[test]
+fn fill_over_the_expected_amount_is_rejected() {
+    let mut test = Test::new();
+    test.send(test.make(), test.maker).unwrap();
+
+    assert_eq!(test.send(test.take(EXPECTED + 1), test.taker), failed(EscrowError::InvalidAmount));
+}
+
+impl Test {
+    // An escrow as made before the fields after `bump` were appended
+    fn create_legacy_escrow(&mut self) {
+        let (escrow, bump) = find_escrow_address(&self.maker, SEED);
+
+        let legacy = anchor_escrow::LegacyEscrow {
+            seed: SEED,
+            maker: self.maker,
+            mint_a: self.mint_a,
+            mint_b: self.mint_b,
+            receive: EXPECTED,
+            bump,
+        };
+
+        let data = [anchor_escrow::Escrow::DISCRIMINATOR, &legacy.try_to_vec().unwrap()].concat();
+
+        self.runtime.set_account(escrow, Account {
+            lamports: self.runtime.minimum_balance(data.len()),
+            data,
+            owner: anchor_escrow::ID,
+            executable: false,
+        });
+
+        self.runtime.create_token_account(&self.vault(), &self.mint_a, &escrow, DEPOSIT);
+    }
+
+    fn migrate(&self, payer: Pubkey) -> Instruction {
+        Instruction {
+            program_id: anchor_escrow::ID,
+            accounts: anchor_escrow::accounts::Migrate {
+                payer,
+                escrow: self.escrow(),
+                mint_a: self.mint_a,
+                vault: self.vault(),
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+            }.to_account_metas(None),
+            data: anchor_escrow::instruction::Migrate {}.data(),
+        }
+    }
+}
+
+#[test]
+fn legacy_escrow_is_migrated_then_taken() {
+    let mut test = Test::new();
+    test.create_legacy_escrow();
+
+    // The old layout can not be loaded until it is migrated
+    assert!(test.send(test.take(EXPECTED), test.taker).is_err());
+
+    let payer = test.taker;
+    test.send(test.migrate(payer), payer).unwrap();
+
+    let account = test.runtime.account(&test.escrow()).unwrap();
+    assert_eq!(account.data.len(), 8 + anchor_escrow::Escrow::INIT_SPACE);
+    assert_eq!(account.lamports, test.runtime.minimum_balance(account.data.len()));
+
+    let escrow = test.runtime.anchor_account::<anchor_escrow::Escrow>(&test.escrow()).unwrap();
+    assert_eq!(escrow.seed, SEED);
+    assert_eq!(escrow.maker, test.maker);
+    assert_eq!((escrow.mint_a, escrow.mint_b), (test.mint_a, test.mint_b));
+    assert_eq!(escrow.receive, EXPECTED);
+    assert_eq!(escrow.bump, find_escrow_address(&test.maker, SEED).1);
+    assert_eq!(escrow.deposited_remaining, DEPOSIT);
+    assert_eq!(escrow.deadline, 0);
+    assert!(escrow.native == anchor_escrow::NativeSide::None);
+    assert_eq!(escrow.arbiter, Pubkey::default());
+
+    // Running it again is rejected
+    assert_eq!(test.send(test.migrate(payer), payer), failed(EscrowError::AlreadyMigrated));
+
+    test.send(test.take(EXPECTED), test.taker).unwrap();
+    assert_eq!(test.balance(&test.taker, &test.mint_a), DEPOSIT);
+}
+
+#[test]
+fn take_after_the_deadline_is_rejected() {
+    let mut test = Test::new();
+    let deadline = test.runtime.clock.unix_timestamp + 100;
+
+    // A deadline already passed can not be set
+    assert_eq!(
+        test.send(test.make_with(test.runtime.clock.unix_timestamp, None), test.maker),
+        failed(EscrowError::InvalidDeadline)
+    );
+
+    test.send(test.make_with(deadline, None), test.maker).unwrap();
+
+    test.runtime.clock.unix_timestamp = deadline;
+    assert_eq!(test.send(test.take(EXPECTED), test.taker), failed(EscrowError::EscrowExpired));
+
+    test.runtime.clock.unix_timestamp = deadline - 1;
+    test.send(test.take(EXPECTED), test.taker).unwrap();
+}
+
+#[test]
+fn take_below_min_receive_is_rejected() {
+    let mut test = Test::new();
+    test.send(test.make(), test.maker).unwrap();
+
+    // Filling a quarter releases 250_000
+    assert_eq!(
+        test.send(test.take_with(100_000, 250_001, None), test.taker),
+        failed(EscrowError::MinReceiveNotMet)
+    );
+
+    test.send(test.take_with(100_000, 250_000, None), test.taker).unwrap();
+    assert_eq!(test.balance(&test.taker, &test.mint_a), 250_000);
+}
+
+impl Test {
+    fn make_sol(&self, native: anchor_escrow::NativeSide, amount_deposited: u64, amount_expected: u64) -> Instruction {
+        // The token side is mint a when the maker deposits tokens and mint b when it expects them
+        let (mint, token_deposit) = match native {
+            anchor_escrow::NativeSide::A => (self.mint_b, false),
+            _ => (self.mint_a, true),
+        };
+
+        Instruction {
+            program_id: anchor_escrow::ID,
+            accounts: anchor_escrow::accounts::MakeSol {
+                maker: self.maker,
+                escrow: self.escrow(),
+                mint,
+                maker_ata: token_deposit.then(|| get_associated_token_address(&self.maker, &mint)),
+                vault: token_deposit.then(|| find_vault_address(&self.escrow(), &mint, &spl_token::ID)),
+                associated_token_program: ASSOCIATED_TOKEN_PROGRAM_ID,
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+            }.to_account_metas(None),
+            data: anchor_escrow::instruction::MakeSol {
+                seed: SEED,
+                amount_deposited,
+                amount_expected,
+                deadline: 0,
+                native,
+            }.data(),
+        }
+    }
+
+    fn take_sol(&self, native: anchor_escrow::NativeSide, min_receive: u64) -> Instruction {
+        let (mint, token_deposit) = match native {
+            anchor_escrow::NativeSide::A => (self.mint_b, false),
+            _ => (self.mint_a, true),
+        };
+
+        Instruction {
+            program_id: anchor_escrow::ID,
+            accounts: anchor_escrow::accounts::TakeSol {
+                taker: self.taker,
+                maker: self.maker,
+                escrow: self.escrow(),
+                mint,
+                vault: token_deposit.then(|| find_vault_address(&self.escrow(), &mint, &spl_token::ID)),
+                taker_ata: get_associated_token_address(&self.taker, &mint),
+                maker_ata: (!token_deposit).then(|| get_associated_token_address(&self.maker, &mint)),
+                associated_token_program: ASSOCIATED_TOKEN_PROGRAM_ID,
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+            }.to_account_metas(None),
+            data: anchor_escrow::instruction::TakeSol { min_receive }.data(),
+        }
+    }
+}
+
+#[test]
+fn tokens_for_sol_settle() {
+    let mut test = Test::new();
+    let token_account_rent = test.runtime.minimum_balance(165);
+
+    test.send(test.make_sol(anchor_escrow::NativeSide::B, DEPOSIT, 2_000_000_000), test.maker).unwrap();
+    test.send(test.take_sol(anchor_escrow::NativeSide::B, DEPOSIT), test.taker).unwrap();
+
+    // The maker is paid in lamports and gets the rent of the escrow and the vault back
+    assert_eq!(test.runtime.lamports(&test.maker), LAMPORTS + 2_000_000_000);
+    assert_eq!(test.balance(&test.maker, &test.mint_a), 0);
+
+    // The taker paid for its new ATA
+    assert_eq!(test.runtime.lamports(&test.taker), LAMPORTS - 2_000_000_000 - token_account_rent);
+    assert_eq!(test.balance(&test.taker, &test.mint_a), DEPOSIT);
+
+    assert!(test.runtime.account(&test.escrow()).is_none());
+    assert!(test.runtime.account(&test.vault()).is_none());
+}
+
+#[test]
+fn sol_for_tokens_settle() {
+    let mut test = Test::new();
+    let token_account_rent = test.runtime.minimum_balance(165);
+
+    test.send(test.make_sol(anchor_escrow::NativeSide::A, 3_000_000_000, EXPECTED), test.maker).unwrap();
+    assert_eq!(test.runtime.lamports(&test.maker) + 3_000_000_000 + test.runtime.minimum_balance(8 +
+        anchor_escrow::Escrow::INIT_SPACE), LAMPORTS);
+
+    test.send(test.take_sol(anchor_escrow::NativeSide::A, 3_000_000_000), test.taker).unwrap();
+
+    // The deposit goes to the taker, the escrow's rent back to the maker
+    assert_eq!(test.runtime.lamports(&test.maker), LAMPORTS - 3_000_000_000);
+    assert_eq!(test.balance(&test.maker, &test.mint_b), EXPECTED);
+
+    assert_eq!(test.runtime.lamports(&test.taker), LAMPORTS + 3_000_000_000 - token_account_rent);
+    assert_eq!(test.balance(&test.taker, &test.mint_b), 0);
+
+    assert!(test.runtime.account(&test.escrow()).is_none());
+}
+
+impl Test {
+    fn refund(&self) -> Instruction {
+        Instruction {
+            program_id: anchor_escrow::ID,
+            accounts: anchor_escrow::accounts::Refund {
+                maker: self.maker,
+                escrow: self.escrow(),
+                mint_a: self.mint_a,
+                vault: self.vault(),
+                maker_ata_a: get_associated_token_address(&self.maker, &self.mint_a),
+                associated_token_program: ASSOCIATED_TOKEN_PROGRAM_ID,
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+            }.to_account_metas(None),
+            data: anchor_escrow::instruction::Refund {}.data(),
+        }
+    }
+}
+
+#[test]
+fn make_take_and_refund_emit_events() {
+    let mut test = Test::new();
+
+    test.send(test.make(), test.maker).unwrap();
+
+    let [made]: [_; 1] = test.runtime.events::<anchor_escrow::EscrowMade>().try_into().ok().unwrap();
+    assert_eq!(made.escrow, test.escrow());
+    assert_eq!(made.maker, test.maker);
+    assert_eq!((made.mint_a, made.mint_b), (test.mint_a, test.mint_b));
+    assert_eq!((made.amount_deposited, made.amount_expected), (DEPOSIT, EXPECTED));
+
+    test.send(test.take(100_000), test.taker).unwrap();
+
+    let [taken]: [_; 1] = test.runtime.events::<anchor_escrow::EscrowTaken>().try_into().ok().unwrap();
+    assert_eq!(taken.escrow, test.escrow());
+    assert_eq!((taken.maker, taken.taker), (test.maker, test.taker));
+    assert_eq!((taken.mint_a, taken.mint_b), (test.mint_a, test.mint_b));
+    assert_eq!((taken.amount_paid, taken.amount_received), (100_000, 250_000));
+
+    test.send(test.refund(), test.maker).unwrap();
+
+    // The refund reports what was left after the partial fill
+    let [refunded]: [_; 1] = test.runtime.events::<anchor_escrow::EscrowRefunded>().try_into().ok().unwrap();
+    assert_eq!(refunded.escrow, test.escrow());
+    assert_eq!(refunded.maker, test.maker);
+    assert_eq!((refunded.mint_a, refunded.mint_b), (test.mint_a, test.mint_b));
+    assert_eq!(refunded.amount_refunded, DEPOSIT - 250_000);
+
+    assert!(test.runtime.events::<anchor_escrow::EscrowTaken>().is_empty());
+}
+
+impl Test {
+    fn relist(&self, maker: Pubkey, amount_expected: u64) -> Instruction {
+        Instruction {
+            program_id: anchor_escrow::ID,
+            accounts: anchor_escrow::accounts::Relist {
+                maker,
+                escrow: self.escrow(),
+            }.to_account_metas(None),
+            data: anchor_escrow::instruction::Relist { amount_expected }.data(),
+        }
+    }
+}
+
+#[test]
+fn relist_changes_the_amount_to_take() {
+    let mut test = Test::new();
+    test.runtime.create_associated_token_account(&test.taker, &test.mint_b, 2 * EXPECTED);
+
+    test.send(test.make(), test.maker).unwrap();
+    test.send(test.relist(test.maker, 2 * EXPECTED), test.maker).unwrap();
+
+    // Paying the old price now only buys half the deposit
+    test.send(test.take(EXPECTED), test.taker).unwrap();
+    assert_eq!(test.balance(&test.taker, &test.mint_a), DEPOSIT / 2);
+    assert!(test.runtime.account(&test.escrow()).is_some());
+
+    test.send(test.take(EXPECTED), test.taker).unwrap();
+    assert_eq!(test.balance(&test.taker, &test.mint_a), DEPOSIT);
+    assert_eq!(test.balance(&test.maker, &test.mint_b), 2 * EXPECTED);
+    assert!(test.runtime.account(&test.escrow()).is_none());
+}
+
+#[test]
+fn relist_by_a_non_maker_is_rejected() {
+    let mut test = Test::new();
+
+    test.send(test.make(), test.maker).unwrap();
+
+    // The escrow is derived from the maker so the seeds no longer match
+    assert_eq!(
+        test.send(test.relist(test.taker, 1), test.taker),
+        Err(TransactionError {
+            index: 0,
+            error: program_error(anchor_lang::error::ErrorCode::ConstraintSeeds),
+        })
+    );
+
+    assert_eq!(test.runtime.anchor_account::<anchor_escrow::Escrow>(&test.escrow()).unwrap().receive, EXPECTED);
+}
+
+#[test]
+fn make_with_the_same_mint_on_both_sides_is_rejected() {
+    let mut test = Test::new();
+    test.mint_b = test.mint_a;
+
+    assert_eq!(test.send(test.make(), test.maker), failed(EscrowError::SameMint));
+
+    assert!(test.runtime.account(&test.escrow()).is_none());
+    assert_eq!(test.balance(&test.maker, &test.mint_a), DEPOSIT);
+}
+
+impl Test {
+    // An escrow expecting wrapped SOL
+    fn new_native() -> Self {
+        let mut test = Test::new();
+        test.mint_b = native_mint::ID;
+        test.runtime.create_native_mint();
+        test
+    }
+
+    fn take_native(&self, amount: u64) -> Instruction {
+        Instruction {
+            program_id: anchor_escrow::ID,
+            accounts: anchor_escrow::accounts::TakeNative {
+                taker: self.taker,
+                maker: self.maker,
+                escrow: self.escrow(),
+                mint_a: self.mint_a,
+                mint_b: self.mint_b,
+                vault: self.vault(),
+                taker_ata_a: get_associated_token_address(&self.taker, &self.mint_a),
+                taker_ata_b: get_associated_token_address(&self.taker, &self.mint_b),
+                maker_ata_b: get_associated_token_address(&self.maker, &self.mint_b),
+                associated_token_program: ASSOCIATED_TOKEN_PROGRAM_ID,
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+                arbiter: None,
+            }.to_account_metas(None),
+            data: anchor_escrow::instruction::TakeNative { amount, min_receive: 0 }.data(),
+        }
+    }
+}
+
+#[test]
+fn take_native_closes_the_wsol_account_it_created() {
+    let mut test = Test::new_native();
+    let token_account_rent = test.runtime.minimum_balance(165);
+
+    test.send(test.make(), test.maker).unwrap();
+    test.send(test.take_native(EXPECTED), test.taker).unwrap();
+
+    // The taker paid for its token a account and the maker's wSOL account, not the closed one
+    assert!(test.runtime.account(&get_associated_token_address(&test.taker, &native_mint::ID)).is_none());
+    assert_eq!(test.runtime.lamports(&test.taker), LAMPORTS - EXPECTED - 2 * token_account_rent);
+
+    assert_eq!(test.balance(&test.taker, &test.mint_a), DEPOSIT);
+    assert_eq!(test.balance(&test.maker, &native_mint::ID), EXPECTED);
+    assert!(test.runtime.account(&test.escrow()).is_none());
+}
+
+#[test]
+fn take_native_leaves_an_existing_wsol_account_open() {
+    let mut test = Test::new_native();
+    let token_account_rent = test.runtime.minimum_balance(165);
+    let taker_ata_b = test.runtime.create_associated_token_account(&test.taker, &native_mint::ID, 5_000);
+
+    test.send(test.make(), test.maker).unwrap();
+    test.send(test.take_native(EXPECTED), test.taker).unwrap();
+
+    // Only the payment is wrapped and paid out, the taker's own wrapped SOL stays put
+    assert_eq!(test.runtime.token_balance(&taker_ata_b), 5_000);
+    assert_eq!(test.runtime.lamports(&taker_ata_b), token_account_rent + 5_000);
+    assert_eq!(test.runtime.lamports(&test.taker), LAMPORTS - EXPECTED - 2 * token_account_rent);
+
+    assert_eq!(test.balance(&test.taker, &test.mint_a), DEPOSIT);
+    assert_eq!(test.balance(&test.maker, &native_mint::ID), EXPECTED);
+}
+
+#[test]
+fn take_with_a_mismatched_mint_b_is_rejected() {
+    let mut test = Test::new();
+    let other_mint = Pubkey::new_unique();
+    test.runtime.create_mint(&other_mint, None, 6);
+    test.runtime.create_associated_token_account(&test.taker, &other_mint, EXPECTED);
+
+    test.send(test.make(), test.maker).unwrap();
+
+    // Paying with another mint than the escrow expects
+    let mint_b = std::mem::replace(&mut test.mint_b, other_mint);
+    assert_eq!(test.send(test.take(EXPECTED), test.taker), failed(EscrowError::InvalidMintB));
+    test.mint_b = mint_b;
+
+    // Paying from an account at the mint b ATA address that holds another mint, caught by
+    // the check in `transfer_tokens`
+    let taker_ata_b = get_associated_token_address(&test.taker, &test.mint_b);
+    test.runtime.create_token_account(&taker_ata_b, &other_mint, &test.taker, EXPECTED);
+
+    assert_eq!(test.send(test.take(EXPECTED), test.taker), failed(EscrowError::InvalidMintB));
+
+    assert_eq!(test.balance(&test.taker, &test.mint_a), 0);
+    assert_eq!(test.runtime.anchor_account::<anchor_escrow::Escrow>(&test.escrow()).unwrap().receive, EXPECTED);
+}
+
+#[test]
+fn take_needs_the_arbiter_signature() {
+    let mut test = Test::new();
+    let arbiter = Pubkey::new_unique();
+    let impostor = Pubkey::new_unique();
+
+    test.send(test.make_with(0, Some(arbiter)), test.maker).unwrap();
+
+    assert_eq!(test.send(test.take_with(EXPECTED, 0, None), test.taker), failed(EscrowError::InvalidArbiter));
+
+    let instruction = test.take_with(EXPECTED, 0, Some(impostor));
+    assert_eq!(
+        test.runtime.process_transaction(&[instruction], &[test.taker, impostor]),
+        failed(EscrowError::InvalidArbiter)
+    );
+
+    // Listing the arbiter without its signature
+    let mut instruction = test.take_with(EXPECTED, 0, Some(arbiter));
+    instruction.accounts.last_mut().unwrap().is_signer = false;
+    assert_eq!(
+        test.send(instruction, test.taker),
+        Err(TransactionError {
+            index: 0,
+            error: program_error(anchor_lang::error::ErrorCode::AccountNotSigner),
+        })
+    );
+
+    let instruction = test.take_with(EXPECTED, 0, Some(arbiter));
+    test.runtime.process_transaction(&[instruction], &[test.taker, arbiter]).unwrap();
+
+    assert_eq!(test.balance(&test.taker, &test.mint_a), DEPOSIT);
+    assert!(test.runtime.account(&test.escrow()).is_none());
+}
+
+#[test]
+fn take_without_an_arbiter_ignores_the_arbiter_account() {
+    let mut test = Test::new();
+    let bystander = Pubkey::new_unique();
+
+    test.send(test.make(), test.maker).unwrap();
+
+    let instruction = test.take_with(EXPECTED, 0, Some(bystander));
+    test.runtime.process_transaction(&[instruction], &[test.taker, bystander]).unwrap();
+
+    assert_eq!(test.balance(&test.taker, &test.mint_a), DEPOSIT);
+}
+
+#[test]
+fn derived_addresses_match_the_program_seeds() {
+    let mut test = Test::new();
+    let (escrow, bump) = find_escrow_address(&test.maker, SEED);
+
+    test.send(test.make(), test.maker).unwrap();
+
+    let state = test.runtime.anchor_account::<anchor_escrow::Escrow>(&escrow).unwrap();
+    assert_eq!((state.seed, state.bump), (SEED, bump));
+
+    let vault = test.runtime.token_account(&find_vault_address(&escrow, &test.mint_a, &spl_token::ID)).unwrap();
+    assert_eq!((vault.owner, vault.mint, vault.amount), (escrow, test.mint_a, DEPOSIT));
+
+    // Another seed or maker derives another escrow
+    assert_ne!(find_escrow_address(&test.maker, SEED + 1).0, escrow);
+    assert_ne!(find_escrow_address(&test.taker, SEED).0, escrow);
+}
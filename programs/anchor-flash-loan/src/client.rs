@@ -0,0 +1,268 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::{
+        bpf_loader_upgradeable,
+        instruction::Instruction,
+        sysvar::instructions::ID as SYSVAR_INSTRUCTIONS_ID
+    },
+    InstructionData
+};
+use anchor_spl::{
+    associated_token::{
+        get_associated_token_address,
+        ID as ASSOCIATED_TOKEN_PROGRAM_ID
+    },
+    token::ID as TOKEN_PROGRAM_ID
+};
+
+// The repay has to follow the borrow, anything run with the borrowed funds
+// goes in between. It can be split into several repays, with other programs'
+// instructions between them. Under strict ordering the borrow also has to be
+// the first instruction of the transaction
+
+pub fn protocol_address() -> Pubkey {
+    Pubkey::find_program_address(&[b"protocol"], &crate::ID).0
+}
+
+pub fn config_address() -> Pubkey {
+    Pubkey::find_program_address(&[b"config"], &crate::ID).0
+}
+
+// The program data account of the program, its upgrade authority sets up the config
+pub fn program_data_address() -> Pubkey {
+    Pubkey::find_program_address(&[crate::ID.as_ref()], &bpf_loader_upgradeable::ID).0
+}
+
+pub fn initialize_config_ix(authority: Pubkey, strict_ordering: bool) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: crate::accounts::InitializeConfig {
+            authority,
+            config: config_address(),
+            program: crate::ID,
+            program_data: program_data_address(),
+            system_program: anchor_lang::system_program::ID,
+        }
+        .to_account_metas(None),
+        data: crate::instruction::InitializeConfig { strict_ordering }.data(),
+    }
+}
+
+pub fn initialize_protocol_ix(authority: Pubkey, mint: Pubkey, strict_ordering: bool) -> Instruction {
+    let protocol = protocol_address();
+
+    Instruction {
+        program_id: crate::ID,
+        accounts: crate::accounts::InitializeProtocol {
+            authority,
+            protocol,
+            mint,
+            protocol_ata: get_associated_token_address(&protocol, &mint),
+            config: config_address(),
+            program: crate::ID,
+            program_data: program_data_address(),
+            token_program: TOKEN_PROGRAM_ID,
+            associated_token_program: ASSOCIATED_TOKEN_PROGRAM_ID,
+            system_program: anchor_lang::system_program::ID,
+        }
+        .to_account_metas(None),
+        data: crate::instruction::InitializeProtocol { strict_ordering }.data(),
+    }
+}
+
+pub fn set_paused_ix(authority: Pubkey, paused: bool) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: crate::accounts::UpdateConfig {
+            authority,
+            config: config_address(),
+        }
+        .to_account_metas(None),
+        data: crate::instruction::SetPaused { paused }.data(),
+    }
+}
+
+// Meant to be simulated, the state is read from the emitted `ProtocolStateEvent`
+pub fn get_protocol_state_ix(mint: Pubkey) -> Instruction {
+    let protocol = protocol_address();
+
+    Instruction {
+        program_id: crate::ID,
+        accounts: crate::accounts::GetProtocolState {
+            protocol,
+            mint,
+            protocol_ata: get_associated_token_address(&protocol, &mint),
+            config: config_address(),
+        }
+        .to_account_metas(None),
+        data: crate::instruction::GetProtocolState {}.data(),
+    }
+}
+
+fn loan_accounts(borrower: Pubkey, mint: Pubkey) -> Vec<AccountMeta> {
+    let protocol = protocol_address();
+
+    crate::accounts::Loan {
+        borrower,
+        protocol,
+        mint,
+        borrower_ata: get_associated_token_address(&borrower, &mint),
+        protocol_ata: get_associated_token_address(&protocol, &mint),
+        sysvar_instructions: SYSVAR_INSTRUCTIONS_ID,
+        token_program: TOKEN_PROGRAM_ID,
+        associated_token_program: ASSOCIATED_TOKEN_PROGRAM_ID,
+        system_program: anchor_lang::system_program::ID,
+        config: config_address(),
+    }
+    .to_account_metas(None)
+}
+
+pub fn borrow_ix(borrower: Pubkey, mint: Pubkey, amount: u64, memo: Option<String>) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: loan_accounts(borrower, mint),
+        data: crate::instruction::Borrow { amount, memo }.data(),
+    }
+}
+
+// The principal plus the fee the repays of a loan of `amount` have to state in total
+pub fn repay_amount(amount: u64) -> Result<u64> {
+    amount.checked_add(crate::loan_fee(amount)?).ok_or(crate::ProtocolError::Overflow.into())
+}
+
+pub fn repay_ix(borrower: Pubkey, mint: Pubkey, repay_amount: u64) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: loan_accounts(borrower, mint),
+        data: crate::instruction::Repay { repay_amount }.data(),
+    }
+}
+
+fn loan_sol_accounts(borrower: Pubkey) -> Vec<AccountMeta> {
+    crate::accounts::LoanSol {
+        borrower,
+        protocol: protocol_address(),
+        sysvar_instructions: SYSVAR_INSTRUCTIONS_ID,
+        system_program: anchor_lang::system_program::ID,
+        config: config_address(),
+    }
+    .to_account_metas(None)
+}
+
+pub fn borrow_sol_ix(borrower: Pubkey, amount: u64) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: loan_sol_accounts(borrower),
+        data: crate::instruction::BorrowSol { amount }.data(),
+    }
+}
+
+pub fn repay_sol_ix(borrower: Pubkey, repay_amount: u64) -> Instruction {
+    Instruction {
+        program_id: crate::ID,
+        accounts: loan_sol_accounts(borrower),
+        data: crate::instruction::RepaySol { repay_amount }.data(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::Discriminator;
+
+    fn keys(instruction: &Instruction) -> Vec<Pubkey> {
+        instruction.accounts.iter().map(|meta| meta.pubkey).collect()
+    }
+
+    fn decode<T: AnchorDeserialize + Discriminator>(instruction: &Instruction) -> T {
+        let data = instruction.data.strip_prefix(T::DISCRIMINATOR).expect("wrong discriminator");
+        T::try_from_slice(data).unwrap()
+    }
+
+    #[test]
+    fn loan_instructions_decode() {
+        let borrower = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let protocol = protocol_address();
+
+        let expected = vec![
+            borrower,
+            protocol,
+            mint,
+            get_associated_token_address(&borrower, &mint),
+            get_associated_token_address(&protocol, &mint),
+            SYSVAR_INSTRUCTIONS_ID,
+            TOKEN_PROGRAM_ID,
+            ASSOCIATED_TOKEN_PROGRAM_ID,
+            anchor_lang::system_program::ID,
+            config_address(),
+        ];
+
+        let borrow = borrow_ix(borrower, mint, 1_000, Some("tag".to_string()));
+        let data: crate::instruction::Borrow = decode(&borrow);
+        assert_eq!((data.amount, data.memo.as_deref()), (1_000, Some("tag")));
+        assert_eq!(keys(&borrow), expected);
+        assert!(borrow.accounts[0].is_signer && borrow.accounts[0].is_writable);
+
+        let repay = repay_ix(borrower, mint, repay_amount(1_000).unwrap());
+        let data: crate::instruction::Repay = decode(&repay);
+        assert_eq!(data.repay_amount, 1_050);
+        assert_eq!(keys(&repay), expected);
+    }
+
+    #[test]
+    fn sol_loan_instructions_decode() {
+        let borrower = Pubkey::new_unique();
+
+        let expected = vec![
+            borrower,
+            protocol_address(),
+            SYSVAR_INSTRUCTIONS_ID,
+            anchor_lang::system_program::ID,
+            config_address(),
+        ];
+
+        let borrow = borrow_sol_ix(borrower, 1_000);
+        let data: crate::instruction::BorrowSol = decode(&borrow);
+        assert_eq!(data.amount, 1_000);
+        assert_eq!(keys(&borrow), expected);
+
+        let repay = repay_sol_ix(borrower, 1_050);
+        let data: crate::instruction::RepaySol = decode(&repay);
+        assert_eq!(data.repay_amount, 1_050);
+        assert_eq!(keys(&repay), expected);
+    }
+
+    #[test]
+    fn initialize_instructions_decode() {
+        let authority = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+
+        let config = initialize_config_ix(authority, true);
+        let data: crate::instruction::InitializeConfig = decode(&config);
+        assert!(data.strict_ordering);
+        assert_eq!(keys(&config), vec![
+            authority,
+            config_address(),
+            crate::ID,
+            program_data_address(),
+            anchor_lang::system_program::ID,
+        ]);
+
+        let protocol = initialize_protocol_ix(authority, mint, false);
+        let data: crate::instruction::InitializeProtocol = decode(&protocol);
+        assert!(!data.strict_ordering);
+        assert_eq!(keys(&protocol), vec![
+            authority,
+            protocol_address(),
+            mint,
+            get_associated_token_address(&protocol_address(), &mint),
+            config_address(),
+            crate::ID,
+            program_data_address(),
+            TOKEN_PROGRAM_ID,
+            ASSOCIATED_TOKEN_PROGRAM_ID,
+            anchor_lang::system_program::ID,
+        ]);
+    }
+}
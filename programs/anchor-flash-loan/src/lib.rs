@@ -1,6 +1,8 @@
 use anchor_lang::prelude::*;
 use anchor_lang::{
+    system_program,
     solana_program::{
+        instruction::Instruction,
         sysvar::{
             instructions::{
                 ID as SYSVAR_INSTRUCTIONS_ID,
@@ -23,46 +25,109 @@ use anchor_spl::{
 
 declare_id!("22222222222222222222222222222222222222222222");
 
+#[cfg(feature = "client")]
+pub mod client;
+
+// The fee charged on every loan, in basis points of the principal
+pub const FEE_BPS: u16 = 500;
+
+// The longest memo a borrow can be tagged with, in bytes
+pub const MAX_MEMO_LEN: usize = 32;
+
 #[program]
 pub mod anchor_flash_loan {
     use super::*;
 
-    pub fn borrow(ctx: Context<Loan>, amount:u64) -> Result<()> {
+    // Creates the protocol config, `strict_ordering` requires every borrow to be
+    // the first instruction of its transaction. Only the upgrade authority can create it
+    pub fn initialize_config(ctx: Context<InitializeConfig>, strict_ordering:bool) -> Result<()> {
+        check_upgrade_authority(&ctx.accounts.program_data, &ctx.accounts.authority.key())?;
+
+        ctx.accounts.config.set_inner(Config {
+            authority: ctx.accounts.authority.key(),
+            strict_ordering,
+            loan_active: false,
+            bump: ctx.bumps.config,
+            paused: false
+        });
+
+        Ok(())
+    }
+
+    // Creates the protocol ATA for a mint, and the config if there is none yet, so a
+    // deployer can bootstrap with a single instruction. Running it again is a no-op, an
+    // existing config keeps its authority and ordering
+    pub fn initialize_protocol(ctx: Context<InitializeProtocol>, strict_ordering:bool) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+
+        if config.authority.eq(&Pubkey::default()) {
+            // Creating the config is held to the same gate as `initialize_config`
+            check_upgrade_authority(&ctx.accounts.program_data, &ctx.accounts.authority.key())?;
+
+            config.set_inner(Config {
+                authority: ctx.accounts.authority.key(),
+                strict_ordering,
+                loan_active: false,
+                bump: ctx.bumps.config,
+                paused: false
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn set_strict_ordering(ctx: Context<UpdateConfig>, strict_ordering:bool) -> Result<()> {
+        ctx.accounts.config.strict_ordering = strict_ordering;
+
+        Ok(())
+    }
+
+    // While paused no new loan can be taken, repays of a loan in flight still go through
+    pub fn set_paused(ctx: Context<UpdateConfig>, paused:bool) -> Result<()> {
+        ctx.accounts.config.paused = paused;
+
+        Ok(())
+    }
+
+    // The memo is only recorded in the event, integrators can use it to tag the loan
+    pub fn borrow(ctx: Context<Loan>, amount:u64, memo:Option<String>) -> Result<()> {
         // Check if the amount is valid
         require_gt!(amount, 0, ProtocolError::InvalidAmount);
 
-        // Check if this is the first instruction in the transaction.
-        let current_index = load_current_index_checked(&ctx.accounts.sysvar_instructions)?;
-        require_eq!(current_index, 0, ProtocolError::InvalidIx);
-        
-        let current_instruction =  load_instruction_at_checked(0, 
-            &ctx.accounts.sysvar_instructions).unwrap();
+        let memo = memo.unwrap_or_default();
+        require_gte!(MAX_MEMO_LEN, memo.len(), ProtocolError::MemoTooLong);
+
+        ctx.accounts.check_mints()?;
+
+        require!(!ctx.accounts.config.paused, ProtocolError::Paused);
+
+        // Only one loan can be outstanding at a time
+        require!(!ctx.accounts.config.loan_active, ProtocolError::LoanActive);
 
-        require_keys_eq!(current_instruction.program_id, crate::ID, ProtocolError::InvalidIx);
+        ctx.accounts.config.loan_active = true;
 
-        // Get the count of instructions in the transaction
-        let instruction_count = u16::from_le_bytes(
-            ctx.accounts.sysvar_instructions.data.borrow()[..2].try_into().unwrap());
+        let repay_instructions = find_repay_instructions(&ctx.accounts.sysvar_instructions,
+            ctx.accounts.config.strict_ordering, instruction::Repay::DISCRIMINATOR)?;
 
-        // Get the repay instruction
-        let repay_instruction = 
-            load_instruction_at_checked(instruction_count as usize - 1, 
-                &ctx.accounts.sysvar_instructions).map_err(|_| ProtocolError::MissingRepayIx)?;
+        let mut repaid:u64 = 0;
 
-        // Affirm the keys
-        require_keys_eq!(crate::ID, repay_instruction.program_id, ProtocolError::InvalidProgram);
-        
-        // Affirm the instruction
-        require!(repay_instruction.data.as_slice()[0..8].eq(instruction::Repay::DISCRIMINATOR), ProtocolError::InvalidIx);
+        for repay_instruction in repay_instructions.iter() {
+            // Affirm the accounts
+            require_keys_eq!(repay_instruction.accounts.get(3).
+                ok_or(ProtocolError::InvalidBorrowerAta)?.pubkey, 
+                ctx.accounts.borrower_ata.key(), ProtocolError::InvalidBorrowerAta);
 
-        // Affirm the accounts
-        require_keys_eq!(repay_instruction.accounts.get(3).
-            ok_or_else(|| ProtocolError::InvalidBorrowerAta)?.pubkey, 
-            ctx.accounts.borrower_ata.key(), ProtocolError::InvalidBorrowerAta);
+            require_keys_eq!(repay_instruction.accounts.get(4).
+                ok_or(ProtocolError::InvalidProtocolAta)?.pubkey, 
+                ctx.accounts.protocol_ata.key(), ProtocolError::InvalidProtocolAta);
 
-        require_keys_eq!(repay_instruction.accounts.get(4).
-            ok_or_else(|| ProtocolError::InvalidProtocolAta)?.pubkey, 
-            ctx.accounts.protocol_ata.key(), ProtocolError::InvalidProtocolAta);
+            repaid = repaid.checked_add(stated_amount(repay_instruction)?)
+                .ok_or(ProtocolError::Overflow)?;
+        }
+
+        // Each repay moves what it states, so together they have to add up to the loan
+        require_eq!(repaid, amount.checked_add(loan_fee(amount)?).ok_or(ProtocolError::Overflow)?,
+            ProtocolError::InvalidRepayAmount);
 
         // Make the transfer
 
@@ -82,24 +147,45 @@ pub mod anchor_flash_loan {
             &signer
         );
 
-        transfer(transfer_context, amount)
+        transfer(transfer_context, amount)?;
+
+        emit!(BorrowEvent {
+            borrower: ctx.accounts.borrower.key(),
+            mint: ctx.accounts.mint.key(),
+            amount,
+            fee: loan_fee(amount)?,
+            memo,
+        });
+
+        Ok(())
     }
 
-    pub fn repay(ctx: Context<Loan>) -> Result<()> {
-        // Get the borrow amount from the first instruction in the transaction
-        let borrow_instruction = 
-            load_instruction_at_checked(0, &ctx.accounts.sysvar_instructions).
-            map_err(|_| ProtocolError::MissingBorrowIx)?;
-        
-        // Get the amount
-        let mut amount = u64::from_le_bytes(borrow_instruction.data.as_slice()[8..16].try_into().unwrap());
+    // A loan can be repaid in parts, `repay_amount` is this part. The borrow checks
+    // that its repays add up to exactly the principal plus the fee, so a lone repay
+    // states the whole of it
+    pub fn repay(ctx: Context<Loan>, repay_amount:u64) -> Result<()> {
+        let borrow_instruction = find_borrow_instruction(&ctx.accounts.sysvar_instructions,
+            instruction::Repay::DISCRIMINATOR)?;
 
-        // Make the tranfer
-        let fee = u64::try_from((amount as u128).checked_mul(500).
-            ok_or_else(|| ProtocolError::Overflow)?.checked_div(10_000).
-            ok_or_else(|| ProtocolError::Overflow)?).map_err(|_| ProtocolError::Overflow)?;
+        require!(borrow_instruction.data.get(0..8).eq(&Some(instruction::Borrow::DISCRIMINATOR)), ProtocolError::MissingBorrowIx);
+
+        ctx.accounts.check_mints()?;
+
+        ctx.accounts.config.loan_active = false;
+
+        // The wallet that borrowed has to be the one repaying
+        require_keys_eq!(borrow_instruction.accounts.first().
+            ok_or(ProtocolError::InvalidBorrower)?.pubkey,
+            ctx.accounts.borrower.key(), ProtocolError::InvalidBorrower);
 
-        amount = amount.checked_add(fee).ok_or_else(|| ProtocolError::Overflow)?;
+        // The borrow instruction stays the source of truth, no part can be more than the whole
+        let principal = stated_amount(&borrow_instruction)?;
+
+        require_gt!(repay_amount, 0, ProtocolError::InvalidRepayAmount);
+        require_gte!(principal.checked_add(loan_fee(principal)?).ok_or(ProtocolError::Overflow)?,
+            repay_amount, ProtocolError::InvalidRepayAmount);
+
+        // Make the tranfer
 
         let transfer_accounts = Transfer{
             from:ctx.accounts.borrower_ata.to_account_info(),
@@ -112,8 +198,302 @@ pub mod anchor_flash_loan {
             transfer_accounts,
         );
 
-        transfer(transfer_context, amount)
+        transfer(transfer_context, repay_amount)
     }
+
+    // Lends lamports held by the protocol PDA itself, paired with `repay_sol` the same way
+    // the token loans are paired with `repay`
+    pub fn borrow_sol(ctx: Context<LoanSol>, amount:u64) -> Result<()> {
+        // Check if the amount is valid
+        require_gt!(amount, 0, ProtocolError::InvalidAmount);
+
+        require!(!ctx.accounts.config.paused, ProtocolError::Paused);
+
+        // Only one loan can be outstanding at a time
+        require!(!ctx.accounts.config.loan_active, ProtocolError::LoanActive);
+
+        ctx.accounts.config.loan_active = true;
+
+        let repay_instructions = find_repay_instructions(&ctx.accounts.sysvar_instructions,
+            ctx.accounts.config.strict_ordering, instruction::RepaySol::DISCRIMINATOR)?;
+
+        let mut repaid:u64 = 0;
+
+        for repay_instruction in repay_instructions.iter() {
+            repaid = repaid.checked_add(stated_amount(repay_instruction)?)
+                .ok_or(ProtocolError::Overflow)?;
+        }
+
+        require_eq!(repaid, amount.checked_add(loan_fee(amount)?).ok_or(ProtocolError::Overflow)?,
+            ProtocolError::InvalidRepayAmount);
+
+        // The PDA has to stay rent exempt, only the lamports above that can be lent
+        let available = ctx.accounts.protocol.lamports()
+            .saturating_sub(Rent::get()?.minimum_balance(0));
+
+        require_gte!(available, amount, ProtocolError::NotEnoughFunds);
+
+        let transfer_accounts = system_program::Transfer{
+            from:ctx.accounts.protocol.to_account_info(),
+            to:ctx.accounts.borrower.to_account_info()
+        };
+
+        let seeds = [b"protocol".as_ref(), &[ctx.bumps.protocol]];
+
+        let signer = [&seeds[..]];
+
+        let transfer_context = CpiContext::new_with_signer(
+            ctx.accounts.system_program.to_account_info(),
+            transfer_accounts,
+            &signer
+        );
+
+        system_program::transfer(transfer_context, amount)
+    }
+
+    pub fn repay_sol(ctx: Context<LoanSol>, repay_amount:u64) -> Result<()> {
+        let borrow_instruction = find_borrow_instruction(&ctx.accounts.sysvar_instructions,
+            instruction::RepaySol::DISCRIMINATOR)?;
+
+        require!(borrow_instruction.data.get(0..8).eq(&Some(instruction::BorrowSol::DISCRIMINATOR)), ProtocolError::MissingBorrowIx);
+
+        ctx.accounts.config.loan_active = false;
+
+        // The wallet that borrowed has to be the one repaying
+        require_keys_eq!(borrow_instruction.accounts.first().
+            ok_or(ProtocolError::InvalidBorrower)?.pubkey,
+            ctx.accounts.borrower.key(), ProtocolError::InvalidBorrower);
+
+        let principal = stated_amount(&borrow_instruction)?;
+
+        require_gt!(repay_amount, 0, ProtocolError::InvalidRepayAmount);
+        require_gte!(principal.checked_add(loan_fee(principal)?).ok_or(ProtocolError::Overflow)?,
+            repay_amount, ProtocolError::InvalidRepayAmount);
+
+        // Make the tranfer
+
+        let transfer_accounts = system_program::Transfer{
+            from:ctx.accounts.borrower.to_account_info(),
+            to:ctx.accounts.protocol.to_account_info()
+        };
+
+        let transfer_context = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            transfer_accounts,
+        );
+
+        system_program::transfer(transfer_context, repay_amount)
+    }
+
+    // Read only, meant to be simulated by keepers polling the protocol
+    pub fn get_protocol_state(ctx: Context<GetProtocolState>) -> Result<()> {
+        emit!(ProtocolStateEvent {
+            mint: ctx.accounts.mint.key(),
+            liquidity: ctx.accounts.protocol_ata.amount,
+            fee_bps: FEE_BPS,
+            authority: ctx.accounts.config.authority,
+            strict_ordering: ctx.accounts.config.strict_ordering,
+            loan_active: ctx.accounts.config.loan_active,
+            paused: ctx.accounts.config.paused,
+        });
+
+        Ok(())
+    }
+}
+
+impl<'info> Loan<'info> {
+    // Restates the ATA constraints so a loan never runs on token accounts of another mint
+    fn check_mints(&self) -> Result<()> {
+        require_keys_eq!(self.borrower_ata.mint, self.mint.key(), ProtocolError::InvalidBorrowerAta);
+        require_keys_eq!(self.protocol_ata.mint, self.mint.key(), ProtocolError::InvalidProtocolAta);
+
+        Ok(())
+    }
+}
+
+// Only the upgrade authority of the program can set up the config
+fn check_upgrade_authority(program_data:&ProgramData, authority:&Pubkey) -> Result<()> {
+    require!(program_data.upgrade_authority_address.eq(&Some(*authority)), ProtocolError::InvalidAuthority);
+
+    Ok(())
+}
+
+// The fee rounds up so that even the smallest loan pays one
+fn loan_fee(amount:u64) -> Result<u64> {
+    u64::try_from((amount as u128).checked_mul(FEE_BPS as u128).
+        ok_or(ProtocolError::Overflow)?.checked_add(9_999).
+        ok_or(ProtocolError::Overflow)?.checked_div(10_000).
+        ok_or(ProtocolError::Overflow)?).map_err(|_| ProtocolError::Overflow.into())
+}
+
+// Returns the amount a borrow or repay instruction states, right after its discriminator
+fn stated_amount(instruction:&Instruction) -> Result<u64> {
+    Ok(u64::from_le_bytes(instruction.data.get(8..16)
+        .ok_or(ProtocolError::InvalidIx)?.try_into().unwrap()))
+}
+
+// Returns the repays paired with the borrow being run, the instructions of this program
+// that follow it as long as they are repays of `repay_discriminator`. Instructions of other
+// programs may sit between them, e.g. to top up the borrower ahead of a later part
+fn find_repay_instructions(sysvar_instructions:&AccountInfo, strict_ordering:bool,
+    repay_discriminator:&[u8]) -> Result<Vec<Instruction>> {
+    // Locate this instruction, other programs' instructions (e.g. compute budget or ATA creation)
+    // may come first unless the protocol requires the borrow to lead the transaction
+    let current_index = load_current_index_checked(sysvar_instructions)? as usize;
+
+    if strict_ordering {
+        require_eq!(current_index, 0, ProtocolError::InvalidInstructionIndex);
+    }
+
+    let current_instruction =  load_instruction_at_checked(current_index, sysvar_instructions)?;
+
+    // This also rejects a borrow reached through a CPI
+    require_keys_eq!(current_instruction.program_id, crate::ID, ProtocolError::InvalidIx);
+
+    // Get the count of instructions in the transaction
+    let instruction_count = u16::from_le_bytes(
+        sysvar_instructions.data.borrow()[..2].try_into().unwrap());
+
+    let mut repay_instructions = Vec::new();
+
+    match (current_index, instruction_count) {
+        // Fast path for a transaction holding only the borrow and its repay
+        (0, 2) => {
+            let instruction = load_instruction_at_checked(1, sysvar_instructions)?;
+
+            if instruction.program_id.eq(&crate::ID) &&
+                instruction.data.get(0..8).eq(&Some(repay_discriminator)) {
+                repay_instructions.push(instruction);
+            }
+        },
+        // The run ends at the next instruction of this program that is not a repay, so a
+        // later borrow never counts the repays of this one
+        _ => {
+            for index in current_index + 1..instruction_count as usize {
+                let instruction = load_instruction_at_checked(index, sysvar_instructions)?;
+
+                if instruction.program_id.ne(&crate::ID) {
+                    continue;
+                }
+
+                if instruction.data.get(0..8).ne(&Some(repay_discriminator)) {
+                    break;
+                }
+
+                repay_instructions.push(instruction);
+            }
+        }
+    }
+
+    require!(!repay_instructions.is_empty(), ProtocolError::MissingRepayIx);
+
+    Ok(repay_instructions)
+}
+
+// Returns the borrow paired with the repay being run, the closest earlier instruction of
+// this program that is not another part of the same repay
+fn find_borrow_instruction(sysvar_instructions:&AccountInfo, repay_discriminator:&[u8]) -> Result<Instruction> {
+    let current_index = load_current_index_checked(sysvar_instructions)? as usize;
+
+    for index in (0..current_index).rev() {
+        let instruction = load_instruction_at_checked(index, sysvar_instructions)?;
+
+        if instruction.program_id.eq(&crate::ID) &&
+            instruction.data.get(0..8).ne(&Some(repay_discriminator)) {
+            return Ok(instruction);
+        }
+    }
+
+    Err(ProtocolError::MissingBorrowIx.into())
+}
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info>{
+
+    #[account(
+        mut
+    )]
+    authority:Signer<'info>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = Config::DISCRIMINATOR.len() + Config::INIT_SPACE,
+        seeds = [b"config"],
+        bump
+    )]
+    config:Account<'info, Config>,
+
+    #[account(
+        constraint = program.programdata_address()?.eq(&Some(program_data.key())) @ ProtocolError::InvalidProgram
+    )]
+    program:Program<'info, crate::program::AnchorFlashLoan>,
+
+    program_data:Account<'info, ProgramData>,
+
+    system_program:Program<'info, System>
+}
+
+#[derive(Accounts)]
+pub struct InitializeProtocol<'info>{
+
+    #[account(
+        mut
+    )]
+    authority:Signer<'info>,
+
+    #[account(
+        seeds = [b"protocol"],
+        bump
+    )]
+    /// CHECK: This is an account that controls the token account for each mint
+    protocol:UncheckedAccount<'info>,
+
+    mint:Account<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        associated_token::mint = mint,
+        associated_token::authority = protocol
+    )]
+    protocol_ata:Account<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = Config::DISCRIMINATOR.len() + Config::INIT_SPACE,
+        seeds = [b"config"],
+        bump
+    )]
+    config:Account<'info, Config>,
+
+    #[account(
+        constraint = program.programdata_address()?.eq(&Some(program_data.key())) @ ProtocolError::InvalidProgram
+    )]
+    program:Program<'info, crate::program::AnchorFlashLoan>,
+
+    program_data:Account<'info, ProgramData>,
+
+    token_program:Program<'info, Token>,
+
+    associated_token_program:Program<'info, AssociatedToken>,
+
+    system_program:Program<'info, System>
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info>{
+
+    authority:Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = authority @ ProtocolError::InvalidAuthority
+    )]
+    config:Account<'info, Config>,
 }
 
 #[derive(Accounts)]
@@ -158,9 +538,107 @@ pub struct Loan<'info>{
 
     associated_token_program:Program<'info, AssociatedToken>,
 
-    system_program:Program<'info, System>
+    system_program:Program<'info, System>,
+
+    #[account(
+        mut,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    config:Account<'info, Config>
+}
+
+
+#[derive(Accounts)]
+pub struct LoanSol<'info>{
+
+    #[account(
+        mut
+    )]
+    borrower:Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"protocol"],
+        bump
+    )]
+    /// CHECK: This is a system owned account holding the lamports that are lent
+    protocol:UncheckedAccount<'info>,
+
+    #[account(
+        address = SYSVAR_INSTRUCTIONS_ID
+    )]
+    /// CHECK: Address is checked above
+    sysvar_instructions:UncheckedAccount<'info>,
+
+    system_program:Program<'info, System>,
+
+    #[account(
+        mut,
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    config:Account<'info, Config>
+}
+
+#[derive(Accounts)]
+pub struct GetProtocolState<'info>{
+
+    #[account(
+        seeds = [b"protocol"],
+        bump
+    )]
+    /// CHECK: This is an account that controls the token account for each mint
+    protocol:UncheckedAccount<'info>,
+
+    mint:Account<'info, Mint>,
+
+    #[account(
+        associated_token::mint = mint,
+        associated_token::authority = protocol
+    )]
+    protocol_ata:Account<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"config"],
+        bump = config.bump
+    )]
+    config:Account<'info, Config>
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Config {
+    pub authority: Pubkey,
+    // When set the borrow has to be the first instruction of the transaction
+    pub strict_ordering: bool,
+    // Set between a borrow and its repay so a nested borrow is rejected
+    pub loan_active: bool,
+    pub bump: u8,
+    // Set by the authority to stop new loans
+    pub paused: bool,
 }
 
+#[event]
+pub struct ProtocolStateEvent {
+    pub mint: Pubkey,
+    pub liquidity: u64,
+    pub fee_bps: u16,
+    pub authority: Pubkey,
+    pub strict_ordering: bool,
+    pub loan_active: bool,
+    pub paused: bool,
+}
+
+// The memo is empty when the borrow was not tagged
+#[event]
+pub struct BorrowEvent {
+    pub borrower: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub fee: u64,
+    pub memo: String,
+}
 
 #[error_code]
 pub enum ProtocolError {
@@ -186,4 +664,46 @@ pub enum ProtocolError {
     MissingBorrowIx,
     #[msg("Overflow")]
     Overflow,
-}
\ No newline at end of file
+    #[msg("Invalid borrower")]
+    InvalidBorrower,
+    #[msg("Invalid authority")]
+    InvalidAuthority,
+    #[msg("A loan is already active")]
+    LoanActive,
+    #[msg("Invalid repay amount")]
+    InvalidRepayAmount,
+    #[msg("Memo too long")]
+    MemoTooLong,
+    #[msg("The protocol is paused")]
+    Paused,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loan_fee_rounds_up() {
+        assert_eq!(loan_fee(0).unwrap(), 0);
+        assert_eq!(loan_fee(1).unwrap(), 1);
+        assert_eq!(loan_fee(10_000).unwrap(), 500);
+        assert_eq!(loan_fee(20_001).unwrap(), 1_001);
+    }
+
+    #[test]
+    fn loan_fee_covers_the_largest_loan() {
+        assert_eq!(loan_fee(u64::MAX).unwrap(), (u64::MAX as u128 * FEE_BPS as u128).div_ceil(10_000) as u64);
+    }
+
+    #[test]
+    fn stated_amount_reads_past_the_discriminator() {
+        let mut data = vec![0u8; 8];
+        data.extend_from_slice(&1_234u64.to_le_bytes());
+
+        let instruction = Instruction { program_id: ID, accounts: vec![], data };
+        assert_eq!(stated_amount(&instruction).unwrap(), 1_234);
+
+        let truncated = Instruction { program_id: ID, accounts: vec![], data: vec![0; 15] };
+        assert!(stated_amount(&truncated).is_err());
+    }
+}
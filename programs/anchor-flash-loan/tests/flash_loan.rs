@@ -0,0 +1,489 @@
+use anchor_flash_loan::{client::*, ProtocolError};
+use anchor_lang::{
+    error::ErrorCode,
+    prelude::{AccountInfo, Pubkey},
+    solana_program::{entrypoint::ProgramResult, instruction::Instruction, program::invoke},
+};
+use anchor_spl::token::spl_token;
+use anchor_test_runtime::{program_error, Runtime, TransactionError};
+
+const LIQUIDITY: u64 = 1_000_000_000;
+
+// Enough to cover the fees of the loans taken in the tests
+const BORROWER_BALANCE: u64 = 100_000_000;
+
+struct Test {
+    runtime: Runtime,
+    authority: Pubkey,
+    borrower: Pubkey,
+    mint: Pubkey,
+}
+
+impl Test {
+    fn new(strict_ordering: bool) -> Self {
+        let mut runtime = Runtime::new();
+        let authority = Pubkey::new_unique();
+        let borrower = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+
+        runtime.add_upgradeable_program(anchor_flash_loan::ID, anchor_flash_loan::entry, Some(authority));
+        runtime.airdrop(&authority, 10_000_000_000);
+        runtime.airdrop(&borrower, 10_000_000_000);
+
+        runtime.create_mint(&mint, Some(&authority), 6);
+        runtime.create_associated_token_account(&protocol_address(), &mint, LIQUIDITY);
+        runtime.create_associated_token_account(&borrower, &mint, BORROWER_BALANCE);
+
+        runtime.process_transaction(&[initialize_config_ix(authority, strict_ordering)], &[authority]).unwrap();
+
+        Self { runtime, authority, borrower, mint }
+    }
+
+    fn borrow(&self, amount: u64) -> Instruction {
+        borrow_ix(self.borrower, self.mint, amount, None)
+    }
+
+    fn repay(&self, repay_amount: u64) -> Instruction {
+        repay_ix(self.borrower, self.mint, repay_amount)
+    }
+
+    fn send(&mut self, instructions: &[Instruction]) -> Result<(), TransactionError> {
+        self.runtime.process_transaction(instructions, &[self.borrower])
+    }
+
+    fn borrower_balance(&self) -> u64 {
+        self.runtime.token_balance(&ata(&self.borrower, &self.mint))
+    }
+
+    fn liquidity(&self) -> u64 {
+        self.runtime.token_balance(&ata(&protocol_address(), &self.mint))
+    }
+
+    // Mints to the borrower in the middle of a loan, e.g. the proceeds of a trade
+    fn top_up(&self, amount: u64) -> Instruction {
+        spl_token::instruction::mint_to(&spl_token::ID, &self.mint,
+            &ata(&self.borrower, &self.mint), &self.authority, &[], amount).unwrap()
+    }
+}
+
+fn ata(owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+    anchor_spl::associated_token::get_associated_token_address(owner, mint)
+}
+
+fn failed(index: usize, error: ProtocolError) -> Result<(), TransactionError> {
+    Err(TransactionError { index, error: program_error(error) })
+}
+
+#[test]
+fn borrow_and_repay() {
+    let mut test = Test::new(true);
+    let amount = 10_000_000;
+
+    test.send(&[test.borrow(amount), test.repay(repay_amount(amount).unwrap())]).unwrap();
+
+    assert_eq!(test.borrower_balance(), BORROWER_BALANCE - 500_000);
+    assert_eq!(test.liquidity(), LIQUIDITY + 500_000);
+}
+
+#[test]
+fn two_partial_repays_cover_the_loan() {
+    let mut test = Test::new(true);
+    let amount = 10_000_000;
+    let total = repay_amount(amount).unwrap();
+
+    // The first part is repaid before the borrower is topped up for the second
+    test.runtime.process_transaction(&[
+        test.borrow(amount),
+        test.repay(4_000_000),
+        test.top_up(1_000_000),
+        test.repay(total - 4_000_000),
+    ], &[test.borrower, test.authority]).unwrap();
+
+    assert_eq!(test.borrower_balance(), BORROWER_BALANCE + 1_000_000 - 500_000);
+    assert_eq!(test.liquidity(), LIQUIDITY + 500_000);
+}
+
+#[test]
+fn partial_repays_falling_short_are_rejected() {
+    let mut test = Test::new(true);
+    let amount = 10_000_000;
+    let total = repay_amount(amount).unwrap();
+
+    assert_eq!(
+        test.send(&[test.borrow(amount), test.repay(4_000_000), test.repay(total - 4_000_001)]),
+        failed(0, ProtocolError::InvalidRepayAmount)
+    );
+
+    assert_eq!(test.borrower_balance(), BORROWER_BALANCE);
+    assert_eq!(test.liquidity(), LIQUIDITY);
+}
+
+fn compute_budget() -> Instruction {
+    // SetComputeUnitLimit(200_000)
+    Instruction {
+        program_id: anchor_test_runtime::COMPUTE_BUDGET_PROGRAM_ID,
+        accounts: vec![],
+        data: [&[2][..], &200_000u32.to_le_bytes()].concat(),
+    }
+}
+
+#[test]
+fn fast_path_matches_the_general_path() {
+    let amount = 10_000_000;
+    let total = repay_amount(amount).unwrap();
+
+    // Builds the instruction following the borrow from the loan amount and its repay amount
+    type Second = fn(&Test, u64, u64) -> Instruction;
+
+    let cases: [(Second, Result<(), TransactionError>); 6] = [
+        (|test, _, total| test.repay(total), Ok(())),
+        (|test, _, total| test.repay(total - 1), failed(0, ProtocolError::InvalidRepayAmount)),
+        (|test, _, total| test.repay(total + 1), failed(0, ProtocolError::InvalidRepayAmount)),
+        (|_, _, _| compute_budget(), failed(0, ProtocolError::MissingRepayIx)),
+        (|test, amount, _| borrow_sol_ix(test.borrower, amount), failed(0, ProtocolError::MissingRepayIx)),
+        (|test, _, total| repay_sol_ix(test.borrower, total), failed(0, ProtocolError::MissingRepayIx)),
+    ];
+
+    for (second, expected) in cases {
+        // Two instructions take the fast path, a trailing one forces the general search
+        let mut fast = Test::new(true);
+        let instructions = [fast.borrow(amount), second(&fast, amount, total)];
+        assert_eq!(fast.send(&instructions), expected);
+
+        let mut general = Test::new(true);
+        let instructions = [general.borrow(amount), second(&general, amount, total), compute_budget()];
+        assert_eq!(general.send(&instructions), expected);
+    }
+}
+
+#[test]
+fn compute_budget_ahead_of_the_borrow() {
+    let amount = 10_000_000;
+    let total = repay_amount(amount).unwrap();
+
+    let mut test = Test::new(false);
+    test.send(&[compute_budget(), test.borrow(amount), test.repay(total)]).unwrap();
+    assert_eq!(test.liquidity(), LIQUIDITY + 500_000);
+
+    // Strict ordering still requires the borrow to lead
+    let mut test = Test::new(true);
+    assert_eq!(
+        test.send(&[compute_budget(), test.borrow(amount), test.repay(total)]),
+        failed(1, ProtocolError::InvalidInstructionIndex)
+    );
+}
+
+#[test]
+fn borrow_at_index_two_under_relaxed_ordering() {
+    let amount = 10_000_000;
+    let total = repay_amount(amount).unwrap();
+
+    let mut test = Test::new(false);
+    test.send(&[compute_budget(), compute_budget(), test.borrow(amount), test.repay(total)]).unwrap();
+    assert_eq!(test.liquidity(), LIQUIDITY + 500_000);
+
+    // A valid repay is still required
+    assert_eq!(
+        test.send(&[compute_budget(), compute_budget(), test.borrow(amount), compute_budget()]),
+        failed(2, ProtocolError::MissingRepayIx)
+    );
+}
+
+#[test]
+fn only_the_upgrade_authority_creates_the_config() {
+    let mut runtime = Runtime::new();
+    let upgrade_authority = Pubkey::new_unique();
+    let other = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+
+    runtime.add_upgradeable_program(anchor_flash_loan::ID, anchor_flash_loan::entry, Some(upgrade_authority));
+    runtime.airdrop(&upgrade_authority, 10_000_000_000);
+    runtime.airdrop(&other, 10_000_000_000);
+    runtime.create_mint(&mint, None, 6);
+
+    for instruction in [initialize_config_ix(other, true), initialize_protocol_ix(other, mint, true)] {
+        assert_eq!(
+            runtime.process_transaction(&[instruction], &[other]),
+            failed(0, ProtocolError::InvalidAuthority)
+        );
+    }
+
+    assert!(runtime.account(&config_address()).is_none());
+
+    runtime.process_transaction(&[initialize_config_ix(upgrade_authority, true)], &[upgrade_authority]).unwrap();
+    assert!(runtime.account(&config_address()).is_some());
+}
+
+#[test]
+fn borrow_and_repay_sol() {
+    let mut test = Test::new(true);
+    let protocol = protocol_address();
+    test.runtime.airdrop(&protocol, LIQUIDITY);
+
+    let amount = 100_000_000;
+    let total = repay_amount(amount).unwrap();
+    let before = test.runtime.lamports(&test.borrower);
+
+    test.send(&[borrow_sol_ix(test.borrower, amount), repay_sol_ix(test.borrower, total)]).unwrap();
+
+    assert_eq!(test.runtime.lamports(&test.borrower), before - 5_000_000);
+    assert_eq!(test.runtime.lamports(&protocol), LIQUIDITY + 5_000_000);
+
+    // The protocol keeps its rent exempt reserve
+    let lendable = LIQUIDITY + 5_000_000 - test.runtime.minimum_balance(0);
+
+    assert_eq!(
+        test.send(&[
+            borrow_sol_ix(test.borrower, lendable + 1),
+            repay_sol_ix(test.borrower, repay_amount(lendable + 1).unwrap()),
+        ]),
+        failed(0, ProtocolError::NotEnoughFunds)
+    );
+}
+
+const REENTRANT_ID: Pubkey = Pubkey::new_from_array([7; 32]);
+
+// Borrows from the protocol through a CPI, with the accounts of a borrow
+fn reentrant(_program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let amount = u64::from_le_bytes(data.try_into().unwrap());
+
+    invoke(&borrow_ix(*accounts[0].key, *accounts[2].key, amount, None), accounts)
+}
+
+impl Test {
+    fn reentrant_borrow(&self, amount: u64) -> Instruction {
+        Instruction {
+            program_id: REENTRANT_ID,
+            accounts: self.borrow(amount).accounts,
+            data: amount.to_le_bytes().to_vec(),
+        }
+    }
+}
+
+#[test]
+fn nested_borrow_through_cpi_is_rejected() {
+    let amount = 10_000_000;
+    let total = repay_amount(amount).unwrap();
+
+    let mut test = Test::new(false);
+    test.runtime.add_program(REENTRANT_ID, reentrant);
+
+    // Re-entering while the loan is outstanding
+    assert_eq!(
+        test.send(&[test.borrow(amount), test.reentrant_borrow(amount), test.repay(total)]),
+        failed(1, ProtocolError::LoanActive)
+    );
+
+    // A borrow reached through a CPI is rejected even without an outstanding loan
+    assert_eq!(
+        test.send(&[test.reentrant_borrow(amount), test.repay(total)]),
+        failed(0, ProtocolError::InvalidIx)
+    );
+
+    assert_eq!(test.liquidity(), LIQUIDITY);
+}
+
+#[test]
+fn wrong_repay_amount_is_rejected() {
+    let amount = 10_000_000;
+    let total = repay_amount(amount).unwrap();
+
+    let mut test = Test::new(true);
+
+    // The principal alone, without the fee
+    assert_eq!(
+        test.send(&[test.borrow(amount), test.repay(amount)]),
+        failed(0, ProtocolError::InvalidRepayAmount)
+    );
+
+    // The parts add up, but a repay stating nothing is rejected by the repay itself
+    assert_eq!(
+        test.send(&[test.borrow(amount), test.repay(0), test.repay(total)]),
+        failed(1, ProtocolError::InvalidRepayAmount)
+    );
+
+    assert_eq!(test.borrower_balance(), BORROWER_BALANCE);
+}
+
+#[test]
+fn repay_by_another_signer_is_rejected() {
+    let amount = 10_000_000;
+    let total = repay_amount(amount).unwrap();
+
+    let mut test = Test::new(true);
+    let other = Pubkey::new_unique();
+    test.runtime.airdrop(&other, 10_000_000_000);
+    test.runtime.create_associated_token_account(&other, &test.mint, BORROWER_BALANCE);
+
+    // Repaying from its own ATA does not match the ATA the borrow paid out to
+    assert_eq!(
+        test.runtime.process_transaction(&[test.borrow(amount), repay_ix(other, test.mint, total)],
+            &[test.borrower, other]),
+        failed(0, ProtocolError::InvalidBorrowerAta)
+    );
+
+    // Nor can it sign for the borrower's ATA
+    let mut repay = test.repay(total);
+    repay.accounts[0].pubkey = other;
+
+    assert_eq!(
+        test.runtime.process_transaction(&[test.borrow(amount), repay], &[test.borrower, other]),
+        Err(TransactionError { index: 1, error: program_error(ErrorCode::ConstraintTokenOwner) })
+    );
+
+    // The SOL repay names no ATA, the borrower check rejects it
+    test.runtime.airdrop(&protocol_address(), LIQUIDITY);
+
+    assert_eq!(
+        test.runtime.process_transaction(&[borrow_sol_ix(test.borrower, amount), repay_sol_ix(other, total)],
+            &[test.borrower, other]),
+        failed(1, ProtocolError::InvalidBorrower)
+    );
+}
+
+#[test]
+fn borrow_memo_is_recorded_in_the_event() {
+    let amount = 10_000_000;
+    let total = repay_amount(amount).unwrap();
+
+    let mut test = Test::new(true);
+    let borrow = borrow_ix(test.borrower, test.mint, amount, Some("desk-7/arb-42".to_string()));
+
+    test.send(&[borrow, test.repay(total)]).unwrap();
+
+    let events = test.runtime.events::<anchor_flash_loan::BorrowEvent>();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].borrower, test.borrower);
+    assert_eq!(events[0].mint, test.mint);
+    assert_eq!((events[0].amount, events[0].fee), (amount, 500_000));
+    assert_eq!(events[0].memo, "desk-7/arb-42");
+
+    // An untagged loan records an empty memo
+    test.send(&[test.borrow(amount), test.repay(total)]).unwrap();
+    assert_eq!(test.runtime.events::<anchor_flash_loan::BorrowEvent>()[0].memo, "");
+}
+
+#[test]
+fn memo_over_the_limit_is_rejected() {
+    let amount = 10_000_000;
+    let total = repay_amount(amount).unwrap();
+
+    let mut test = Test::new(true);
+
+    let longest = "m".repeat(anchor_flash_loan::MAX_MEMO_LEN);
+    test.send(&[borrow_ix(test.borrower, test.mint, amount, Some(longest)), test.repay(total)]).unwrap();
+
+    let too_long = "m".repeat(anchor_flash_loan::MAX_MEMO_LEN + 1);
+    assert_eq!(
+        test.send(&[borrow_ix(test.borrower, test.mint, amount, Some(too_long)), test.repay(total)]),
+        failed(0, ProtocolError::MemoTooLong)
+    );
+}
+
+#[test]
+fn borrow_with_a_mismatched_mint_is_rejected() {
+    let amount = 10_000_000;
+    let total = repay_amount(amount).unwrap();
+
+    let mut test = Test::new(true);
+    let other_mint = Pubkey::new_unique();
+    test.runtime.create_mint(&other_mint, None, 6);
+
+    // The ATAs are the ones of the funded mint, the mint is another one
+    let mut borrow = test.borrow(amount);
+    borrow.accounts[2].pubkey = other_mint;
+    let mut repay = test.repay(total);
+    repay.accounts[2].pubkey = other_mint;
+
+    assert_eq!(
+        test.send(&[borrow, repay]),
+        Err(TransactionError { index: 0, error: program_error(ErrorCode::ConstraintTokenMint) })
+    );
+
+    assert_eq!(test.liquidity(), LIQUIDITY);
+}
+
+#[test]
+fn bootstrap_then_borrow() {
+    let mut runtime = Runtime::new();
+    let authority = Pubkey::new_unique();
+    let borrower = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+
+    runtime.add_upgradeable_program(anchor_flash_loan::ID, anchor_flash_loan::entry, Some(authority));
+    runtime.airdrop(&authority, 10_000_000_000);
+    runtime.airdrop(&borrower, 10_000_000_000);
+    runtime.create_mint(&mint, Some(&authority), 6);
+    runtime.create_associated_token_account(&borrower, &mint, BORROWER_BALANCE);
+
+    let protocol_ata = ata(&protocol_address(), &mint);
+    let fund = spl_token::instruction::mint_to(&spl_token::ID, &mint, &protocol_ata, &authority, &[], LIQUIDITY)
+        .unwrap();
+
+    runtime.process_transaction(&[initialize_protocol_ix(authority, mint, true), fund], &[authority]).unwrap();
+
+    // Running it again changes nothing
+    runtime.process_transaction(&[initialize_protocol_ix(authority, mint, false)], &[authority]).unwrap();
+
+    let config = runtime.anchor_account::<anchor_flash_loan::Config>(&config_address()).unwrap();
+    assert_eq!(config.authority, authority);
+    assert!(config.strict_ordering);
+
+    let amount = 10_000_000;
+    runtime.process_transaction(&[
+        borrow_ix(borrower, mint, amount, None),
+        repay_ix(borrower, mint, repay_amount(amount).unwrap()),
+    ], &[borrower]).unwrap();
+
+    assert_eq!(runtime.token_balance(&protocol_ata), LIQUIDITY + 500_000);
+}
+
+#[test]
+fn protocol_state_is_read_by_simulation() {
+    let mut test = Test::new(true);
+    let state = get_protocol_state_ix(test.mint);
+
+    // No signer is needed and nothing is written
+    test.runtime.simulate_transaction(std::slice::from_ref(&state), &[]).unwrap();
+
+    let events = test.runtime.events::<anchor_flash_loan::ProtocolStateEvent>();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].mint, test.mint);
+    assert_eq!(events[0].liquidity, LIQUIDITY);
+    assert_eq!(events[0].fee_bps, anchor_flash_loan::FEE_BPS);
+    assert_eq!(events[0].authority, test.authority);
+    assert!(events[0].strict_ordering);
+    assert!(!events[0].loan_active);
+    assert!(!events[0].paused);
+
+    test.runtime.process_transaction(&[set_paused_ix(test.authority, true)], &[test.authority]).unwrap();
+
+    test.runtime.simulate_transaction(&[state], &[]).unwrap();
+    assert!(test.runtime.events::<anchor_flash_loan::ProtocolStateEvent>()[0].paused);
+}
+
+#[test]
+fn paused_protocol_rejects_new_loans() {
+    let amount = 10_000_000;
+    let total = repay_amount(amount).unwrap();
+
+    let mut test = Test::new(true);
+    test.runtime.airdrop(&protocol_address(), LIQUIDITY);
+
+    // Only the authority pauses
+    assert_eq!(
+        test.send(&[set_paused_ix(test.borrower, true)]),
+        failed(0, ProtocolError::InvalidAuthority)
+    );
+
+    test.runtime.process_transaction(&[set_paused_ix(test.authority, true)], &[test.authority]).unwrap();
+
+    assert_eq!(test.send(&[test.borrow(amount), test.repay(total)]), failed(0, ProtocolError::Paused));
+    assert_eq!(
+        test.send(&[borrow_sol_ix(test.borrower, amount), repay_sol_ix(test.borrower, total)]),
+        failed(0, ProtocolError::Paused)
+    );
+
+    test.runtime.process_transaction(&[set_paused_ix(test.authority, false)], &[test.authority]).unwrap();
+    test.send(&[test.borrow(amount), test.repay(total)]).unwrap();
+}
@@ -0,0 +1,608 @@
+// Runs the anchor programs natively in their tests. A program is called through its `entry`
+// function and its CPIs are routed through the syscall stubs, to the system program
+// implemented in `system` and to the SPL Token and Associated Token Account processors.
+// Transactions are atomic and the instructions sysvar is filled in, so introspection works
+#![allow(deprecated)]
+
+use std::{cell::RefCell, collections::HashMap, sync::Once};
+
+use anchor_lang::{
+    error::Error,
+    solana_program::{
+        account_info::AccountInfo,
+        bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+        clock::Clock,
+        entrypoint::{ProgramResult, MAX_PERMITTED_DATA_INCREASE},
+        instruction::Instruction,
+        program_error::ProgramError,
+        program_option::COption,
+        program_pack::Pack,
+        program_stubs::{set_syscall_stubs, SyscallStubs},
+        pubkey::Pubkey,
+        rent::Rent,
+        system_program,
+        sysvar::{
+            self,
+            instructions::{construct_instructions_data, store_current_index},
+        },
+    },
+    AnchorDeserialize, Discriminator,
+};
+use anchor_spl::{
+    associated_token::{get_associated_token_address, spl_associated_token_account},
+    token::spl_token::{self, native_mint},
+};
+use solana_instruction::{BorrowedAccountMeta, BorrowedInstruction};
+
+mod system;
+
+pub type Entrypoint = for<'a> fn(&Pubkey, &'a [AccountInfo<'a>], &[u8]) -> ProgramResult;
+
+pub const COMPUTE_BUDGET_PROGRAM_ID: Pubkey =
+    anchor_lang::solana_program::pubkey!("ComputeBudget111111111111111111111111111111");
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Account {
+    pub lamports: u64,
+    pub data: Vec<u8>,
+    pub owner: Pubkey,
+    pub executable: bool,
+}
+
+// The instruction a transaction failed at and its error
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TransactionError {
+    pub index: usize,
+    pub error: ProgramError,
+}
+
+// The error an anchor program returns for `error`
+pub fn program_error(error: impl Into<Error>) -> ProgramError {
+    error.into().into()
+}
+
+// The state of the transaction being run, read by the syscall stubs
+struct Context {
+    programs: HashMap<Pubkey, Entrypoint>,
+    // The programs being run, the last one is the caller of a CPI
+    stack: Vec<Pubkey>,
+    return_data: Option<(Pubkey, Vec<u8>)>,
+    clock: Clock,
+    logs: Vec<String>,
+    events: Vec<Vec<u8>>,
+}
+
+thread_local! {
+    static CONTEXT: RefCell<Option<Context>> = const { RefCell::new(None) };
+}
+
+fn with_context<T>(f: impl FnOnce(&mut Context) -> T) -> Option<T> {
+    CONTEXT.with(|context| context.borrow_mut().as_mut().map(f))
+}
+
+struct Stubs;
+
+impl SyscallStubs for Stubs {
+    fn sol_log(&self, message: &str) {
+        if with_context(|context| context.logs.push(message.to_string())).is_none() {
+            println!("{message}");
+        }
+    }
+
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        with_context(|context| context.events.push(fields.concat()));
+    }
+
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        invoke(instruction, account_infos, signers_seeds)
+    }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = with_context(|context| context.clock.clone()).unwrap_or_default();
+        unsafe { *(var_addr as *mut Clock) = clock };
+        0
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { *(var_addr as *mut Rent) = Rent::default() };
+        0
+    }
+
+    fn sol_get_return_data(&self) -> Option<(Pubkey, Vec<u8>)> {
+        with_context(|context| context.return_data.clone()).flatten()
+    }
+
+    fn sol_set_return_data(&self, data: &[u8]) {
+        with_context(|context| {
+            let program_id = *context.stack.last().unwrap();
+            context.return_data = (!data.is_empty()).then(|| (program_id, data.to_vec()));
+        });
+    }
+
+    fn sol_get_stack_height(&self) -> u64 {
+        with_context(|context| context.stack.len() as u64).unwrap_or_default()
+    }
+}
+
+// Calls a program with the account infos of the instruction it runs
+fn call(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let entrypoint = with_context(|context| context.programs.get(program_id).copied())
+        .flatten()
+        .ok_or(ProgramError::IncorrectProgramId)?;
+
+    with_context(|context| context.stack.push(*program_id));
+
+    // The infos outlive the call, programs only need them for as long as they run
+    let accounts = unsafe { std::mem::transmute::<&[AccountInfo], &[AccountInfo]>(accounts) };
+    let result = entrypoint(program_id, accounts, data);
+
+    with_context(|context| context.stack.pop());
+
+    result
+}
+
+// Runs a CPI, with the privileges the caller holds or signs for through its seeds
+fn invoke(instruction: &Instruction, account_infos: &[AccountInfo], signers_seeds: &[&[&[u8]]]) -> ProgramResult {
+    let caller = with_context(|context| *context.stack.last().unwrap()).unwrap();
+
+    let signers = signers_seeds
+        .iter()
+        .map(|seeds| Pubkey::create_program_address(seeds, &caller))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| ProgramError::InvalidSeeds)?;
+
+    let mut accounts = Vec::with_capacity(instruction.accounts.len());
+
+    for meta in instruction.accounts.iter() {
+        let account = account_infos
+            .iter()
+            .find(|account| account.key.eq(&meta.pubkey))
+            .ok_or(ProgramError::NotEnoughAccountKeys)?;
+
+        if meta.is_signer && !account.is_signer && !signers.contains(&meta.pubkey) {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if meta.is_writable && !account.is_writable {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        let mut account = account.clone();
+        account.is_signer = meta.is_signer;
+        account.is_writable = meta.is_writable;
+        accounts.push(account);
+    }
+
+    call(&instruction.program_id, &accounts, &instruction.data)
+}
+
+fn noop(_program_id: &Pubkey, _accounts: &[AccountInfo], _data: &[u8]) -> ProgramResult {
+    Ok(())
+}
+
+fn token_process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    spl_token::processor::Processor::process(program_id, accounts, data)
+}
+
+fn associated_token_process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    spl_associated_token_account::processor::process_instruction(program_id, accounts, data)
+}
+
+// The memory an account info points into, laid out the way the runtime serializes an
+// account so `AccountInfo::resize` finds the original length and the length prefix
+struct Slot {
+    buffer: Vec<u64>,
+    executable: bool,
+}
+
+impl Slot {
+    const ORIGINAL_DATA_LEN: usize = 4;
+    const KEY: usize = 8;
+    const OWNER: usize = 40;
+    const LAMPORTS: usize = 72;
+    const DATA_LEN: usize = 80;
+    const DATA: usize = 88;
+
+    fn new(key: &Pubkey, account: &Account) -> Self {
+        let len = Self::DATA + account.data.len() + MAX_PERMITTED_DATA_INCREASE;
+        let mut slot = Self { buffer: vec![0; len.div_ceil(8)], executable: account.executable };
+
+        let bytes = slot.bytes_mut();
+        bytes[Self::ORIGINAL_DATA_LEN..Self::KEY].copy_from_slice(&(account.data.len() as u32).to_le_bytes());
+        bytes[Self::KEY..Self::OWNER].copy_from_slice(key.as_ref());
+        bytes[Self::OWNER..Self::LAMPORTS].copy_from_slice(account.owner.as_ref());
+        bytes[Self::LAMPORTS..Self::DATA_LEN].copy_from_slice(&account.lamports.to_le_bytes());
+        bytes[Self::DATA_LEN..Self::DATA].copy_from_slice(&(account.data.len() as u64).to_le_bytes());
+        bytes[Self::DATA..Self::DATA + account.data.len()].copy_from_slice(&account.data);
+
+        slot
+    }
+
+    fn bytes(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.buffer.as_ptr() as *const u8, self.buffer.len() * 8) }
+    }
+
+    fn bytes_mut(&mut self) -> &mut [u8] {
+        unsafe { std::slice::from_raw_parts_mut(self.buffer.as_mut_ptr() as *mut u8, self.buffer.len() * 8) }
+    }
+
+    fn read_u64(&self, offset: usize) -> u64 {
+        u64::from_le_bytes(self.bytes()[offset..offset + 8].try_into().unwrap())
+    }
+
+    // The info has to be dropped before the slot
+    fn info<'a>(&mut self, is_signer: bool, is_writable: bool) -> AccountInfo<'a> {
+        let executable = self.executable;
+        let data_len = self.read_u64(Self::DATA_LEN) as usize;
+        let base = self.buffer.as_mut_ptr() as *mut u8;
+
+        unsafe {
+            AccountInfo::new(
+                &*(base.add(Self::KEY) as *const Pubkey),
+                is_signer,
+                is_writable,
+                &mut *(base.add(Self::LAMPORTS) as *mut u64),
+                std::slice::from_raw_parts_mut(base.add(Self::DATA), data_len),
+                &*(base.add(Self::OWNER) as *const Pubkey),
+                executable,
+                0,
+            )
+        }
+    }
+
+    fn account(&self) -> Account {
+        let data_len = self.read_u64(Self::DATA_LEN) as usize;
+
+        Account {
+            lamports: self.read_u64(Self::LAMPORTS),
+            data: self.bytes()[Self::DATA..Self::DATA + data_len].to_vec(),
+            owner: Pubkey::try_from(&self.bytes()[Self::OWNER..Self::LAMPORTS]).unwrap(),
+            executable: self.executable,
+        }
+    }
+}
+
+pub struct Runtime {
+    accounts: HashMap<Pubkey, Account>,
+    programs: HashMap<Pubkey, Entrypoint>,
+    pub clock: Clock,
+    logs: Vec<String>,
+    events: Vec<Vec<u8>>,
+}
+
+impl Default for Runtime {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Runtime {
+    pub fn new() -> Self {
+        static STUBS: Once = Once::new();
+        STUBS.call_once(|| {
+            set_syscall_stubs(Box::new(Stubs));
+        });
+
+        let mut runtime = Self {
+            accounts: HashMap::new(),
+            programs: HashMap::new(),
+            clock: Clock { slot: 1, unix_timestamp: 1_700_000_000, ..Clock::default() },
+            logs: Vec::new(),
+            events: Vec::new(),
+        };
+
+        runtime.add_program(system_program::ID, system::process);
+        runtime.add_program(spl_token::ID, token_process);
+        runtime.add_program(spl_associated_token_account::ID, associated_token_process);
+        runtime.add_program(COMPUTE_BUDGET_PROGRAM_ID, noop);
+
+        runtime
+    }
+
+    pub fn add_program(&mut self, program_id: Pubkey, entrypoint: Entrypoint) {
+        self.programs.insert(program_id, entrypoint);
+    }
+
+    // Deploys the program behind the upgradeable loader, so its program data records
+    // `upgrade_authority`
+    pub fn add_upgradeable_program(&mut self, program_id: Pubkey, entrypoint: Entrypoint, upgrade_authority: Option<Pubkey>) {
+        self.add_program(program_id, entrypoint);
+
+        let programdata_address = bpf_loader_upgradeable::get_program_data_address(&program_id);
+
+        self.set_account(program_id, Account {
+            lamports: 1,
+            data: bincode::serialize(&UpgradeableLoaderState::Program { programdata_address }).unwrap(),
+            owner: bpf_loader_upgradeable::ID,
+            executable: true,
+        });
+
+        self.set_account(programdata_address, Account {
+            lamports: 1,
+            data: bincode::serialize(&UpgradeableLoaderState::ProgramData {
+                slot: 0,
+                upgrade_authority_address: upgrade_authority,
+            }).unwrap(),
+            owner: bpf_loader_upgradeable::ID,
+            executable: false,
+        });
+    }
+
+    pub fn set_account(&mut self, key: Pubkey, account: Account) {
+        self.accounts.insert(key, account);
+    }
+
+    pub fn account(&self, key: &Pubkey) -> Option<&Account> {
+        self.accounts.get(key)
+    }
+
+    pub fn lamports(&self, key: &Pubkey) -> u64 {
+        self.accounts.get(key).map_or(0, |account| account.lamports)
+    }
+
+    pub fn airdrop(&mut self, key: &Pubkey, lamports: u64) {
+        self.accounts.entry(*key).or_insert_with(|| Account { owner: system_program::ID, ..Account::default() })
+            .lamports += lamports;
+    }
+
+    pub fn minimum_balance(&self, data_len: usize) -> u64 {
+        Rent::default().minimum_balance(data_len)
+    }
+
+    pub fn create_mint(&mut self, mint: &Pubkey, authority: Option<&Pubkey>, decimals: u8) {
+        let mut data = vec![0; spl_token::state::Mint::LEN];
+
+        spl_token::state::Mint {
+            mint_authority: authority.copied().into(),
+            supply: 0,
+            decimals,
+            is_initialized: true,
+            freeze_authority: COption::None,
+        }.pack_into_slice(&mut data);
+
+        self.set_account(*mint, Account {
+            lamports: self.minimum_balance(data.len()),
+            data,
+            owner: spl_token::ID,
+            executable: false,
+        });
+    }
+
+    pub fn create_native_mint(&mut self) {
+        self.create_mint(&native_mint::ID, None, native_mint::DECIMALS);
+    }
+
+    // Wrapped SOL accounts hold their balance as lamports above the rent exempt reserve
+    pub fn create_token_account(&mut self, key: &Pubkey, mint: &Pubkey, owner: &Pubkey, amount: u64) {
+        let rent = self.minimum_balance(spl_token::state::Account::LEN);
+        let is_native = mint.eq(&native_mint::ID);
+        let mut data = vec![0; spl_token::state::Account::LEN];
+
+        spl_token::state::Account {
+            mint: *mint,
+            owner: *owner,
+            amount,
+            delegate: COption::None,
+            state: spl_token::state::AccountState::Initialized,
+            is_native: match is_native {
+                true => COption::Some(rent),
+                false => COption::None,
+            },
+            delegated_amount: 0,
+            close_authority: COption::None,
+        }.pack_into_slice(&mut data);
+
+        self.set_account(*key, Account {
+            lamports: rent + if is_native { amount } else { 0 },
+            data,
+            owner: spl_token::ID,
+            executable: false,
+        });
+
+        if !is_native {
+            let mint = self.accounts.get_mut(mint).expect("the mint has to exist");
+            let mut state = spl_token::state::Mint::unpack(&mint.data).unwrap();
+            state.supply += amount;
+            state.pack_into_slice(&mut mint.data);
+        }
+    }
+
+    pub fn create_associated_token_account(&mut self, owner: &Pubkey, mint: &Pubkey, amount: u64) -> Pubkey {
+        let key = get_associated_token_address(owner, mint);
+        self.create_token_account(&key, mint, owner, amount);
+        key
+    }
+
+    pub fn token_account(&self, key: &Pubkey) -> Option<spl_token::state::Account> {
+        self.accounts.get(key)
+            .filter(|account| account.owner.eq(&spl_token::ID))
+            .and_then(|account| spl_token::state::Account::unpack(&account.data).ok())
+    }
+
+    pub fn token_balance(&self, key: &Pubkey) -> u64 {
+        self.token_account(key).map_or(0, |account| account.amount)
+    }
+
+    // Deserializes an anchor account, skipping its discriminator
+    pub fn anchor_account<T: AnchorDeserialize + Discriminator>(&self, key: &Pubkey) -> Option<T> {
+        let data = &self.accounts.get(key)?.data;
+
+        data.strip_prefix(T::DISCRIMINATOR)
+            .and_then(|mut data| T::deserialize(&mut data).ok())
+    }
+
+    // The logs of the last transaction
+    pub fn logs(&self) -> &[String] {
+        &self.logs
+    }
+
+    // The events of type `T` emitted by the last transaction
+    pub fn events<T: AnchorDeserialize + Discriminator>(&self) -> Vec<T> {
+        self.events
+            .iter()
+            .filter_map(|event| event.strip_prefix(T::DISCRIMINATOR))
+            .map(|mut data| T::deserialize(&mut data).unwrap())
+            .collect()
+    }
+
+    pub fn process_transaction(&mut self, instructions: &[Instruction], signers: &[Pubkey]) -> Result<(), TransactionError> {
+        let snapshot = self.accounts.clone();
+        let result = self.run(instructions, signers);
+
+        if result.is_err() {
+            self.accounts = snapshot;
+        }
+
+        result
+    }
+
+    // Runs the transaction and discards its changes, its logs and events are kept
+    pub fn simulate_transaction(&mut self, instructions: &[Instruction], signers: &[Pubkey]) -> Result<(), TransactionError> {
+        let snapshot = self.accounts.clone();
+        let result = self.run(instructions, signers);
+        self.accounts = snapshot;
+        result
+    }
+
+    fn run(&mut self, instructions: &[Instruction], signers: &[Pubkey]) -> Result<(), TransactionError> {
+        CONTEXT.with(|context| *context.borrow_mut() = Some(Context {
+            programs: self.programs.clone(),
+            stack: Vec::new(),
+            return_data: None,
+            clock: self.clock.clone(),
+            logs: Vec::new(),
+            events: Vec::new(),
+        }));
+
+        let borrowed = instructions
+            .iter()
+            .map(|instruction| BorrowedInstruction {
+                program_id: &instruction.program_id,
+                accounts: instruction.accounts.iter().map(|meta| BorrowedAccountMeta {
+                    pubkey: &meta.pubkey,
+                    is_signer: meta.is_signer,
+                    is_writable: meta.is_writable,
+                }).collect(),
+                data: &instruction.data,
+            })
+            .collect::<Vec<_>>();
+
+        let sysvar_instructions = construct_instructions_data(&borrowed);
+
+        let result = instructions.iter().enumerate().try_for_each(|(index, instruction)| {
+            let mut sysvar_instructions = sysvar_instructions.clone();
+            store_current_index(&mut sysvar_instructions, index as u16);
+
+            self.execute(instruction, signers, sysvar_instructions)
+                .map_err(|error| TransactionError { index, error })
+        });
+
+        let context = CONTEXT.with(|context| context.borrow_mut().take()).unwrap();
+        self.logs = context.logs;
+        self.events = context.events;
+
+        // Accounts left without lamports are removed
+        self.accounts.retain(|_, account| account.lamports.gt(&0));
+
+        result
+    }
+
+    fn load(&self, key: &Pubkey, sysvar_instructions: &[u8]) -> Account {
+        if let Some(account) = self.accounts.get(key) {
+            return account.clone();
+        }
+
+        let sysvar = |data: Vec<u8>| Account { lamports: 1, data, owner: sysvar::ID, executable: false };
+
+        match *key {
+            key if key.eq(&sysvar::clock::ID) => sysvar(bincode::serialize(&self.clock).unwrap()),
+            key if key.eq(&sysvar::rent::ID) => sysvar(bincode::serialize(&Rent::default()).unwrap()),
+            key if key.eq(&sysvar::instructions::ID) => sysvar(sysvar_instructions.to_vec()),
+            key if self.programs.contains_key(&key) => Account {
+                lamports: 1,
+                data: Vec::new(),
+                owner: bpf_loader_upgradeable::ID,
+                executable: true,
+            },
+            _ => Account { owner: system_program::ID, ..Account::default() },
+        }
+    }
+
+    fn execute(&mut self, instruction: &Instruction, signers: &[Pubkey], sysvar_instructions: Vec<u8>) -> ProgramResult {
+        // The privileges of an account are the ones of all its metas
+        let mut keys: Vec<(Pubkey, bool, bool)> = Vec::new();
+
+        for meta in instruction.accounts.iter() {
+            match keys.iter_mut().find(|(key, _, _)| key.eq(&meta.pubkey)) {
+                Some((_, is_signer, is_writable)) => {
+                    *is_signer |= meta.is_signer;
+                    *is_writable |= meta.is_writable;
+                }
+                None => keys.push((meta.pubkey, meta.is_signer, meta.is_writable)),
+            }
+        }
+
+        if keys.iter().any(|(key, is_signer, _)| *is_signer && !signers.contains(key)) {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        let pre = keys.iter()
+            .map(|(key, _, _)| self.load(key, &sysvar_instructions))
+            .collect::<Vec<_>>();
+
+        let mut slots = keys.iter().zip(pre.iter())
+            .map(|((key, _, _), account)| Slot::new(key, account))
+            .collect::<Vec<_>>();
+
+        let result = {
+            let infos = slots.iter_mut().zip(keys.iter())
+                .map(|(slot, (_, is_signer, is_writable))| slot.info(*is_signer, *is_writable))
+                .collect::<Vec<_>>();
+
+            // Duplicate metas share the info, as they do on chain
+            let accounts = instruction.accounts.iter()
+                .map(|meta| infos[keys.iter().position(|(key, _, _)| key.eq(&meta.pubkey)).unwrap()].clone())
+                .collect::<Vec<_>>();
+
+            call(&instruction.program_id, &accounts, &instruction.data)
+        };
+
+        result?;
+
+        let post = slots.iter().map(Slot::account).collect::<Vec<_>>();
+
+        let sum = |accounts: &[Account]| accounts.iter().map(|account| account.lamports as u128).sum::<u128>();
+
+        if sum(&pre).ne(&sum(&post)) {
+            return Err(ProgramError::ArithmeticOverflow);
+        }
+
+        let rent = Rent::default();
+
+        for (((key, _, is_writable), pre), post) in keys.iter().zip(pre.iter()).zip(post) {
+            if !is_writable {
+                if pre.ne(&post) {
+                    return Err(ProgramError::InvalidAccountData);
+                }
+                continue;
+            }
+
+            // An account can only become rent paying if it already was one and does not grow
+            let rent_paying = |account: &Account| account.lamports.gt(&0) &&
+                !rent.is_exempt(account.lamports, account.data.len());
+
+            if rent_paying(&post) && !(rent_paying(pre) && post.data.len().le(&pre.data.len())) {
+                return Err(ProgramError::AccountNotRentExempt);
+            }
+
+            self.accounts.insert(*key, post);
+        }
+
+        Ok(())
+    }
+}
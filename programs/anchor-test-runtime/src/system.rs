@@ -0,0 +1,105 @@
+// The parts of the system program the programs use, with the checks the real one makes
+use anchor_lang::solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    system_instruction::{SystemError, SystemInstruction, MAX_PERMITTED_DATA_LENGTH},
+    system_program,
+};
+
+pub fn process(_program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let instruction: SystemInstruction = bincode::deserialize(data)
+        .map_err(|_| ProgramError::InvalidInstructionData)?;
+
+    match instruction {
+        SystemInstruction::CreateAccount { lamports, space, owner } => {
+            let [from, to, ..] = accounts else {
+                return Err(ProgramError::NotEnoughAccountKeys);
+            };
+
+            if to.lamports().gt(&0) {
+                return Err(ProgramError::Custom(SystemError::AccountAlreadyInUse as u32));
+            }
+
+            allocate(to, space)?;
+            assign(to, &owner)?;
+            transfer(from, to, lamports)
+        }
+        SystemInstruction::Transfer { lamports } => {
+            let [from, to, ..] = accounts else {
+                return Err(ProgramError::NotEnoughAccountKeys);
+            };
+
+            transfer(from, to, lamports)
+        }
+        SystemInstruction::Allocate { space } => {
+            let [account, ..] = accounts else {
+                return Err(ProgramError::NotEnoughAccountKeys);
+            };
+
+            allocate(account, space)
+        }
+        SystemInstruction::Assign { owner } => {
+            let [account, ..] = accounts else {
+                return Err(ProgramError::NotEnoughAccountKeys);
+            };
+
+            assign(account, &owner)
+        }
+        _ => Err(ProgramError::InvalidInstructionData),
+    }
+}
+
+fn transfer(from: &AccountInfo, to: &AccountInfo, lamports: u64) -> ProgramResult {
+    if !from.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !from.data_is_empty() || from.owner.ne(&system_program::ID) {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    if from.lamports().lt(&lamports) {
+        return Err(ProgramError::Custom(SystemError::ResultWithNegativeLamports as u32));
+    }
+
+    **from.try_borrow_mut_lamports()? -= lamports;
+    **to.try_borrow_mut_lamports()? += lamports;
+
+    Ok(())
+}
+
+fn allocate(account: &AccountInfo, space: u64) -> ProgramResult {
+    if !account.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !account.data_is_empty() || account.owner.ne(&system_program::ID) {
+        return Err(ProgramError::Custom(SystemError::AccountAlreadyInUse as u32));
+    }
+
+    if space.gt(&MAX_PERMITTED_DATA_LENGTH) {
+        return Err(ProgramError::Custom(SystemError::InvalidAccountDataLength as u32));
+    }
+
+    account.resize(space as usize)
+}
+
+fn assign(account: &AccountInfo, owner: &Pubkey) -> ProgramResult {
+    if account.owner.eq(owner) {
+        return Ok(());
+    }
+
+    if !account.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if account.owner.ne(&system_program::ID) {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    account.assign(owner);
+
+    Ok(())
+}
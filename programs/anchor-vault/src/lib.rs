@@ -9,8 +9,11 @@ pub mod anchor_vault {
     use super::*;
 
     pub fn deposit(ctx: Context<VaultAction>, amount:u64) -> Result<()> {
-        
-        require_eq!(ctx.accounts.vault.lamports(), 0, VaultError::VaultAlreadyExists);
+
+        // A vault that only holds the rent `withdraw` left in it counts as empty
+        let lamports = ctx.accounts.vault.lamports();
+
+        require!(lamports == 0 || lamports == Rent::get()?.minimum_balance(0), VaultError::VaultAlreadyExists);
 
         require_keys_eq!(*ctx.accounts.vault.owner, system_program::ID, VaultError::VaultAlreadyExists);
 
@@ -28,10 +31,21 @@ pub mod anchor_vault {
         Ok(())
     }
 
-    pub fn withdraw(ctx: Context<VaultAction>) -> Result<()> {
+    pub fn withdraw(ctx: Context<VaultAction>, leave_rent:bool) -> Result<()> {
 
         require_neq!(ctx.accounts.vault.lamports(), 0, VaultError::InvalidAmount);
 
+        // Optionally keep the vault rent exempt so it is not reaped
+        let retained = match leave_rent {
+            true => Rent::get()?.minimum_balance(0),
+            false => 0
+        };
+
+        let amount = ctx.accounts.vault.lamports().checked_sub(retained)
+            .ok_or(VaultError::InvalidAmount)?;
+
+        require_neq!(amount, 0, VaultError::InvalidAmount);
+
         let instruction = system_program::Transfer{
             from:ctx.accounts.vault.to_account_info(),
             to:ctx.accounts.signer.to_account_info(),
@@ -44,11 +58,18 @@ pub mod anchor_vault {
             new_with_signer(
                 ctx.accounts.system_program.to_account_info(), instruction,
                 &[&signer_seeds[..]]), 
-            ctx.accounts.vault.lamports()
+            amount
         )?;
 
         Ok(())
     }
+
+    // Withdraws everything and leaves the vault to be closed, the vault is a system
+    // owned account without data, so once drained it is removed by the runtime at the
+    // end of the transaction and a later deposit creates it from scratch
+    pub fn withdraw_all(ctx: Context<VaultAction>) -> Result<()> {
+        withdraw(ctx, false)
+    }
 }
 
 #[derive(Accounts)]
@@ -0,0 +1,108 @@
+use anchor_lang::{
+    prelude::Pubkey, solana_program::instruction::Instruction, system_program, InstructionData,
+    ToAccountMetas,
+};
+use anchor_test_runtime::{program_error, Runtime};
+use anchor_vault::VaultError;
+
+const LAMPORTS: u64 = 10_000_000_000;
+
+fn setup() -> (Runtime, Pubkey, Pubkey) {
+    let mut runtime = Runtime::new();
+    runtime.add_program(anchor_vault::ID, anchor_vault::entry);
+
+    let signer = Pubkey::new_unique();
+    runtime.airdrop(&signer, LAMPORTS);
+
+    let (vault, _) = Pubkey::find_program_address(&[b"vault", signer.as_ref()], &anchor_vault::ID);
+
+    (runtime, signer, vault)
+}
+
+fn instruction(signer: &Pubkey, vault: &Pubkey, data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: anchor_vault::ID,
+        accounts: anchor_vault::accounts::VaultAction {
+            signer: *signer,
+            vault: *vault,
+            system_program: system_program::ID,
+        }.to_account_metas(None),
+        data: data.data(),
+    }
+}
+
+fn deposit(runtime: &mut Runtime, signer: &Pubkey, vault: &Pubkey, amount: u64) {
+    runtime.process_transaction(
+        &[instruction(signer, vault, anchor_vault::instruction::Deposit { amount })],
+        &[*signer],
+    ).unwrap();
+}
+
+#[test]
+fn withdraw_leaving_rent_keeps_the_vault_alive() {
+    let (mut runtime, signer, vault) = setup();
+    deposit(&mut runtime, &signer, &vault, 1_000_000_000);
+
+    runtime.process_transaction(
+        &[instruction(&signer, &vault, anchor_vault::instruction::Withdraw { leave_rent: true })],
+        &[signer],
+    ).unwrap();
+
+    let rent = runtime.minimum_balance(0);
+    assert_eq!(runtime.lamports(&vault), rent);
+    assert_eq!(runtime.lamports(&signer), LAMPORTS - rent);
+
+    // Nothing is left above the rent to withdraw
+    let error = runtime.process_transaction(
+        &[instruction(&signer, &vault, anchor_vault::instruction::Withdraw { leave_rent: true })],
+        &[signer],
+    ).unwrap_err();
+    assert_eq!(error.error, program_error(VaultError::InvalidAmount));
+
+    // The retained rent does not keep the vault from being deposited into again
+    deposit(&mut runtime, &signer, &vault, 1_000_000_000);
+    assert_eq!(runtime.lamports(&vault), rent + 1_000_000_000);
+}
+
+#[test]
+fn deposit_into_a_funded_vault_is_rejected() {
+    let (mut runtime, signer, vault) = setup();
+    deposit(&mut runtime, &signer, &vault, 1_000_000_000);
+
+    let error = runtime.process_transaction(
+        &[instruction(&signer, &vault, anchor_vault::instruction::Deposit { amount: 1_000_000_000 })],
+        &[signer],
+    ).unwrap_err();
+
+    assert_eq!(error.error, program_error(VaultError::VaultAlreadyExists));
+}
+
+#[test]
+fn withdraw_all_closes_the_vault_for_a_fresh_deposit() {
+    let (mut runtime, signer, vault) = setup();
+    deposit(&mut runtime, &signer, &vault, 1_000_000_000);
+
+    runtime.process_transaction(
+        &[instruction(&signer, &vault, anchor_vault::instruction::WithdrawAll {})],
+        &[signer],
+    ).unwrap();
+
+    assert!(runtime.account(&vault).is_none());
+    assert_eq!(runtime.lamports(&signer), LAMPORTS);
+
+    deposit(&mut runtime, &signer, &vault, 2_000_000_000);
+    assert_eq!(runtime.lamports(&vault), 2_000_000_000);
+    assert_eq!(runtime.account(&vault).unwrap().owner, system_program::ID);
+}
+
+#[test]
+fn withdraw_all_from_an_empty_vault_is_rejected() {
+    let (mut runtime, signer, vault) = setup();
+
+    let error = runtime.process_transaction(
+        &[instruction(&signer, &vault, anchor_vault::instruction::WithdrawAll {})],
+        &[signer],
+    ).unwrap_err();
+
+    assert_eq!(error.error, program_error(VaultError::InvalidAmount));
+}
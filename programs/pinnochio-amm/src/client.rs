@@ -0,0 +1,133 @@
+use solana_pubkey::Pubkey;
+
+// Off-chain counterparts of the derivations the handlers check against
+
+// Returns the vault of `mint` held by the pool `config` and its bump, the vaults are
+// the associated token accounts of the config, derived the same way as in `Initialize`
+pub fn vault_address(config: &Pubkey, token_program: &Pubkey, mint: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[config.as_ref(), token_program.as_ref(), mint.as_ref()],
+        &Pubkey::new_from_array(pinocchio_associated_token_account::ID)
+    )
+}
+
+// Returns the `is_x` flag of a swap selling `input_mint`, the pool's `mint_x` is sold for
+// y when true. Any other mint is taken as `mint_y`, the handler rejects a wrong account
+pub fn swap_direction(input_mint: &Pubkey, mint_x: &Pubkey) -> bool {
+    input_mint.eq(mint_x)
+}
+
+// Returns the swap cooldown account of `user` in the pool `config` and its bump,
+// passed to swaps of pools with a cooldown as derived in `Swap::enforce_cooldown`
+pub fn cooldown_address(config: &Pubkey, user: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[b"cooldown".as_ref(), config.as_ref(), user.as_ref()],
+        &Pubkey::new_from_array(crate::ID)
+    )
+}
+
+// Builds the `Initialize` instruction data that follows the discriminator, in the packed
+// layout `InitializeInstructionData` reads. A pool without an `authority` can never be
+// administered, the program takes the zero authority as the renounced one so it is always
+// sent. The price check fields are only sent for a curated pool, which makes the data
+// 109 bytes or 119 with them
+#[allow(clippy::too_many_arguments)]
+pub fn encode_initialize(
+    seed: [u8; 8],
+    fee: u16,
+    mint_x: Pubkey,
+    mint_y: Pubkey,
+    lp_decimals: u8,
+    lp_freeze: bool,
+    curve_type: crate::CurveType,
+    authority: Option<Pubkey>,
+    initial_price: Option<(u64, u16)>,
+) -> Vec<u8> {
+    let mut data = Vec::with_capacity(core::mem::size_of::<crate::InitializeInstructionData>());
+
+    data.extend_from_slice(&seed);
+    data.extend_from_slice(&fee.to_le_bytes());
+    data.extend_from_slice(mint_x.as_ref());
+    data.extend_from_slice(mint_y.as_ref());
+    data.push(lp_decimals);
+    data.push(lp_freeze as u8);
+    data.push(curve_type as u8);
+    data.extend_from_slice(&authority.map_or([0; 32], |authority| authority.to_bytes()));
+
+    if let Some((price, tolerance_bps)) = initial_price {
+        data.extend_from_slice(&price.to_le_bytes());
+        data.extend_from_slice(&tolerance_bps.to_le_bytes());
+    }
+
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vault_address_is_the_associated_token_account_of_the_config() {
+        let config = Pubkey::new_from_array([1; 32]);
+        let mint = Pubkey::new_from_array([2; 32]);
+
+        let token_program: Pubkey = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA".parse().unwrap();
+        let associated_token_program: Pubkey = "ATokenGPvbdGVxr1b2hRZbpiLjZKB7G3fGhg5sdUz3Z8vq".parse().unwrap();
+
+        let expected = Pubkey::find_program_address(
+            &[config.as_ref(), token_program.as_ref(), mint.as_ref()],
+            &associated_token_program,
+        );
+
+        assert_eq!(vault_address(&config, &token_program, &mint), expected);
+    }
+
+    #[test]
+    fn swap_direction_sells_x_for_mint_x() {
+        let mint_x = Pubkey::new_from_array([1; 32]);
+        let mint_y = Pubkey::new_from_array([2; 32]);
+
+        assert!(swap_direction(&mint_x, &mint_x));
+        assert!(!swap_direction(&mint_y, &mint_x));
+    }
+
+    #[test]
+    fn encode_initialize_round_trips() {
+        let mint_x = Pubkey::new_from_array([1; 32]);
+        let mint_y = Pubkey::new_from_array([2; 32]);
+        let authority = Pubkey::new_from_array([3; 32]);
+
+        let data = encode_initialize([7; 8], 30, mint_x, mint_y, 6, true,
+            crate::CurveType::ConstantSum, Some(authority), Some((5, 100)));
+
+        assert_eq!(data.len(), 119);
+
+        let parsed = crate::InitializeInstructionData::try_from(data.as_slice()).unwrap();
+
+        // Copied out since the struct is packed
+        let (seed, fee, parsed_x, parsed_y) = (parsed.seed, parsed.fee, parsed.mint_x, parsed.mint_y);
+        let (lp_decimals, lp_freeze, curve_type) = (parsed.lp_decimals, parsed.lp_freeze, parsed.curve_type);
+        let (parsed_authority, price, tolerance_bps) = (parsed.authority, parsed.initial_price, parsed.price_tolerance_bps);
+
+        assert_eq!((seed, fee), ([7; 8], 30));
+        assert_eq!((parsed_x, parsed_y), (mint_x.to_bytes(), mint_y.to_bytes()));
+        assert_eq!((lp_decimals, lp_freeze, curve_type), (6, 1, crate::CurveType::ConstantSum as u8));
+        assert_eq!(parsed_authority, authority.to_bytes());
+        assert_eq!((price, tolerance_bps), (5, 100));
+    }
+
+    #[test]
+    fn encode_initialize_without_an_authority_renounces_it() {
+        let data = encode_initialize([0; 8], 30, Pubkey::new_from_array([1; 32]), Pubkey::new_from_array([2; 32]),
+            6, false, crate::CurveType::ConstantProduct, None, None);
+
+        assert_eq!(data.len(), 109);
+
+        let parsed = crate::InitializeInstructionData::try_from(data.as_slice()).unwrap();
+
+        let (authority, price, tolerance_bps) = (parsed.authority, parsed.initial_price, parsed.price_tolerance_bps);
+
+        assert_eq!(authority, [0; 32]);
+        assert_eq!((price, tolerance_bps), (0, 0));
+    }
+}
@@ -0,0 +1,358 @@
+use constant_product_curve::{
+    ConstantProduct,
+    LiquidityPair
+};
+use pinocchio::program_error::ProgramError;
+
+use crate::{math::{checked_mul_u128, mul_div_ceil, mul_div_floor}, AmmError, CurveType};
+
+// All the constant product math used by the handlers goes through here so that
+// every instruction reads the reserves and maps the curve errors the same way
+
+// Returns the x and y required to mint `amount` of liquidity against the reserves
+#[inline(always)]
+pub fn deposit_amounts(
+    reserve_x: u64,
+    reserve_y: u64,
+    supply: u64,
+    amount: u64,
+    precision: u32,
+) -> Result<(u64, u64), ProgramError> {
+    // Checked before the curve divides by them
+    if supply.eq(&0) || reserve_x.eq(&0) || reserve_y.eq(&0) {
+        return Err(AmmError::CurveError.into());
+    }
+
+    let amounts = ConstantProduct::xy_deposit_amounts_from_l(
+        reserve_x,
+        reserve_y,
+        supply,
+        amount,
+        precision,
+    )
+    .map_err(|_| AmmError::CurveError)?;
+
+    // The curve works at `precision` and can ask for less than the share of the reserves
+    // the liquidity is worth, the amounts are raised to that share rounded up so a deposit
+    // never mints liquidity it does not back
+    let x = amounts.x.max(mul_div_ceil(reserve_x as u128, amount as u128, supply as u128)?);
+    let y = amounts.y.max(mul_div_ceil(reserve_y as u128, amount as u128, supply as u128)?);
+
+    Ok((x, y))
+}
+
+// Returns the x and y released by burning `amount` of liquidity against the reserves
+#[inline(always)]
+pub fn withdraw_amounts(
+    reserve_x: u64,
+    reserve_y: u64,
+    supply: u64,
+    amount: u64,
+    precision: u32,
+) -> Result<(u64, u64), ProgramError> {
+    if amount.gt(&supply) {
+        return Err(AmmError::InsufficientLiquidity.into());
+    }
+
+    // The last of the liquidity takes whatever is left
+    if amount.eq(&supply) {
+        return Ok((reserve_x, reserve_y));
+    }
+
+    let amounts = ConstantProduct::xy_withdraw_amounts_from_l(
+        reserve_x,
+        reserve_y,
+        supply,
+        amount,
+        precision,
+    )
+    .map_err(|_| AmmError::CurveError)?;
+
+    // Likewise a withdraw never takes more than the share its liquidity is worth, rounded down
+    let x = amounts.x.min(mul_div_floor(reserve_x as u128, amount as u128, supply as u128)?);
+    let y = amounts.y.min(mul_div_floor(reserve_y as u128, amount as u128, supply as u128)?);
+
+    Ok((x, y))
+}
+
+// Returns the output and the fee for swapping `amount_in` of x (or y when `is_x` is false),
+// the fee is denominated in the input token
+#[inline(always)]
+pub fn swap(
+    reserve_x: u64,
+    reserve_y: u64,
+    is_x: bool,
+    amount_in: u64,
+    fee_bps: u16,
+    curve_type: CurveType,
+) -> Result<(u64, u64), ProgramError> {
+    if curve_type.eq(&CurveType::ConstantSum) {
+        return constant_sum_swap(reserve_x, reserve_y, is_x, amount_in, fee_bps);
+    }
+
+    // Initialize curve from the reserves, the LP supply does not affect swaps
+    let mut curve = ConstantProduct::init(reserve_x, reserve_y, reserve_x, fee_bps, None)
+        .map_err(|_| AmmError::CurveError)?;
+
+    let pair = if is_x { LiquidityPair::X } else { LiquidityPair::Y };
+
+    let res = curve
+        .swap(pair, amount_in, 0)
+        .map_err(|_| AmmError::CurveError)?;
+
+    Ok((res.withdraw, res.fee))
+}
+
+// Constant sum prices raw amounts 1:1, which only holds for mints of the same decimals.
+// The decimals are the ones cached in the config at initialize, so no mint is read
+#[inline(always)]
+pub fn check_curve_decimals(
+    curve_type: CurveType,
+    mint_x_decimals: u8,
+    mint_y_decimals: u8,
+) -> Result<(), ProgramError> {
+    if curve_type.eq(&CurveType::ConstantSum) && mint_x_decimals.ne(&mint_y_decimals) {
+        return Err(AmmError::InvalidCurve.into());
+    }
+
+    Ok(())
+}
+
+// Fails if a swap left the pool with less than it started with under its curve,
+// the product of the reserves for constant product and their sum for constant sum.
+// A last line of defence against a miscomputed output, the reserves are read from the vaults
+#[inline(always)]
+pub fn check_invariant(
+    before: (u64, u64),
+    after: (u64, u64),
+    curve_type: CurveType,
+) -> Result<(), ProgramError> {
+    // The sum of two u64 cannot overflow a u128
+    let invariant = |(x, y): (u64, u64)| match curve_type {
+        CurveType::ConstantProduct => checked_mul_u128(x as u128, y as u128),
+        CurveType::ConstantSum => Ok(x as u128 + y as u128),
+    };
+
+    if invariant(after)?.lt(&invariant(before)?) {
+        return Err(AmmError::InvariantViolated.into());
+    }
+
+    Ok(())
+}
+
+// Returns the output for swapping `amount_in` of x (or y when `is_x` is false)
+#[inline(always)]
+pub fn swap_out(
+    reserve_x: u64,
+    reserve_y: u64,
+    is_x: bool,
+    amount_in: u64,
+    fee_bps: u16,
+    curve_type: CurveType,
+) -> Result<u64, ProgramError> {
+    swap(reserve_x, reserve_y, is_x, amount_in, fee_bps, curve_type).map(|(withdraw, _)| withdraw)
+}
+
+// Returns how far, in basis points, the output falls short of what `amount_in` would
+// fetch at the spot price, the fee counts towards the impact
+#[inline(always)]
+pub fn price_impact_bps(
+    reserve_x: u64,
+    reserve_y: u64,
+    is_x: bool,
+    amount_in: u64,
+    amount_out: u64,
+    curve_type: CurveType,
+) -> Result<u64, ProgramError> {
+    let (reserve_in, reserve_out) = match is_x {
+        true => (reserve_x, reserve_y),
+        false => (reserve_y, reserve_x),
+    };
+
+    // The constant sum curve always prices 1:1
+    let spot_out = match curve_type {
+        CurveType::ConstantProduct => (amount_in as u128).checked_mul(reserve_out as u128)
+            .ok_or(ProgramError::ArithmeticOverflow)?
+            .checked_div(reserve_in as u128)
+            .ok_or(AmmError::CurveError)?,
+        CurveType::ConstantSum => amount_in as u128,
+    };
+
+    if spot_out.le(&(amount_out as u128)) {
+        return Ok(0);
+    }
+
+    let impact = (spot_out - amount_out as u128).checked_mul(10_000)
+        .ok_or(ProgramError::ArithmeticOverflow)?
+        .checked_div(spot_out)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+
+    // Bounded by 10_000 since the output is below the spot output
+    Ok(impact as u64)
+}
+
+// x + y = k, the output is the input less the fee as long as the other side can cover it,
+// both mints share their decimals so the raw amounts are priced 1:1
+#[inline(always)]
+fn constant_sum_swap(
+    reserve_x: u64,
+    reserve_y: u64,
+    is_x: bool,
+    amount_in: u64,
+    fee_bps: u16,
+) -> Result<(u64, u64), ProgramError> {
+    // Rounded up so splitting a swap into dust cannot dodge the fee
+    let fee = mul_div_ceil(amount_in as u128, fee_bps as u128, 10_000)?;
+
+    // Bounded by the amount since the fee is below 10_000 basis points
+    let withdraw = amount_in - fee;
+
+    let reserve_out = match is_x {
+        true => reserve_y,
+        false => reserve_x,
+    };
+
+    if withdraw.gt(&reserve_out) {
+        return Err(AmmError::InsufficientReserves.into());
+    }
+
+    Ok((withdraw, fee))
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn constant_sum_requires_matching_decimals() {
+        assert!(check_curve_decimals(CurveType::ConstantSum, 6, 6).is_ok());
+        assert!(check_curve_decimals(CurveType::ConstantSum, 6, 9).is_err());
+        assert!(check_curve_decimals(CurveType::ConstantProduct, 6, 9).is_ok());
+    }
+
+    #[test]
+    fn deposit_amounts_are_pro_rata() {
+        assert_eq!(deposit_amounts(1_000, 2_000, 1_000, 100, 1_000_000), Ok((100, 200)));
+
+        // At a precision of 10 the curve asks for 300 of the 333.3 a third of the pool is worth
+        assert_eq!(deposit_amounts(1_000, 1_000, 3, 1, 10), Ok((334, 334)));
+    }
+
+    #[test]
+    fn deposit_amounts_rejects_empty_reserves() {
+        assert_eq!(deposit_amounts(0, 1_000, 1_000, 100, 1_000_000), Err(AmmError::CurveError.into()));
+        assert_eq!(deposit_amounts(1_000, 0, 1_000, 100, 1_000_000), Err(AmmError::CurveError.into()));
+        assert_eq!(deposit_amounts(1_000, 1_000, 0, 100, 1_000_000), Err(AmmError::CurveError.into()));
+    }
+
+    #[test]
+    fn withdraw_amounts_are_pro_rata() {
+        assert_eq!(withdraw_amounts(1_000, 2_000, 1_000, 100, 1_000_000), Ok((100, 200)));
+
+        // At a precision of 10 the curve pays out 400 of the 333.3 a third of the pool is worth
+        assert_eq!(withdraw_amounts(1_000, 1_000, 3, 1, 10), Ok((333, 333)));
+
+        // The last of the liquidity takes the whole pool
+        assert_eq!(withdraw_amounts(1_000, 2_000, 1_000, 1_000, 1_000_000), Ok((1_000, 2_000)));
+
+        assert_eq!(
+            withdraw_amounts(1_000, 2_000, 1_000, 1_001, 1_000_000),
+            Err(AmmError::InsufficientLiquidity.into())
+        );
+    }
+
+    proptest! {
+        // Whatever a swap pays out, the input it takes keeps the pool's invariant from falling
+        #[test]
+        fn constant_product_swap_keeps_invariant(
+            reserve_x in 1_000u64..1_000_000_000_000,
+            reserve_y in 1_000u64..1_000_000_000_000,
+            amount_in in 1u64..1_000_000_000_000,
+            fee_bps in 0u16..1_000,
+            is_x: bool,
+        ) {
+            if let Ok((out, _)) = swap(reserve_x, reserve_y, is_x, amount_in, fee_bps, CurveType::ConstantProduct) {
+                let after = match is_x {
+                    true => (reserve_x + amount_in, reserve_y - out),
+                    false => (reserve_x - out, reserve_y + amount_in),
+                };
+
+                prop_assert!(check_invariant((reserve_x, reserve_y), after, CurveType::ConstantProduct).is_ok());
+            }
+        }
+
+        #[test]
+        fn constant_sum_swap_keeps_invariant(
+            reserve_x in 0u64..1_000_000_000_000,
+            reserve_y in 0u64..1_000_000_000_000,
+            amount_in in 1u64..1_000_000_000_000,
+            fee_bps in 0u16..1_000,
+            is_x: bool,
+        ) {
+            if let Ok((out, fee)) = swap(reserve_x, reserve_y, is_x, amount_in, fee_bps, CurveType::ConstantSum) {
+                prop_assert_eq!(out + fee, amount_in);
+
+                let after = match is_x {
+                    true => (reserve_x + amount_in, reserve_y - out),
+                    false => (reserve_x - out, reserve_y + amount_in),
+                };
+
+                prop_assert!(check_invariant((reserve_x, reserve_y), after, CurveType::ConstantSum).is_ok());
+            }
+        }
+
+        // A swap never pays out the whole of the other side
+        #[test]
+        fn constant_product_swap_leaves_reserves(
+            reserve_x in 1_000u64..1_000_000_000_000,
+            reserve_y in 1_000u64..1_000_000_000_000,
+            amount_in in 1u64..u64::MAX / 2,
+            fee_bps in 0u16..1_000,
+            is_x: bool,
+        ) {
+            if let Ok((out, _)) = swap(reserve_x, reserve_y, is_x, amount_in, fee_bps, CurveType::ConstantProduct) {
+                prop_assert!(out < if is_x { reserve_y } else { reserve_x });
+            }
+        }
+
+        // Withdrawing the liquidity a deposit just minted never returns more than it put in
+        #[test]
+        fn withdraw_after_deposit_returns_at_most_the_deposit(
+            reserve_x in 1u64..1_000_000_000_000,
+            reserve_y in 1u64..1_000_000_000_000,
+            supply in 1u64..1_000_000_000_000,
+            amount in 1u64..1_000_000_000_000,
+            decimals in 0u32..=9,
+        ) {
+            let precision = 10u32.pow(decimals);
+
+            let Ok((x, y)) = deposit_amounts(reserve_x, reserve_y, supply, amount, precision) else {
+                return Ok(());
+            };
+
+            // A deposit that would overflow the vaults is never made
+            let (Some(after_x), Some(after_y)) = (reserve_x.checked_add(x), reserve_y.checked_add(y)) else {
+                return Ok(());
+            };
+
+            if let Ok((withdrawn_x, withdrawn_y)) = withdraw_amounts(after_x, after_y, supply + amount, amount, precision) {
+                prop_assert!(withdrawn_x <= x);
+                prop_assert!(withdrawn_y <= y);
+            }
+        }
+
+        #[test]
+        fn check_invariant_rejects_a_drop(
+            x in 1u64..u32::MAX as u64,
+            y in 1u64..u32::MAX as u64,
+            taken in 1u64..1_000,
+        ) {
+            let after = (x.saturating_sub(taken), y);
+
+            prop_assert!(check_invariant((x, y), after, CurveType::ConstantProduct).is_err());
+            prop_assert!(check_invariant((x, y), after, CurveType::ConstantSum).is_err());
+        }
+    }
+}
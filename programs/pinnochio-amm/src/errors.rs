@@ -0,0 +1,101 @@
+use pinocchio::program_error::ProgramError;
+
+// The codes are part of the program interface, new variants go at the end
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AmmError {
+    // The input is too small relative to the reserves to produce any output
+    DustSwap,
+    // The output is below the minimum or the input above the maximum the user accepted
+    SlippageExceeded,
+    // The pool state does not permit the operation
+    PoolDisabled,
+    // The vault does not match the one derived from the config
+    InvalidVault,
+    // The LP mint does not match the one derived from the config
+    InvalidMintLp,
+    // A token account has the wrong mint or owner
+    InvalidTokenAccount,
+    // A mint account does not match the pool mints
+    InvalidMint,
+    // The config account has the wrong size
+    InvalidConfig,
+    // The state is not a valid pool state
+    InvalidState,
+    // The fee is out of the basis point range
+    InvalidFee,
+    // The address could not be derived from the seeds
+    InvalidPda,
+    // The stored bump is not the canonical one
+    NonCanonicalBump,
+    // The deadline of the instruction has passed
+    Expired,
+    // The curve rejected the reserves or the amounts
+    CurveError,
+    // The minted liquidity is not backed by the deposited amounts
+    UnbackedLiquidity,
+    // The liquidity requested is more than the pool holds or can lock
+    InsufficientLiquidity,
+    // The pool still holds liquidity
+    PoolNotEmpty,
+    // The pool has not been seeded with a two sided deposit
+    PoolNotSeeded,
+    // The flash swap is not paired with a matching repay
+    InvalidFlashSwap,
+    // The output vault holds less than the computed withdraw
+    InsufficientReserves,
+    // The LP mint was initialized without the config as freeze authority
+    LpNotFreezable,
+    // A vault did not receive exactly the deposited amount
+    VaultBalanceMismatch,
+    // The account holds one of the pool mints
+    PoolMint,
+    // The curve does not fit the pair
+    InvalidCurve,
+    // The swap moves the price further than the user accepted
+    PriceImpactExceeded,
+    // The first deposit does not match the price set for the pool
+    InitialPriceMismatch,
+    // The authority was left out instead of explicitly renounced
+    MissingAuthority,
+    // The LP supply and the vault balances disagree on whether the pool holds liquidity
+    InconsistentReserves,
+    // The swap left the pool with a lower curve invariant than before
+    InvariantViolated,
+    // The user swapped in this pool fewer than the cooldown slots ago
+    SwapCooldown,
+    // The LP mint passed to initialize is not the one derived from the config
+    InvalidLpMintPda,
+    // The config passed to initialize is not the one derived from the seed and mints
+    InvalidConfigPda,
+    // The pool cannot move from its current state to the requested one
+    InvalidStateTransition,
+    // A flash swap on the pool has not been repaid yet
+    FlashSwapActive,
+    // A mint of the pool has more decimals than the curve precision can hold
+    UnsupportedDecimals,
+}
+
+impl From<AmmError> for ProgramError {
+    fn from(error: AmmError) -> Self {
+        ProgramError::Custom(error as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_map_to_their_custom_codes() {
+        for (error, code) in [
+            (AmmError::DustSwap, 0),
+            (AmmError::SlippageExceeded, 1),
+            (AmmError::PoolDisabled, 2),
+            (AmmError::InvalidVault, 3),
+            (AmmError::Expired, 12),
+        ] {
+            assert_eq!(ProgramError::from(error), ProgramError::Custom(code));
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use pinocchio::{log::sol_log_data, pubkey::Pubkey};
+
+// Admin changes to a pool, logged so indexers can follow them without diffing the config.
+// The codes are part of the program interface, new events go at the end
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AdminEvent {
+    // old authority, new authority, the zero pubkey meaning renounced
+    AuthorityTransferred,
+    // old bps, new bps as u16
+    ReferralFeeSet,
+    // old and new snapshot as fees_x, fees_y and timestamp
+    FeesSnapshotted,
+    // old deposit and withdraw bps, new deposit and withdraw bps as u16
+    LiquidityFeesSet,
+    // old slots, new slots as u64
+    SwapCooldownSet,
+    // old state, new state as u8
+    StateUpdated,
+}
+
+// The largest event is the tag, the config and two pubkeys
+const MAX_EVENT_LEN: usize = 1 + 32 * 3;
+
+// Writes an event as the tag, the config address and then the fields in order,
+// integers little endian. The layout is fixed so clients decode it by offset
+pub struct EventWriter {
+    buffer: [u8; MAX_EVENT_LEN],
+    len: usize,
+}
+
+impl EventWriter {
+    #[inline(always)]
+    pub fn new(event: AdminEvent, config: &Pubkey) -> Self {
+        let mut writer = Self { buffer: [0; MAX_EVENT_LEN], len: 0 };
+        writer.write(&[event as u8]);
+        writer.write(config);
+        writer
+    }
+
+    // The events are sized at compile time, so running past the buffer is a bug
+    #[inline(always)]
+    fn write(&mut self, bytes: &[u8]) {
+        self.buffer[self.len..self.len + bytes.len()].copy_from_slice(bytes);
+        self.len += bytes.len();
+    }
+
+    #[inline(always)]
+    pub fn u8(mut self, value: u8) -> Self {
+        self.write(&[value]);
+        self
+    }
+
+    #[inline(always)]
+    pub fn u16(mut self, value: u16) -> Self {
+        self.write(&value.to_le_bytes());
+        self
+    }
+
+    #[inline(always)]
+    pub fn u64(mut self, value: u64) -> Self {
+        self.write(&value.to_le_bytes());
+        self
+    }
+
+    #[inline(always)]
+    pub fn i64(mut self, value: i64) -> Self {
+        self.write(&value.to_le_bytes());
+        self
+    }
+
+    #[inline(always)]
+    pub fn pubkey(mut self, value: &Pubkey) -> Self {
+        self.write(value);
+        self
+    }
+
+    // The event as it is logged
+    #[inline(always)]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buffer[..self.len]
+    }
+
+    #[inline(always)]
+    pub fn emit(self) {
+        sol_log_data(&[self.as_bytes()]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_fee_change_decodes_by_offset() {
+        let config = [7; 32];
+
+        let event = EventWriter::new(AdminEvent::LiquidityFeesSet, &config)
+            .u16(10)
+            .u16(20)
+            .u16(30)
+            .u16(50);
+
+        let bytes = event.as_bytes();
+
+        assert_eq!(bytes.len(), 1 + 32 + 2 * 4);
+        assert_eq!(bytes[0], AdminEvent::LiquidityFeesSet as u8);
+        assert_eq!(&bytes[1..33], &config);
+
+        let bps = |offset: usize| u16::from_le_bytes([bytes[offset], bytes[offset + 1]]);
+
+        // Old deposit and withdraw bps, then the new ones
+        assert_eq!([bps(33), bps(35), bps(37), bps(39)], [10, 20, 30, 50]);
+    }
+
+    #[test]
+    fn an_authority_transfer_fills_the_buffer() {
+        let event = EventWriter::new(AdminEvent::AuthorityTransferred, &[1; 32])
+            .pubkey(&[2; 32])
+            .pubkey(&[0; 32]);
+
+        assert_eq!(event.as_bytes().len(), MAX_EVENT_LEN);
+        assert_eq!(&event.as_bytes()[65..], &[0; 32]);
+    }
+}
@@ -0,0 +1,335 @@
+use pinocchio::{
+    ProgramResult,
+    account_info::AccountInfo,
+    instruction::{
+        Seed,
+        Signer
+    },
+    program_error::ProgramError
+};
+use pinocchio_token::{
+    instructions::{
+        Burn,
+        CloseAccount,
+        Transfer
+    },
+    state::{
+        Mint,
+        TokenAccount
+    }
+};
+
+use crate::{check_token_program, create_canonical_address, state::Config, AmmError};
+
+pub struct ClosePoolAccounts<'a> {
+    pub authority: &'a AccountInfo,
+    pub mint_lp: &'a AccountInfo,
+    pub vault_x: &'a AccountInfo,
+    pub vault_y: &'a AccountInfo,
+    pub config: &'a AccountInfo,
+    pub token_program: &'a AccountInfo,
+    // Only required once the pool was funded, see `LockedLiquidity`
+    pub locked: Option<LockedLiquidity<'a>>,
+}
+
+// The minimum liquidity locked on the first deposit can never be withdrawn, so a funded
+// pool is closed by burning it and paying the reserves it backs out to the authority
+pub struct LockedLiquidity<'a> {
+    pub lp_lock: &'a AccountInfo,
+    pub authority_x_ata: &'a AccountInfo,
+    pub authority_y_ata: &'a AccountInfo,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for ClosePoolAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        let [authority, mint_lp, vault_x, vault_y,
+            config, token_program, remaining @ ..] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        let locked = match remaining {
+            [] => None,
+            [lp_lock, authority_x_ata, authority_y_ata] => Some(LockedLiquidity {
+                lp_lock,
+                authority_x_ata,
+                authority_y_ata,
+            }),
+            _ => return Err(ProgramError::NotEnoughAccountKeys),
+        };
+
+        check_token_program(token_program.key())?;
+
+        Ok(Self { authority, mint_lp, vault_x, vault_y, config, token_program, locked })
+    }
+}
+
+pub struct ClosePool<'a> {
+    pub accounts: ClosePoolAccounts<'a>,
+}
+
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for ClosePool<'a> {
+    type Error = ProgramError;
+
+    fn try_from((data, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
+        if !data.is_empty() {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let accounts = ClosePoolAccounts::try_from(accounts)?;
+        Ok(Self { accounts })
+    }
+}
+
+impl<'a> ClosePool<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &4;
+
+    // This function is only called once and unconditionally
+    // It is separated for readability
+    #[inline(always)]
+    pub fn check(&mut self) -> ProgramResult {
+        let config = crate::state::Config::load(&self.accounts.config)?;
+
+        // Pools without an authority can not be closed
+        config.check_admin(self.accounts.authority)?;
+
+        // Derive vault PDAs and compare
+        let vault_x = create_canonical_address(
+            [
+                self.accounts.config.key(),
+                self.accounts.token_program.key(),
+                config.mint_x(),
+                config.vault_x_bump()
+            ],
+            &pinocchio_associated_token_account::ID,
+        )?;
+
+        if vault_x.ne(self.accounts.vault_x.key()) {
+            return Err(AmmError::InvalidVault.into());
+        }
+
+        let vault_y = create_canonical_address(
+            [
+                self.accounts.config.key(),
+                self.accounts.token_program.key(),
+                config.mint_y(),
+                config.vault_y_bump()
+            ],
+            &pinocchio_associated_token_account::ID,
+        )?;
+
+        if vault_y.ne(self.accounts.vault_y.key()) {
+            return Err(AmmError::InvalidVault.into());
+        }
+
+        // Derive LP mint PDA
+        let mint_lp = create_canonical_address(
+            [
+                b"mint_lp".as_ref(),
+                self.accounts.config.key(),
+                config.mint_lp_bump()
+            ],
+            &crate::ID
+        )?;
+
+        if mint_lp.ne(self.accounts.mint_lp.key()) {
+            return Err(AmmError::InvalidMintLp.into());
+        }
+
+        // Deserialize accounts
+        let mint_lp = unsafe { Mint::from_account_info_unchecked(self.accounts.mint_lp)? };
+        let vault_x = unsafe { TokenAccount::from_account_info_unchecked(self.accounts.vault_x)? };
+        let vault_y = unsafe { TokenAccount::from_account_info_unchecked(self.accounts.vault_y)? };
+
+        let Some(locked) = &self.accounts.locked else {
+            return Self::check_closable(&config, mint_lp.supply(), vault_x.amount(), vault_y.amount(), None);
+        };
+
+        // The lock has to be the config's LP account
+        let lp_lock = TokenAccount::from_account_info(locked.lp_lock)?;
+
+        if lp_lock.mint().ne(self.accounts.mint_lp.key()) || lp_lock.owner().ne(self.accounts.config.key()) {
+            return Err(AmmError::InvalidTokenAccount.into());
+        }
+
+        Self::check_closable(&config, mint_lp.supply(), vault_x.amount(), vault_y.amount(), Some(lp_lock.amount()))?;
+
+        // The reserves backing the lock go to the authority
+        let authority_x_ata = TokenAccount::from_account_info(locked.authority_x_ata)?;
+
+        if authority_x_ata.mint().ne(config.mint_x()) || authority_x_ata.owner().ne(self.accounts.authority.key()) {
+            return Err(AmmError::InvalidTokenAccount.into());
+        }
+
+        let authority_y_ata = TokenAccount::from_account_info(locked.authority_y_ata)?;
+
+        if authority_y_ata.mint().ne(config.mint_y()) || authority_y_ata.owner().ne(self.accounts.authority.key()) {
+            return Err(AmmError::InvalidTokenAccount.into());
+        }
+
+        Ok(())
+    }
+
+    // A pool can be closed once the locked liquidity is all that is left of it, a pool that
+    // was never funded has no lock and has to hold nothing. The reserves are out on a flash
+    // swap until its repay, so such a pool can not be closed either
+    #[inline(always)]
+    pub fn check_closable(
+        config: &Config,
+        supply: u64,
+        reserve_x: u64,
+        reserve_y: u64,
+        locked_lp: Option<u64>,
+    ) -> ProgramResult {
+        if config.flash_swap_active() {
+            return Err(AmmError::FlashSwapActive.into());
+        }
+
+        let empty = match locked_lp {
+            Some(locked_lp) => supply.eq(&locked_lp),
+            None => supply.eq(&0) && reserve_x.eq(&0) && reserve_y.eq(&0),
+        };
+
+        if !empty {
+            return Err(AmmError::PoolNotEmpty.into());
+        }
+
+        Ok(())
+    }
+
+    // This function is only called once and unconditionally
+    // It is separated for readability
+    //
+    // The LP mint is left in place since legacy token mints can not be closed
+    #[inline(always)]
+    pub fn close_vaults_and_config(&mut self) -> ProgramResult {
+        let config = crate::state::Config::load(&self.accounts.config)?;
+
+        let config_seeds = [
+            Seed::from(b"config"),
+            Seed::from(config.seed()),
+            Seed::from(config.mint_x()),
+            Seed::from(config.mint_y()),
+            Seed::from(config.config_bump()),
+        ];
+
+        let signer_seeds = [Signer::from(&config_seeds)];
+
+        if let Some(locked) = &self.accounts.locked {
+            let locked_lp = unsafe { TokenAccount::from_account_info_unchecked(locked.lp_lock)? }.amount();
+
+            Burn {
+                account: locked.lp_lock,
+                mint: self.accounts.mint_lp,
+                authority: self.accounts.config,
+                amount: locked_lp,
+            }
+            .invoke_signed(&signer_seeds)?;
+
+            CloseAccount {
+                account: locked.lp_lock,
+                destination: self.accounts.authority,
+                authority: self.accounts.config,
+            }
+            .invoke_signed(&signer_seeds)?;
+
+            for (vault, to) in [
+                (self.accounts.vault_x, locked.authority_x_ata),
+                (self.accounts.vault_y, locked.authority_y_ata),
+            ] {
+                let amount = unsafe { TokenAccount::from_account_info_unchecked(vault)? }.amount();
+
+                Transfer {
+                    from: vault,
+                    to,
+                    authority: self.accounts.config,
+                    amount,
+                }
+                .invoke_signed(&signer_seeds)?;
+            }
+        }
+
+        // Close the vaults, reclaiming the rent to the authority
+        CloseAccount {
+            account: self.accounts.vault_x,
+            destination: self.accounts.authority,
+            authority: self.accounts.config,
+        }
+        .invoke_signed(&signer_seeds)?;
+
+        CloseAccount {
+            account: self.accounts.vault_y,
+            destination: self.accounts.authority,
+            authority: self.accounts.config,
+        }
+        .invoke_signed(&signer_seeds)?;
+
+        core::mem::drop(config); // We borrow the config mutably below
+
+        // Close the config account
+        let lamports = self.accounts.config.lamports();
+
+        let mut authority_lamports = self.accounts.authority.try_borrow_mut_lamports()?;
+        *authority_lamports = authority_lamports.checked_add(lamports)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        core::mem::drop(authority_lamports);
+
+        self.accounts.config.close()
+    }
+
+    pub fn process(&mut self) -> ProgramResult {
+        self.check()?;
+        self.close_vaults_and_config()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(data: &mut [u8; Config::LEN]) -> &mut Config {
+        let config = unsafe { Config::from_bytes_unchecked_mut(data) };
+
+        config.set_inner(crate::AmmState::Initialized, [0; 8], [1; 32], [2; 32], [3; 32], 30,
+            [255], [255], [255], [255], 6, 6, 6, 0, crate::CurveType::ConstantProduct, 0, 0).unwrap();
+
+        config
+    }
+
+    #[test]
+    fn an_empty_pool_can_be_closed() {
+        let mut data = [0; Config::LEN];
+        let config = config(&mut data);
+
+        // Never funded
+        assert!(ClosePool::check_closable(config, 0, 0, 0, None).is_ok());
+
+        // Funded and fully withdrawn down to the locked liquidity, which backs the reserves left
+        assert!(ClosePool::check_closable(config, 1_000, 7, 9, Some(1_000)).is_ok());
+    }
+
+    #[test]
+    fn a_pool_with_liquidity_can_not_be_closed() {
+        let mut data = [0; Config::LEN];
+        let config = config(&mut data);
+
+        // Funded pools have to hand in the lock
+        assert_eq!(ClosePool::check_closable(config, 1_000, 7, 9, None), Err(AmmError::PoolNotEmpty.into()));
+
+        // Donated reserves of a pool that was never funded
+        assert_eq!(ClosePool::check_closable(config, 0, 5, 0, None), Err(AmmError::PoolNotEmpty.into()));
+
+        // LP held outside the lock
+        assert_eq!(ClosePool::check_closable(config, 1_500, 7, 9, Some(1_000)), Err(AmmError::PoolNotEmpty.into()));
+    }
+
+    #[test]
+    fn a_pool_in_a_flash_swap_can_not_be_closed() {
+        let mut data = [0; Config::LEN];
+        let config = config(&mut data);
+        config.open_flash_swap(0, 0);
+
+        assert_eq!(ClosePool::check_closable(config, 0, 0, 0, None), Err(AmmError::FlashSwapActive.into()));
+    }
+}
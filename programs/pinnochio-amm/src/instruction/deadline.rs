@@ -0,0 +1,81 @@
+use pinocchio::{
+    program_error::ProgramError,
+    sysvars::{
+        Sysvar,
+        clock::Clock
+    }
+};
+
+use crate::AmmError;
+
+#[repr(u8)]
+pub enum DeadlineKind {
+    Timestamp = 0u8,
+    Slot = 1u8,
+}
+
+impl TryFrom<Option<&u8>> for DeadlineKind {
+    type Error = ProgramError;
+
+    // The kind byte is optional and trails the instruction data, older clients
+    // that omit it keep the timestamp semantics
+    fn try_from(kind: Option<&u8>) -> Result<Self, Self::Error> {
+        match kind {
+            None | Some(0) => Ok(DeadlineKind::Timestamp),
+            Some(1) => Ok(DeadlineKind::Slot),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
+// Rejects the instruction once the deadline, expressed as either a unix
+// timestamp or an absolute slot, has been reached
+#[inline(always)]
+pub fn check_deadline(expiration: i64, kind: DeadlineKind) -> Result<(), ProgramError> {
+    let clock = Clock::get()?;
+
+    let now = match kind {
+        DeadlineKind::Timestamp => clock.unix_timestamp,
+        DeadlineKind::Slot => i64::try_from(clock.slot)
+            .map_err(|_| ProgramError::ArithmeticOverflow)?,
+    };
+
+    check_expiration(expiration, now)
+}
+
+// Kept free of the clock sysvar so the comparison can be exercised directly
+#[inline(always)]
+pub fn check_expiration(expiration: i64, now: i64) -> Result<(), ProgramError> {
+    if now.ge(&expiration) {
+        return Err(AmmError::Expired.into());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_expiration_rejects_reached_deadlines() {
+        assert!(check_expiration(10, 9).is_ok());
+        assert_eq!(check_expiration(10, 10), Err(AmmError::Expired.into()));
+        assert_eq!(check_expiration(10, 11), Err(AmmError::Expired.into()));
+    }
+
+    #[test]
+    fn check_deadline_rejects_a_passed_deadline() {
+        // Fails whether or not a clock is available
+        assert!(check_deadline(i64::MIN, DeadlineKind::Timestamp).is_err());
+        assert!(check_deadline(i64::MIN, DeadlineKind::Slot).is_err());
+    }
+
+    #[test]
+    fn deadline_kind_defaults_to_timestamp() {
+        assert!(matches!(DeadlineKind::try_from(None), Ok(DeadlineKind::Timestamp)));
+        assert!(matches!(DeadlineKind::try_from(Some(&0)), Ok(DeadlineKind::Timestamp)));
+        assert!(matches!(DeadlineKind::try_from(Some(&1)), Ok(DeadlineKind::Slot)));
+        assert!(DeadlineKind::try_from(Some(&2)).is_err());
+    }
+}
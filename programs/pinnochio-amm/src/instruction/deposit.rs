@@ -1,18 +1,14 @@
-use constant_product_curve::ConstantProduct;
 use pinocchio::{
     ProgramResult, account_info::AccountInfo, instruction::{
         Seed, 
         Signer
-    }, program_error::ProgramError, 
-    pubkey::{
-        create_program_address
-    }, sysvars::{
-        Sysvar, clock::Clock
-    }
+    }, log::sol_log_data, program_error::ProgramError, pubkey::Pubkey
 };
 use pinocchio_token::state::{Mint, TokenAccount};
 use bytemuck::{Pod, Zeroable};
 
+use crate::{check_deadline, check_token_program, check_treasury, create_canonical_address, liquidity_fee, sync_native_vault, AmmError, DeadlineKind};
+
 pub struct DepositAccounts<'a> {
     pub user: &'a AccountInfo,
     pub mint_lp: &'a AccountInfo,
@@ -23,6 +19,11 @@ pub struct DepositAccounts<'a> {
     pub user_lp_ata: &'a AccountInfo,
     pub config: &'a AccountInfo,
     pub token_program: &'a AccountInfo,
+    // Only read on the first deposit, receives the locked liquidity. It keeps its
+    // position whenever a treasury follows, the same lock can be passed every time
+    pub lp_lock: Option<&'a AccountInfo>,
+    // Only required while the pool charges a deposit fee, always after the lp_lock
+    pub treasury: Option<&'a AccountInfo>,
 }
  
 impl<'a> TryFrom<&'a [AccountInfo]> for DepositAccounts<'a> {
@@ -31,11 +32,22 @@ impl<'a> TryFrom<&'a [AccountInfo]> for DepositAccounts<'a> {
   fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
     let [user, mint_lp, vault_x, 
         vault_y, user_x_ata, user_y_ata, 
-        user_lp_ata, config, token_program]  =
+        user_lp_ata, config, token_program, remaining @ ..]  =
         accounts else {
         return Err(ProgramError::NotEnoughAccountKeys);
     };
 
+    check_token_program(token_program.key())?;
+
+    let (lp_lock, treasury) = (remaining.first(), remaining.get(1));
+
+    // The locked liquidity would otherwise land in the treasury or the fee in the lock
+    if let (Some(lp_lock), Some(treasury)) = (lp_lock, treasury) {
+        if lp_lock.key().eq(treasury.key()) {
+            return Err(AmmError::InvalidTokenAccount.into());
+        }
+    }
+
     Ok(Self {
         user,
         mint_lp,
@@ -46,6 +58,8 @@ impl<'a> TryFrom<&'a [AccountInfo]> for DepositAccounts<'a> {
         user_lp_ata,
         config,
         token_program,
+        lp_lock,
+        treasury,
     })
   }
 }
@@ -56,19 +70,38 @@ pub struct DepositInstructionData {
     pub max_x: u64,
     pub max_y: u64,
     pub expiration: i64,
+    // The least LP the user accepts, after the deposit fee and the liquidity locked on the first deposit
+    pub min_lp_out: u64,
+}
+ 
+impl DepositInstructionData {
+    const LEN: usize = core::mem::size_of::<DepositInstructionData>();
+
+    // Clients from before `min_lp_out` send the data without it
+    const LEGACY_LEN: usize = Self::LEN - core::mem::size_of::<u64>();
+
+    // Reads the fields and the optional deadline kind byte that trails them. The legacy
+    // layout leaves out `min_lp_out`, which reads as zero and accepts any LP amount
+    #[inline(always)]
+    pub fn decode(data: &[u8]) -> Result<(Self, Option<&u8>), ProgramError> {
+        let fields_len = match data.len() {
+            len if len == Self::LEN || len == Self::LEN + 1 => Self::LEN,
+            len if len == Self::LEGACY_LEN || len == Self::LEGACY_LEN + 1 => Self::LEGACY_LEN,
+            _ => return Err(ProgramError::InvalidInstructionData),
+        };
+
+        let mut raw = [0u8; Self::LEN];
+        raw[..fields_len].copy_from_slice(&data[..fields_len]);
+
+        Ok((bytemuck::pod_read_unaligned::<DepositInstructionData>(&raw), data.get(fields_len)))
+    }
 }
  
 impl<'a> TryFrom<&[u8]> for DepositInstructionData {
     type Error = ProgramError;
  
     fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
-        if data.len() != core::mem::size_of::<Self>() {
-            return Err(ProgramError::InvalidInstructionData);
-        }
-
-        let instruction_data = bytemuck::pod_read_unaligned::
-            <DepositInstructionData>(data);
-
+        let (instruction_data, kind) = Self::decode(data)?;
 
         // Check if values are  > 0
         if instruction_data.amount.eq(&0) || instruction_data.max_x.eq(&0) ||
@@ -77,15 +110,61 @@ impl<'a> TryFrom<&[u8]> for DepositInstructionData {
         }
 
         // Check if expired
-        let clock = Clock::get()?;
-        if clock.unix_timestamp.ge(&instruction_data.expiration) {
-            return Err(ProgramError::InvalidArgument);
-        }
+        check_deadline(instruction_data.expiration, DeadlineKind::try_from(kind)?)?;
 
         Ok(instruction_data)
     }
 }
 
+// Checks that x / y is within `tolerance_bps` of `price`, a fixed point number scaled by `Config::PRICE_SCALE`
+#[inline(always)]
+fn check_initial_price(x: u64, y: u64, price: u64, tolerance_bps: u16) -> ProgramResult {
+    let expected_x = (y as u128).checked_mul(price as u128)
+        .ok_or(ProgramError::ArithmeticOverflow)?
+        / crate::state::Config::PRICE_SCALE as u128;
+
+    let deviation = (x as u128).abs_diff(expected_x);
+
+    // The expected amount is below 2^98 after the scale, so neither product overflows
+    if deviation * 10_000 > expected_x * tolerance_bps as u128 {
+        return Err(AmmError::InitialPriceMismatch.into());
+    }
+
+    Ok(())
+}
+
+// A pool with liquidity outstanding has to hold both sides, or it would be priced against
+// a zero. An empty pool may hold tokens donated to its vaults, the first deposit absorbs
+// them and they go to the first LP
+#[inline(always)]
+fn check_reserves(supply: u64, reserve_x: u64, reserve_y: u64) -> ProgramResult {
+    if supply.gt(&0) && (reserve_x.eq(&0) || reserve_y.eq(&0)) {
+        return Err(AmmError::InconsistentReserves.into());
+    }
+
+    Ok(())
+}
+
+// The vaults have to hold the pool mints on behalf of the config
+#[inline(always)]
+fn check_vault(vault_mint: &Pubkey, vault_owner: &Pubkey, mint: &Pubkey, config: &Pubkey) -> ProgramResult {
+    if vault_mint.ne(mint) || vault_owner.ne(config) {
+        return Err(AmmError::InvalidVault.into());
+    }
+
+    Ok(())
+}
+
+// Fails unless the vaults went from `before` to `after` by exactly the deposited amounts
+#[inline(always)]
+fn check_vault_deltas(before: (u64, u64), after: (u64, u64), (x, y): (u64, u64)) -> ProgramResult {
+    if after.0.checked_sub(before.0).ne(&Some(x)) || after.1.checked_sub(before.1).ne(&Some(y)) {
+        return Err(AmmError::VaultBalanceMismatch.into());
+    }
+
+    Ok(())
+}
+
 pub struct Deposit<'a> {
     pub accounts: DepositAccounts<'a>,
     pub instruction_data: DepositInstructionData,
@@ -109,45 +188,88 @@ impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for Deposit<'a> {
 impl<'a> Deposit<'a> {
     pub const DISCRIMINATOR: &'a u8 = &1;
 
+    // LP tokens permanently locked on the first deposit
+    pub const MINIMUM_LIQUIDITY: u64 = 1_000;
+
+    // Returns the x and y taken to mint `amount` of liquidity and the part of it that is locked.
+    // The first deposit prices the pool, so it takes the max amounts whole and has to cover the
+    // locked liquidity. Later ones take the share of the reserves the liquidity is worth and
+    // leave the rest of the max amounts with the user
+    #[inline(always)]
+    pub fn amounts(
+        supply: u64,
+        (reserve_x, reserve_y): (u64, u64),
+        amount: u64,
+        (max_x, max_y): (u64, u64),
+        precision: u32,
+    ) -> Result<(u64, u64, u64), ProgramError> {
+        let (x, y, locked) = match supply.eq(&0) {
+            true => {
+                if amount.le(&Self::MINIMUM_LIQUIDITY) {
+                    return Err(AmmError::InsufficientLiquidity.into());
+                }
+
+                (max_x, max_y, Self::MINIMUM_LIQUIDITY)
+            },
+            false => {
+                let (x, y) = crate::curve::deposit_amounts(reserve_x, reserve_y, supply, amount, precision)?;
+
+                (x, y, 0)
+            }
+        };
+
+        // Check for slippage
+        if !(x.le(&max_x) && y.le(&max_y)) {
+            return Err(AmmError::SlippageExceeded.into());
+        }
+
+        Ok((x, y, locked))
+    }
+
     // This function is only called once and unconditionally
     // It is separated for readability
     #[inline(always)] 
-    pub fn check(&mut self) -> Result<(u64, u64), ProgramError>{
+    pub fn check(&mut self) -> Result<(u64, u64, u64, u64), ProgramError>{
 
         // Get the config account
         let config = crate::state::Config::load(&self.accounts.config)?;
 
         // Check if the pool state permits deposits
         if !config.can_deposit() {
-            return Err(ProgramError::InvalidAccountData);
+            return Err(AmmError::PoolDisabled.into());
+        }
+
+        // The reserves are out on a flash swap until its repay
+        if config.flash_swap_active() {
+            return Err(AmmError::FlashSwapActive.into());
         }
 
-        let vault_x = create_program_address(
-            &[
+        let vault_x = create_canonical_address(
+            [
                 self.accounts.config.key(),
                 self.accounts.token_program.key(),
                 config.mint_x(),
                 config.vault_x_bump()
             ],
             &pinocchio_associated_token_account::ID,
-        ).map_err(|_| ProgramError::InvalidAccountData)?;
+        )?;
         
         if vault_x.ne(self.accounts.vault_x.key()) {
-            return Err(ProgramError::InvalidAccountData);
+            return Err(AmmError::InvalidVault.into());
         }
 
-        let vault_y = create_program_address(
-            &[
+        let vault_y = create_canonical_address(
+            [
                 self.accounts.config.key(),
                 self.accounts.token_program.key(),
                 config.mint_y(),
                 config.vault_y_bump()
             ],
             &pinocchio_associated_token_account::ID,
-        ).map_err(|_| ProgramError::InvalidAccountData)?;
+        )?;
 
         if vault_y.ne(self.accounts.vault_y.key()) {
-            return Err(ProgramError::InvalidAccountData);
+            return Err(AmmError::InvalidVault.into());
         }
 
         // Check mint derivation
@@ -158,13 +280,16 @@ impl<'a> Deposit<'a> {
         ];
 
         let mint_lp = 
-            create_program_address(&mint_lp_seeds, &crate::ID).
-            map_err(|_| ProgramError::InvalidAccountData)?;
+            create_canonical_address(mint_lp_seeds, &crate::ID)?;
 
         if mint_lp.ne(self.accounts.mint_lp.key()) {
-            return Err(ProgramError::InvalidAccountData);
+            return Err(AmmError::InvalidMintLp.into());
         }
-        
+
+        // The reserves are read after the sync so lamports sent to a wrapped SOL vault
+        // are in the price
+        sync_native_vault(self.accounts.vault_x)?;
+        sync_native_vault(self.accounts.vault_y)?;
 
         // Deserialize the token accounts
         let mint_lp = unsafe { 
@@ -173,37 +298,68 @@ impl<'a> Deposit<'a> {
             TokenAccount::from_account_info_unchecked(self.accounts.vault_x)? };
         let vault_y = unsafe { 
             TokenAccount::from_account_info_unchecked(self.accounts.vault_y)? };
+
+        check_vault(vault_x.mint(), vault_x.owner(), config.mint_x(), self.accounts.config.key())?;
+        check_vault(vault_y.mint(), vault_y.owner(), config.mint_y(), self.accounts.config.key())?;
         
-        // Grab the amounts to deposit
-        let (x, y) = match mint_lp.supply().eq(&0) && 
-            vault_x.amount().eq(&0) && vault_y.amount().eq(&0) {
-            true => (self.instruction_data.max_x, self.instruction_data.max_y),
-            false => {
-                let amounts = ConstantProduct::xy_deposit_amounts_from_l(
-                    vault_x.amount(),
-                    vault_y.amount(),
-                    mint_lp.supply(),
-                    self.instruction_data.amount,
-                    6,
-                )
-                .map_err(|_| ProgramError::InvalidArgument)?;
-        
-                (amounts.x, amounts.y)
+        check_reserves(mint_lp.supply(), vault_x.amount(), vault_y.amount())?;
+
+        // The first deposit sets the price, on top of the lock it is held to the initial
+        // price of curated pools
+        if mint_lp.supply().eq(&0) {
+            // The locked liquidity is held by an LP token account owned by the config
+            let lp_lock = self.accounts.lp_lock.ok_or(ProgramError::NotEnoughAccountKeys)?;
+            let lp_lock = TokenAccount::from_account_info(lp_lock)?;
+
+            if lp_lock.mint().ne(self.accounts.mint_lp.key()) ||
+                lp_lock.owner().ne(self.accounts.config.key()) {
+                return Err(AmmError::InvalidTokenAccount.into());
+            }
+
+            // Donated tokens are part of the reserves the pool opens with
+            if config.initial_price().ne(&0) {
+                check_initial_price(
+                    vault_x.amount().checked_add(self.instruction_data.max_x)
+                        .ok_or(ProgramError::ArithmeticOverflow)?,
+                    vault_y.amount().checked_add(self.instruction_data.max_y)
+                        .ok_or(ProgramError::ArithmeticOverflow)?,
+                    config.initial_price(),
+                    config.price_tolerance_bps(),
+                )?;
             }
+        }
+
+        // Grab the amounts to deposit
+        let (x, y, locked) = Self::amounts(
+            mint_lp.supply(),
+            (vault_x.amount(), vault_y.amount()),
+            self.instruction_data.amount,
+            (self.instruction_data.max_x, self.instruction_data.max_y),
+            config.precision()?,
+        )?;
+
+        // The deposit fee is skimmed from the LP the user would get
+        let fee = match check_treasury(
+            &config,
+            config.deposit_fee_bps(),
+            self.accounts.treasury,
+            self.accounts.mint_lp.key(),
+        )? {
+            Some(_) => liquidity_fee(self.instruction_data.amount - locked, config.deposit_fee_bps())?,
+            None => 0,
         };
 
-        // Check for slippage
-        if !(x.le(&self.instruction_data.max_x) && y.le(&self.instruction_data.max_y)) {
-            return Err(ProgramError::InvalidArgument);
+        if (self.instruction_data.amount - locked - fee).lt(&self.instruction_data.min_lp_out) {
+            return Err(AmmError::SlippageExceeded.into());
         }
 
-        Ok((x,y))
+        Ok((x, y, locked, fee))
     }
 
     // This function is only called once and unconditionally
     // It is separated for readability
     #[inline(always)] 
-    pub fn transfer_to_vault_and_mint_to_user(&mut self, x:u64, y:u64)->ProgramResult{
+    pub fn transfer_to_vault_and_mint_to_user(&mut self, x:u64, y:u64, locked:u64, fee:u64)->ProgramResult{
         // Get the config account
         let config = crate::state::Config::load(&self.accounts.config)?;
 
@@ -215,6 +371,10 @@ impl<'a> Deposit<'a> {
             Seed::from(config.mint_y()),
             Seed::from(config.config_bump())
         ];
+
+        // Snapshot the reserves so the transfers can be checked before minting
+        let vault_x_before = unsafe { TokenAccount::from_account_info_unchecked(self.accounts.vault_x)? }.amount();
+        let vault_y_before = unsafe { TokenAccount::from_account_info_unchecked(self.accounts.vault_y)? }.amount();
         
         // Transfer X tokens to the vault
         
@@ -234,19 +394,204 @@ impl<'a> Deposit<'a> {
             amount: y,
         }.invoke()?;
 
+        // Only mint once both vaults received exactly what was computed
+        let vault_x_after = unsafe { TokenAccount::from_account_info_unchecked(self.accounts.vault_x)? }.amount();
+        let vault_y_after = unsafe { TokenAccount::from_account_info_unchecked(self.accounts.vault_y)? }.amount();
+
+        check_vault_deltas((vault_x_before, vault_y_before), (vault_x_after, vault_y_after), (x, y))?;
+
+        // Lock the minimum liquidity on the first deposit
+        if locked.gt(&0) {
+            pinocchio_token::instructions::MintTo {
+                mint: self.accounts.mint_lp,
+                account: self.accounts.lp_lock.ok_or(ProgramError::NotEnoughAccountKeys)?,
+                mint_authority: self.accounts.config, 
+                amount: locked,
+            }.invoke_signed(&[Signer::from(&config_seeds)])?;
+        }
+
+        if fee.gt(&0) {
+            pinocchio_token::instructions::MintTo {
+                mint: self.accounts.mint_lp,
+                account: self.accounts.treasury.ok_or(ProgramError::NotEnoughAccountKeys)?,
+                mint_authority: self.accounts.config,
+                amount: fee,
+            }.invoke_signed(&[Signer::from(&config_seeds)])?;
+        }
+
         // Mint tokens to the user
         pinocchio_token::instructions::MintTo {
             mint: self.accounts.mint_lp,
             account: self.accounts.user_lp_ata,
             mint_authority: self.accounts.config, 
-            amount: self.instruction_data.amount,
+            amount: self.instruction_data.amount - locked - fee,
         }.invoke_signed(&[Signer::from(&config_seeds)])
     }
  
     pub fn process(&mut self) -> ProgramResult {
 
-        let (x, y) = self.check()?;
+        cu_trace!("deposit: check");
+        let (x, y, locked, fee) = self.check()?;
+
+        cu_trace!("deposit: transfer");
+        self.transfer_to_vault_and_mint_to_user(x, y, locked, fee)?;
+
+        cu_trace!("deposit: done");
+
+        // Log the amounts used and minted so clients can reconcile against max_x/max_y
+        sol_log_data(&[
+            &x.to_le_bytes(),
+            &y.to_le_bytes(),
+            &(self.instruction_data.amount - locked - fee).to_le_bytes(),
+        ]);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deposit_data(min_lp_out: Option<u64>, kind: Option<u8>) -> Vec<u8> {
+        let mut data = Vec::new();
+
+        for field in [1u64, 2, 3, 4] {
+            data.extend_from_slice(&field.to_le_bytes());
+        }
+
+        data.extend(min_lp_out.map(u64::to_le_bytes).into_iter().flatten());
+        data.extend(kind);
+
+        data
+    }
+
+    #[test]
+    fn min_lp_out_is_optional() {
+        for kind in [None, Some(1)] {
+            let data = deposit_data(Some(5), kind);
+            let (decoded, decoded_kind) = DepositInstructionData::decode(&data).unwrap();
+
+            assert_eq!((decoded.amount, decoded.expiration, decoded.min_lp_out), (1, 4, 5));
+            assert_eq!(decoded_kind, kind.as_ref());
+
+            // The 32 and 33 byte layouts of older clients
+            let data = deposit_data(None, kind);
+            let (decoded, decoded_kind) = DepositInstructionData::decode(&data).unwrap();
+
+            assert_eq!((decoded.amount, decoded.expiration, decoded.min_lp_out), (1, 4, 0));
+            assert_eq!(decoded_kind, kind.as_ref());
+        }
+
+        for len in [31, 34, 39, 42] {
+            assert!(DepositInstructionData::decode(&[0; 42][..len]).is_err());
+        }
+    }
+
+    #[test]
+    fn first_deposit_absorbs_donated_tokens() {
+        // Donated to one or both vaults before any liquidity was minted
+        assert!(check_reserves(0, 500, 0).is_ok());
+        assert!(check_reserves(0, 500, 700).is_ok());
+        assert!(check_reserves(0, 0, 0).is_ok());
+
+        // The first deposit of 1_000 / 1_000 into a pool priced 1:1 opens at 1_500 / 1_000
+        // after 500 x was donated, which the price check sees
+        let price = crate::state::Config::PRICE_SCALE;
+        assert!(check_initial_price(1_000, 1_000, price, 100).is_ok());
+        assert_eq!(check_initial_price(500 + 1_000, 1_000, price, 100), Err(AmmError::InitialPriceMismatch.into()));
+        assert!(check_initial_price(500 + 500, 1_000, price, 100).is_ok());
+    }
+
+    #[test]
+    fn drained_side_under_a_supply_is_rejected() {
+        assert_eq!(check_reserves(1_000, 0, 700), Err(AmmError::InconsistentReserves.into()));
+        assert_eq!(check_reserves(1_000, 500, 0), Err(AmmError::InconsistentReserves.into()));
+        assert!(check_reserves(1_000, 500, 700).is_ok());
+    }
+
+    #[test]
+    fn first_deposit_locks_the_minimum_liquidity() {
+        // The whole of the max amounts prices the pool, the user gets all but the lock
+        assert_eq!(
+            Deposit::amounts(0, (0, 0), 10_000, (5_000, 20_000), 1_000_000),
+            Ok((5_000, 20_000, Deposit::MINIMUM_LIQUIDITY))
+        );
 
-        self.transfer_to_vault_and_mint_to_user(x, y)
+        // A dust first deposit would leave nothing above the lock
+        for amount in [1, Deposit::MINIMUM_LIQUIDITY] {
+            assert_eq!(
+                Deposit::amounts(0, (0, 0), amount, (5_000, 20_000), 1_000_000),
+                Err(AmmError::InsufficientLiquidity.into())
+            );
+        }
+    }
+
+    #[test]
+    fn an_amount_the_max_amounts_do_not_back_is_rejected() {
+        // A tenth of a 1_000 / 2_000 pool costs 100 / 200
+        assert_eq!(Deposit::amounts(1_000, (1_000, 2_000), 100, (100, 200), 1_000_000), Ok((100, 200, 0)));
+
+        // Asking for more liquidity than the max amounts pay for
+        assert_eq!(
+            Deposit::amounts(1_000, (1_000, 2_000), 101, (100, 200), 1_000_000),
+            Err(AmmError::SlippageExceeded.into())
+        );
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn unused_max_amounts_stay_with_the_user() {
+        let (x, y, _) = Deposit::amounts(1_000, (1_000, 2_000), 100, (150, 300), 1_000_000).unwrap();
+
+        // What is logged as used, 50 x and 100 y of the allowance are never pulled
+        assert_eq!((x, y), (100, 200));
+        assert_eq!((150 - x, 300 - y), (50, 100));
+    }
+
+    #[test]
+    fn a_short_transfer_is_caught_before_minting() {
+        assert!(check_vault_deltas((1_000, 2_000), (1_100, 2_200), (100, 200)).is_ok());
+
+        // The y transfer landed 1 short
+        assert_eq!(
+            check_vault_deltas((1_000, 2_000), (1_100, 2_199), (100, 200)),
+            Err(AmmError::VaultBalanceMismatch.into())
+        );
+
+        // A vault that went down
+        assert_eq!(
+            check_vault_deltas((1_000, 2_000), (900, 2_200), (100, 200)),
+            Err(AmmError::VaultBalanceMismatch.into())
+        );
+    }
+
+    #[test]
+    fn a_9_and_6_decimal_pair_deposits_in_balance() {
+        let mut data = [0; crate::state::Config::LEN];
+        let config = unsafe { crate::state::Config::from_bytes_unchecked_mut(&mut data) };
+
+        config.set_inner(crate::AmmState::Initialized, [0; 8], [1; 32], [2; 32], [3; 32], 30,
+            [255], [255], [255], [255], 9, 6, 6, 0, crate::CurveType::ConstantProduct, 0, 0).unwrap();
+
+        // 1_000 x and 2_000 y, a third of a percent of the pool costs the same share of each side
+        let reserves = (1_000 * 10u64.pow(9), 2_000 * 10u64.pow(6));
+        let supply = 3_000_000_000;
+
+        let (x, y, _) = Deposit::amounts(supply, reserves, 10_000_000, (u64::MAX, u64::MAX), config.precision().unwrap()).unwrap();
+
+        assert_eq!((x, y), (3_333_333_334, 6_666_667));
+    }
+
+    #[test]
+    fn a_vault_of_another_mint_is_rejected() {
+        let (mint_x, mint_y, config) = ([2; 32], [3; 32], [4; 32]);
+
+        assert_eq!(check_vault(&mint_x, &config, &mint_x, &config), Ok(()));
+
+        // The y vault passed as the x vault
+        assert_eq!(check_vault(&mint_y, &config, &mint_x, &config), Err(AmmError::InvalidVault.into()));
+
+        // A token account of the right mint that the config does not own
+        assert_eq!(check_vault(&mint_x, &[5; 32], &mint_x, &config), Err(AmmError::InvalidVault.into()));
+    }
+}
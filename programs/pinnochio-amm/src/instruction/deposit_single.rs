@@ -0,0 +1,335 @@
+use pinocchio::{
+    ProgramResult,
+    account_info::AccountInfo,
+    instruction::{
+        Seed,
+        Signer
+    },
+    program_error::ProgramError,
+};
+use pinocchio_token::{
+    instructions::{
+        MintTo,
+        Transfer
+    },
+    state::TokenAccount
+};
+
+use crate::{
+    check_deadline, check_single_sided_pool, check_token_program, check_treasury, liquidity_fee,
+    math::mul_div_floor, AmmError, CurveType, DeadlineKind, SwapLeg
+};
+
+pub struct DepositSingleAccounts<'a> {
+    pub user: &'a AccountInfo,
+    pub mint_lp: &'a AccountInfo,
+    pub vault_x: &'a AccountInfo,
+    pub vault_y: &'a AccountInfo,
+    pub user_ata: &'a AccountInfo,
+    pub user_lp_ata: &'a AccountInfo,
+    // Writable, the fee of the swap leg is counted in it
+    pub config: &'a AccountInfo,
+    pub token_program: &'a AccountInfo,
+    // Only required while the pool charges a deposit fee
+    pub treasury: Option<&'a AccountInfo>,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for DepositSingleAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        let [user, mint_lp, vault_x, vault_y, user_ata,
+            user_lp_ata, config, token_program, remaining @ ..] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        check_token_program(token_program.key())?;
+
+        Ok(Self { user, mint_lp, vault_x, vault_y, user_ata, user_lp_ata, config, token_program,
+            treasury: remaining.first() })
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct DepositSingleInstructionData {
+    pub is_x: bool,
+    pub amount: u64,
+    pub min_lp_out: u64,
+    pub expiration: i64,
+}
+
+impl TryFrom<&[u8]> for DepositSingleInstructionData {
+    type Error = ProgramError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        // The deadline kind byte is optional
+        if data.len() != 25 && data.len() != 26 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let is_x = match data[0] {
+            0 => false,
+            1 => true,
+            _ => return Err(ProgramError::InvalidInstructionData),
+        };
+
+        // Safe because slices are exactly sized by the check above
+        let amount = u64::from_le_bytes(data[1..9].try_into().unwrap());
+        let min_lp_out = u64::from_le_bytes(data[9..17].try_into().unwrap());
+        let expiration = i64::from_le_bytes(data[17..25].try_into().unwrap());
+
+        if amount == 0 || min_lp_out == 0 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        // Expiration check
+        check_deadline(expiration, DeadlineKind::try_from(data.get(25))?)?;
+
+        Ok(Self { is_x, amount, min_lp_out, expiration })
+    }
+}
+
+/// Deposits a single side of the pair, the optimal part of it is swapped
+/// through the curve and the balanced result is deposited for LP.
+pub struct DepositSingle<'a> {
+    pub accounts: DepositSingleAccounts<'a>,
+    pub instruction_data: DepositSingleInstructionData,
+}
+
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for DepositSingle<'a> {
+    type Error = ProgramError;
+
+    fn try_from((data, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
+        let accounts = DepositSingleAccounts::try_from(accounts)?;
+        let instruction_data = DepositSingleInstructionData::try_from(data)?;
+        Ok(Self { accounts, instruction_data })
+    }
+}
+
+impl<'a> DepositSingle<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &8;
+
+    // Returns the part of `amount` to swap so that what is left and the swap
+    // output are in the ratio of the pool after the swap.
+    //
+    // With r = 1 - fee, the optimal swap is (sqrt(x^2 (1 + r)^2 + 4 r a x) - x (1 + r)) / 2r,
+    // it is computed as 2a / (sqrt((1 + r)^2 + 4 r a / x) + (1 + r)) to avoid the cancellation
+    #[inline(always)]
+    pub fn swap_amount(reserve_in: u64, amount: u64, fee: u16) -> Result<u64, ProgramError> {
+        const BPS: u128 = 10_000;
+        const SCALE_BITS: u32 = 16;
+
+        let fee = fee as u128;
+        let reserve_in = reserve_in as u128;
+        let amount = amount as u128;
+
+        // (1 + r) and 4r scaled by BPS
+        let one_plus_r = BPS.checked_mul(2).and_then(|v| v.checked_sub(fee))
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        let four_r = BPS.checked_sub(fee).and_then(|v| v.checked_mul(4 * BPS))
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+
+        // ((1 + r)^2 x + 4 r a) / x, scaled by 2^(2 * SCALE_BITS) before the square root
+        let inner = one_plus_r.checked_mul(one_plus_r)
+            .and_then(|v| v.checked_mul(reserve_in))
+            .and_then(|v| v.checked_add(four_r.checked_mul(amount)?))
+            .and_then(|v| v.checked_mul(1 << (2 * SCALE_BITS)))
+            .and_then(|v| v.checked_div(reserve_in))
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+
+        let denominator = Self::isqrt(inner)
+            .checked_add(one_plus_r << SCALE_BITS)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+
+        let swap_amount = amount.checked_mul(2 * BPS << SCALE_BITS)
+            .and_then(|v| v.checked_div(denominator))
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+
+        u64::try_from(swap_amount).map_err(|_| ProgramError::ArithmeticOverflow)
+    }
+
+    // Integer square root, rounded down
+    #[inline(always)]
+    fn isqrt(value: u128) -> u128 {
+        if value < 2 {
+            return value;
+        }
+
+        let mut x = value;
+        let mut y = value / 2 + 1;
+        while y < x {
+            x = y;
+            y = (x + value / x) / 2;
+        }
+
+        x
+    }
+
+    // Returns the swap leg and the liquidity minted, before the deposit fee, for depositing
+    // all of `amount` into reserves of `reserve_in` and `reserve_out` with `supply` LP outstanding
+    #[inline(always)]
+    pub fn amounts(
+        reserve_in: u64,
+        reserve_out: u64,
+        supply: u64,
+        amount: u64,
+        fee: u16,
+        curve_type: CurveType,
+    ) -> Result<(SwapLeg, u64), ProgramError> {
+        let leg = SwapLeg::new(reserve_in, reserve_out, amount, fee, curve_type)?;
+
+        // Deposit what is left of the input with the swap output at the post swap reserves
+        let deposit_in = amount.checked_sub(leg.swapped)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        let reserve_in = reserve_in.checked_add(leg.swapped)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        let reserve_out = reserve_out.checked_sub(leg.swap_out)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+
+        // The minted liquidity must be backed by both sides of the deposit
+        let l_in = mul_div_floor(deposit_in as u128, supply as u128, reserve_in as u128)?;
+        let l_out = mul_div_floor(leg.swap_out as u128, supply as u128, reserve_out as u128)?;
+
+        Ok((leg, l_in.min(l_out)))
+    }
+
+    // This function is only called once and unconditionally
+    // It is separated for readability
+    #[inline(always)]
+    pub fn check(&mut self) -> Result<(u64, u64), ProgramError> {
+        // Mutable for the fee the swap leg accrues
+        let mut config = crate::state::Config::load_mut(&self.accounts.config)?;
+
+        let (reserve_x, reserve_y, supply) = check_single_sided_pool(
+            &config,
+            self.accounts.config,
+            self.accounts.mint_lp,
+            self.accounts.vault_x,
+            self.accounts.vault_y,
+            self.accounts.token_program,
+        )?;
+
+        // Check that the user token account belongs to the user and holds the deposited mint
+        let input_mint = match self.instruction_data.is_x {
+            true => config.mint_x(),
+            false => config.mint_y(),
+        };
+
+        let user_ata = TokenAccount::from_account_info(self.accounts.user_ata)?;
+
+        if user_ata.mint().ne(input_mint) || user_ata.owner().ne(self.accounts.user.key()) {
+            return Err(AmmError::InvalidTokenAccount.into());
+        }
+
+        let (reserve_in, reserve_out) = match self.instruction_data.is_x {
+            true => (reserve_x, reserve_y),
+            false => (reserve_y, reserve_x),
+        };
+
+        // Swap the optimal part of the input to the other side
+        let (leg, lp) = Self::amounts(
+            reserve_in,
+            reserve_out,
+            supply,
+            self.instruction_data.amount,
+            config.fee(),
+            config.curve_type(),
+        )?;
+
+        leg.settle(&mut config, reserve_in, reserve_out, self.instruction_data.is_x)?;
+
+        // The deposit fee is skimmed from the LP the user would get
+        let fee = match check_treasury(
+            &config,
+            config.deposit_fee_bps(),
+            self.accounts.treasury,
+            self.accounts.mint_lp.key(),
+        )? {
+            Some(_) => liquidity_fee(lp, config.deposit_fee_bps())?,
+            None => 0,
+        };
+
+        // Slippage check
+        if (lp - fee).eq(&0) || (lp - fee).lt(&self.instruction_data.min_lp_out) {
+            return Err(AmmError::SlippageExceeded.into());
+        }
+
+        Ok((lp - fee, fee))
+    }
+
+    pub fn process(&mut self) -> ProgramResult {
+        let (lp, fee) = self.check()?;
+
+        let config = crate::state::Config::load(&self.accounts.config)?;
+
+        let config_seeds = [
+            Seed::from(b"config"),
+            Seed::from(config.seed()),
+            Seed::from(config.mint_x()),
+            Seed::from(config.mint_y()),
+            Seed::from(config.config_bump())
+        ];
+
+        let vault = match self.instruction_data.is_x {
+            true => self.accounts.vault_x,
+            false => self.accounts.vault_y,
+        };
+
+        // The swapped part stays in the pool, so the whole input goes to its vault
+        Transfer {
+            from: self.accounts.user_ata,
+            to: vault,
+            authority: self.accounts.user,
+            amount: self.instruction_data.amount,
+        }.invoke()?;
+
+        if fee.gt(&0) {
+            MintTo {
+                mint: self.accounts.mint_lp,
+                account: self.accounts.treasury.ok_or(ProgramError::NotEnoughAccountKeys)?,
+                mint_authority: self.accounts.config,
+                amount: fee,
+            }.invoke_signed(&[Signer::from(&config_seeds)])?;
+        }
+
+        MintTo {
+            mint: self.accounts.mint_lp,
+            account: self.accounts.user_lp_ata,
+            mint_authority: self.accounts.config,
+            amount: lp,
+        }.invoke_signed(&[Signer::from(&config_seeds)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn isqrt_rounds_down() {
+        for (value, root) in [(0, 0), (1, 1), (3, 1), (4, 2), (99, 9), (100, 10)] {
+            assert_eq!(DepositSingle::isqrt(value), root);
+        }
+
+        let max = u64::MAX as u128;
+        assert_eq!(DepositSingle::isqrt(max * max), max);
+        assert_eq!(DepositSingle::isqrt(max * max - 1), max - 1);
+    }
+
+    #[test]
+    fn single_sided_deposit_matches_the_two_sided_path() {
+        let (reserve, supply, amount) = (1_000_000, 1_000_000, 10_000);
+
+        let (leg, lp) = DepositSingle::amounts(reserve, reserve, supply, amount, 30, CurveType::ConstantProduct).unwrap();
+
+        // The rest of the input and the swap output mint the same LP as a two sided deposit
+        // of them into the pool the swap left
+        let deposit_in = amount - leg.swapped;
+        let reserves = (reserve + leg.swapped, reserve - leg.swap_out);
+
+        assert!(crate::Deposit::amounts(supply, reserves, lp, (deposit_in, leg.swap_out), 1_000_000).is_ok());
+
+        // Within the swap fee of the 4_987.6 a fee-less split of 10_000 into 1_000_000 / 1_000_000 mints
+        assert!((4_970..=4_988).contains(&lp), "{lp}");
+    }
+}
@@ -0,0 +1,266 @@
+use pinocchio::{
+    ProgramResult,
+    account_info::AccountInfo,
+    instruction::{
+        Seed,
+        Signer
+    },
+    program_error::ProgramError,
+    sysvars::instructions::Instructions
+};
+use pinocchio_token::{instructions::Transfer, state::TokenAccount};
+
+use crate::{check_token_program, create_canonical_address, AmmError, FlashSwapRepay, FlashSwapRepayInstructionData};
+
+pub struct FlashSwapAccounts<'a> {
+    pub user: &'a AccountInfo,
+    pub user_x_ata: &'a AccountInfo,
+    pub user_y_ata: &'a AccountInfo,
+    pub vault_x: &'a AccountInfo,
+    pub vault_y: &'a AccountInfo,
+    pub config: &'a AccountInfo,
+    pub token_program: &'a AccountInfo,
+    pub instructions: &'a AccountInfo,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for FlashSwapAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        let [user, user_x_ata, user_y_ata, vault_x, vault_y,
+            config, token_program, instructions] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        check_token_program(token_program.key())?;
+
+        Ok(Self { user, user_x_ata, user_y_ata, vault_x, vault_y, config, token_program, instructions })
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct FlashSwapInstructionData {
+    // The side repaid by the user, the other side is sent out first
+    pub is_x: bool,
+    pub amount_out: u64,
+}
+
+impl TryFrom<&[u8]> for FlashSwapInstructionData {
+    type Error = ProgramError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() != 9 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let is_x = match data[0] {
+            0 => false,
+            1 => true,
+            _ => return Err(ProgramError::InvalidInstructionData),
+        };
+
+        // Safe because slices are exactly sized by the check above
+        let amount_out = u64::from_le_bytes(data[1..9].try_into().unwrap());
+
+        if amount_out == 0 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        Ok(Self { is_x, amount_out })
+    }
+}
+
+/// Sends the output side of a swap to the user before the input is paid, the
+/// transaction must end with a `FlashSwapRepay` paying enough input to cover
+/// the output at the reserves seen here.
+pub struct FlashSwap<'a> {
+    pub accounts: FlashSwapAccounts<'a>,
+    pub instruction_data: FlashSwapInstructionData,
+}
+
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for FlashSwap<'a> {
+    type Error = ProgramError;
+
+    fn try_from((data, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
+        let accounts = FlashSwapAccounts::try_from(accounts)?;
+        let instruction_data = FlashSwapInstructionData::try_from(data)?;
+        Ok(Self { accounts, instruction_data })
+    }
+}
+
+impl<'a> FlashSwap<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &6;
+
+    // This function is only called once and unconditionally
+    // It is separated for readability
+    #[inline(always)]
+    pub fn check(&mut self) -> ProgramResult {
+        if !self.accounts.user.is_signer() {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // Load config and guard rails
+        let config = crate::state::Config::load(&self.accounts.config)?;
+        if !config.can_swap() {
+            return Err(AmmError::PoolDisabled.into());
+        }
+
+        // The reserves are out on a flash swap until its repay
+        if config.flash_swap_active() {
+            return Err(AmmError::FlashSwapActive.into());
+        }
+
+        // There is no cooldown account here to rate limit by, so such pools only take `Swap`
+        if config.swap_cooldown_slots().gt(&0) {
+            return Err(AmmError::SwapCooldown.into());
+        }
+
+        // Derive vault PDAs and compare
+        let vault_x = create_canonical_address(
+            [
+                self.accounts.config.key(),
+                self.accounts.token_program.key(),
+                config.mint_x(),
+                config.vault_x_bump()
+            ],
+            &pinocchio_associated_token_account::ID,
+        )?;
+
+        if vault_x.ne(self.accounts.vault_x.key()) {
+            return Err(AmmError::InvalidVault.into());
+        }
+
+        let vault_y = create_canonical_address(
+            [
+                self.accounts.config.key(),
+                self.accounts.token_program.key(),
+                config.mint_y(),
+                config.vault_y_bump()
+            ],
+            &pinocchio_associated_token_account::ID,
+        )?;
+
+        if vault_y.ne(self.accounts.vault_y.key()) {
+            return Err(AmmError::InvalidVault.into());
+        }
+
+        // Check that the user token accounts belong to the user and hold the pool mints
+        let user_x_ata = TokenAccount::from_account_info(self.accounts.user_x_ata)?;
+
+        if user_x_ata.mint().ne(config.mint_x()) || user_x_ata.owner().ne(self.accounts.user.key()) {
+            return Err(AmmError::InvalidTokenAccount.into());
+        }
+
+        let user_y_ata = TokenAccount::from_account_info(self.accounts.user_y_ata)?;
+
+        if user_y_ata.mint().ne(config.mint_y()) || user_y_ata.owner().ne(self.accounts.user.key()) {
+            return Err(AmmError::InvalidTokenAccount.into());
+        }
+
+        let instructions = Instructions::try_from(self.accounts.instructions)?;
+
+        // The flash swap has to be the first instruction in the transaction, this also
+        // keeps it from being reached through a CPI
+        if instructions.load_current_index().ne(&0) {
+            return Err(AmmError::InvalidFlashSwap.into());
+        }
+
+        let current_instruction = instructions.load_instruction_at(0)?;
+
+        if current_instruction.get_program_id().ne(&crate::ID) {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        // The repay has to be the last instruction in the transaction
+        let repay_instruction = instructions.load_instruction_at(
+            instructions.num_instructions().checked_sub(1)
+                .ok_or(AmmError::InvalidFlashSwap)?
+        )?;
+
+        if repay_instruction.get_program_id().ne(&crate::ID) {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let repay_instruction_data = match repay_instruction.get_instruction_data().split_first() {
+            Some((FlashSwapRepay::DISCRIMINATOR, data)) => FlashSwapRepayInstructionData::try_from(data)?,
+            _ => return Err(AmmError::InvalidFlashSwap.into()),
+        };
+
+        if repay_instruction_data.is_x.ne(&self.instruction_data.is_x) {
+            return Err(AmmError::InvalidFlashSwap.into());
+        }
+
+        // The repay has to pay into this pool from the user
+        let user_ata_index = match self.instruction_data.is_x {
+            true => FlashSwapRepay::USER_X_ATA_INDEX,
+            false => FlashSwapRepay::USER_Y_ATA_INDEX,
+        };
+
+        let user_ata = match self.instruction_data.is_x {
+            true => self.accounts.user_x_ata,
+            false => self.accounts.user_y_ata,
+        };
+
+        if repay_instruction.get_account_meta_at(user_ata_index)?.key.ne(user_ata.key()) ||
+            repay_instruction.get_account_meta_at(FlashSwapRepay::VAULT_X_INDEX)?.key.ne(self.accounts.vault_x.key()) ||
+            repay_instruction.get_account_meta_at(FlashSwapRepay::VAULT_Y_INDEX)?.key.ne(self.accounts.vault_y.key()) ||
+            repay_instruction.get_account_meta_at(FlashSwapRepay::CONFIG_INDEX)?.key.ne(self.accounts.config.key()) {
+            return Err(AmmError::InvalidFlashSwap.into());
+        }
+
+        // The repaid input has to cover the output at the current reserves, fee included
+        let vault_x = unsafe { TokenAccount::from_account_info_unchecked(self.accounts.vault_x)? };
+        let vault_y = unsafe { TokenAccount::from_account_info_unchecked(self.accounts.vault_y)? };
+
+        let withdraw = crate::curve::swap_out(
+            vault_x.amount(),
+            vault_y.amount(),
+            self.instruction_data.is_x,
+            repay_instruction_data.amount_in,
+            config.fee(),
+            config.curve_type(),
+        )?;
+
+        if withdraw.lt(&self.instruction_data.amount_out) {
+            return Err(AmmError::SlippageExceeded.into());
+        }
+
+        Ok(())
+    }
+
+    pub fn process(&mut self) -> ProgramResult {
+        self.check()?;
+
+        // Lock the pool until the repay, which checks the invariant against these reserves
+        let reserve_x = unsafe { TokenAccount::from_account_info_unchecked(self.accounts.vault_x)? }.amount();
+        let reserve_y = unsafe { TokenAccount::from_account_info_unchecked(self.accounts.vault_y)? }.amount();
+
+        crate::state::Config::load_mut(self.accounts.config)?.open_flash_swap(reserve_x, reserve_y);
+
+        let config = crate::state::Config::load(&self.accounts.config)?;
+
+        // Build signer seeds for the config PDA authority
+        let config_seeds = [
+            Seed::from(b"config"),
+            Seed::from(config.seed()),
+            Seed::from(config.mint_x()),
+            Seed::from(config.mint_y()),
+            Seed::from(config.config_bump()),
+        ];
+        let signer_seeds = [Signer::from(&config_seeds)];
+
+        let (from, to) = match self.instruction_data.is_x {
+            true => (self.accounts.vault_y, self.accounts.user_y_ata),
+            false => (self.accounts.vault_x, self.accounts.user_x_ata),
+        };
+
+        // Send the output first, the repay instruction pays the input
+        Transfer {
+            from,
+            to,
+            authority: self.accounts.config,
+            amount: self.instruction_data.amount_out,
+        }
+        .invoke_signed(&signer_seeds)
+    }
+}
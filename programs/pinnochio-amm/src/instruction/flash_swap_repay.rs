@@ -0,0 +1,218 @@
+use pinocchio::{
+    ProgramResult,
+    account_info::AccountInfo,
+    program_error::ProgramError,
+};
+use pinocchio_token::{instructions::Transfer, state::TokenAccount};
+
+use crate::{check_token_program, create_canonical_address, state::Config, AmmError};
+
+pub struct FlashSwapRepayAccounts<'a> {
+    pub user: &'a AccountInfo,
+    pub user_x_ata: &'a AccountInfo,
+    pub user_y_ata: &'a AccountInfo,
+    pub vault_x: &'a AccountInfo,
+    pub vault_y: &'a AccountInfo,
+    pub config: &'a AccountInfo,
+    pub token_program: &'a AccountInfo,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for FlashSwapRepayAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        let [user, user_x_ata, user_y_ata, vault_x, vault_y, config, token_program] = accounts
+        else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        check_token_program(token_program.key())?;
+
+        Ok(Self { user, user_x_ata, user_y_ata, vault_x, vault_y, config, token_program })
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct FlashSwapRepayInstructionData {
+    pub is_x: bool,
+    pub amount_in: u64,
+}
+
+impl TryFrom<&[u8]> for FlashSwapRepayInstructionData {
+    type Error = ProgramError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() != 9 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let is_x = match data[0] {
+            0 => false,
+            1 => true,
+            _ => return Err(ProgramError::InvalidInstructionData),
+        };
+
+        // Safe because slices are exactly sized by the check above
+        let amount_in = u64::from_le_bytes(data[1..9].try_into().unwrap());
+
+        if amount_in == 0 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        Ok(Self { is_x, amount_in })
+    }
+}
+
+/// Pays the input side of a `FlashSwap` into the vault, it must be the last
+/// instruction of a transaction started by a `FlashSwap`. The pool is unlocked
+/// once the repaid reserves hold the curve invariant the flash swap started from.
+pub struct FlashSwapRepay<'a> {
+    pub accounts: FlashSwapRepayAccounts<'a>,
+    pub instruction_data: FlashSwapRepayInstructionData,
+}
+
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for FlashSwapRepay<'a> {
+    type Error = ProgramError;
+
+    fn try_from((data, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
+        let accounts = FlashSwapRepayAccounts::try_from(accounts)?;
+        let instruction_data = FlashSwapRepayInstructionData::try_from(data)?;
+        Ok(Self { accounts, instruction_data })
+    }
+}
+
+impl<'a> FlashSwapRepay<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &7;
+
+    // Account indices checked by the `FlashSwap` introspection
+    pub const USER_X_ATA_INDEX: usize = 1;
+    pub const USER_Y_ATA_INDEX: usize = 2;
+    pub const VAULT_X_INDEX: usize = 3;
+    pub const VAULT_Y_INDEX: usize = 4;
+    pub const CONFIG_INDEX: usize = 5;
+
+    // This function is only called once and unconditionally
+    // It is separated for readability
+    #[inline(always)]
+    pub fn check(&mut self) -> ProgramResult {
+        let config = crate::state::Config::load(&self.accounts.config)?;
+
+        // Only a flash swap opens the pool to a repay
+        if !config.flash_swap_active() {
+            return Err(AmmError::InvalidFlashSwap.into());
+        }
+
+        // Both vaults are read for the invariant, not just the one receiving the repayment
+        let vault_x = create_canonical_address(
+            [
+                self.accounts.config.key(),
+                self.accounts.token_program.key(),
+                config.mint_x(),
+                config.vault_x_bump()
+            ],
+            &pinocchio_associated_token_account::ID,
+        )?;
+
+        let vault_y = create_canonical_address(
+            [
+                self.accounts.config.key(),
+                self.accounts.token_program.key(),
+                config.mint_y(),
+                config.vault_y_bump()
+            ],
+            &pinocchio_associated_token_account::ID,
+        )?;
+
+        if vault_x.ne(self.accounts.vault_x.key()) || vault_y.ne(self.accounts.vault_y.key()) {
+            return Err(AmmError::InvalidVault.into());
+        }
+
+        Ok(())
+    }
+
+    pub fn process(&mut self) -> ProgramResult {
+        self.check()?;
+
+        let (from, to) = match self.instruction_data.is_x {
+            true => (self.accounts.user_x_ata, self.accounts.vault_x),
+            false => (self.accounts.user_y_ata, self.accounts.vault_y),
+        };
+
+        Transfer {
+            from,
+            to,
+            authority: self.accounts.user,
+            amount: self.instruction_data.amount_in,
+        }
+        .invoke()?;
+
+        let reserves_after = (
+            unsafe { TokenAccount::from_account_info_unchecked(self.accounts.vault_x)? }.amount(),
+            unsafe { TokenAccount::from_account_info_unchecked(self.accounts.vault_y)? }.amount(),
+        );
+
+        let mut config = crate::state::Config::load_mut(self.accounts.config)?;
+
+        Self::settle(&mut config, reserves_after, self.instruction_data.is_x, self.instruction_data.amount_in)
+    }
+
+    // Checks the repaid reserves against the ones the flash swap started from, counts the fee
+    // of the repaid input like a `Swap` of it at those reserves would and unlocks the pool.
+    // The flash swap already priced the same input at the same reserves, so the quote holds
+    #[inline(always)]
+    pub fn settle(config: &mut Config, reserves_after: (u64, u64), is_x: bool, amount_in: u64) -> ProgramResult {
+        let (reserve_x, reserve_y) = config.flash_reserves();
+
+        crate::curve::check_invariant((reserve_x, reserve_y), reserves_after, config.curve_type())?;
+
+        let (_, fee) = crate::curve::swap(reserve_x, reserve_y, is_x, amount_in, config.fee(), config.curve_type())?;
+
+        config.accrue_fee(is_x, fee);
+        config.close_flash_swap();
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CurveType;
+
+    // A constant sum pool with a 1% fee, flash swapped at 1_000 of each side
+    fn config(data: &mut [u8; Config::LEN]) -> &mut Config {
+        let config = unsafe { Config::from_bytes_unchecked_mut(data) };
+
+        config.set_inner(crate::AmmState::Initialized, [0; 8], [1; 32], [2; 32], [3; 32], 100,
+            [255], [255], [255], [255], 6, 6, 6, 0, CurveType::ConstantSum, 0, 0).unwrap();
+        config.open_flash_swap(1_000, 1_000);
+
+        config
+    }
+
+    #[test]
+    fn settle_counts_the_fee_of_the_repaid_input() {
+        let mut data = [0; Config::LEN];
+        let config = config(&mut data);
+
+        // 495 of y went out, 500 of x came back with a fee of 5
+        FlashSwapRepay::settle(config, (1_500, 505), true, 500).unwrap();
+
+        assert_eq!((config.fees_x(), config.fees_y()), (5, 0));
+        assert!(!config.flash_swap_active());
+    }
+
+    #[test]
+    fn settle_rejects_a_short_repay() {
+        let mut data = [0; Config::LEN];
+        let config = config(&mut data);
+
+        assert_eq!(
+            FlashSwapRepay::settle(config, (1_400, 505), true, 400),
+            Err(AmmError::InvariantViolated.into())
+        );
+
+        assert_eq!((config.fees_x(), config.fees_y()), (0, 0));
+        assert!(config.flash_swap_active());
+    }
+}
@@ -0,0 +1,148 @@
+use pinocchio::{
+    ProgramResult,
+    account_info::AccountInfo,
+    instruction::{
+        Seed,
+        Signer
+    },
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+use pinocchio_token::{
+    instructions::FreezeAccount,
+    state::{
+        Mint,
+        TokenAccount
+    }
+};
+
+use crate::{check_token_program, create_canonical_address, AmmError};
+
+// The accounts of both `FreezeLp` and `ThawLp`, which only differ in the CPI they sign
+pub struct LpFreezeAccounts<'a> {
+    pub authority: &'a AccountInfo,
+    pub config: &'a AccountInfo,
+    pub mint_lp: &'a AccountInfo,
+    pub lp_account: &'a AccountInfo,
+    pub token_program: &'a AccountInfo,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for LpFreezeAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        let [authority, config, mint_lp, lp_account, token_program] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        check_token_program(token_program.key())?;
+
+        Ok(Self { authority, config, mint_lp, lp_account, token_program })
+    }
+}
+
+impl<'a> LpFreezeAccounts<'a> {
+    #[inline(always)]
+    pub fn check(&self) -> ProgramResult {
+        let config = crate::state::Config::load(&self.config)?;
+
+        config.check_admin(self.authority)?;
+
+        // Derive LP mint PDA
+        let mint_lp = create_canonical_address(
+            [
+                b"mint_lp".as_ref(),
+                self.config.key(),
+                config.mint_lp_bump()
+            ],
+            &crate::ID
+        )?;
+
+        if mint_lp.ne(self.mint_lp.key()) {
+            return Err(AmmError::InvalidMintLp.into());
+        }
+
+        check_lp_freeze_authority(Mint::from_account_info(self.mint_lp)?.freeze_authority(), self.config.key())?;
+
+        if TokenAccount::from_account_info(self.lp_account)?.mint().ne(self.mint_lp.key()) {
+            return Err(AmmError::InvalidTokenAccount.into());
+        }
+
+        Ok(())
+    }
+
+    // Checks the accounts and runs `cpi` signed by the config, the freeze authority of the LP mint
+    #[inline(always)]
+    pub fn invoke_signed<F>(&self, cpi: F) -> ProgramResult
+    where
+        F: FnOnce(&[Signer]) -> ProgramResult,
+    {
+        self.check()?;
+
+        let config = crate::state::Config::load(&self.config)?;
+
+        let config_seeds = [
+            Seed::from(b"config"),
+            Seed::from(config.seed()),
+            Seed::from(config.mint_x()),
+            Seed::from(config.mint_y()),
+            Seed::from(config.config_bump()),
+        ];
+
+        cpi(&[Signer::from(&config_seeds)])
+    }
+}
+
+// Only pools initialized with the config as freeze authority can freeze or thaw
+#[inline(always)]
+pub fn check_lp_freeze_authority(freeze_authority: Option<&Pubkey>, config: &Pubkey) -> ProgramResult {
+    if freeze_authority.ne(&Some(config)) {
+        return Err(AmmError::LpNotFreezable.into());
+    }
+
+    Ok(())
+}
+
+pub struct FreezeLp<'a> {
+    pub accounts: LpFreezeAccounts<'a>,
+}
+
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for FreezeLp<'a> {
+    type Error = ProgramError;
+
+    fn try_from((_, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
+        let accounts = LpFreezeAccounts::try_from(accounts)?;
+        Ok(Self { accounts })
+    }
+}
+
+impl<'a> FreezeLp<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &10;
+
+    pub fn process(&mut self) -> ProgramResult {
+        self.accounts.invoke_signed(|signers| {
+            FreezeAccount {
+                account: self.accounts.lp_account,
+                mint: self.accounts.mint_lp,
+                freeze_authority: self.accounts.config,
+            }
+            .invoke_signed(signers)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_config_can_be_the_freeze_authority() {
+        let config = [1; 32];
+
+        assert!(check_lp_freeze_authority(Some(&config), &config).is_ok());
+        assert_eq!(check_lp_freeze_authority(Some(&[2; 32]), &config), Err(AmmError::LpNotFreezable.into()));
+
+        // Pools initialized without a freeze authority can never freeze
+        assert_eq!(check_lp_freeze_authority(None, &config), Err(AmmError::LpNotFreezable.into()));
+    }
+}
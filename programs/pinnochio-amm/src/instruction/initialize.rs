@@ -1,10 +1,5 @@
 use pinocchio::{
-    ProgramResult, account_info::AccountInfo, instruction::{Seed, Signer}, program_error::ProgramError, pubkey::find_program_address, sysvars::{Sysvar, rent::Rent}
-};
-use pinocchio_system::{
-    instructions::{
-        CreateAccount
-    }
+    ProgramResult, account_info::AccountInfo, instruction::{Seed, Signer}, msg, program_error::ProgramError, pubkey::{find_program_address, Pubkey}
 };
 use pinocchio_token::{
     instructions::{
@@ -13,9 +8,9 @@ use pinocchio_token::{
     state::Mint,
     ID as TOKEN_PROGRAM_ID
 };
-use core::mem::{size_of, MaybeUninit};
+use core::mem::size_of;
 
-use crate::state;
+use crate::{check_token_program, create_pda_account, state, AmmError, CurveType};
 
 
 pub struct InitializeAccounts<'a> {
@@ -23,6 +18,8 @@ pub struct InitializeAccounts<'a> {
     pub mint_lp: &'a AccountInfo,
     pub config: &'a AccountInfo,
     pub token_program: &'a AccountInfo,
+    pub mint_x: &'a AccountInfo,
+    pub mint_y: &'a AccountInfo,
     pub config_bump: [u8;1],
     pub mint_lp_bump: [u8;1],
     pub vault_x_bump: [u8;1],
@@ -33,15 +30,19 @@ impl<'a> TryFrom<&'a [AccountInfo]> for InitializeAccounts<'a> {
   type Error = ProgramError;
  
   fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
-    let [initializer, mint_lp, config, _, token_program] = accounts else {
+    let [initializer, mint_lp, config, _, token_program, mint_x, mint_y] = accounts else {
         return Err(ProgramError::NotEnoughAccountKeys);
     };
 
+    check_token_program(token_program.key())?;
+
     Ok(Self {
         initializer,
         mint_lp,
         config,
         token_program,
+        mint_x,
+        mint_y,
         config_bump:[0], 
         mint_lp_bump:[0],
         vault_x_bump:[0],
@@ -59,39 +60,96 @@ pub struct InitializeInstructionData {
     pub fee: u16,
     pub mint_x: [u8; 32],
     pub mint_y: [u8; 32],
+    pub lp_decimals: u8,
+    // Set to 1 to make the config the freeze authority of the LP mint
+    pub lp_freeze: u8,
+    // 0 for constant product, 1 for constant sum
+    pub curve_type: u8,
+    // Required, all zeroes renounces the authority
     pub authority: [u8; 32],
+    // Optional, raw x per raw y scaled by `Config::PRICE_SCALE` that the first
+    // deposit has to match, only read when the authority is also present
+    pub initial_price: u64,
+    pub price_tolerance_bps: u16,
 }
  
 impl TryFrom<&[u8]> for InitializeInstructionData {
     type Error = ProgramError;
  
     fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
-        const INITIALIZE_DATA_LEN_WITH_AUTHORITY: usize = size_of::<InitializeInstructionData>();
+        const INITIALIZE_DATA_LEN_WITH_PRICE: usize = size_of::<InitializeInstructionData>();
+        const INITIALIZE_DATA_LEN_WITH_AUTHORITY: usize =
+            INITIALIZE_DATA_LEN_WITH_PRICE - size_of::<u64>() - size_of::<u16>();
         const INITIALIZE_DATA_LEN: usize =
             INITIALIZE_DATA_LEN_WITH_AUTHORITY - size_of::<[u8; 32]>();
  
-        match data.len() {
-            INITIALIZE_DATA_LEN_WITH_AUTHORITY => {
-                Ok(unsafe { (data.as_ptr() as *const Self).read_unaligned() })
+        let instruction_data: Self = match data.len() {
+            INITIALIZE_DATA_LEN_WITH_PRICE => {
+                unsafe { (data.as_ptr() as *const Self).read_unaligned() }
             }
-            INITIALIZE_DATA_LEN => {
-                // If the authority is not present, we need to build the buffer and add it at the end before transmuting to the struct
-                let mut raw: MaybeUninit<[u8; INITIALIZE_DATA_LEN_WITH_AUTHORITY]> = MaybeUninit::uninit();
-                let raw_ptr = raw.as_mut_ptr() as *mut u8;
-                unsafe {
-                    // Copy the provided data
-                    core::ptr::copy_nonoverlapping(data.as_ptr(), raw_ptr, INITIALIZE_DATA_LEN);
-                    // Add the authority to the end of the buffer
-                    core::ptr::write_bytes(raw_ptr.add(INITIALIZE_DATA_LEN), 0, 32);
-                    // Now transmute to the struct
-                    Ok((raw.as_ptr() as *const Self).read_unaligned())
-                }
+            // An admin-less pool has to be asked for by passing the zero authority,
+            // leaving the authority out is taken as a mistake
+            INITIALIZE_DATA_LEN => return Err(AmmError::MissingAuthority.into()),
+            INITIALIZE_DATA_LEN_WITH_AUTHORITY => {
+                // The optional fields that are not present are zeroed before transmuting to the struct
+                let mut raw = [0u8; INITIALIZE_DATA_LEN_WITH_PRICE];
+                raw[..data.len()].copy_from_slice(data);
+                unsafe { (raw.as_ptr() as *const Self).read_unaligned() }
             }
-            _ => Err(ProgramError::InvalidInstructionData),
+            _ => return Err(ProgramError::InvalidInstructionData),
+        };
+
+        if instruction_data.lp_decimals.gt(&state::Config::MAX_DECIMALS) {
+            return Err(ProgramError::InvalidInstructionData);
         }
+
+        if instruction_data.lp_freeze.gt(&1) {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        CurveType::try_from(instruction_data.curve_type)?;
+
+        // Copied out since the struct is packed
+        let price_tolerance_bps = instruction_data.price_tolerance_bps;
+
+        if price_tolerance_bps.gt(&10_000) {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        Ok(instruction_data)
     }
 }
 
+// An account initialize creates has to still belong to the system program and hold no data,
+// lamports sent to its address ahead of the pool are fine
+#[inline(always)]
+pub fn check_uninitialized(owner: &Pubkey, data_len: usize) -> ProgramResult {
+    if owner.ne(&pinocchio_system::ID) || data_len.ne(&0) {
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    Ok(())
+}
+
+// The LP mint and the config are told apart so a client knows which account it got wrong
+#[inline(always)]
+pub fn check_mint_lp_pda(derived: &Pubkey, mint_lp: &Pubkey) -> ProgramResult {
+    if derived.ne(mint_lp) {
+        return Err(AmmError::InvalidLpMintPda.into());
+    }
+
+    Ok(())
+}
+
+#[inline(always)]
+pub fn check_config_pda(derived: &Pubkey, config: &Pubkey) -> ProgramResult {
+    if derived.ne(config) {
+        return Err(AmmError::InvalidConfigPda.into());
+    }
+
+    Ok(())
+}
+
 pub struct Initialize<'a> {
     pub accounts: InitializeAccounts<'a>,
     pub instruction_data: InitializeInstructionData,
@@ -118,6 +176,11 @@ impl<'a> Initialize<'a> {
  
     pub fn process(&mut self) -> ProgramResult {
 
+        // Reject re-initialization up front instead of failing inside the system program
+        for account in [self.accounts.config, self.accounts.mint_lp] {
+            check_uninitialized(account.owner(), account.data_len())?;
+        }
+
         // Make checks
         let mint_lp_seeds = [
             b"mint_lp",
@@ -126,9 +189,7 @@ impl<'a> Initialize<'a> {
 
         let (mint_lp, mint_lp_bump) = find_program_address(&mint_lp_seeds, &crate::ID);
 
-        if mint_lp != *self.accounts.mint_lp.key(){
-            return Err(ProgramError::InvalidInstructionData);
-        }
+        check_mint_lp_pda(&mint_lp, self.accounts.mint_lp.key())?;
 
         self.accounts.mint_lp_bump = [mint_lp_bump];
 
@@ -158,9 +219,7 @@ impl<'a> Initialize<'a> {
             Seed::from(&self.accounts.config_bump)
         ];
 
-        if config != *self.accounts.config.key(){
-            return Err(ProgramError::InvalidInstructionData);
-        }
+        check_config_pda(&config, self.accounts.config.key())?;
 
         let (_, vault_x_bump)= find_program_address(
             &[
@@ -180,33 +239,58 @@ impl<'a> Initialize<'a> {
             &pinocchio_associated_token_account::ID,
         );
 
+        // Read the decimals of the pair
+        if self.accounts.mint_x.key().ne(&self.instruction_data.mint_x) ||
+            self.accounts.mint_y.key().ne(&self.instruction_data.mint_y) {
+            return Err(AmmError::InvalidMint.into());
+        }
+
+        let mint_x_decimals = Mint::from_account_info(self.accounts.mint_x)?.decimals();
+        let mint_y_decimals = Mint::from_account_info(self.accounts.mint_y)?.decimals();
+
+        // The precision is taken from the largest decimals of the pool, see `Config::precision`
+        if mint_x_decimals.gt(&state::Config::MAX_DECIMALS) || mint_y_decimals.gt(&state::Config::MAX_DECIMALS) {
+            return Err(AmmError::UnsupportedDecimals.into());
+        }
+
+        let curve_type = CurveType::try_from(self.instruction_data.curve_type)?;
+
+        crate::curve::check_curve_decimals(curve_type, mint_x_decimals, mint_y_decimals)?;
+
         // Create accouts and set data
 
-        // Create the LP mint account
-        CreateAccount {
-            from: self.accounts.initializer,
-            to: self.accounts.mint_lp,
-            owner: &TOKEN_PROGRAM_ID,
-            lamports: Rent::get()?.minimum_balance(Mint::LEN),
-            space: Mint::LEN as u64,
-        }.invoke_signed(&[Signer::from(&mint_lp_seeds)])?;
+        // Create the LP mint account, topping up lamports sent to its address ahead of the pool
+        create_pda_account(
+            self.accounts.initializer,
+            self.accounts.mint_lp,
+            Mint::LEN,
+            &TOKEN_PROGRAM_ID,
+            &[Signer::from(&mint_lp_seeds)],
+        )?;
 
         // Initialize the LP mint
         InitializeMint2 {
             mint: self.accounts.mint_lp,
-            decimals: 6,
+            decimals: self.instruction_data.lp_decimals,
             mint_authority: self.accounts.config.key(),
-            freeze_authority: None
+            freeze_authority: match self.instruction_data.lp_freeze {
+                1 => Some(self.accounts.config.key()),
+                _ => None
+            }
         }.invoke()?;
 
         // Create the config account
-        CreateAccount {
-            from: self.accounts.initializer,
-            to: self.accounts.config,
-            owner: &crate::ID,
-            lamports: Rent::get()?.minimum_balance(crate::state::Config::LEN),
-            space: crate::state::Config::LEN as u64
-        }.invoke_signed(&[Signer::from(&config_seeds)])?;
+        create_pda_account(
+            self.accounts.initializer,
+            self.accounts.config,
+            crate::state::Config::LEN,
+            &crate::ID,
+            &[Signer::from(&config_seeds)],
+        )?;
+
+        if self.instruction_data.authority.eq(&[0; 32]) {
+            msg!("Warning: creating a pool without an authority");
+        }
 
         // Set the config data
         let mut config = 
@@ -222,8 +306,82 @@ impl<'a> Initialize<'a> {
             [config_seeds[4][0]],
             [vault_x_bump],
             [vault_y_bump],        
-            [mint_lp_seeds[2][0]]
+            [mint_lp_seeds[2][0]],
+            mint_x_decimals,
+            mint_y_decimals,
+            self.instruction_data.lp_decimals,
+            0, // Referrals are enabled by the authority later on
+            curve_type,
+            self.instruction_data.initial_price,
+            self.instruction_data.price_tolerance_bps
         )
 
      }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_pool_can_not_be_initialized_twice() {
+        // A fresh or pre-funded address
+        assert!(check_uninitialized(&pinocchio_system::ID, 0).is_ok());
+
+        // The config and LP mint of an initialized pool
+        assert_eq!(
+            check_uninitialized(&crate::ID, state::Config::LEN),
+            Err(ProgramError::AccountAlreadyInitialized)
+        );
+        assert_eq!(
+            check_uninitialized(&TOKEN_PROGRAM_ID, Mint::LEN),
+            Err(ProgramError::AccountAlreadyInitialized)
+        );
+    }
+
+    #[test]
+    fn a_pool_can_take_a_9_decimal_lp_mint() {
+        // seed, fee, mint_x, mint_y, then the LP decimals at 74 ahead of the freeze flag,
+        // the curve type and the zero authority
+        let mut data = [0u8; 109];
+        data[10..42].copy_from_slice(&[2; 32]);
+        data[42..74].copy_from_slice(&[3; 32]);
+        data[74] = 9;
+
+        let parsed = InitializeInstructionData::try_from(data.as_slice()).unwrap();
+        let lp_decimals = parsed.lp_decimals;
+        assert_eq!(lp_decimals, 9);
+
+        // Past what the curve precision holds
+        data[74] = 10;
+        assert!(InitializeInstructionData::try_from(data.as_slice()).is_err());
+
+        // The LP decimals outweigh the 6 decimals of the pair in the deposit math
+        let mut config = [0; state::Config::LEN];
+        let config = unsafe { state::Config::from_bytes_unchecked_mut(&mut config) };
+        config.set_inner(crate::AmmState::Initialized, [0; 8], [1; 32], [2; 32], [3; 32], 30,
+            [255], [255], [255], [255], 6, 6, 9, 0, CurveType::ConstantProduct, 0, 0).unwrap();
+
+        assert_eq!(config.precision().unwrap(), 1_000_000_000);
+
+        let amounts = crate::Deposit::amounts(
+            2_000_000_000, (1_000_000, 4_000_000), 1_000_000_000, (u64::MAX, u64::MAX), config.precision().unwrap()
+        ).unwrap();
+
+        assert_eq!(amounts, (500_000, 2_000_000, 0));
+    }
+
+    #[test]
+    fn a_wrong_mint_lp_and_a_wrong_config_have_distinct_codes() {
+        let (derived, passed) = ([1; 32], [2; 32]);
+
+        assert_eq!(check_mint_lp_pda(&derived, &derived), Ok(()));
+        assert_eq!(check_config_pda(&derived, &derived), Ok(()));
+
+        assert_eq!(check_mint_lp_pda(&derived, &passed), Err(ProgramError::Custom(30)));
+        assert_eq!(check_config_pda(&derived, &passed), Err(ProgramError::Custom(31)));
+
+        assert_eq!(ProgramError::from(AmmError::InvalidLpMintPda), ProgramError::Custom(30));
+        assert_eq!(ProgramError::from(AmmError::InvalidConfigPda), ProgramError::Custom(31));
+    }
+}
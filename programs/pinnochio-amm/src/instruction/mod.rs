@@ -1,9 +1,51 @@
+pub mod close_pool;
+pub mod deadline;
 pub mod deposit;
+pub mod deposit_single;
+pub mod flash_swap;
+pub mod flash_swap_repay;
+pub mod freeze_lp;
 pub mod initialize;
+pub mod native;
+pub mod pda;
+pub mod set_liquidity_fees;
+pub mod set_referral_fee;
+pub mod set_swap_cooldown;
+pub mod single_sided;
+pub mod snapshot_fees;
 pub mod swap;
+pub mod swap_route;
+pub mod sweep_dust;
+pub mod thaw_lp;
+pub mod token_program;
+pub mod transfer_authority;
+pub mod treasury;
+pub mod update_state;
 pub mod withdraw;
+pub mod zap;
 
+pub use close_pool::*;
+pub use deadline::*;
 pub use deposit::*;
+pub use deposit_single::*;
+pub use flash_swap::*;
+pub use flash_swap_repay::*;
+pub use freeze_lp::*;
 pub use initialize::*;
+pub use native::*;
+pub use pda::*;
+pub use set_liquidity_fees::*;
+pub use set_referral_fee::*;
+pub use set_swap_cooldown::*;
+pub use single_sided::*;
+pub use snapshot_fees::*;
 pub use swap::*;
+pub use swap_route::*;
+pub use sweep_dust::*;
+pub use thaw_lp::*;
+pub use token_program::*;
+pub use transfer_authority::*;
+pub use treasury::*;
+pub use update_state::*;
 pub use withdraw::*;
+pub use zap::*;
@@ -0,0 +1,54 @@
+use pinocchio::{
+    ProgramResult,
+    account_info::AccountInfo,
+    program_error::ProgramError
+};
+use pinocchio_token::{instructions::SyncNative, state::TokenAccount};
+
+// Brings a wrapped SOL vault's token amount up to its lamports, so SOL sent straight
+// to the vault is priced like any other donation. Other vaults are left as they are.
+// The vault has to be checked against the config before
+#[inline(always)]
+pub fn sync_native_vault(vault: &AccountInfo) -> ProgramResult {
+    let (amount, rent_reserve) = {
+        let vault = unsafe { TokenAccount::from_account_info_unchecked(vault)? };
+        (vault.amount(), vault.native_amount())
+    };
+
+    if let Some(rent_reserve) = rent_reserve {
+        // Already in sync, nothing was sent to the vault since the last one
+        if synced_native_amount(vault.lamports(), rent_reserve)?.ne(&amount) {
+            SyncNative { native_token: vault }.invoke()?;
+        }
+    }
+
+    Ok(())
+}
+
+// Returns the token amount `SyncNative` leaves a wrapped SOL account holding `lamports` with,
+// everything above the rent exempt reserve is wrapped
+#[inline(always)]
+pub fn synced_native_amount(lamports: u64, rent_reserve: u64) -> Result<u64, ProgramError> {
+    lamports.checked_sub(rent_reserve)
+        .ok_or(ProgramError::InvalidAccountData)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_wsol_deposit_is_synced_into_the_vault_balance() {
+        const RENT_RESERVE: u64 = 2_039_280;
+
+        // A vault holding 1 wrapped SOL
+        let lamports = RENT_RESERVE + 1_000_000_000;
+        assert_eq!(synced_native_amount(lamports, RENT_RESERVE).unwrap(), 1_000_000_000);
+
+        // 0.5 SOL sent straight to the vault is wrapped by the sync
+        assert_eq!(synced_native_amount(lamports + 500_000_000, RENT_RESERVE).unwrap(), 1_500_000_000);
+
+        // An account below its own reserve is not a valid wrapped SOL account
+        assert_eq!(synced_native_amount(RENT_RESERVE - 1, RENT_RESERVE), Err(ProgramError::InvalidAccountData));
+    }
+}
@@ -0,0 +1,139 @@
+use pinocchio::{
+    ProgramResult,
+    account_info::AccountInfo,
+    instruction::Signer,
+    program_error::ProgramError,
+    pubkey::{
+        create_program_address,
+        Pubkey
+    },
+    sysvars::{
+        Sysvar,
+        rent::Rent
+    }
+};
+use pinocchio_system::instructions::{Allocate, Assign, CreateAccount, Transfer};
+
+use crate::AmmError;
+
+// Every bump as a 'static seed, so the candidates can be swapped into the seeds in place
+const BUMPS: [u8; 256] = {
+    let mut bumps = [0u8; 256];
+    let mut i = 0;
+    while i < 256 {
+        bumps[i] = i as u8;
+        i += 1;
+    }
+    bumps
+};
+
+// Derives the address from seeds ending with a stored bump and rejects the bump
+// if it is not the canonical one.
+//
+// The canonical bump is the highest one giving an off curve address, so every
+// higher bump is tried first. Canonical bumps are usually 255 or 254, which
+// keeps this to one or two extra derivations in practice.
+#[inline(always)]
+pub fn create_canonical_address<const N: usize>(
+    seeds: [&[u8]; N],
+    program_id: &Pubkey,
+) -> Result<Pubkey, ProgramError> {
+    canonical_address(seeds, program_id, create_program_address)
+}
+
+// The bump search of `create_canonical_address`, taking the derivation so it can be
+// exercised without the syscall
+#[inline(always)]
+fn canonical_address<const N: usize, F>(
+    mut seeds: [&[u8]; N],
+    program_id: &Pubkey,
+    derive: F,
+) -> Result<Pubkey, ProgramError>
+where
+    F: Fn(&[&[u8]], &Pubkey) -> Result<Pubkey, ProgramError>,
+{
+    let bump = *seeds.last()
+        .and_then(|bump| bump.first())
+        .ok_or(ProgramError::InvalidSeeds)?;
+
+    let address = derive(&seeds, program_id)
+        .map_err(|_| AmmError::InvalidPda)?;
+
+    for higher in (bump as usize + 1)..BUMPS.len() {
+        seeds[N - 1] = core::slice::from_ref(&BUMPS[higher]);
+
+        if derive(&seeds, program_id).is_ok() {
+            return Err(AmmError::NonCanonicalBump.into());
+        }
+    }
+
+    Ok(address)
+}
+
+// Creates a program owned account of `space` bytes at a PDA signed for by `signers`.
+// `CreateAccount` fails on an address already holding lamports, and anyone can send
+// lamports to a predictable PDA, so a funded address is topped up to rent exemption,
+// allocated and assigned instead
+#[inline(always)]
+pub fn create_pda_account(
+    payer: &AccountInfo,
+    account: &AccountInfo,
+    space: usize,
+    owner: &Pubkey,
+    signers: &[Signer],
+) -> ProgramResult {
+    let rent = Rent::get()?.minimum_balance(space);
+
+    if account.lamports().eq(&0) {
+        return CreateAccount {
+            from: payer,
+            to: account,
+            lamports: rent,
+            space: space as u64,
+            owner,
+        }.invoke_signed(signers);
+    }
+
+    if rent.gt(&account.lamports()) {
+        Transfer {
+            from: payer,
+            to: account,
+            lamports: rent - account.lamports(),
+        }.invoke()?;
+    }
+
+    Allocate { account, space: space as u64 }.invoke_signed(signers)?;
+    Assign { account, owner }.invoke_signed(signers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Stands in for the derivation with 255 and 253 landing on the curve, which makes 254
+    // the canonical bump
+    fn derive(seeds: &[&[u8]], _: &Pubkey) -> Result<Pubkey, ProgramError> {
+        match seeds.last().unwrap()[0] {
+            255 | 253 => Err(ProgramError::InvalidSeeds),
+            bump => Ok([bump; 32]),
+        }
+    }
+
+    #[test]
+    fn a_tampered_bump_is_rejected() {
+        let config = [1; 32];
+
+        assert_eq!(canonical_address([config.as_ref(), &[254]], &crate::ID, derive), Ok([254; 32]));
+
+        // A stored vault bump swapped for a lower one that still derives an address
+        assert_eq!(
+            canonical_address([config.as_ref(), &[252]], &crate::ID, derive),
+            Err(AmmError::NonCanonicalBump.into())
+        );
+
+        assert_eq!(
+            canonical_address([config.as_ref(), &[255]], &crate::ID, derive),
+            Err(AmmError::InvalidPda.into())
+        );
+    }
+}
@@ -0,0 +1,88 @@
+use pinocchio::{
+    ProgramResult,
+    account_info::AccountInfo,
+    program_error::ProgramError
+};
+
+use crate::{AdminEvent, EventWriter};
+
+pub struct SetLiquidityFeesAccounts<'a> {
+    pub authority: &'a AccountInfo,
+    pub config: &'a AccountInfo,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for SetLiquidityFeesAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        let [authority, config] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        Ok(Self { authority, config })
+    }
+}
+
+pub struct SetLiquidityFeesInstructionData {
+    // Both in basis points of the LP minted or redeemed, zero turns the fee off
+    pub deposit_fee_bps: u16,
+    pub withdraw_fee_bps: u16,
+}
+
+impl TryFrom<&[u8]> for SetLiquidityFeesInstructionData {
+    type Error = ProgramError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        if data.len() != 4 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        // Safe because the length is checked above
+        let deposit_fee_bps = u16::from_le_bytes(data[0..2].try_into().unwrap());
+        let withdraw_fee_bps = u16::from_le_bytes(data[2..4].try_into().unwrap());
+
+        Ok(Self { deposit_fee_bps, withdraw_fee_bps })
+    }
+}
+
+// Sets the fees skimmed to the treasury on deposits and withdrawals, the swap fee is untouched
+pub struct SetLiquidityFees<'a> {
+    pub accounts: SetLiquidityFeesAccounts<'a>,
+    pub instruction_data: SetLiquidityFeesInstructionData,
+}
+
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for SetLiquidityFees<'a> {
+    type Error = ProgramError;
+
+    fn try_from((data, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
+        let accounts = SetLiquidityFeesAccounts::try_from(accounts)?;
+        let instruction_data = SetLiquidityFeesInstructionData::try_from(data)?;
+        Ok(Self { accounts, instruction_data })
+    }
+}
+
+impl<'a> SetLiquidityFees<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &15;
+
+    pub fn process(&mut self) -> ProgramResult {
+        let mut config = crate::state::Config::load_mut(self.accounts.config)?;
+
+        config.check_admin(self.accounts.authority)?;
+
+        let (old_deposit_fee_bps, old_withdraw_fee_bps) =
+            (config.deposit_fee_bps(), config.withdraw_fee_bps());
+
+        let SetLiquidityFeesInstructionData { deposit_fee_bps, withdraw_fee_bps } = self.instruction_data;
+
+        config.set_liquidity_fees(deposit_fee_bps, withdraw_fee_bps)?;
+
+        EventWriter::new(AdminEvent::LiquidityFeesSet, self.accounts.config.key())
+            .u16(old_deposit_fee_bps)
+            .u16(old_withdraw_fee_bps)
+            .u16(deposit_fee_bps)
+            .u16(withdraw_fee_bps)
+            .emit();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+use pinocchio::{
+    ProgramResult,
+    account_info::AccountInfo,
+    program_error::ProgramError
+};
+
+use crate::{AdminEvent, EventWriter};
+
+pub struct SetReferralFeeAccounts<'a> {
+    pub authority: &'a AccountInfo,
+    pub config: &'a AccountInfo,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for SetReferralFeeAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        let [authority, config] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        Ok(Self { authority, config })
+    }
+}
+
+pub struct SetReferralFeeInstructionData {
+    // The share of the swap fee paid to referrers, in basis points of the fee
+    pub referral_bps: u16,
+}
+
+impl TryFrom<&[u8]> for SetReferralFeeInstructionData {
+    type Error = ProgramError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        let referral_bps = u16::from_le_bytes(data.try_into()
+            .map_err(|_| ProgramError::InvalidInstructionData)?);
+
+        Ok(Self { referral_bps })
+    }
+}
+
+pub struct SetReferralFee<'a> {
+    pub accounts: SetReferralFeeAccounts<'a>,
+    pub instruction_data: SetReferralFeeInstructionData,
+}
+
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for SetReferralFee<'a> {
+    type Error = ProgramError;
+
+    fn try_from((data, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
+        let accounts = SetReferralFeeAccounts::try_from(accounts)?;
+        let instruction_data = SetReferralFeeInstructionData::try_from(data)?;
+        Ok(Self { accounts, instruction_data })
+    }
+}
+
+impl<'a> SetReferralFee<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &9;
+
+    pub fn process(&mut self) -> ProgramResult {
+        let mut config = crate::state::Config::load_mut(self.accounts.config)?;
+
+        config.check_admin(self.accounts.authority)?;
+
+        let old_referral_bps = config.referral_bps();
+
+        config.set_referral_bps(self.instruction_data.referral_bps)?;
+
+        EventWriter::new(AdminEvent::ReferralFeeSet, self.accounts.config.key())
+            .u16(old_referral_bps)
+            .u16(self.instruction_data.referral_bps)
+            .emit();
+
+        Ok(())
+    }
+}
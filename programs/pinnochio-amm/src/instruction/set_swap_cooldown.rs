@@ -0,0 +1,76 @@
+use pinocchio::{
+    ProgramResult,
+    account_info::AccountInfo,
+    program_error::ProgramError
+};
+
+use crate::{AdminEvent, EventWriter};
+
+pub struct SetSwapCooldownAccounts<'a> {
+    pub authority: &'a AccountInfo,
+    pub config: &'a AccountInfo,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for SetSwapCooldownAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        let [authority, config] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        Ok(Self { authority, config })
+    }
+}
+
+pub struct SetSwapCooldownInstructionData {
+    // The slots a user has to wait between two swaps in the pool, zero disables the cooldown
+    pub cooldown_slots: u64,
+}
+
+impl TryFrom<&[u8]> for SetSwapCooldownInstructionData {
+    type Error = ProgramError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        let cooldown_slots = u64::from_le_bytes(data.try_into()
+            .map_err(|_| ProgramError::InvalidInstructionData)?);
+
+        Ok(Self { cooldown_slots })
+    }
+}
+
+pub struct SetSwapCooldown<'a> {
+    pub accounts: SetSwapCooldownAccounts<'a>,
+    pub instruction_data: SetSwapCooldownInstructionData,
+}
+
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for SetSwapCooldown<'a> {
+    type Error = ProgramError;
+
+    fn try_from((data, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
+        let accounts = SetSwapCooldownAccounts::try_from(accounts)?;
+        let instruction_data = SetSwapCooldownInstructionData::try_from(data)?;
+        Ok(Self { accounts, instruction_data })
+    }
+}
+
+impl<'a> SetSwapCooldown<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &16;
+
+    pub fn process(&mut self) -> ProgramResult {
+        let mut config = crate::state::Config::load_mut(self.accounts.config)?;
+
+        config.check_admin(self.accounts.authority)?;
+
+        let old_cooldown_slots = config.swap_cooldown_slots();
+
+        config.set_swap_cooldown_slots(self.instruction_data.cooldown_slots);
+
+        EventWriter::new(AdminEvent::SwapCooldownSet, self.accounts.config.key())
+            .u64(old_cooldown_slots)
+            .u64(self.instruction_data.cooldown_slots)
+            .emit();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,239 @@
+use pinocchio::{
+    ProgramResult,
+    account_info::AccountInfo,
+    program_error::ProgramError
+};
+use pinocchio_token::state::{Mint, TokenAccount};
+
+use crate::{
+    create_canonical_address, math::mul_div_floor, state::Config, sync_native_vault, AmmError,
+    CurveType, DepositSingle
+};
+
+// `DepositSingle` and `Zap` both take a single side of the pair and swap part of it through
+// the pool before depositing, the pool checks and the swap leg are shared here
+
+// Checks the pool accounts of a single sided deposit against the config and returns the
+// reserves and the LP supply. The reserves are read after the sync so lamports sent to a
+// wrapped SOL vault are in the price
+#[inline(always)]
+pub fn check_single_sided_pool(
+    config: &Config,
+    config_account: &AccountInfo,
+    mint_lp: &AccountInfo,
+    vault_x: &AccountInfo,
+    vault_y: &AccountInfo,
+    token_program: &AccountInfo,
+) -> Result<(u64, u64, u64), ProgramError> {
+    // Check if the pool state permits deposits
+    if !config.can_deposit() {
+        return Err(AmmError::PoolDisabled.into());
+    }
+
+    // The reserves are out on a flash swap until its repay
+    if config.flash_swap_active() {
+        return Err(AmmError::FlashSwapActive.into());
+    }
+
+    let vault_x_address = create_canonical_address(
+        [
+            config_account.key(),
+            token_program.key(),
+            config.mint_x(),
+            config.vault_x_bump()
+        ],
+        &pinocchio_associated_token_account::ID,
+    )?;
+
+    if vault_x_address.ne(vault_x.key()) {
+        return Err(AmmError::InvalidVault.into());
+    }
+
+    let vault_y_address = create_canonical_address(
+        [
+            config_account.key(),
+            token_program.key(),
+            config.mint_y(),
+            config.vault_y_bump()
+        ],
+        &pinocchio_associated_token_account::ID,
+    )?;
+
+    if vault_y_address.ne(vault_y.key()) {
+        return Err(AmmError::InvalidVault.into());
+    }
+
+    // Check mint derivation
+    let mint_lp_address = create_canonical_address(
+        [
+            b"mint_lp".as_ref(),
+            config_account.key(),
+            config.mint_lp_bump()
+        ],
+        &crate::ID
+    )?;
+
+    if mint_lp_address.ne(mint_lp.key()) {
+        return Err(AmmError::InvalidMintLp.into());
+    }
+
+    sync_native_vault(vault_x)?;
+    sync_native_vault(vault_y)?;
+
+    // Deserialize the token accounts
+    let supply = unsafe { Mint::from_account_info_unchecked(mint_lp)? }.supply();
+    let reserve_x = unsafe { TokenAccount::from_account_info_unchecked(vault_x)? }.amount();
+    let reserve_y = unsafe { TokenAccount::from_account_info_unchecked(vault_y)? }.amount();
+
+    // The pool has to be seeded with a two sided deposit first
+    if supply.eq(&0) || reserve_x.eq(&0) || reserve_y.eq(&0) {
+        return Err(AmmError::PoolNotSeeded.into());
+    }
+
+    Ok((reserve_x, reserve_y, supply))
+}
+
+// The swap of a single sided deposit, `swapped` of the input is sold for `swap_out` of the
+// other side. Both stay in the pool as far as the deposit takes them
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SwapLeg {
+    pub swapped: u64,
+    pub swap_out: u64,
+    // Denominated in the input token
+    pub fee: u64,
+}
+
+impl SwapLeg {
+    // Returns the swap of the part of `amount` that leaves the rest and the output in the
+    // ratio of the pool after the swap, the reserves are taken as the input and output sides
+    #[inline(always)]
+    pub fn new(
+        reserve_in: u64,
+        reserve_out: u64,
+        amount: u64,
+        fee: u16,
+        curve_type: CurveType,
+    ) -> Result<Self, ProgramError> {
+        let swapped = match curve_type {
+            CurveType::ConstantProduct => DepositSingle::swap_amount(reserve_in, amount, fee)?,
+            CurveType::ConstantSum => constant_sum_swap_amount(reserve_in, reserve_out, amount, fee)?,
+        };
+
+        // `swap` takes the reserves as x and y, the input is priced as x here
+        let (swap_out, fee) = crate::curve::swap(reserve_in, reserve_out, true, swapped, fee, curve_type)?;
+
+        Ok(Self { swapped, swap_out, fee })
+    }
+
+    // Holds the leg to what a `Swap` of the same amount is held to: pools with a swap cooldown
+    // are rejected, the curve invariant is checked and the fee is counted for the input side.
+    // The reserves are the ones the leg was priced against
+    #[inline(always)]
+    pub fn settle(
+        &self,
+        config: &mut Config,
+        reserve_in: u64,
+        reserve_out: u64,
+        is_x: bool,
+    ) -> ProgramResult {
+        // There is no cooldown account here to rate limit by, so such pools only take `Swap`
+        if config.swap_cooldown_slots().gt(&0) {
+            return Err(AmmError::SwapCooldown.into());
+        }
+
+        // Checked on the reserves the swap alone leaves, the deposit only adds to them
+        let after = (
+            reserve_in.checked_add(self.swapped).ok_or(ProgramError::ArithmeticOverflow)?,
+            reserve_out.checked_sub(self.swap_out).ok_or(ProgramError::ArithmeticOverflow)?,
+        );
+
+        crate::curve::check_invariant((reserve_in, reserve_out), after, config.curve_type())?;
+
+        config.accrue_fee(is_x, self.fee);
+
+        Ok(())
+    }
+}
+
+// Constant sum sells s of the input for r s of the other side, with r = 1 - fee. The rest of
+// the input and the output are in the ratio of the reserves after the swap when
+// (a - s) / (x + s) = r s / (y - r s), which gives s = a y / (y + r (a + x))
+#[inline(always)]
+fn constant_sum_swap_amount(reserve_in: u64, reserve_out: u64, amount: u64, fee: u16) -> Result<u64, ProgramError> {
+    const BPS: u128 = 10_000;
+
+    let r = BPS.checked_sub(fee as u128).ok_or(ProgramError::ArithmeticOverflow)?;
+
+    // Scaled by BPS like `r`, the sum of two u64 cannot overflow a u128
+    let denominator = (reserve_out as u128 * BPS)
+        .checked_add(r.checked_mul(amount as u128 + reserve_in as u128).ok_or(ProgramError::ArithmeticOverflow)?)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+
+    mul_div_floor(amount as u128, reserve_out as u128 * BPS, denominator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A live constant sum pool with a 1% fee and no cooldown
+    fn config(data: &mut [u8; Config::LEN]) -> &mut Config {
+        let config = unsafe { Config::from_bytes_unchecked_mut(data) };
+
+        config.set_inner(crate::AmmState::Initialized, [0; 8], [1; 32], [2; 32], [3; 32], 100,
+            [255], [255], [255], [255], 6, 6, 6, 0, CurveType::ConstantSum, 0, 0).unwrap();
+
+        config
+    }
+
+    #[test]
+    fn constant_sum_leg_balances_the_deposit() {
+        // 600 of 3_000 sold 1:1 leaves 2_400 and 600, in the 1_600 / 400 ratio of the pool after
+        assert_eq!(
+            SwapLeg::new(1_000, 1_000, 3_000, 0, CurveType::ConstantSum).unwrap(),
+            SwapLeg { swapped: 600, swap_out: 600, fee: 0 }
+        );
+
+        // The fee is rounded up on the input like a `Swap`
+        assert_eq!(
+            SwapLeg::new(1_000, 1_000, 3_000, 100, CurveType::ConstantSum).unwrap(),
+            SwapLeg { swapped: 604, swap_out: 597, fee: 7 }
+        );
+    }
+
+    #[test]
+    fn settle_counts_the_fee_on_the_input_side() {
+        let mut data = [0; Config::LEN];
+        let config = config(&mut data);
+
+        let leg = SwapLeg::new(1_000, 1_000, 3_000, 100, CurveType::ConstantSum).unwrap();
+
+        leg.settle(config, 1_000, 1_000, false).unwrap();
+
+        assert_eq!((config.fees_x(), config.fees_y()), (0, 7));
+    }
+
+    #[test]
+    fn settle_rejects_a_pool_with_a_swap_cooldown() {
+        let mut data = [0; Config::LEN];
+        let config = config(&mut data);
+        config.set_swap_cooldown_slots(10);
+
+        // There is no cooldown account to rate limit a deposit's swap by
+        let leg = SwapLeg::new(1_000, 1_000, 3_000, 100, CurveType::ConstantSum).unwrap();
+
+        assert_eq!(leg.settle(config, 1_000, 1_000, true), Err(AmmError::SwapCooldown.into()));
+        assert_eq!(config.fees_x(), 0);
+    }
+
+    #[test]
+    fn settle_rejects_a_leg_that_lowers_the_invariant() {
+        let mut data = [0; Config::LEN];
+        let config = config(&mut data);
+
+        let leg = SwapLeg { swapped: 100, swap_out: 101, fee: 0 };
+
+        assert_eq!(leg.settle(config, 1_000, 1_000, true), Err(AmmError::InvariantViolated.into()));
+        assert_eq!(config.fees_x(), 0);
+    }
+}
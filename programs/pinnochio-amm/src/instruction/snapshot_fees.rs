@@ -0,0 +1,74 @@
+use pinocchio::{
+    ProgramResult,
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    sysvars::{
+        Sysvar,
+        clock::Clock
+    }
+};
+
+use crate::{AdminEvent, EventWriter};
+
+pub struct SnapshotFeesAccounts<'a> {
+    pub authority: &'a AccountInfo,
+    pub config: &'a AccountInfo,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for SnapshotFeesAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        let [authority, config] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        Ok(Self { authority, config })
+    }
+}
+
+// Earmarks the swap fees accrued since the last snapshot for the LPs, withdrawals
+// then pay them out pro rata next to the principal. The snapshot time and the
+// counters let the fee APR be computed between two snapshots
+pub struct SnapshotFees<'a> {
+    pub accounts: SnapshotFeesAccounts<'a>,
+}
+
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for SnapshotFees<'a> {
+    type Error = ProgramError;
+
+    fn try_from((data, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
+        if !data.is_empty() {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let accounts = SnapshotFeesAccounts::try_from(accounts)?;
+        Ok(Self { accounts })
+    }
+}
+
+impl<'a> SnapshotFees<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &13;
+
+    pub fn process(&mut self) -> ProgramResult {
+        let mut config = crate::state::Config::load_mut(self.accounts.config)?;
+
+        config.check_admin(self.accounts.authority)?;
+
+        let (old_fees_x, old_fees_y, old_ts) =
+            (config.snapshot_fees_x(), config.snapshot_fees_y(), config.snapshot_ts());
+
+        config.snapshot_fees(Clock::get()?.unix_timestamp);
+
+        EventWriter::new(AdminEvent::FeesSnapshotted, self.accounts.config.key())
+            .u64(old_fees_x)
+            .u64(old_fees_y)
+            .i64(old_ts)
+            .u64(config.snapshot_fees_x())
+            .u64(config.snapshot_fees_y())
+            .i64(config.snapshot_ts())
+            .emit();
+
+        Ok(())
+    }
+}
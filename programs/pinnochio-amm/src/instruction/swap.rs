@@ -1,7 +1,3 @@
-use constant_product_curve::{
-    ConstantProduct, 
-    LiquidityPair
-};
 use pinocchio::{
     ProgramResult, 
     account_info::AccountInfo, 
@@ -9,17 +5,17 @@ use pinocchio::{
         Seed, 
         Signer
     }, 
-    program_error::ProgramError, 
-    pubkey::{
-        create_program_address
-    }, 
+    program_error::ProgramError,
+    pubkey::{find_program_address, Pubkey},
     sysvars::{
-        Sysvar, 
+        Sysvar,
         clock::Clock
     }
 };
 use pinocchio_token::{instructions::Transfer, state::TokenAccount};
 
+use crate::{check_deadline, check_token_program, create_canonical_address, create_pda_account, AmmError, CurveType, DeadlineKind};
+
 pub struct SwapAccounts<'a> {
     pub user: &'a AccountInfo,
     pub user_x_ata: &'a AccountInfo,
@@ -28,18 +24,43 @@ pub struct SwapAccounts<'a> {
     pub vault_y: &'a AccountInfo,
     pub config: &'a AccountInfo,
     pub token_program: &'a AccountInfo,
+    // Optional, the referrer's token account for the input mint
+    pub referral: Option<&'a AccountInfo>,
+    // Only for pools with a swap cooldown, the user's cooldown account. It is followed by the
+    // system program, which creates the account on the user's first swap
+    pub cooldown: Option<&'a AccountInfo>,
 }
 
 impl<'a> TryFrom<&'a [AccountInfo]> for SwapAccounts<'a> {
     type Error = ProgramError;
 
     fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
-        let [user, user_x_ata, user_y_ata, vault_x, vault_y, config, token_program] = accounts
+        let [user, user_x_ata, user_y_ata, vault_x, vault_y, config, 
+            token_program, remaining @ ..] = accounts
         else {
             return Err(ProgramError::NotEnoughAccountKeys);
         };
 
-        Ok(Self { user, user_x_ata, user_y_ata, vault_x, vault_y, config, token_program })
+        check_token_program(token_program.key())?;
+
+        // The cooldown accounts come ahead of the referral when the pool has a cooldown
+        let (cooldown, remaining) = match crate::state::Config::load(config)?.swap_cooldown_slots().gt(&0) {
+            true => {
+                let [cooldown, system_program, remaining @ ..] = remaining else {
+                    return Err(ProgramError::NotEnoughAccountKeys);
+                };
+
+                if system_program.key().ne(&pinocchio_system::ID) {
+                    return Err(ProgramError::IncorrectProgramId);
+                }
+
+                (Some(cooldown), remaining)
+            }
+            false => (None, remaining),
+        };
+
+        Ok(Self { user, user_x_ata, user_y_ata, vault_x, vault_y, config, token_program,
+            referral: remaining.first(), cooldown })
     }
 }
 
@@ -49,13 +70,16 @@ pub struct SwapInstructionData {
     pub amount: u64,     
     pub min: u64,        
     pub expiration: i64,
+    // Optional, reverts the swap if the execution price is further from the spot price
+    pub max_price_impact_bps: Option<u16>,
 }
 
 impl<'a> TryFrom<&[u8]> for SwapInstructionData {
     type Error = ProgramError;
 
     fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
-        if data.len() != 25 {
+        // The deadline kind byte is optional, the price impact cap follows it
+        if data.len() != 25 && data.len() != 26 && data.len() != 28 {
             return Err(ProgramError::InvalidInstructionData);
         }
 
@@ -76,15 +100,97 @@ impl<'a> TryFrom<&[u8]> for SwapInstructionData {
         }
 
         // Expiration check
-        let now = Clock::get()?.unix_timestamp;
-        if expiration <= now {
-            return Err(ProgramError::InvalidArgument);
-        }
+        check_deadline(expiration, DeadlineKind::try_from(data.get(25))?)?;
+
+        let max_price_impact_bps = match data.get(26..28) {
+            Some(cap) => {
+                let cap = u16::from_le_bytes(cap.try_into().unwrap());
+
+                if cap.gt(&10_000) {
+                    return Err(ProgramError::InvalidInstructionData);
+                }
+
+                Some(cap)
+            }
+            None => None,
+        };
 
-        Ok(Self { is_x, amount, min, expiration })
+        Ok(Self { is_x, amount, min, expiration, max_price_impact_bps })
     }
 }
 
+/// Returns the amount of the opposite token received for swapping `amount_in`
+/// against the given reserves, using the same curve math as the `Swap` handler.
+/// `is_x` is true when `amount_in` is denominated in token X.
+#[inline(always)]
+pub fn quote_swap(
+    reserve_x: u64,
+    reserve_y: u64,
+    is_x: bool,
+    amount_in: u64,
+    fee_bps: u16,
+) -> Result<u64, ProgramError> {
+    crate::curve::swap_out(reserve_x, reserve_y, is_x, amount_in, fee_bps, CurveType::ConstantProduct)
+}
+
+// Fails if the output falls short of the spot price by more than `cap_bps`
+#[inline(always)]
+pub fn check_price_impact(
+    (reserve_x, reserve_y): (u64, u64),
+    is_x: bool,
+    amount_in: u64,
+    amount_out: u64,
+    curve_type: CurveType,
+    cap_bps: u16,
+) -> ProgramResult {
+    let impact = crate::curve::price_impact_bps(reserve_x, reserve_y, is_x, amount_in, amount_out, curve_type)?;
+
+    if impact.gt(&(cap_bps as u64)) {
+        return Err(AmmError::PriceImpactExceeded.into());
+    }
+
+    Ok(())
+}
+
+// Fails unless the token account holds `mint` and is owned by `user`
+#[inline(always)]
+pub fn check_user_token_account(
+    account_mint: &Pubkey,
+    account_owner: &Pubkey,
+    mint: &Pubkey,
+    user: &Pubkey,
+) -> ProgramResult {
+    if account_mint.ne(mint) || account_owner.ne(user) {
+        return Err(AmmError::InvalidTokenAccount.into());
+    }
+
+    Ok(())
+}
+
+// Splits the input of a swap that charged `fee` into the part deposited to the pool, the
+// referrer's share and the fee left to the LPs. `referral_bps` is only set when a referral
+// account was passed
+#[inline(always)]
+pub fn split_referral(
+    amount_in: u64,
+    fee: u64,
+    referral_bps: Option<u16>,
+) -> Result<(u64, u64, u64), ProgramError> {
+    let referral = match referral_bps {
+        // Bounded by the fee since the referral share is at most 10_000 bps
+        Some(bps) => crate::math::mul_div_floor(fee as u128, bps as u128, 10_000)?,
+        None => 0,
+    };
+
+    let deposit = amount_in.checked_sub(referral)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+
+    // Bounded by the fee for the same reason
+    let pool_fee = fee - referral;
+
+    Ok((deposit, referral, pool_fee))
+}
+
 pub struct Swap<'a> {
     pub accounts: SwapAccounts<'a>,
     pub instruction_data: SwapInstructionData,
@@ -106,76 +212,158 @@ impl<'a> Swap<'a> {
     // This function is only called once and unconditionally
     // It is separated for readability
     #[inline(always)]
-    pub fn check(&mut self) -> Result<(u64, u64), ProgramError> {
+    pub fn check(&mut self) -> Result<(u64, u64, u64, u64), ProgramError> {
         // Load config and guard rails
         let config = crate::state::Config::load(&self.accounts.config)?;
         if !config.can_swap() {
-            return Err(ProgramError::InvalidAccountData);
+            return Err(AmmError::PoolDisabled.into());
+        }
+
+        // The reserves are out on a flash swap until its repay
+        if config.flash_swap_active() {
+            return Err(AmmError::FlashSwapActive.into());
         }
 
+        // Constant sum prices the raw amounts 1:1, checked against the decimals cached in
+        // the config so the swap never reads the mint accounts
+        crate::curve::check_curve_decimals(config.curve_type(), config.mint_x_decimals(), config.mint_y_decimals())?;
+
         // Derive vault PDAs and compare
-        let vault_x = create_program_address(
-            &[
+        let vault_x = create_canonical_address(
+            [
                 self.accounts.config.key(),
                 self.accounts.token_program.key(),
                 config.mint_x(),
                 config.vault_x_bump()
             ],
             &pinocchio_associated_token_account::ID,
-        ).map_err(|_| ProgramError::InvalidAccountData)?;
+        )?;
 
 
         if vault_x.ne(self.accounts.vault_x.key()) {
-            return Err(ProgramError::InvalidAccountData);
+            return Err(AmmError::InvalidVault.into());
         }
 
-        let vault_y = create_program_address(
-            &[
+        let vault_y = create_canonical_address(
+            [
                 self.accounts.config.key(),
                 self.accounts.token_program.key(),
                 config.mint_y(),
                 config.vault_y_bump()
             ],
             &pinocchio_associated_token_account::ID,
-        ).map_err(|_| ProgramError::InvalidAccountData)?;
+        )?;
 
         
         if vault_y.ne(self.accounts.vault_y.key()) {
-            return Err(ProgramError::InvalidAccountData);
+            return Err(AmmError::InvalidVault.into());
         }
 
+        // Check that the user token accounts belong to the user and hold the pool mints
+        let user_x_ata = TokenAccount::from_account_info(self.accounts.user_x_ata)?;
+        check_user_token_account(user_x_ata.mint(), user_x_ata.owner(), config.mint_x(), self.accounts.user.key())?;
+
+        let user_y_ata = TokenAccount::from_account_info(self.accounts.user_y_ata)?;
+        check_user_token_account(user_y_ata.mint(), user_y_ata.owner(), config.mint_y(), self.accounts.user.key())?;
+
         // Read vault balances
         let vault_x = unsafe { TokenAccount::from_account_info_unchecked(self.accounts.vault_x)? };
         let vault_y = unsafe { TokenAccount::from_account_info_unchecked(self.accounts.vault_y)? };
 
-        // Initialize curve from vault reserves
-        let mut curve = ConstantProduct::init(
+        // Compute swap
+        let (withdraw, fee) = crate::curve::swap(
             vault_x.amount(),
             vault_y.amount(),
-            vault_x.amount(), // kept to match the original implementation
+            self.instruction_data.is_x,
+            self.instruction_data.amount,
             config.fee(),
-            None,
-        )
-        .map_err(|_| ProgramError::InvalidArgument)?;
+            config.curve_type(),
+        )?;
+
+        Self::check_output(withdraw, self.instruction_data.min)?;
+
+        // Price impact check, independent of the minimum so a bad quote cannot drain a thin pool
+        if let Some(cap) = self.instruction_data.max_price_impact_bps {
+            check_price_impact(
+                (vault_x.amount(), vault_y.amount()),
+                self.instruction_data.is_x,
+                self.instruction_data.amount,
+                withdraw,
+                config.curve_type(),
+                cap,
+            )?;
+        }
 
-        let pair = if self.instruction_data.is_x { LiquidityPair::X } else { LiquidityPair::Y };
+        // Split the referrer's share off the fee, the rest stays in the pool for the LPs
+        let referral_bps = match self.accounts.referral {
+            Some(referral) => {
+                let input_mint = match self.instruction_data.is_x {
+                    true => config.mint_x(),
+                    false => config.mint_y(),
+                };
 
-        // Compute swap
-        let res = curve
-            .swap(pair, self.instruction_data.amount, self.instruction_data.min)
-            .map_err(|_| ProgramError::InvalidArgument)?;
+                if TokenAccount::from_account_info(referral)?.mint().ne(input_mint) {
+                    return Err(AmmError::InvalidTokenAccount.into());
+                }
+
+                Some(config.referral_bps())
+            }
+            None => None,
+        };
+
+        let (deposit, referral, pool_fee) = split_referral(self.instruction_data.amount, fee, referral_bps)?;
+
+        Ok((deposit, withdraw, referral, pool_fee))
+    }
+
+    // An input too small for the reserves rounds to no output, which is told apart from slippage
+    #[inline(always)]
+    pub fn check_output(withdraw: u64, min: u64) -> ProgramResult {
+        if withdraw.eq(&0) {
+            return Err(AmmError::DustSwap.into());
+        }
+
+        // Slippage check
+        if withdraw.lt(&min) {
+            return Err(AmmError::SlippageExceeded.into());
+        }
+
+        Ok(())
+    }
+
+    // The user never pays more than the amount and never receives less than the minimum
+    #[inline(always)]
+    pub fn check_bounds(amount: u64, min: u64, deposit: u64, withdraw: u64, referral: u64) -> ProgramResult {
+        let paid = deposit.checked_add(referral).ok_or(ProgramError::ArithmeticOverflow)?;
+
+        if paid.gt(&amount) {
+            return Err(AmmError::SlippageExceeded.into());
+        }
 
-        if res.deposit.eq(&0) || res.withdraw.eq(&0) {
-            return Err(ProgramError::InvalidArgument);
+        if withdraw.lt(&min) {
+            return Err(AmmError::SlippageExceeded.into());
         }
 
-        Ok((res.deposit, res.withdraw))
+        Ok(())
+    }
+
+    // The output can take the whole reserve but no more
+    #[inline(always)]
+    pub fn check_reserve_out(withdraw: u64, reserve_out: u64) -> ProgramResult {
+        if withdraw.gt(&reserve_out) {
+            return Err(AmmError::InsufficientReserves.into());
+        }
+
+        Ok(())
     }
 
     // This function is only called once and unconditionally
     // It is separated for readability
     #[inline(always)] 
-    pub fn transfer(&mut self, deposit: u64, withdraw: u64) -> ProgramResult {
+    pub fn transfer(&mut self, deposit: u64, withdraw: u64, referral: u64) -> ProgramResult {
+        // Re-assert the bounds right before moving funds
+        Self::check_bounds(self.instruction_data.amount, self.instruction_data.min, deposit, withdraw, referral)?;
+
         let config = crate::state::Config::load(&self.accounts.config)?;
 
         // Build signer seeds for the config PDA authority
@@ -188,6 +376,17 @@ impl<'a> Swap<'a> {
         ];
         let signer_seeds = [Signer::from(&config_seeds)];
 
+        // Re-read the output reserve so an overdraw fails with a clear error
+        // instead of inside the signed transfer
+        let vault_out = match self.instruction_data.is_x {
+            true => self.accounts.vault_y,
+            false => self.accounts.vault_x,
+        };
+
+        let reserve_out = unsafe { TokenAccount::from_account_info_unchecked(vault_out)? }.amount();
+
+        Self::check_reserve_out(withdraw, reserve_out)?;
+
         match self.instruction_data.is_x {
             true => {
                 // user X -> vault X
@@ -229,11 +428,235 @@ impl<'a> Swap<'a> {
             }
         }
 
+        // Pay the referrer's share of the fee
+        if let (Some(to), true) = (self.accounts.referral, referral.gt(&0)) {
+            let from = match self.instruction_data.is_x {
+                true => self.accounts.user_x_ata,
+                false => self.accounts.user_y_ata,
+            };
+
+            Transfer {
+                from,
+                to,
+                authority: self.accounts.user,
+                amount: referral,
+            }
+            .invoke()?;
+        }
+
+        Ok(())
+    }
+
+    // Rejects the swap if the user swapped in this pool within the cooldown and records
+    // the slot otherwise, the cooldown account is created on the user's first swap
+    #[inline(always)]
+    pub fn enforce_cooldown(&self) -> ProgramResult {
+        let Some(cooldown) = self.accounts.cooldown else {
+            return Ok(());
+        };
+
+        let cooldown_slots = crate::state::Config::load(self.accounts.config)?.swap_cooldown_slots();
+        let slot = Clock::get()?.slot;
+
+        if cooldown.data_is_empty() {
+            let (address, bump) = find_program_address(
+                &[b"cooldown".as_ref(), self.accounts.config.key(), self.accounts.user.key()],
+                &crate::ID,
+            );
+
+            if address.ne(cooldown.key()) {
+                return Err(AmmError::InvalidPda.into());
+            }
+
+            let bump = [bump];
+            let cooldown_seeds = [
+                Seed::from(b"cooldown"),
+                Seed::from(self.accounts.config.key()),
+                Seed::from(self.accounts.user.key()),
+                Seed::from(&bump),
+            ];
+
+            // The address is known ahead of time, so it may already hold lamports
+            create_pda_account(
+                self.accounts.user,
+                cooldown,
+                crate::state::SwapCooldown::LEN,
+                &crate::ID,
+                &[Signer::from(&cooldown_seeds)],
+            )?;
+
+            let mut state = crate::state::SwapCooldown::load_mut(cooldown)?;
+            state.set_bump(bump);
+            state.set_last_swap_slot(slot);
+
+            return Ok(());
+        }
+
+        let mut state = crate::state::SwapCooldown::load_mut(cooldown)?;
+
+        let address = create_canonical_address(
+            [b"cooldown".as_ref(), self.accounts.config.key(), self.accounts.user.key(), state.bump()],
+            &crate::ID,
+        )?;
+
+        if address.ne(cooldown.key()) {
+            return Err(AmmError::InvalidPda.into());
+        }
+
+        crate::state::check_cooldown(state.last_swap_slot(), slot, cooldown_slots)?;
+
+        state.set_last_swap_slot(slot);
+
         Ok(())
     }
 
+    #[inline(always)]
+    fn reserves(&self) -> Result<(u64, u64), ProgramError> {
+        Ok((
+            unsafe { TokenAccount::from_account_info_unchecked(self.accounts.vault_x)? }.amount(),
+            unsafe { TokenAccount::from_account_info_unchecked(self.accounts.vault_y)? }.amount(),
+        ))
+    }
+
     pub fn process(&mut self) -> ProgramResult {
-        let (deposit, withdraw) = self.check()?;
-        self.transfer( deposit, withdraw)
+        cu_trace!("swap: check");
+        let (deposit, withdraw, referral, pool_fee) = self.check()?;
+
+        self.enforce_cooldown()?;
+
+        let reserves_before = self.reserves()?;
+
+        cu_trace!("swap: transfer");
+        self.transfer(deposit, withdraw, referral)?;
+
+        crate::curve::check_invariant(
+            reserves_before,
+            self.reserves()?,
+            crate::state::Config::load(self.accounts.config)?.curve_type(),
+        )?;
+
+        cu_trace!("swap: done");
+
+        // The fee is paid in the input token
+        crate::state::Config::load_mut(self.accounts.config)?
+            .accrue_fee(self.instruction_data.is_x, pool_fee);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The reserves are picked so the curve divides exactly and the outputs do not depend
+    // on how it rounds
+
+    #[test]
+    fn quote_swap_without_fee() {
+        // k = 1_000_000, x grows to 2_000 so y falls to 500
+        assert_eq!(quote_swap(1_000, 1_000, true, 1_000, 0).unwrap(), 500);
+        assert_eq!(quote_swap(1_000, 1_000, false, 1_000, 0).unwrap(), 500);
+    }
+
+    #[test]
+    fn quote_swap_takes_the_fee_from_the_input() {
+        // 1% of 10_000 leaves 9_900 to swap, x grows to 10_000 so y falls to 10_000
+        assert_eq!(quote_swap(100, 1_000_000, true, 10_000, 100).unwrap(), 990_000);
+        assert_eq!(quote_swap(1_000_000, 100, false, 10_000, 100).unwrap(), 990_000);
+    }
+
+    #[test]
+    fn quote_swap_rejects_empty_reserves() {
+        assert!(quote_swap(0, 1_000, true, 100, 0).is_err());
+    }
+
+    #[test]
+    fn price_impact_is_capped() {
+        // Half of a 1_000 / 1_000 pool's x only fetches 500 y, 50% short of the spot price
+        let out = quote_swap(1_000, 1_000, true, 1_000, 0).unwrap();
+
+        assert_eq!(
+            check_price_impact((1_000, 1_000), true, 1_000, out, CurveType::ConstantProduct, 100),
+            Err(AmmError::PriceImpactExceeded.into())
+        );
+        assert!(check_price_impact((1_000, 1_000), true, 1_000, out, CurveType::ConstantProduct, 5_000).is_ok());
+
+        // A thousandth of a deep pool moves the price by about 10 bps
+        let out = quote_swap(1_000_000, 1_000_000, true, 1_000, 0).unwrap();
+
+        assert!(check_price_impact((1_000_000, 1_000_000), true, 1_000, out, CurveType::ConstantProduct, 100).is_ok());
+    }
+
+    #[test]
+    fn user_token_accounts_must_hold_the_mint_and_belong_to_the_user() {
+        let (mint, user) = ([2; 32], [7; 32]);
+
+        assert_eq!(check_user_token_account(&mint, &user, &mint, &user), Ok(()));
+
+        // A token account of another mint
+        assert_eq!(
+            check_user_token_account(&[3; 32], &user, &mint, &user),
+            Err(AmmError::InvalidTokenAccount.into())
+        );
+
+        // Someone else's token account of the right mint
+        assert_eq!(
+            check_user_token_account(&mint, &[8; 32], &mint, &user),
+            Err(AmmError::InvalidTokenAccount.into())
+        );
+    }
+
+    #[test]
+    fn the_referrer_takes_a_share_of_the_fee_only_when_passed() {
+        // Without a referral account the whole fee stays in the pool
+        assert_eq!(split_referral(10_000, 30, None).unwrap(), (10_000, 0, 30));
+
+        // A 20% referral share of a 30 fee is 6, held back from the pool's deposit
+        assert_eq!(split_referral(10_000, 30, Some(2_000)).unwrap(), (9_994, 6, 24));
+
+        // The share rounds down in favour of the pool
+        assert_eq!(split_referral(10_000, 31, Some(2_000)).unwrap(), (9_994, 6, 25));
+
+        // The whole fee at 10_000 bps
+        assert_eq!(split_referral(10_000, 30, Some(10_000)).unwrap(), (9_970, 30, 0));
+    }
+
+    #[test]
+    fn an_output_rounded_below_the_minimum_reverts() {
+        // 3 of a 1_000 / 1_000 pool is worth 2.99 of the other side, the curve rounds it down
+        let out = quote_swap(1_000, 1_000, true, 3, 0).unwrap();
+        assert!(out < 3);
+
+        assert_eq!(Swap::check_bounds(3, out + 1, 3, out, 0), Err(AmmError::SlippageExceeded.into()));
+        assert_eq!(Swap::check_bounds(3, out, 3, out, 0), Ok(()));
+
+        // Nor may the deposit and the referral take more than the amount
+        assert_eq!(Swap::check_bounds(3, out, 3, out, 1), Err(AmmError::SlippageExceeded.into()));
+    }
+
+    #[test]
+    fn a_sub_threshold_swap_is_a_dust_swap() {
+        // A single unit into a large pool goes entirely to the fee, which is rounded up
+        let (out, fee) = crate::curve::swap(1_000_000_000_000, 1_000_000_000_000, true, 1, 30, CurveType::ConstantSum).unwrap();
+        assert_eq!((out, fee), (0, 1));
+
+        assert_eq!(Swap::check_output(out, 1), Err(AmmError::DustSwap.into()));
+
+        // Told apart from an output that is only short of the minimum
+        assert_eq!(Swap::check_output(1, 2), Err(AmmError::SlippageExceeded.into()));
+        assert_eq!(Swap::check_output(1, 1), Ok(()));
+    }
+
+    #[test]
+    fn a_swap_can_withdraw_the_full_reserve() {
+        // Without a fee, 500 of x fetches all 500 y of a constant sum pool
+        let (out, _) = crate::curve::swap(1_000, 500, true, 500, 0, CurveType::ConstantSum).unwrap();
+        assert_eq!(out, 500);
+
+        assert_eq!(Swap::check_reserve_out(out, 500), Ok(()));
+
+        // An overdraw fails ahead of the signed transfer
+        assert_eq!(Swap::check_reserve_out(out + 1, 500), Err(AmmError::InsufficientReserves.into()));
     }
 }
@@ -0,0 +1,329 @@
+use pinocchio::{
+    ProgramResult,
+    account_info::AccountInfo,
+    instruction::{
+        Seed,
+        Signer
+    },
+    program_error::ProgramError
+};
+use pinocchio_token::{instructions::Transfer, state::TokenAccount};
+
+use crate::{check_deadline, check_token_program, create_canonical_address, AmmError, DeadlineKind};
+
+// One pool of the route, the user accounts are the ones holding the hop's input and output
+pub struct HopAccounts<'a> {
+    pub config: &'a AccountInfo,
+    pub vault_x: &'a AccountInfo,
+    pub vault_y: &'a AccountInfo,
+    pub user_in_ata: &'a AccountInfo,
+    pub user_out_ata: &'a AccountInfo,
+}
+
+pub struct SwapRouteAccounts<'a> {
+    pub user: &'a AccountInfo,
+    pub token_program: &'a AccountInfo,
+    pub first: HopAccounts<'a>,
+    pub second: HopAccounts<'a>,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for SwapRouteAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        // The intermediate token account is the output of the first hop and the input of the second
+        let [user, user_in_ata, user_mid_ata, user_out_ata,
+            config_a, vault_a_x, vault_a_y,
+            config_b, vault_b_x, vault_b_y, token_program] = accounts
+        else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        check_token_program(token_program.key())?;
+
+        // Routing a pool back into itself is just a round trip
+        if config_a.key().eq(config_b.key()) {
+            return Err(AmmError::InvalidConfig.into());
+        }
+
+        Ok(Self {
+            user,
+            token_program,
+            first: HopAccounts { config: config_a, vault_x: vault_a_x, vault_y: vault_a_y,
+                user_in_ata, user_out_ata: user_mid_ata },
+            second: HopAccounts { config: config_b, vault_x: vault_b_x, vault_y: vault_b_y,
+                user_in_ata: user_mid_ata, user_out_ata },
+        })
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct SwapRouteInstructionData {
+    // The direction of each hop, true when the hop takes in token X of its pool
+    pub first_is_x: bool,
+    pub second_is_x: bool,
+    pub amount: u64,
+    // The least of the final token the user accepts
+    pub min_out: u64,
+    pub expiration: i64,
+}
+
+impl TryFrom<&[u8]> for SwapRouteInstructionData {
+    type Error = ProgramError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        // The deadline kind byte is optional
+        if data.len() != 26 && data.len() != 27 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let direction = |byte: u8| match byte {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(ProgramError::InvalidInstructionData),
+        };
+
+        let first_is_x = direction(data[0])?;
+        let second_is_x = direction(data[1])?;
+
+        // Safe because slices are exactly sized by the check above
+        let amount = u64::from_le_bytes(data[2..10].try_into().unwrap());
+        let min_out = u64::from_le_bytes(data[10..18].try_into().unwrap());
+        let expiration = i64::from_le_bytes(data[18..26].try_into().unwrap());
+
+        if amount == 0 || min_out == 0 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        check_deadline(expiration, DeadlineKind::try_from(data.get(26))?)?;
+
+        Ok(Self { first_is_x, second_is_x, amount, min_out, expiration })
+    }
+}
+
+// Swaps through two pools in one instruction, X to Y in the first and Y to Z in the
+// second, so tokens without a direct pool can be traded atomically. Each hop uses
+// the same checks and curve math as `Swap`, only the final output is slippage checked
+pub struct SwapRoute<'a> {
+    pub accounts: SwapRouteAccounts<'a>,
+    pub instruction_data: SwapRouteInstructionData,
+}
+
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for SwapRoute<'a> {
+    type Error = ProgramError;
+
+    fn try_from((data, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
+        let accounts = SwapRouteAccounts::try_from(accounts)?;
+        let instruction_data = SwapRouteInstructionData::try_from(data)?;
+        Ok(Self { accounts, instruction_data })
+    }
+}
+
+impl<'a> SwapRoute<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &14;
+
+    // Checks the hop's accounts and returns its output and fee for `amount` in
+    #[inline(always)]
+    pub fn check_hop(&self, hop: &HopAccounts, is_x: bool, amount: u64) -> Result<(u64, u64), ProgramError> {
+        let config = crate::state::Config::load(hop.config)?;
+
+        if !config.can_swap() {
+            return Err(AmmError::PoolDisabled.into());
+        }
+
+        // The reserves are out on a flash swap until its repay
+        if config.flash_swap_active() {
+            return Err(AmmError::FlashSwapActive.into());
+        }
+
+        // There is no cooldown account here to rate limit by, so such pools only take `Swap`
+        if config.swap_cooldown_slots().gt(&0) {
+            return Err(AmmError::SwapCooldown.into());
+        }
+
+        let vault_x = create_canonical_address(
+            [
+                hop.config.key(),
+                self.accounts.token_program.key(),
+                config.mint_x(),
+                config.vault_x_bump()
+            ],
+            &pinocchio_associated_token_account::ID,
+        )?;
+
+        if vault_x.ne(hop.vault_x.key()) {
+            return Err(AmmError::InvalidVault.into());
+        }
+
+        let vault_y = create_canonical_address(
+            [
+                hop.config.key(),
+                self.accounts.token_program.key(),
+                config.mint_y(),
+                config.vault_y_bump()
+            ],
+            &pinocchio_associated_token_account::ID,
+        )?;
+
+        if vault_y.ne(hop.vault_y.key()) {
+            return Err(AmmError::InvalidVault.into());
+        }
+
+        // The user accounts have to belong to the user and hold the hop's mints
+        let (mint_in, mint_out) = match is_x {
+            true => (config.mint_x(), config.mint_y()),
+            false => (config.mint_y(), config.mint_x()),
+        };
+
+        let user_in_ata = TokenAccount::from_account_info(hop.user_in_ata)?;
+
+        if user_in_ata.mint().ne(mint_in) || user_in_ata.owner().ne(self.accounts.user.key()) {
+            return Err(AmmError::InvalidTokenAccount.into());
+        }
+
+        let user_out_ata = TokenAccount::from_account_info(hop.user_out_ata)?;
+
+        if user_out_ata.mint().ne(mint_out) || user_out_ata.owner().ne(self.accounts.user.key()) {
+            return Err(AmmError::InvalidTokenAccount.into());
+        }
+
+        let vault_x = unsafe { TokenAccount::from_account_info_unchecked(hop.vault_x)? };
+        let vault_y = unsafe { TokenAccount::from_account_info_unchecked(hop.vault_y)? };
+
+        let (withdraw, fee) = crate::curve::swap(
+            vault_x.amount(),
+            vault_y.amount(),
+            is_x,
+            amount,
+            config.fee(),
+            config.curve_type(),
+        )?;
+
+        if withdraw.eq(&0) {
+            return Err(AmmError::DustSwap.into());
+        }
+
+        Ok((withdraw, fee))
+    }
+
+    // Moves the hop's input into its pool and pays the output out of it
+    #[inline(always)]
+    pub fn transfer_hop(&self, hop: &HopAccounts, is_x: bool, deposit: u64, withdraw: u64) -> ProgramResult {
+        let config = crate::state::Config::load(hop.config)?;
+
+        let config_seeds = [
+            Seed::from(b"config"),
+            Seed::from(config.seed()),
+            Seed::from(config.mint_x()),
+            Seed::from(config.mint_y()),
+            Seed::from(config.config_bump()),
+        ];
+        let signer_seeds = [Signer::from(&config_seeds)];
+
+        let (vault_in, vault_out) = match is_x {
+            true => (hop.vault_x, hop.vault_y),
+            false => (hop.vault_y, hop.vault_x),
+        };
+
+        let reserves_before = Self::reserves(hop)?;
+
+        let reserve_out = match is_x {
+            true => reserves_before.1,
+            false => reserves_before.0,
+        };
+
+        if withdraw.gt(&reserve_out) {
+            return Err(AmmError::InsufficientReserves.into());
+        }
+
+        Transfer {
+            from: hop.user_in_ata,
+            to: vault_in,
+            authority: self.accounts.user,
+            amount: deposit,
+        }
+        .invoke()?;
+
+        Transfer {
+            from: vault_out,
+            to: hop.user_out_ata,
+            authority: hop.config,
+            amount: withdraw,
+        }
+        .invoke_signed(&signer_seeds)?;
+
+        // Each pool is held to its own curve like a single `Swap`
+        crate::curve::check_invariant(reserves_before, Self::reserves(hop)?, config.curve_type())
+    }
+
+    // Reads the hop's vault balances as x and y
+    #[inline(always)]
+    fn reserves(hop: &HopAccounts) -> Result<(u64, u64), ProgramError> {
+        Ok((
+            unsafe { TokenAccount::from_account_info_unchecked(hop.vault_x)? }.amount(),
+            unsafe { TokenAccount::from_account_info_unchecked(hop.vault_y)? }.amount(),
+        ))
+    }
+
+    pub fn process(&mut self) -> ProgramResult {
+        let SwapRouteInstructionData { first_is_x, second_is_x, amount, min_out, .. } = self.instruction_data;
+
+        // The whole output of the first hop is swapped in the second
+        let (middle, first_fee) = self.check_hop(&self.accounts.first, first_is_x, amount)?;
+        let (out, second_fee) = self.check_hop(&self.accounts.second, second_is_x, middle)?;
+
+        // Slippage check
+        if out.lt(&min_out) {
+            return Err(AmmError::SlippageExceeded.into());
+        }
+
+        self.transfer_hop(&self.accounts.first, first_is_x, amount, middle)?;
+        self.transfer_hop(&self.accounts.second, second_is_x, middle, out)?;
+
+        // The fees are paid in each hop's input token
+        crate::state::Config::load_mut(self.accounts.first.config)?
+            .accrue_fee(first_is_x, first_fee);
+
+        crate::state::Config::load_mut(self.accounts.second.config)?
+            .accrue_fee(second_is_x, second_fee);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::CurveType;
+
+    // Quotes a hop without a fee at `reserves` and returns them after its transfers, so
+    // only the rounding of the output is left over for the pool
+    fn hop(reserves: (u64, u64), is_x: bool, amount: u64) -> (u64, (u64, u64)) {
+        let (withdraw, _) = crate::curve::swap(reserves.0, reserves.1, is_x, amount, 0, CurveType::ConstantProduct).unwrap();
+
+        let after = match is_x {
+            true => (reserves.0 + amount, reserves.1 - withdraw),
+            false => (reserves.0 - withdraw, reserves.1 + amount),
+        };
+
+        (withdraw, after)
+    }
+
+    #[test]
+    fn each_leg_of_a_route_keeps_its_invariant() {
+        let (first, second) = ((1_000_000, 2_000_000), (5_000_000, 3_000_000));
+
+        let (middle, first_after) = hop(first, true, 10_000);
+        let (_, second_after) = hop(second, false, middle);
+
+        assert!(crate::curve::check_invariant(first, first_after, CurveType::ConstantProduct).is_ok());
+        assert!(crate::curve::check_invariant(second, second_after, CurveType::ConstantProduct).is_ok());
+
+        // A second leg paying out one more than its quote is caught on its own pool
+        let overpaid = (second_after.0 - 1, second_after.1);
+
+        assert_eq!(
+            crate::curve::check_invariant(second, overpaid, CurveType::ConstantProduct),
+            Err(crate::AmmError::InvariantViolated.into())
+        );
+    }
+}
@@ -0,0 +1,158 @@
+use pinocchio::{
+    ProgramResult,
+    account_info::AccountInfo,
+    instruction::{
+        Seed,
+        Signer
+    },
+    program_error::ProgramError,
+    pubkey::Pubkey,
+};
+use pinocchio_token::{
+    instructions::Transfer,
+    state::TokenAccount
+};
+
+use crate::{check_token_program, create_canonical_address, state::Config, AmmError};
+
+pub struct SweepDustAccounts<'a> {
+    pub authority: &'a AccountInfo,
+    pub config: &'a AccountInfo,
+    pub source: &'a AccountInfo,
+    pub destination: &'a AccountInfo,
+    pub token_program: &'a AccountInfo,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for SweepDustAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        let [authority, config, source, destination, token_program] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        check_token_program(token_program.key())?;
+
+        Ok(Self { authority, config, source, destination, token_program })
+    }
+}
+
+pub struct SweepDust<'a> {
+    pub accounts: SweepDustAccounts<'a>,
+}
+
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for SweepDust<'a> {
+    type Error = ProgramError;
+
+    fn try_from((data, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
+        if !data.is_empty() {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let accounts = SweepDustAccounts::try_from(accounts)?;
+        Ok(Self { accounts })
+    }
+}
+
+impl<'a> SweepDust<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &12;
+
+    // This function is only called once and unconditionally
+    // It is separated for readability
+    #[inline(always)]
+    pub fn check(&mut self) -> Result<u64, ProgramError> {
+        let config = crate::state::Config::load(&self.accounts.config)?;
+
+        config.check_admin(self.accounts.authority)?;
+
+        // Derive LP mint PDA, the LP lock holds it and must stay untouched
+        let mint_lp = create_canonical_address(
+            [
+                b"mint_lp".as_ref(),
+                self.accounts.config.key(),
+                config.mint_lp_bump()
+            ],
+            &crate::ID
+        )?;
+
+        let source = TokenAccount::from_account_info(self.accounts.source)?;
+
+        if source.owner().ne(self.accounts.config.key()) {
+            return Err(AmmError::InvalidTokenAccount.into());
+        }
+
+        Self::check_stray_mint(&config, &mint_lp, source.mint())?;
+
+        let destination = TokenAccount::from_account_info(self.accounts.destination)?;
+
+        if destination.mint().ne(source.mint()) || destination.owner().ne(self.accounts.authority.key()) {
+            return Err(AmmError::InvalidTokenAccount.into());
+        }
+
+        Ok(source.amount())
+    }
+
+    // Only tokens the pool does not trade can be swept. The vaults hold the pool mints, so
+    // this also keeps them from being swept
+    #[inline(always)]
+    pub fn check_stray_mint(config: &Config, mint_lp: &Pubkey, mint: &Pubkey) -> ProgramResult {
+        if mint.eq(config.mint_x()) || mint.eq(config.mint_y()) || mint.eq(mint_lp) {
+            return Err(AmmError::PoolMint.into());
+        }
+
+        Ok(())
+    }
+
+    pub fn process(&mut self) -> ProgramResult {
+        let amount = self.check()?;
+
+        let config = crate::state::Config::load(&self.accounts.config)?;
+
+        let config_seeds = [
+            Seed::from(b"config"),
+            Seed::from(config.seed()),
+            Seed::from(config.mint_x()),
+            Seed::from(config.mint_y()),
+            Seed::from(config.config_bump()),
+        ];
+
+        Transfer {
+            from: self.accounts.source,
+            to: self.accounts.destination,
+            authority: self.accounts.config,
+            amount,
+        }
+        .invoke_signed(&[Signer::from(&config_seeds)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_stray_tokens_are_swept() {
+        let mut data = [0; Config::LEN];
+        let config = unsafe { Config::from_bytes_unchecked_mut(&mut data) };
+
+        config.set_inner(crate::AmmState::Initialized, [0; 8], [1; 32], [2; 32], [3; 32], 30,
+            [255], [255], [255], [255], 6, 6, 6, 0, crate::CurveType::ConstantProduct, 0, 0).unwrap();
+
+        let mint_lp = [4; 32];
+
+        // Sent to the config by mistake
+        assert!(SweepDust::check_stray_mint(config, &mint_lp, &[5; 32]).is_ok());
+
+        for mint in [[2; 32], [3; 32], mint_lp] {
+            assert_eq!(SweepDust::check_stray_mint(config, &mint_lp, &mint), Err(AmmError::PoolMint.into()));
+        }
+    }
+
+    #[test]
+    fn instruction_data_is_rejected() {
+        assert!(matches!(
+            SweepDust::try_from((&[0u8][..], &[][..])),
+            Err(ProgramError::InvalidInstructionData)
+        ));
+    }
+}
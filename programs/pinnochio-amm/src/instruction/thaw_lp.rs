@@ -0,0 +1,37 @@
+use pinocchio::{
+    ProgramResult,
+    account_info::AccountInfo,
+    program_error::ProgramError,
+};
+use pinocchio_token::instructions::ThawAccount;
+
+use crate::LpFreezeAccounts;
+
+pub struct ThawLp<'a> {
+    pub accounts: LpFreezeAccounts<'a>,
+}
+
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for ThawLp<'a> {
+    type Error = ProgramError;
+
+    fn try_from((_, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
+        let accounts = LpFreezeAccounts::try_from(accounts)?;
+        Ok(Self { accounts })
+    }
+}
+
+impl<'a> ThawLp<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &11;
+
+    // Checked like `FreezeLp`, see `LpFreezeAccounts::check`
+    pub fn process(&mut self) -> ProgramResult {
+        self.accounts.invoke_signed(|signers| {
+            ThawAccount {
+                account: self.accounts.lp_account,
+                mint: self.accounts.mint_lp,
+                freeze_authority: self.accounts.config,
+            }
+            .invoke_signed(signers)
+        })
+    }
+}
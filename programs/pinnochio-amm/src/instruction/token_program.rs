@@ -0,0 +1,29 @@
+use pinocchio::{
+    ProgramResult,
+    program_error::ProgramError,
+    pubkey::Pubkey
+};
+
+// Only the SPL Token program is supported, every instruction that takes a token
+// program account checks it here before any CPI is built with it
+#[inline(always)]
+pub fn check_token_program(token_program: &Pubkey) -> ProgramResult {
+    if token_program.ne(&pinocchio_token::ID) {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_bogus_token_program_is_rejected() {
+        assert_eq!(check_token_program(&[9; 32]), Err(ProgramError::IncorrectProgramId));
+        assert_eq!(check_token_program(&pinocchio_system::ID), Err(ProgramError::IncorrectProgramId));
+
+        assert_eq!(check_token_program(&pinocchio_token::ID), Ok(()));
+    }
+}
@@ -0,0 +1,117 @@
+use pinocchio::{
+    ProgramResult,
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    pubkey::Pubkey
+};
+
+use crate::{AdminEvent, EventWriter};
+
+pub struct TransferAuthorityAccounts<'a> {
+    pub authority: &'a AccountInfo,
+    pub config: &'a AccountInfo,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for TransferAuthorityAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        let [authority, config] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        Ok(Self { authority, config })
+    }
+}
+
+pub struct TransferAuthorityInstructionData {
+    // The zero pubkey renounces the authority
+    pub new_authority: Pubkey,
+}
+
+impl TryFrom<&[u8]> for TransferAuthorityInstructionData {
+    type Error = ProgramError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        let new_authority: Pubkey = data.try_into()
+            .map_err(|_| ProgramError::InvalidInstructionData)?;
+
+        Ok(Self { new_authority })
+    }
+}
+
+pub struct TransferAuthority<'a> {
+    pub accounts: TransferAuthorityAccounts<'a>,
+    pub instruction_data: TransferAuthorityInstructionData,
+}
+
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for TransferAuthority<'a> {
+    type Error = ProgramError;
+
+    fn try_from((data, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
+        let accounts = TransferAuthorityAccounts::try_from(accounts)?;
+        let instruction_data = TransferAuthorityInstructionData::try_from(data)?;
+        Ok(Self { accounts, instruction_data })
+    }
+}
+
+impl<'a> TransferAuthority<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &5;
+
+    pub fn process(&mut self) -> ProgramResult {
+        let mut config = crate::state::Config::load_mut(self.accounts.config)?;
+
+        // A renounced pool has no admin left to transfer from
+        config.check_admin(self.accounts.authority)?;
+
+        let old_authority = *config.authority();
+
+        config.set_authority(self.instruction_data.new_authority);
+
+        EventWriter::new(AdminEvent::AuthorityTransferred, self.accounts.config.key())
+            .pubkey(&old_authority)
+            .pubkey(&self.instruction_data.new_authority)
+            .emit();
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::state::Config;
+
+    fn config(data: &mut [u8; Config::LEN]) -> &mut Config {
+        let config = unsafe { Config::from_bytes_unchecked_mut(data) };
+
+        config.set_inner(crate::AmmState::Initialized, [0; 8], [1; 32], [2; 32], [3; 32], 30,
+            [255], [255], [255], [255], 6, 6, 6, 0, crate::CurveType::ConstantProduct, 0, 0).unwrap();
+
+        config
+    }
+
+    #[test]
+    fn a_transfer_hands_the_pool_to_the_new_authority() {
+        let mut data = [0; Config::LEN];
+        let config = config(&mut data);
+
+        config.set_authority([4; 32]);
+
+        assert!(config.check_authority(&[4; 32]).is_ok());
+        assert!(config.check_authority(&[1; 32]).is_err());
+    }
+
+    #[test]
+    fn a_renounced_pool_has_no_authority_left() {
+        let mut data = [0; Config::LEN];
+        let config = config(&mut data);
+
+        config.set_authority([0; 32]);
+
+        assert_eq!(config.has_authority(), None);
+        assert!(config.check_authority(&[1; 32]).is_err());
+
+        // The zero key does not stand in for the renounced authority
+        assert!(config.check_authority(&[0; 32]).is_err());
+    }
+}
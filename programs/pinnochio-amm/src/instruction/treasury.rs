@@ -0,0 +1,83 @@
+use pinocchio::{
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    pubkey::Pubkey
+};
+use pinocchio_token::state::TokenAccount;
+
+use crate::{math::mul_div_floor, state::Config, AmmError};
+
+// Returns the LP skimmed from `amount` at `fee_bps`, rounded down in favour of the user
+#[inline(always)]
+pub fn liquidity_fee(amount: u64, fee_bps: u16) -> Result<u64, ProgramError> {
+    mul_div_floor(amount as u128, fee_bps as u128, 10_000)
+}
+
+// Returns the account the liquidity fee is paid to, none when there is nothing to skim.
+// The treasury is an LP token account of the pool authority, a renounced pool has no
+// treasury so its liquidity fees are no longer charged
+#[inline(always)]
+pub fn check_treasury<'a>(
+    config: &Config,
+    fee_bps: u16,
+    treasury: Option<&'a AccountInfo>,
+    mint_lp: &Pubkey,
+) -> Result<Option<&'a AccountInfo>, ProgramError> {
+    let authority = match config.has_authority() {
+        Some(authority) if fee_bps.gt(&0) => authority,
+        _ => return Ok(None),
+    };
+
+    let treasury = treasury.ok_or(ProgramError::NotEnoughAccountKeys)?;
+    let treasury_account = TokenAccount::from_account_info(treasury)?;
+
+    if treasury_account.mint().ne(mint_lp) || treasury_account.owner().ne(&authority) {
+        return Err(AmmError::InvalidTokenAccount.into());
+    }
+
+    Ok(Some(treasury))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn liquidity_fees_apply_apart_from_the_swap_fee() {
+        let mut data = [0; Config::LEN];
+        let config = unsafe { Config::from_bytes_unchecked_mut(&mut data) };
+
+        config.set_inner(crate::AmmState::Initialized, [0; 8], [1; 32], [2; 32], [3; 32], 30,
+            [255], [255], [255], [255], 6, 6, 6, 0, crate::CurveType::ConstantProduct, 0, 0).unwrap();
+
+        config.set_liquidity_fees(50, 100).unwrap();
+
+        assert_eq!(liquidity_fee(10_000, config.deposit_fee_bps()), Ok(50));
+        assert_eq!(liquidity_fee(10_000, config.withdraw_fee_bps()), Ok(100));
+
+        // Rounded down in favour of the user
+        assert_eq!(liquidity_fee(199, config.deposit_fee_bps()), Ok(0));
+
+        // The swap fee is left as it was
+        assert_eq!(config.fee(), 30);
+
+        assert_eq!(config.set_liquidity_fees(10_000, 0), Err(AmmError::InvalidFee.into()));
+        assert_eq!(config.set_liquidity_fees(0, 10_000), Err(AmmError::InvalidFee.into()));
+    }
+
+    #[test]
+    fn nothing_is_skimmed_without_a_fee_or_an_authority() {
+        let mut data = [0; Config::LEN];
+        let config = unsafe { Config::from_bytes_unchecked_mut(&mut data) };
+
+        config.set_inner(crate::AmmState::Initialized, [0; 8], [1; 32], [2; 32], [3; 32], 30,
+            [255], [255], [255], [255], 6, 6, 6, 0, crate::CurveType::ConstantProduct, 0, 0).unwrap();
+
+        // No treasury is read when there is nothing to pay into it
+        assert!(matches!(check_treasury(config, 0, None, &[4; 32]), Ok(None)));
+
+        config.set_authority([0; 32]);
+
+        assert!(matches!(check_treasury(config, 50, None, &[4; 32]), Ok(None)));
+    }
+}
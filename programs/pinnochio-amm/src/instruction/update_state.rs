@@ -0,0 +1,82 @@
+use pinocchio::{
+    ProgramResult,
+    account_info::AccountInfo,
+    program_error::ProgramError
+};
+
+use crate::{AdminEvent, AmmError, AmmState, EventWriter};
+
+pub struct UpdateStateAccounts<'a> {
+    pub authority: &'a AccountInfo,
+    pub config: &'a AccountInfo,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for UpdateStateAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        let [authority, config] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        Ok(Self { authority, config })
+    }
+}
+
+pub struct UpdateStateInstructionData {
+    pub state: AmmState,
+}
+
+impl TryFrom<&[u8]> for UpdateStateInstructionData {
+    type Error = ProgramError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        let [state] = data else {
+            return Err(ProgramError::InvalidInstructionData);
+        };
+
+        Ok(Self { state: AmmState::try_from(*state)? })
+    }
+}
+
+// Moves the pool along its lifecycle, only the moves `AmmState::can_transition` allows
+pub struct UpdateState<'a> {
+    pub accounts: UpdateStateAccounts<'a>,
+    pub instruction_data: UpdateStateInstructionData,
+}
+
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for UpdateState<'a> {
+    type Error = ProgramError;
+
+    fn try_from((data, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
+        let accounts = UpdateStateAccounts::try_from(accounts)?;
+        let instruction_data = UpdateStateInstructionData::try_from(data)?;
+        Ok(Self { accounts, instruction_data })
+    }
+}
+
+impl<'a> UpdateState<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &17;
+
+    pub fn process(&mut self) -> ProgramResult {
+        let mut config = crate::state::Config::load_mut(self.accounts.config)?;
+
+        config.check_admin(self.accounts.authority)?;
+
+        let old_state = AmmState::try_from(config.state())?;
+        let new_state = self.instruction_data.state;
+
+        if !AmmState::can_transition(old_state, new_state) {
+            return Err(AmmError::InvalidStateTransition.into());
+        }
+
+        config.set_state(new_state as u8)?;
+
+        EventWriter::new(AdminEvent::StateUpdated, self.accounts.config.key())
+            .u8(old_state as u8)
+            .u8(new_state as u8)
+            .emit();
+
+        Ok(())
+    }
+}
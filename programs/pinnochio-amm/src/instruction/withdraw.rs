@@ -2,7 +2,6 @@ use bytemuck::{
     Pod, 
     Zeroable
 };
-use constant_product_curve::ConstantProduct;
 use pinocchio::{
     ProgramResult, 
     account_info::AccountInfo, 
@@ -10,20 +9,16 @@ use pinocchio::{
         Seed, 
         Signer
     }, 
-    program_error::ProgramError, 
-    pubkey::{
-        create_program_address
-    }, 
-    sysvars::{
-        Sysvar, 
-        clock::Clock
-    }
+    log::sol_log_data,
+    program_error::ProgramError
 };
 use pinocchio_token::state::{
     Mint, 
     TokenAccount
 };
 
+use crate::{check_deadline, check_token_program, check_treasury, create_canonical_address, liquidity_fee, sync_native_vault, AmmError, DeadlineKind};
+
 pub struct WithdrawAccounts<'a> {
     pub user: &'a AccountInfo,
     pub mint_lp: &'a AccountInfo,
@@ -34,6 +29,8 @@ pub struct WithdrawAccounts<'a> {
     pub user_lp_ata: &'a AccountInfo,
     pub config: &'a AccountInfo,
     pub token_program: &'a AccountInfo,
+    // Only required while the pool charges a withdraw fee
+    pub treasury: Option<&'a AccountInfo>,
 }
 
 impl<'a> TryFrom<&'a [AccountInfo]> for WithdrawAccounts<'a> {
@@ -42,12 +39,14 @@ impl<'a> TryFrom<&'a [AccountInfo]> for WithdrawAccounts<'a> {
     fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
         let [user, mint_lp, vault_x, vault_y, 
             user_x_ata, user_y_ata, user_lp_ata, 
-            config, token_program] = accounts else {
+            config, token_program, remaining @ ..] = accounts else {
             return Err(ProgramError::NotEnoughAccountKeys);
         };
 
+        check_token_program(token_program.key())?;
+
         Ok(Self { user, mint_lp, vault_x, vault_y, user_x_ata, 
-            user_y_ata, user_lp_ata, config, token_program })
+            user_y_ata, user_lp_ata, config, token_program, treasury: remaining.first() })
     }
 }
 
@@ -64,26 +63,39 @@ impl<'a> TryFrom<&[u8]> for WithdrawInstructionData {
     type Error = ProgramError;
 
     fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
-        if data.len() != core::mem::size_of::<Self>() {
+        const WITHDRAW_DATA_LEN: usize = core::mem::size_of::<WithdrawInstructionData>();
+
+        // The deadline kind byte is optional
+        if data.len() != WITHDRAW_DATA_LEN && data.len() != WITHDRAW_DATA_LEN + 1 {
             return Err(ProgramError::InvalidInstructionData);
         }
         let instruction_data = 
-            bytemuck::pod_read_unaligned::<WithdrawInstructionData>(data);
+            bytemuck::pod_read_unaligned::<WithdrawInstructionData>(&data[..WITHDRAW_DATA_LEN]);
 
         if instruction_data.amount.eq(&0) || instruction_data.min_x.eq(&0) 
             || instruction_data.min_y.eq(&0) {
             return Err(ProgramError::InvalidInstructionData);
         }
 
-        let now = Clock::get()?.unix_timestamp;
-        if instruction_data.expiration <= now {
-            return Err(ProgramError::InvalidArgument);
-        }
+        check_deadline(
+            instruction_data.expiration,
+            DeadlineKind::try_from(data.get(WITHDRAW_DATA_LEN))?
+        )?;
 
         Ok(instruction_data)
     }
 }
 
+// Returns the share of the pending fees owed to `amount` of the LP supply
+#[inline(always)]
+fn pending_fee_share(pending: u64, amount: u64, supply: u64) -> Result<u64, ProgramError> {
+    if supply.eq(&0) {
+        return Ok(0);
+    }
+
+    crate::math::mul_div_floor(pending as u128, amount as u128, supply as u128)
+}
+
 pub struct Withdraw<'a> {
     pub accounts: WithdrawAccounts<'a>,
     pub instruction_data: WithdrawInstructionData,
@@ -105,91 +117,139 @@ impl<'a> Withdraw<'a> {
     // This function is only called once and unconditionally
     // It is separated for readability
     #[inline(always)] 
-    pub fn check(&mut self) -> Result<(u64, u64), ProgramError> {
+    pub fn check(&mut self) -> Result<(u64, u64, u64, u64, u64), ProgramError> {
         let config = crate::state::Config::load(&self.accounts.config)?;
 
         if !config.can_withdraw() {
-            return Err(ProgramError::InvalidAccountData);
+            return Err(AmmError::PoolDisabled.into());
+        }
+
+        // The reserves are out on a flash swap until its repay
+        if config.flash_swap_active() {
+            return Err(AmmError::FlashSwapActive.into());
         }
 
         // Derive vault PDAs
-        let vault_x = create_program_address(
-            &[
+        let vault_x = create_canonical_address(
+            [
                 self.accounts.config.key(),
                 self.accounts.token_program.key(),
                 config.mint_x(),
                 config.vault_x_bump()
             ],
             &pinocchio_associated_token_account::ID,
-        ).map_err(|_| ProgramError::InvalidAccountData)?;
+        )?;
 
 
         if vault_x.ne(self.accounts.vault_x.key()) {
-            return Err(ProgramError::InvalidAccountData);
+            return Err(AmmError::InvalidVault.into());
         }
 
-        let vault_y = create_program_address(
-            &[
+        let vault_y = create_canonical_address(
+            [
                 self.accounts.config.key(),
                 self.accounts.token_program.key(),
                 config.mint_y(),
                 config.vault_y_bump()
             ],
             &pinocchio_associated_token_account::ID,
-        ).map_err(|_| ProgramError::InvalidAccountData)?;
+        )?;
 
         if vault_y.ne(self.accounts.vault_y.key()) {
-            return Err(ProgramError::InvalidAccountData);
+            return Err(AmmError::InvalidVault.into());
         }
 
         // Derive LP mint PDA
         let mint_lp_seeds = [
             b"mint_lp".as_ref(), 
             self.accounts.config.key(),
-            config.config_bump()
+            config.mint_lp_bump()
         ];
 
-        let mint_lp = create_program_address(
-            &mint_lp_seeds, &crate::ID
-        ).map_err(|_| ProgramError::InvalidAccountData)?;
+        let mint_lp = create_canonical_address(mint_lp_seeds, &crate::ID)?;
 
 
         if mint_lp.ne(self.accounts.mint_lp.key()) {
-            return Err(ProgramError::InvalidAccountData);
+            return Err(AmmError::InvalidMintLp.into());
         }
 
+        // The reserves are read after the sync so lamports sent to a wrapped SOL vault
+        // are paid out with the rest
+        sync_native_vault(self.accounts.vault_x)?;
+        sync_native_vault(self.accounts.vault_y)?;
+
         // Deserialize accounts
         let mint_lp = unsafe { Mint::from_account_info_unchecked(self.accounts.mint_lp)? };
         let vault_x = unsafe { TokenAccount::from_account_info_unchecked(self.accounts.vault_x)? };
         let vault_y = unsafe { TokenAccount::from_account_info_unchecked(self.accounts.vault_y)? };
 
-        // Compute withdrawal amounts
-        let (x, y) = if mint_lp.supply() == self.instruction_data.amount {
-            (vault_x.amount(), vault_y.amount())
-        } else {
-            let res = ConstantProduct::xy_withdraw_amounts_from_l(
-                vault_x.amount(),
-                vault_y.amount(),
-                mint_lp.supply(),
-                self.instruction_data.amount,
-                6,
-            )
-            .map_err(|_| ProgramError::InvalidArgument)?;
-            (res.x, res.y)
+        // The withdraw fee is skimmed from the LP before it is redeemed
+        let lp_fee = match check_treasury(
+            &config,
+            config.withdraw_fee_bps(),
+            self.accounts.treasury,
+            self.accounts.mint_lp.key(),
+        )? {
+            Some(_) => liquidity_fee(self.instruction_data.amount, config.withdraw_fee_bps())?,
+            None => 0,
         };
 
+        let amount = self.instruction_data.amount - lp_fee;
+
+        let (x, y, fee_x, fee_y) = Self::payout(
+            (vault_x.amount(), vault_y.amount()),
+            (config.pending_fees_x(), config.pending_fees_y()),
+            mint_lp.supply(),
+            amount,
+            config.precision()?,
+        )?;
+
         // Slippage check
         if x < self.instruction_data.min_x || y < self.instruction_data.min_y {
-            return Err(ProgramError::InvalidArgument);
+            return Err(AmmError::SlippageExceeded.into());
         }
 
-        Ok((x, y))
+        Ok((x, y, fee_x, fee_y, lp_fee))
+    }
+
+    // Returns the x and y paid for burning `amount` of the LP supply and the part of them
+    // that is the snapshotted fees.
+    //
+    // The fees never left the vaults, so this is only a reporting split and not a bonus: the
+    // principal is computed against the reserves without the pending fees and the fee share
+    // is added back on top, which pays the same pro rata share of the vaults as a withdraw
+    // without pending fees would, give or take the rounding of each part
+    #[inline(always)]
+    pub fn payout(
+        (reserve_x, reserve_y): (u64, u64),
+        (pending_x, pending_y): (u64, u64),
+        supply: u64,
+        amount: u64,
+        precision: u32,
+    ) -> Result<(u64, u64, u64, u64), ProgramError> {
+        let (fee_x, fee_y) = (
+            pending_fee_share(pending_x, amount, supply)?,
+            pending_fee_share(pending_y, amount, supply)?,
+        );
+
+        let (principal_x, principal_y) = crate::curve::withdraw_amounts(
+            reserve_x.saturating_sub(pending_x),
+            reserve_y.saturating_sub(pending_y),
+            supply,
+            amount,
+            precision,
+        )?;
+
+        let x = principal_x.checked_add(fee_x).ok_or(ProgramError::ArithmeticOverflow)?;
+        let y = principal_y.checked_add(fee_y).ok_or(ProgramError::ArithmeticOverflow)?;
+
+        Ok((x, y, fee_x, fee_y))
     }
 
     // This function is only called once and unconditionally
     // It is separated for readability
     #[inline(always)] 
-    pub fn transfer_tokens_and_burn_lp_tokens(&mut self, x: u64, y: u64) -> ProgramResult {
+    pub fn transfer_tokens_and_burn_lp_tokens(&mut self, x: u64, y: u64, lp_fee: u64) -> ProgramResult {
         let config = crate::state::Config::load(&self.accounts.config)?;
 
         let config_seeds = [
@@ -219,18 +279,66 @@ impl<'a> Withdraw<'a> {
         }
         .invoke_signed(&signer_seeds)?;
 
+        if lp_fee.gt(&0) {
+            pinocchio_token::instructions::Transfer {
+                from: self.accounts.user_lp_ata,
+                to: self.accounts.treasury.ok_or(ProgramError::NotEnoughAccountKeys)?,
+                authority: self.accounts.user,
+                amount: lp_fee,
+            }
+            .invoke()?;
+        }
+
         // Burn LP tokens from user
         pinocchio_token::instructions::Burn {
             mint: self.accounts.mint_lp,
             account: self.accounts.user_lp_ata,
             authority: self.accounts.user,
-            amount: self.instruction_data.amount,
+            amount: self.instruction_data.amount - lp_fee,
         }
         .invoke()
     }
 
     pub fn process(&mut self) -> ProgramResult {
-        let (x, y) = self.check()?;
-        self.transfer_tokens_and_burn_lp_tokens(x, y)
+        cu_trace!("withdraw: check");
+        let (x, y, fee_x, fee_y, lp_fee) = self.check()?;
+
+        cu_trace!("withdraw: transfer");
+        self.transfer_tokens_and_burn_lp_tokens(x, y, lp_fee)?;
+
+        cu_trace!("withdraw: done");
+
+        crate::state::Config::load_mut(self.accounts.config)?
+            .pay_pending_fees(fee_x, fee_y);
+
+        // Log the fee part of the payout so LPs can tell it from the principal
+        sol_log_data(&[
+            &fee_x.to_le_bytes(),
+            &fee_y.to_le_bytes(),
+        ]);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pending_fees_are_reported_out_of_the_pro_rata_share() {
+        let (reserves, supply, amount) = ((10_000, 20_000), 1_000, 100);
+
+        // A tenth of the pool, of which a tenth of the pending fees
+        assert_eq!(
+            Withdraw::payout(reserves, (1_000, 2_000), supply, amount, 1_000_000),
+            Ok((1_000, 2_000, 100, 200))
+        );
+
+        // The same payout as without a snapshot, the fees were in the vaults all along
+        assert_eq!(
+            Withdraw::payout(reserves, (0, 0), supply, amount, 1_000_000),
+            Ok((1_000, 2_000, 0, 0))
+        );
     }
 }
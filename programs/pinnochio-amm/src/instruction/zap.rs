@@ -0,0 +1,342 @@
+use pinocchio::{
+    ProgramResult,
+    account_info::AccountInfo,
+    instruction::{
+        Seed,
+        Signer
+    },
+    program_error::ProgramError,
+};
+use pinocchio_token::{
+    instructions::{
+        MintTo,
+        Transfer
+    },
+    state::TokenAccount
+};
+
+use crate::{
+    check_deadline, check_single_sided_pool, check_token_program, check_treasury, liquidity_fee,
+    math::{mul_div_ceil, mul_div_floor}, AmmError, CurveType, DeadlineKind, SwapLeg
+};
+
+pub struct ZapAccounts<'a> {
+    pub user: &'a AccountInfo,
+    pub mint_lp: &'a AccountInfo,
+    pub vault_x: &'a AccountInfo,
+    pub vault_y: &'a AccountInfo,
+    pub user_x_ata: &'a AccountInfo,
+    pub user_y_ata: &'a AccountInfo,
+    pub user_lp_ata: &'a AccountInfo,
+    // Writable, the fee of the swap leg is counted in it
+    pub config: &'a AccountInfo,
+    pub token_program: &'a AccountInfo,
+    // Only required while the pool charges a deposit fee
+    pub treasury: Option<&'a AccountInfo>,
+}
+
+impl<'a> TryFrom<&'a [AccountInfo]> for ZapAccounts<'a> {
+    type Error = ProgramError;
+
+    fn try_from(accounts: &'a [AccountInfo]) -> Result<Self, Self::Error> {
+        let [user, mint_lp, vault_x, vault_y, user_x_ata, user_y_ata,
+            user_lp_ata, config, token_program, remaining @ ..] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        check_token_program(token_program.key())?;
+
+        Ok(Self { user, mint_lp, vault_x, vault_y, user_x_ata, user_y_ata, user_lp_ata, config,
+            token_program, treasury: remaining.first() })
+    }
+}
+
+// Laid out like `DepositSingleInstructionData`
+#[derive(Clone, Copy)]
+pub struct ZapInstructionData {
+    pub is_x: bool,
+    pub amount: u64,
+    pub min_lp_out: u64,
+    pub expiration: i64,
+}
+
+impl TryFrom<&[u8]> for ZapInstructionData {
+    type Error = ProgramError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        // The deadline kind byte is optional
+        if data.len() != 25 && data.len() != 26 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let is_x = match data[0] {
+            0 => false,
+            1 => true,
+            _ => return Err(ProgramError::InvalidInstructionData),
+        };
+
+        // Safe because slices are exactly sized by the check above
+        let amount = u64::from_le_bytes(data[1..9].try_into().unwrap());
+        let min_lp_out = u64::from_le_bytes(data[9..17].try_into().unwrap());
+        let expiration = i64::from_le_bytes(data[17..25].try_into().unwrap());
+
+        if amount == 0 || min_lp_out == 0 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        // Expiration check
+        check_deadline(expiration, DeadlineKind::try_from(data.get(25))?)?;
+
+        Ok(Self { is_x, amount, min_lp_out, expiration })
+    }
+}
+
+// The amounts of a zap, the input side is the one the user pays in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ZapAmounts {
+    // The part of the input swapped to the other side and what it pays out of the other vault
+    pub leg: SwapLeg,
+    // The liquidity minted, before the deposit fee
+    pub lp: u64,
+    // The input deposited next to the swapped part, the rest never leaves the user
+    pub deposit_in: u64,
+    // The swap output deposited, the rest is paid back to the user
+    pub deposit_out: u64,
+}
+
+/// Takes a single side of the pair, swaps the optimal part of it to the other side and
+/// deposits both at the curve's ratio for LP. Unlike `DepositSingle` the deposit is
+/// balanced exactly, the input not needed stays with the user and the swap output
+/// not needed is paid back to them.
+pub struct Zap<'a> {
+    pub accounts: ZapAccounts<'a>,
+    pub instruction_data: ZapInstructionData,
+}
+
+impl<'a> TryFrom<(&'a [u8], &'a [AccountInfo])> for Zap<'a> {
+    type Error = ProgramError;
+
+    fn try_from((data, accounts): (&'a [u8], &'a [AccountInfo])) -> Result<Self, Self::Error> {
+        let accounts = ZapAccounts::try_from(accounts)?;
+        let instruction_data = ZapInstructionData::try_from(data)?;
+        Ok(Self { accounts, instruction_data })
+    }
+}
+
+impl<'a> Zap<'a> {
+    pub const DISCRIMINATOR: &'a u8 = &18;
+
+    // Returns the amounts of zapping `amount` into reserves of `reserve_in` and `reserve_out`
+    // with `supply` LP outstanding. The liquidity is rounded down and the amounts backing it
+    // up, so the deposit never takes more than the swap left on either side
+    #[inline(always)]
+    pub fn amounts(
+        reserve_in: u64,
+        reserve_out: u64,
+        supply: u64,
+        amount: u64,
+        fee: u16,
+        curve_type: CurveType,
+    ) -> Result<ZapAmounts, ProgramError> {
+        let leg = SwapLeg::new(reserve_in, reserve_out, amount, fee, curve_type)?;
+
+        let remaining_in = amount.checked_sub(leg.swapped)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        let reserve_in = reserve_in.checked_add(leg.swapped)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        let reserve_out = reserve_out.checked_sub(leg.swap_out)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+
+        if reserve_out.eq(&0) {
+            return Err(AmmError::InsufficientReserves.into());
+        }
+
+        // The liquidity both sides of what the user holds after the swap can back
+        let l_in = mul_div_floor(remaining_in as u128, supply as u128, reserve_in as u128)?;
+        let l_out = mul_div_floor(leg.swap_out as u128, supply as u128, reserve_out as u128)?;
+
+        let lp = l_in.min(l_out);
+
+        // Bounded by what was left on each side since `lp` is rounded down from it
+        let deposit_in = mul_div_ceil(lp as u128, reserve_in as u128, supply as u128)?;
+        let deposit_out = mul_div_ceil(lp as u128, reserve_out as u128, supply as u128)?;
+
+        Ok(ZapAmounts { leg, lp, deposit_in, deposit_out })
+    }
+
+    // This function is only called once and unconditionally
+    // It is separated for readability
+    #[inline(always)]
+    pub fn check(&mut self) -> Result<(ZapAmounts, u64), ProgramError> {
+        // Mutable for the fee the swap leg accrues
+        let mut config = crate::state::Config::load_mut(&self.accounts.config)?;
+
+        let (reserve_x, reserve_y, supply) = check_single_sided_pool(
+            &config,
+            self.accounts.config,
+            self.accounts.mint_lp,
+            self.accounts.vault_x,
+            self.accounts.vault_y,
+            self.accounts.token_program,
+        )?;
+
+        // Check that the user token accounts belong to the user and hold the pool mints,
+        // the one for the other side receives what the deposit does not take
+        let user_x_ata = TokenAccount::from_account_info(self.accounts.user_x_ata)?;
+
+        if user_x_ata.mint().ne(config.mint_x()) || user_x_ata.owner().ne(self.accounts.user.key()) {
+            return Err(AmmError::InvalidTokenAccount.into());
+        }
+
+        let user_y_ata = TokenAccount::from_account_info(self.accounts.user_y_ata)?;
+
+        if user_y_ata.mint().ne(config.mint_y()) || user_y_ata.owner().ne(self.accounts.user.key()) {
+            return Err(AmmError::InvalidTokenAccount.into());
+        }
+
+        let (reserve_in, reserve_out) = match self.instruction_data.is_x {
+            true => (reserve_x, reserve_y),
+            false => (reserve_y, reserve_x),
+        };
+
+        let amounts = Self::amounts(
+            reserve_in,
+            reserve_out,
+            supply,
+            self.instruction_data.amount,
+            config.fee(),
+            config.curve_type(),
+        )?;
+
+        amounts.leg.settle(&mut config, reserve_in, reserve_out, self.instruction_data.is_x)?;
+
+        // The deposit fee is skimmed from the LP the user would get
+        let fee = match check_treasury(
+            &config,
+            config.deposit_fee_bps(),
+            self.accounts.treasury,
+            self.accounts.mint_lp.key(),
+        )? {
+            Some(_) => liquidity_fee(amounts.lp, config.deposit_fee_bps())?,
+            None => 0,
+        };
+
+        // Slippage check
+        if (amounts.lp - fee).eq(&0) || (amounts.lp - fee).lt(&self.instruction_data.min_lp_out) {
+            return Err(AmmError::SlippageExceeded.into());
+        }
+
+        Ok((amounts, fee))
+    }
+
+    pub fn process(&mut self) -> ProgramResult {
+        let (amounts, fee) = self.check()?;
+
+        let config = crate::state::Config::load(&self.accounts.config)?;
+
+        let config_seeds = [
+            Seed::from(b"config"),
+            Seed::from(config.seed()),
+            Seed::from(config.mint_x()),
+            Seed::from(config.mint_y()),
+            Seed::from(config.config_bump())
+        ];
+
+        let (user_in, user_out, vault_in, vault_out) = match self.instruction_data.is_x {
+            true => (self.accounts.user_x_ata, self.accounts.user_y_ata, self.accounts.vault_x, self.accounts.vault_y),
+            false => (self.accounts.user_y_ata, self.accounts.user_x_ata, self.accounts.vault_y, self.accounts.vault_x),
+        };
+
+        // The swapped part and the deposited part of the input both end up in its vault
+        let paid_in = amounts.leg.swapped.checked_add(amounts.deposit_in)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+
+        Transfer {
+            from: user_in,
+            to: vault_in,
+            authority: self.accounts.user,
+            amount: paid_in,
+        }.invoke()?;
+
+        // The swap output the deposit does not take is the only part that leaves the pool
+        let paid_out = amounts.leg.swap_out - amounts.deposit_out;
+
+        if paid_out.gt(&0) {
+            Transfer {
+                from: vault_out,
+                to: user_out,
+                authority: self.accounts.config,
+                amount: paid_out,
+            }.invoke_signed(&[Signer::from(&config_seeds)])?;
+        }
+
+        if fee.gt(&0) {
+            MintTo {
+                mint: self.accounts.mint_lp,
+                account: self.accounts.treasury.ok_or(ProgramError::NotEnoughAccountKeys)?,
+                mint_authority: self.accounts.config,
+                amount: fee,
+            }.invoke_signed(&[Signer::from(&config_seeds)])?;
+        }
+
+        MintTo {
+            mint: self.accounts.mint_lp,
+            account: self.accounts.user_lp_ata,
+            mint_authority: self.accounts.config,
+            amount: amounts.lp - fee,
+        }.invoke_signed(&[Signer::from(&config_seeds)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zap_x_into_a_pool_mints_lp() {
+        let amounts = Zap::amounts(1_000_000, 2_000_000, 1_000_000, 10_000, 30, CurveType::ConstantProduct).unwrap();
+
+        assert!(amounts.lp > 0);
+        assert!(amounts.leg.swapped < 10_000);
+        // The deposit takes no more than what the user holds on each side after the swap
+        assert!(amounts.deposit_in <= 10_000 - amounts.leg.swapped);
+        assert!(amounts.deposit_out <= amounts.leg.swap_out);
+    }
+
+    #[test]
+    fn zap_leaves_little_unused() {
+        let amounts = Zap::amounts(1_000_000_000, 1_000_000_000, 1_000_000_000, 1_000_000, 30, CurveType::ConstantProduct).unwrap();
+
+        let unused_in = 1_000_000 - amounts.leg.swapped - amounts.deposit_in;
+        let unused_out = amounts.leg.swap_out - amounts.deposit_out;
+
+        // The optimal swap leaves the two sides close to the pool's ratio, the pool prices
+        // x and y about 1:1 here so the leftovers are compared in the same units
+        assert!(unused_in + unused_out <= 1_000_000 / 1_000);
+    }
+
+    #[test]
+    fn zap_into_a_constant_sum_pool() {
+        // Sold 1:1, 600 of the 3_000 leaves 2_400 and 600 against reserves of 1_600 and 400
+        assert_eq!(
+            Zap::amounts(1_000, 1_000, 1_000, 3_000, 0, CurveType::ConstantSum).unwrap(),
+            ZapAmounts {
+                leg: SwapLeg { swapped: 600, swap_out: 600, fee: 0 },
+                lp: 1_500,
+                deposit_in: 2_400,
+                deposit_out: 600,
+            }
+        );
+
+        // With a 1% fee the liquidity is rounded down and the amounts backing it up
+        assert_eq!(
+            Zap::amounts(1_000, 1_000, 1_000, 3_000, 100, CurveType::ConstantSum).unwrap(),
+            ZapAmounts {
+                leg: SwapLeg { swapped: 604, swap_out: 597, fee: 7 },
+                lp: 1_481,
+                deposit_in: 2_376,
+                deposit_out: 597,
+            }
+        );
+    }
+}
@@ -3,12 +3,36 @@ use pinocchio_pubkey::{
     declare_id
 };
 
+// Logs the remaining compute units with a label, compiled out without `cu-trace`
+macro_rules! cu_trace {
+    ($label:expr) => {
+        #[cfg(feature = "cu-trace")]
+        {
+            pinocchio::msg!($label);
+            pinocchio::log::sol_log_compute_units();
+        }
+    };
+}
+
 pub mod state;
 pub use state::*;
 
 pub mod instruction;
 pub use instruction::*;
 
+mod curve;
+
+mod math;
+
+pub mod errors;
+pub use errors::*;
+
+pub mod events;
+pub use events::*;
+
+#[cfg(feature = "client")]
+pub mod client;
+
 declare_id!("22222222222222222222222222222222222222222222");
 
 entrypoint!(process_instruction);
@@ -25,6 +49,37 @@ fn process_instruction(
         Some((Deposit::DISCRIMINATOR, data)) => Deposit::try_from((data, accounts))?.process(),
         Some((Withdraw::DISCRIMINATOR, data)) => Withdraw::try_from((data, accounts))?.process(),
         Some((Swap::DISCRIMINATOR, data)) => Swap::try_from((data, accounts))?.process(),
+        Some((ClosePool::DISCRIMINATOR, data)) => ClosePool::try_from((data, accounts))?.process(),
+        Some((TransferAuthority::DISCRIMINATOR, data)) => {
+            TransferAuthority::try_from((data, accounts))?.process()
+        }
+        Some((FlashSwap::DISCRIMINATOR, data)) => FlashSwap::try_from((data, accounts))?.process(),
+        Some((FlashSwapRepay::DISCRIMINATOR, data)) => {
+            FlashSwapRepay::try_from((data, accounts))?.process()
+        }
+        Some((DepositSingle::DISCRIMINATOR, data)) => {
+            DepositSingle::try_from((data, accounts))?.process()
+        }
+        Some((SetReferralFee::DISCRIMINATOR, data)) => {
+            SetReferralFee::try_from((data, accounts))?.process()
+        }
+        Some((FreezeLp::DISCRIMINATOR, data)) => FreezeLp::try_from((data, accounts))?.process(),
+        Some((ThawLp::DISCRIMINATOR, data)) => ThawLp::try_from((data, accounts))?.process(),
+        Some((SweepDust::DISCRIMINATOR, data)) => SweepDust::try_from((data, accounts))?.process(),
+        Some((SnapshotFees::DISCRIMINATOR, data)) => {
+            SnapshotFees::try_from((data, accounts))?.process()
+        }
+        Some((SwapRoute::DISCRIMINATOR, data)) => SwapRoute::try_from((data, accounts))?.process(),
+        Some((SetLiquidityFees::DISCRIMINATOR, data)) => {
+            SetLiquidityFees::try_from((data, accounts))?.process()
+        }
+        Some((SetSwapCooldown::DISCRIMINATOR, data)) => {
+            SetSwapCooldown::try_from((data, accounts))?.process()
+        }
+        Some((UpdateState::DISCRIMINATOR, data)) => {
+            UpdateState::try_from((data, accounts))?.process()
+        }
+        Some((Zap::DISCRIMINATOR, data)) => Zap::try_from((data, accounts))?.process(),
         _ => Err(ProgramError::InvalidInstructionData),
     }
 }
\ No newline at end of file
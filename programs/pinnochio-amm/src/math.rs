@@ -0,0 +1,68 @@
+use pinocchio::program_error::ProgramError;
+
+// The u128 intermediate math shared by the fee and invariant computations. The
+// results are checked back into a u64 so an overflow fails instead of truncating
+
+// Returns `a * b`, for the products that have to stay in u128
+#[inline(always)]
+pub fn checked_mul_u128(a: u128, b: u128) -> Result<u128, ProgramError> {
+    a.checked_mul(b).ok_or(ProgramError::ArithmeticOverflow)
+}
+
+// Returns `a * b / denominator` rounded down
+#[inline(always)]
+pub fn mul_div_floor(a: u128, b: u128, denominator: u128) -> Result<u64, ProgramError> {
+    let quotient = checked_mul_u128(a, b)?
+        .checked_div(denominator)
+        .ok_or(ProgramError::ArithmeticOverflow)?;
+
+    u64::try_from(quotient).map_err(|_| ProgramError::ArithmeticOverflow)
+}
+
+// Returns `a * b / denominator` rounded up
+#[inline(always)]
+pub fn mul_div_ceil(a: u128, b: u128, denominator: u128) -> Result<u64, ProgramError> {
+    if denominator.eq(&0) {
+        return Err(ProgramError::ArithmeticOverflow);
+    }
+
+    let quotient = checked_mul_u128(a, b)?.div_ceil(denominator);
+
+    u64::try_from(quotient).map_err(|_| ProgramError::ArithmeticOverflow)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checked_mul_u128_overflows() {
+        assert_eq!(checked_mul_u128(u64::MAX as u128, u64::MAX as u128).unwrap(), (u64::MAX as u128).pow(2));
+        assert_eq!(checked_mul_u128(u128::MAX, 2), Err(ProgramError::ArithmeticOverflow));
+    }
+
+    #[test]
+    fn mul_div_rounds() {
+        assert_eq!(mul_div_floor(10, 3, 4).unwrap(), 7);
+        assert_eq!(mul_div_ceil(10, 3, 4).unwrap(), 8);
+
+        // Exact quotients are the same both ways
+        assert_eq!(mul_div_floor(10, 4, 4).unwrap(), 10);
+        assert_eq!(mul_div_ceil(10, 4, 4).unwrap(), 10);
+    }
+
+    #[test]
+    fn mul_div_rejects_a_zero_denominator() {
+        assert_eq!(mul_div_floor(1, 1, 0), Err(ProgramError::ArithmeticOverflow));
+        assert_eq!(mul_div_ceil(1, 1, 0), Err(ProgramError::ArithmeticOverflow));
+    }
+
+    #[test]
+    fn mul_div_rejects_a_quotient_past_u64() {
+        assert_eq!(mul_div_floor(u64::MAX as u128, 2, 1), Err(ProgramError::ArithmeticOverflow));
+        assert_eq!(mul_div_ceil(u64::MAX as u128, 2, 1), Err(ProgramError::ArithmeticOverflow));
+
+        // The intermediate product may exceed u64 as long as the quotient does not
+        assert_eq!(mul_div_floor(u64::MAX as u128, 2, 2).unwrap(), u64::MAX);
+    }
+}
@@ -0,0 +1,82 @@
+use core::mem::size_of;
+use pinocchio::{
+    ProgramResult,
+    account_info::{
+        AccountInfo,
+        RefMut
+    },
+    program_error::ProgramError
+};
+
+use crate::AmmError;
+
+// Per user and pool, at `[b"cooldown", config, user]`, only used by pools with a swap cooldown
+#[repr(C)]
+pub struct SwapCooldown {
+    last_swap_slot: [u8; 8],
+    bump: [u8; 1],
+}
+
+impl SwapCooldown {
+    pub const LEN: usize = size_of::<SwapCooldown>();
+
+    #[inline(always)]
+    pub fn load_mut(account_info: &AccountInfo) -> Result<RefMut<Self>, ProgramError> {
+        if account_info.data_len() != Self::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        if account_info.owner().ne(&crate::ID) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+        Ok(RefMut::map(account_info.try_borrow_mut_data()?, |data| unsafe {
+            &mut *(data.as_mut_ptr() as *mut SwapCooldown)
+        }))
+    }
+
+    #[inline(always)]
+    pub fn last_swap_slot(&self) -> u64 { u64::from_le_bytes(self.last_swap_slot) }
+
+    #[inline(always)]
+    pub fn bump(&self) -> &[u8; 1] { &self.bump }
+
+    #[inline(always)]
+    pub fn set_last_swap_slot(&mut self, slot: u64) {
+        self.last_swap_slot = slot.to_le_bytes();
+    }
+
+    #[inline(always)]
+    pub fn set_bump(&mut self, bump: [u8; 1]) {
+        self.bump = bump;
+    }
+}
+
+// Rejects a swap fewer than `cooldown_slots` after the user's last one. Kept free of the
+// clock sysvar so the comparison can be exercised directly
+#[inline(always)]
+pub fn check_cooldown(last_swap_slot: u64, slot: u64, cooldown_slots: u64) -> ProgramResult {
+    if slot.saturating_sub(last_swap_slot).lt(&cooldown_slots) {
+        return Err(AmmError::SwapCooldown.into());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn back_to_back_swaps_are_rejected() {
+        assert_eq!(check_cooldown(100, 100, 10), Err(AmmError::SwapCooldown.into()));
+        assert_eq!(check_cooldown(100, 109, 10), Err(AmmError::SwapCooldown.into()));
+    }
+
+    #[test]
+    fn spaced_swaps_succeed() {
+        assert!(check_cooldown(100, 110, 10).is_ok());
+        assert!(check_cooldown(100, 1_000, 10).is_ok());
+
+        // No cooldown leaves the same slot open
+        assert!(check_cooldown(100, 100, 0).is_ok());
+    }
+}
@@ -1,2 +1,5 @@
 pub mod state;
-pub use state::*;
\ No newline at end of file
+pub use state::*;
+
+pub mod cooldown;
+pub use cooldown::*;
@@ -5,9 +5,12 @@ use pinocchio::{
         Ref, 
         RefMut
     }, 
+    msg,
     program_error::ProgramError, 
     pubkey::Pubkey
 };
+
+use crate::AmmError;
  
 #[repr(C)]
 pub struct Config {
@@ -21,18 +24,101 @@ pub struct Config {
     vault_x_bump: [u8; 1],
     vault_y_bump: [u8; 1],
     mint_lp_bump: [u8; 1],
+    mint_x_decimals: u8,
+    mint_y_decimals: u8,
+    mint_lp_decimals: u8,
+    referral_bps: [u8; 2],
+    fees_x: [u8; 8], // Lifetime swap fees kept by the pool, per side
+    fees_y: [u8; 8],
+    curve_type: u8,
+    initial_price: [u8; 8], // Zero when the first deposit may set any price
+    price_tolerance_bps: [u8; 2],
+    // The lifetime fee counters as of the last fee snapshot
+    snapshot_fees_x: [u8; 8],
+    snapshot_fees_y: [u8; 8],
+    snapshot_ts: [u8; 8],
+    // Snapshotted fees not yet paid out to LPs, withdrawals pay them pro rata
+    pending_fees_x: [u8; 8],
+    pending_fees_y: [u8; 8],
+    // Skimmed in LP tokens to the treasury on deposits and withdrawals, apart from the swap fee
+    deposit_fee_bps: [u8; 2],
+    withdraw_fee_bps: [u8; 2],
+    swap_cooldown_slots: [u8; 8], // Zero when swaps are not rate limited per user
+    // Set by a flash swap until its repay, with the reserves the repay is checked against
+    flash_swap_active: u8,
+    flash_reserve_x: [u8; 8],
+    flash_reserve_y: [u8; 8],
 }
  
 #[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum AmmState {
     Initialized = 1u8,
     Disabled = 2u8,
     WithdrawOnly = 3u8,
 }
+
+impl TryFrom<u8> for AmmState {
+    type Error = ProgramError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(AmmState::Initialized),
+            2 => Ok(AmmState::Disabled),
+            3 => Ok(AmmState::WithdrawOnly),
+            _ => Err(AmmError::InvalidState.into()),
+        }
+    }
+}
+
+impl AmmState {
+    // The lifecycle of a pool. A live pool can be wound down to withdrawals only and
+    // back, and any pool can be disabled. A disabled pool can only be resumed as a
+    // whole, so LPs are never let out of a pool the authority stopped without it
+    // explicitly reopening. Staying in the same state is not a transition
+    #[inline(always)]
+    pub fn can_transition(from: AmmState, to: AmmState) -> bool {
+        matches!(
+            (from, to),
+            (AmmState::Initialized, AmmState::WithdrawOnly)
+                | (AmmState::WithdrawOnly, AmmState::Initialized)
+                | (AmmState::Initialized, AmmState::Disabled)
+                | (AmmState::WithdrawOnly, AmmState::Disabled)
+                | (AmmState::Disabled, AmmState::Initialized)
+        )
+    }
+}
  
+// The invariant the swaps are priced with, deposits and withdrawals are pro rata either way
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CurveType {
+    ConstantProduct = 0u8,
+    // Prices every unit 1:1 less the fee, meant for pairs of stable assets
+    ConstantSum = 1u8,
+}
+
+impl TryFrom<u8> for CurveType {
+    type Error = ProgramError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(CurveType::ConstantProduct),
+            1 => Ok(CurveType::ConstantSum),
+            _ => Err(ProgramError::InvalidInstructionData),
+        }
+    }
+}
+
 impl Config {
     // Constants
     pub const LEN: usize = size_of::<Config>();
+
+    // The initial price is a fixed point number of raw x units per raw y unit
+    pub const PRICE_SCALE: u64 = 1_000_000_000;
+
+    // The curve precision is 10^decimals as a u32, so 9 is the most it can hold
+    pub const MAX_DECIMALS: u8 = 9;
 }
 
 impl Config {
@@ -40,7 +126,7 @@ impl Config {
     #[inline(always)]
     pub fn load(account_info: &AccountInfo) -> Result<Ref<Self>, ProgramError> {
         if account_info.data_len() != Self::LEN {
-            return Err(ProgramError::InvalidAccountData);
+            return Err(AmmError::InvalidConfig.into());
         }
         if account_info.owner().ne(&crate::ID) {
             return Err(ProgramError::InvalidAccountOwner);
@@ -53,7 +139,7 @@ impl Config {
     #[inline(always)]
     pub unsafe fn load_unchecked(account_info: &AccountInfo) -> Result<&Self, ProgramError> {
         if account_info.data_len() != Self::LEN {
-            return Err(ProgramError::InvalidAccountData);
+            return Err(AmmError::InvalidConfig.into());
         }
         if account_info.owner() != &crate::ID {
             return Err(ProgramError::InvalidAccountOwner);
@@ -131,6 +217,82 @@ impl Config {
 
     #[inline(always)]
     pub fn vault_y_bump(&self) -> &[u8; 1] { &self.vault_y_bump }
+
+    // The share of the swap fee paid to a referrer, in basis points of the fee
+    #[inline(always)]
+    pub fn referral_bps(&self) -> u16 { u16::from_le_bytes(self.referral_bps) }
+
+    #[inline(always)]
+    pub fn fees_x(&self) -> u64 { u64::from_le_bytes(self.fees_x) }
+
+    #[inline(always)]
+    pub fn fees_y(&self) -> u64 { u64::from_le_bytes(self.fees_y) }
+
+    #[inline(always)]
+    pub fn snapshot_fees_x(&self) -> u64 { u64::from_le_bytes(self.snapshot_fees_x) }
+
+    #[inline(always)]
+    pub fn snapshot_fees_y(&self) -> u64 { u64::from_le_bytes(self.snapshot_fees_y) }
+
+    #[inline(always)]
+    pub fn snapshot_ts(&self) -> i64 { i64::from_le_bytes(self.snapshot_ts) }
+
+    #[inline(always)]
+    pub fn pending_fees_x(&self) -> u64 { u64::from_le_bytes(self.pending_fees_x) }
+
+    #[inline(always)]
+    pub fn pending_fees_y(&self) -> u64 { u64::from_le_bytes(self.pending_fees_y) }
+
+    #[inline(always)]
+    pub fn deposit_fee_bps(&self) -> u16 { u16::from_le_bytes(self.deposit_fee_bps) }
+
+    #[inline(always)]
+    pub fn withdraw_fee_bps(&self) -> u16 { u16::from_le_bytes(self.withdraw_fee_bps) }
+
+    #[inline(always)]
+    pub fn swap_cooldown_slots(&self) -> u64 { u64::from_le_bytes(self.swap_cooldown_slots) }
+
+    // The reserves stay borrowed while a flash swap is open, nothing else may price off them
+    #[inline(always)]
+    pub fn flash_swap_active(&self) -> bool { self.flash_swap_active.ne(&0) }
+
+    #[inline(always)]
+    pub fn flash_reserves(&self) -> (u64, u64) {
+        (u64::from_le_bytes(self.flash_reserve_x), u64::from_le_bytes(self.flash_reserve_y))
+    }
+
+    #[inline(always)]
+    pub fn mint_x_decimals(&self) -> u8 { self.mint_x_decimals }
+
+    #[inline(always)]
+    pub fn mint_y_decimals(&self) -> u8 { self.mint_y_decimals }
+
+    #[inline(always)]
+    pub fn mint_lp_decimals(&self) -> u8 { self.mint_lp_decimals }
+
+    #[inline(always)]
+    pub fn curve_type(&self) -> CurveType {
+        match self.curve_type {
+            1 => CurveType::ConstantSum,
+            _ => CurveType::ConstantProduct,
+        }
+    }
+
+    // The price the first deposit has to match, scaled by `PRICE_SCALE`
+    #[inline(always)]
+    pub fn initial_price(&self) -> u64 { u64::from_le_bytes(self.initial_price) }
+
+    #[inline(always)]
+    pub fn price_tolerance_bps(&self) -> u16 { u16::from_le_bytes(self.price_tolerance_bps) }
+
+    // The curve's liquidity math is scaled to the largest of the pair's and the LP mint's decimals
+    #[inline(always)]
+    pub fn precision(&self) -> Result<u32, ProgramError> {
+        let decimals = self.mint_x_decimals.max(self.mint_y_decimals).max(self.mint_lp_decimals);
+
+        10u32.checked_pow(decimals as u32)
+            .ok_or(ProgramError::ArithmeticOverflow)
+    }
 }
 
 impl Config {
@@ -138,7 +300,7 @@ impl Config {
     #[inline(always)]
     pub fn load_mut(account_info: &AccountInfo) -> Result<RefMut<Self>, ProgramError> {
         if account_info.data_len() != Self::LEN {
-            return Err(ProgramError::InvalidAccountData);
+            return Err(AmmError::InvalidConfig.into());
         }
         if account_info.owner().ne(&crate::ID) {
             return Err(ProgramError::InvalidAccountOwner);
@@ -150,23 +312,67 @@ impl Config {
  
     #[inline(always)]
     pub fn set_state(&mut self, state: u8) -> Result<(), ProgramError> {
-        if state.ge(&(AmmState::WithdrawOnly as u8)) {
-            return Err(ProgramError::InvalidAccountData);
-        }
-        self.state = state as u8;
+        self.state = AmmState::try_from(state)? as u8;
         Ok(())
     }
  
     #[inline(always)]
     pub fn set_fee(&mut self, fee: u16) -> Result<(), ProgramError> {
         if fee.ge(&10_000) {
-            return Err(ProgramError::InvalidAccountData);
+            return Err(AmmError::InvalidFee.into());
         }
         self.fee = fee.to_le_bytes();
         Ok(())
     }
 
 
+    #[inline(always)]
+    pub fn set_referral_bps(&mut self, referral_bps: u16) -> Result<(), ProgramError> {
+        if referral_bps.gt(&10_000) {
+            return Err(AmmError::InvalidFee.into());
+        }
+        self.referral_bps = referral_bps.to_le_bytes();
+        Ok(())
+    }
+
+    // The counters are informational, so an overflow is logged and
+    // saturates instead of failing the swap
+    #[inline(always)]
+    pub fn accrue_fee(&mut self, is_x: bool, fee: u64) {
+        let fees = match is_x {
+            true => &mut self.fees_x,
+            false => &mut self.fees_y,
+        };
+
+        let accrued = u64::from_le_bytes(*fees).checked_add(fee).unwrap_or_else(|| {
+            msg!("Fee counter overflow");
+            u64::MAX
+        });
+
+        *fees = accrued.to_le_bytes();
+    }
+
+    // Earmarks the fees accrued since the last snapshot for the LPs
+    #[inline(always)]
+    pub fn snapshot_fees(&mut self, now: i64) {
+        // The counters saturate, so the lifetime values never fall below the last snapshot
+        let accrued_x = self.fees_x().saturating_sub(self.snapshot_fees_x());
+        let accrued_y = self.fees_y().saturating_sub(self.snapshot_fees_y());
+
+        self.pending_fees_x = self.pending_fees_x().saturating_add(accrued_x).to_le_bytes();
+        self.pending_fees_y = self.pending_fees_y().saturating_add(accrued_y).to_le_bytes();
+
+        self.snapshot_fees_x = self.fees_x;
+        self.snapshot_fees_y = self.fees_y;
+        self.snapshot_ts = now.to_le_bytes();
+    }
+
+    #[inline(always)]
+    pub fn pay_pending_fees(&mut self, fee_x: u64, fee_y: u64) {
+        self.pending_fees_x = self.pending_fees_x().saturating_sub(fee_x).to_le_bytes();
+        self.pending_fees_y = self.pending_fees_y().saturating_sub(fee_y).to_le_bytes();
+    }
+
     #[inline(always)]
     pub fn set_seed(&mut self, seed: [u8;8]) {
         self.seed = seed;
@@ -207,6 +413,65 @@ impl Config {
         self.mint_lp_bump = bump;
     }
 
+    #[inline(always)]
+    pub fn set_mint_x_decimals(&mut self, decimals: u8) {
+        self.mint_x_decimals = decimals;
+    }
+
+    #[inline(always)]
+    pub fn set_mint_y_decimals(&mut self, decimals: u8) {
+        self.mint_y_decimals = decimals;
+    }
+
+    #[inline(always)]
+    pub fn set_mint_lp_decimals(&mut self, decimals: u8) {
+        self.mint_lp_decimals = decimals;
+    }
+
+    #[inline(always)]
+    pub fn set_curve_type(&mut self, curve_type: CurveType) {
+        self.curve_type = curve_type as u8;
+    }
+
+    #[inline(always)]
+    pub fn set_liquidity_fees(&mut self, deposit_fee_bps: u16, withdraw_fee_bps: u16) -> Result<(), ProgramError> {
+        if deposit_fee_bps.ge(&10_000) || withdraw_fee_bps.ge(&10_000) {
+            return Err(AmmError::InvalidFee.into());
+        }
+        self.deposit_fee_bps = deposit_fee_bps.to_le_bytes();
+        self.withdraw_fee_bps = withdraw_fee_bps.to_le_bytes();
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub fn set_swap_cooldown_slots(&mut self, slots: u64) {
+        self.swap_cooldown_slots = slots.to_le_bytes();
+    }
+
+    #[inline(always)]
+    pub fn open_flash_swap(&mut self, reserve_x: u64, reserve_y: u64) {
+        self.flash_swap_active = 1;
+        self.flash_reserve_x = reserve_x.to_le_bytes();
+        self.flash_reserve_y = reserve_y.to_le_bytes();
+    }
+
+    #[inline(always)]
+    pub fn close_flash_swap(&mut self) {
+        self.flash_swap_active = 0;
+        self.flash_reserve_x = [0; 8];
+        self.flash_reserve_y = [0; 8];
+    }
+
+    #[inline(always)]
+    pub fn set_initial_price(&mut self, initial_price: u64, price_tolerance_bps: u16) -> Result<(), ProgramError> {
+        if price_tolerance_bps.gt(&10_000) {
+            return Err(AmmError::InvalidFee.into());
+        }
+        self.initial_price = initial_price.to_le_bytes();
+        self.price_tolerance_bps = price_tolerance_bps.to_le_bytes();
+        Ok(())
+    }
+
     // ---- Updated initializer ----
     #[inline(always)]
     pub fn set_inner(
@@ -221,6 +486,13 @@ impl Config {
         vault_x_bump: [u8; 1],
         vault_y_bump: [u8; 1],
         mint_lp_bump: [u8; 1],
+        mint_x_decimals: u8,
+        mint_y_decimals: u8,
+        mint_lp_decimals: u8,
+        referral_bps: u16,
+        curve_type: CurveType,
+        initial_price: u64,
+        price_tolerance_bps: u16,
     ) -> Result<(), ProgramError> {
         self.set_state(state as u8)?;
         self.set_seed(seed);
@@ -232,6 +504,23 @@ impl Config {
         self.set_vault_x_bump(vault_x_bump);
         self.set_vault_y_bump(vault_y_bump);
         self.set_mint_lp_bump(mint_lp_bump);
+        self.set_mint_x_decimals(mint_x_decimals);
+        self.set_mint_y_decimals(mint_y_decimals);
+        self.set_mint_lp_decimals(mint_lp_decimals);
+        self.set_referral_bps(referral_bps)?;
+        self.set_curve_type(curve_type);
+        self.set_initial_price(initial_price, price_tolerance_bps)?;
+        self.fees_x = [0; 8];
+        self.fees_y = [0; 8];
+        self.snapshot_fees_x = [0; 8];
+        self.snapshot_fees_y = [0; 8];
+        self.snapshot_ts = [0; 8];
+        self.pending_fees_x = [0; 8];
+        self.pending_fees_y = [0; 8];
+        self.deposit_fee_bps = [0; 2];
+        self.withdraw_fee_bps = [0; 2];
+        self.swap_cooldown_slots = [0; 8];
+        self.close_flash_swap();
         Ok(())
     }
  
@@ -245,4 +534,88 @@ impl Config {
             None
         }
     }
-}
\ No newline at end of file
+
+    // Fails unless `authority` is the pool's authority, a renounced pool matches no key
+    #[inline(always)]
+    pub fn check_authority(&self, authority: &Pubkey) -> Result<(), ProgramError> {
+        match self.has_authority() {
+            Some(expected) if expected.eq(authority) => Ok(()),
+            _ => Err(ProgramError::IncorrectAuthority),
+        }
+    }
+
+    // The admin instructions have to be signed by the pool's authority
+    #[inline(always)]
+    pub fn check_admin(&self, authority: &AccountInfo) -> Result<(), ProgramError> {
+        if !authority.is_signer() {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        self.check_authority(authority.key())
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(data: &mut [u8; Config::LEN]) -> &mut Config {
+        let config = unsafe { Config::from_bytes_unchecked_mut(data) };
+
+        config.set_inner(AmmState::Initialized, [0; 8], [1; 32], [2; 32], [3; 32], 30,
+            [255], [255], [255], [255], 6, 6, 6, 0, crate::CurveType::ConstantProduct, 0, 0).unwrap();
+
+        config
+    }
+
+    #[test]
+    fn accrued_fees_add_up_per_side() {
+        let mut data = [0; Config::LEN];
+        let config = config(&mut data);
+
+        let mut summed = (0, 0);
+
+        for (is_x, amount_in) in [(true, 10_000), (false, 25_000), (true, 7_000)] {
+            let (_, fee) = crate::curve::swap(1_000_000, 1_000_000, is_x, amount_in, 30, crate::CurveType::ConstantSum).unwrap();
+
+            config.accrue_fee(is_x, fee);
+
+            match is_x {
+                true => summed.0 += fee,
+                false => summed.1 += fee,
+            }
+        }
+
+        assert_eq!(summed, (51, 75));
+        assert_eq!((config.fees_x(), config.fees_y()), summed);
+    }
+
+    #[test]
+    fn accrued_fees_saturate() {
+        let mut data = [0; Config::LEN];
+        let config = config(&mut data);
+
+        config.accrue_fee(true, u64::MAX - 1);
+        config.accrue_fee(true, 5);
+
+        // Informational only, the overflow is logged and the swap goes through
+        assert_eq!((config.fees_x(), config.fees_y()), (u64::MAX, 0));
+    }
+
+    #[test]
+    fn can_transition_follows_the_lifecycle() {
+        use AmmState::*;
+
+        assert!(AmmState::can_transition(Initialized, WithdrawOnly));
+        assert!(AmmState::can_transition(WithdrawOnly, Initialized));
+        assert!(AmmState::can_transition(Initialized, Disabled));
+        assert!(AmmState::can_transition(WithdrawOnly, Disabled));
+        assert!(AmmState::can_transition(Disabled, Initialized));
+
+        // A disabled pool is only ever resumed as a whole
+        assert!(!AmmState::can_transition(Disabled, WithdrawOnly));
+
+        for state in [Initialized, Disabled, WithdrawOnly] {
+            assert!(!AmmState::can_transition(state, state));
+        }
+    }
+}
@@ -0,0 +1,573 @@
+use core::mem::{size_of, size_of_val};
+use std::vec;
+
+use pinocchio::program_error::ProgramError;
+use solana_instruction::{AccountMeta, Instruction};
+use solana_pubkey::Pubkey;
+
+use crate::{AddExtra, Escrow, Make, MakeDelegated, Migrate, Refund, RefundMany, RentRecipients, Take, TopUp};
+
+// The account metas follow the order expected by the `try_from` of each
+// instruction's accounts, which is not always the order of the struct fields
+
+#[inline]
+fn program_id() -> Pubkey {
+    Pubkey::new_from_array(crate::ID)
+}
+
+pub fn escrow_address(maker: &Pubkey, seed: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"escrow", maker.as_ref(), seed.to_le_bytes().as_ref()],
+        &program_id()
+    ).0
+}
+
+pub fn associated_token_address(owner: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[owner.as_ref(), token_program.as_ref(), mint.as_ref()],
+        &Pubkey::new_from_array(pinocchio_associated_token_account::ID)
+    ).0
+}
+
+pub fn make(
+    maker: &Pubkey,
+    mint_a: &Pubkey,
+    mint_b: &Pubkey,
+    token_program: &Pubkey,
+    seed: u64,
+    receive: u64,
+    amount: u64,
+) -> Instruction {
+    let escrow = escrow_address(maker, seed);
+
+    let mut data = vec![Make::DISCRIMINATOR];
+    data.extend_from_slice(&seed.to_le_bytes());
+    data.extend_from_slice(&receive.to_le_bytes());
+    data.extend_from_slice(&amount.to_le_bytes());
+
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(*maker, true),
+            AccountMeta::new(escrow, false),
+            AccountMeta::new_readonly(*mint_a, false),
+            AccountMeta::new_readonly(*mint_b, false),
+            AccountMeta::new(associated_token_address(maker, mint_a, token_program), false),
+            AccountMeta::new(associated_token_address(&escrow, mint_a, token_program), false),
+            AccountMeta::new_readonly(Pubkey::new_from_array(pinocchio_system::ID), false),
+            AccountMeta::new_readonly(*token_program, false),
+            AccountMeta::new_readonly(Pubkey::new_from_array(pinocchio_associated_token_account::ID), false),
+        ],
+        data,
+    }
+}
+
+// The maker approves `relayer` as delegate of its mint_a ATA for `amount` beforehand,
+// the relayer signs and pays for the escrow in its place
+#[allow(clippy::too_many_arguments)]
+pub fn make_delegated(
+    relayer: &Pubkey,
+    maker: &Pubkey,
+    mint_a: &Pubkey,
+    mint_b: &Pubkey,
+    token_program: &Pubkey,
+    seed: u64,
+    receive: u64,
+    amount: u64,
+) -> Instruction {
+    // The accounts of `make` behind the relayer, with the maker no longer signing
+    let mut instruction = make(maker, mint_a, mint_b, token_program, seed, receive, amount);
+
+    instruction.data[0] = MakeDelegated::DISCRIMINATOR;
+    instruction.accounts[0].is_signer = false;
+    instruction.accounts.insert(0, AccountMeta::new(*relayer, true));
+
+    instruction
+}
+
+// Escrows made through a relayer return their rent to it, so it has to be
+// passed as `close_to`, otherwise the rent goes to the maker. The rent payer can
+// send the vault rent to `vault_rent_to` instead, it has to sign for that.
+// Returns the flags telling the program which of them were passed
+fn push_close_to(
+    accounts: &mut vec::Vec<AccountMeta>,
+    close_to: Option<&Pubkey>,
+    vault_rent_to: Option<&Pubkey>,
+) -> RentRecipients {
+    if let Some(close_to) = close_to {
+        accounts.push(AccountMeta::new(*close_to, vault_rent_to.is_some()));
+    }
+
+    if let Some(vault_rent_to) = vault_rent_to {
+        accounts.push(AccountMeta::new(*vault_rent_to, false));
+    }
+
+    RentRecipients {
+        close_to: close_to.is_some(),
+        vault_rent_to: vault_rent_to.is_some(),
+    }
+}
+
+// The extra assets go after the rent recipients as a (vault, mint) pair per mint, in the order
+// they were added to the escrow, then the account of `owner` receiving each
+fn push_extra(
+    accounts: &mut vec::Vec<AccountMeta>,
+    escrow: &Pubkey,
+    owner: &Pubkey,
+    extra_mints: &[Pubkey],
+    token_program: &Pubkey,
+) {
+    for mint in extra_mints {
+        accounts.push(AccountMeta::new(associated_token_address(escrow, mint, token_program), false));
+        accounts.push(AccountMeta::new_readonly(*mint, false));
+    }
+
+    for mint in extra_mints {
+        accounts.push(AccountMeta::new(associated_token_address(owner, mint, token_program), false));
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn take(
+    taker: &Pubkey,
+    maker: &Pubkey,
+    mint_a: &Pubkey,
+    mint_b: &Pubkey,
+    token_program: &Pubkey,
+    seed: u64,
+    close_to: Option<&Pubkey>,
+    vault_rent_to: Option<&Pubkey>,
+    extra_mints: &[Pubkey],
+    expected_receive: u64,
+) -> Instruction {
+    let escrow = escrow_address(maker, seed);
+
+    // Without a `close_to` the maker is the rent payer, so it signs to move the vault rent
+    let mut accounts = vec![
+        AccountMeta::new(*taker, true),
+        AccountMeta::new(*maker, close_to.is_none() && vault_rent_to.is_some()),
+        AccountMeta::new(escrow, false),
+        AccountMeta::new_readonly(*mint_a, false),
+        AccountMeta::new_readonly(*mint_b, false),
+        AccountMeta::new(associated_token_address(&escrow, mint_a, token_program), false),
+        AccountMeta::new(associated_token_address(taker, mint_a, token_program), false),
+        AccountMeta::new(associated_token_address(taker, mint_b, token_program), false),
+        AccountMeta::new(associated_token_address(maker, mint_b, token_program), false),
+        AccountMeta::new_readonly(Pubkey::new_from_array(pinocchio_system::ID), false),
+        AccountMeta::new_readonly(*token_program, false),
+        AccountMeta::new_readonly(Pubkey::new_from_array(pinocchio_associated_token_account::ID), false),
+    ];
+
+    let recipients = push_close_to(&mut accounts, close_to, vault_rent_to);
+    push_extra(&mut accounts, &escrow, taker, extra_mints, token_program);
+
+    let mut data = vec![Take::DISCRIMINATOR];
+    data.extend_from_slice(&expected_receive.to_le_bytes());
+    data.push(recipients.to_byte());
+
+    Instruction {
+        program_id: program_id(),
+        accounts,
+        data,
+    }
+}
+
+pub fn refund(
+    maker: &Pubkey,
+    mint_a: &Pubkey,
+    token_program: &Pubkey,
+    seed: u64,
+    close_to: Option<&Pubkey>,
+    vault_rent_to: Option<&Pubkey>,
+    extra_mints: &[Pubkey],
+) -> Instruction {
+    let escrow = escrow_address(maker, seed);
+
+    let mut accounts = vec![
+        AccountMeta::new(*maker, true),
+        AccountMeta::new(escrow, false),
+        AccountMeta::new_readonly(*mint_a, false),
+        AccountMeta::new(associated_token_address(&escrow, mint_a, token_program), false),
+        AccountMeta::new(associated_token_address(maker, mint_a, token_program), false),
+        AccountMeta::new_readonly(Pubkey::new_from_array(pinocchio_system::ID), false),
+        AccountMeta::new_readonly(*token_program, false),
+        AccountMeta::new_readonly(Pubkey::new_from_array(pinocchio_associated_token_account::ID), false),
+    ];
+
+    let recipients = push_close_to(&mut accounts, close_to, vault_rent_to);
+    push_extra(&mut accounts, &escrow, maker, extra_mints, token_program);
+
+    Instruction {
+        program_id: program_id(),
+        accounts,
+        data: vec![Refund::DISCRIMINATOR, recipients.to_byte()],
+    }
+}
+
+// Each escrow is refunded with the vault and maker ATA of its own mint
+pub fn refund_many(
+    maker: &Pubkey,
+    token_program: &Pubkey,
+    escrows: &[(u64, Pubkey)],
+) -> Instruction {
+    let mut accounts = vec![
+        AccountMeta::new(*maker, true),
+        AccountMeta::new_readonly(Pubkey::new_from_array(pinocchio_system::ID), false),
+        AccountMeta::new_readonly(*token_program, false),
+    ];
+
+    for (seed, mint_a) in escrows {
+        let escrow = escrow_address(maker, *seed);
+
+        accounts.push(AccountMeta::new(escrow, false));
+        accounts.push(AccountMeta::new(associated_token_address(&escrow, mint_a, token_program), false));
+        accounts.push(AccountMeta::new(associated_token_address(maker, mint_a, token_program), false));
+    }
+
+    Instruction {
+        program_id: program_id(),
+        accounts,
+        data: vec![RefundMany::DISCRIMINATOR],
+    }
+}
+
+pub fn top_up(
+    maker: &Pubkey,
+    mint_a: &Pubkey,
+    token_program: &Pubkey,
+    seed: u64,
+    amount: u64,
+    receive_increase: u64,
+) -> Instruction {
+    let escrow = escrow_address(maker, seed);
+
+    let mut data = vec![TopUp::DISCRIMINATOR];
+    data.extend_from_slice(&amount.to_le_bytes());
+    data.extend_from_slice(&receive_increase.to_le_bytes());
+
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+        AccountMeta::new(*maker, true),
+        AccountMeta::new(escrow, false),
+        AccountMeta::new_readonly(*mint_a, false),
+        AccountMeta::new(associated_token_address(maker, mint_a, token_program), false),
+        AccountMeta::new(associated_token_address(&escrow, mint_a, token_program), false),
+        AccountMeta::new_readonly(*token_program, false),
+        ],
+        data,
+    }
+}
+
+pub fn add_extra(
+    maker: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+    seed: u64,
+    amount: u64,
+) -> Instruction {
+    let escrow = escrow_address(maker, seed);
+
+    let mut data = vec![AddExtra::DISCRIMINATOR];
+    data.extend_from_slice(&amount.to_le_bytes());
+
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(*maker, true),
+            AccountMeta::new(escrow, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new(associated_token_address(maker, mint, token_program), false),
+            AccountMeta::new(associated_token_address(&escrow, mint, token_program), false),
+            AccountMeta::new_readonly(Pubkey::new_from_array(pinocchio_system::ID), false),
+            AccountMeta::new_readonly(*token_program, false),
+            AccountMeta::new_readonly(Pubkey::new_from_array(pinocchio_associated_token_account::ID), false),
+        ],
+        data,
+    }
+}
+
+// Grows an escrow made before the rent payer was recorded, `payer` covers the extra rent
+pub fn migrate(payer: &Pubkey, escrow: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: program_id(),
+        accounts: vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(*escrow, false),
+            AccountMeta::new_readonly(Pubkey::new_from_array(pinocchio_system::ID), false),
+        ],
+        data: vec![Migrate::DISCRIMINATOR],
+    }
+}
+
+// A plain copy of the escrow fields for tooling reading escrow accounts
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EscrowView {
+    pub seed: u64,
+    pub maker: Pubkey,
+    pub mint_a: Pubkey,
+    pub mint_b: Pubkey,
+    pub receive: u64,
+    pub rent_payer: Pubkey,
+    pub bump: u8,
+}
+
+pub fn decode_escrow(data: &[u8]) -> Result<EscrowView, ProgramError> {
+    // Fetched account data carries no alignment guarantee, so it is copied
+    // into an aligned buffer before going through the same load as the program
+    let mut aligned = [0u64; size_of::<Escrow>().div_ceil(8)];
+
+    let buffer = unsafe {
+        core::slice::from_raw_parts_mut(aligned.as_mut_ptr() as *mut u8, size_of_val(&aligned))
+    };
+
+    // Only the escrow fields are decoded, the extra mints past them are left out
+    let data = data.get(..Escrow::LEN).ok_or(ProgramError::InvalidAccountData)?;
+    let buffer = buffer.get_mut(..data.len()).ok_or(ProgramError::InvalidAccountData)?;
+    buffer.copy_from_slice(data);
+
+    let escrow = Escrow::load(buffer)?;
+
+    Ok(EscrowView {
+        seed: u64::from_le_bytes(*escrow.seed()),
+        maker: Pubkey::new_from_array(*escrow.maker()),
+        mint_a: Pubkey::new_from_array(*escrow.mint_a()),
+        mint_b: Pubkey::new_from_array(*escrow.mint_b()),
+        receive: escrow.receive,
+        rent_payer: Pubkey::new_from_array(*escrow.rent_payer()),
+        bump: escrow.bump()[0],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pinocchio::account_info::AccountInfo;
+
+    use crate::{testing::TestAccounts, MakeAccounts, MakeData, RefundAccounts, RefundData, TakeAccounts, TakeData};
+
+    // The accounts of `instruction` as the program is handed them
+    fn accounts(instruction: &Instruction) -> TestAccounts {
+        let mut accounts = TestAccounts::default();
+
+        for meta in &instruction.accounts {
+            accounts.add(meta.pubkey.to_bytes(), [0; 32], meta.is_signer, 0, &[]);
+        }
+
+        accounts
+    }
+
+    fn key(account: &AccountInfo) -> Pubkey {
+        Pubkey::new_from_array(*account.key())
+    }
+
+    fn keys(accounts: &[AccountInfo]) -> vec::Vec<Pubkey> {
+        accounts.iter().map(key).collect()
+    }
+
+    const TOKEN_PROGRAM: Pubkey = Pubkey::new_from_array(pinocchio_token::ID);
+
+    #[test]
+    fn make_round_trips_through_the_parser() {
+        let (maker, mint_a, mint_b) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let escrow = escrow_address(&maker, 7);
+
+        let instruction = make(&maker, &mint_a, &mint_b, &TOKEN_PROGRAM, 7, 250, 100);
+        let mut accounts = accounts(&instruction);
+        let infos = accounts.infos();
+
+        let parsed = MakeAccounts::try_from(infos.as_slice()).unwrap();
+        assert_eq!(
+            keys(&[
+                parsed.maker.clone(), parsed.payer.clone(), parsed.escrow.clone(), parsed.mint_a.clone(),
+                parsed.mint_b.clone(), parsed.maker_ata_a.clone(), parsed.vault.clone(), parsed.token_program.clone()
+            ]),
+            [
+                maker, maker, escrow, mint_a, mint_b,
+                associated_token_address(&maker, &mint_a, &TOKEN_PROGRAM),
+                associated_token_address(&escrow, &mint_a, &TOKEN_PROGRAM),
+                TOKEN_PROGRAM
+            ]
+        );
+        assert!(parsed.maker.is_signer());
+
+        assert_eq!(instruction.data[0], Make::DISCRIMINATOR);
+        let data = MakeData::try_from(&instruction.data[1..]).unwrap();
+        assert_eq!((data.seed, data.recieve, data.amount), (7u64.to_le_bytes(), 250, 100));
+    }
+
+    #[test]
+    fn make_delegated_puts_the_relayer_ahead_of_the_make_accounts() {
+        let (relayer, maker, mint_a, mint_b) =
+            (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+
+        let instruction = make_delegated(&relayer, &maker, &mint_a, &mint_b, &TOKEN_PROGRAM, 7, 250, 100);
+        let mut accounts = accounts(&instruction);
+        let infos = accounts.infos();
+
+        assert_eq!(instruction.data[0], MakeDelegated::DISCRIMINATOR);
+        assert_eq!(instruction.data[1..], make(&maker, &mint_a, &mint_b, &TOKEN_PROGRAM, 7, 250, 100).data[1..]);
+
+        // Only the relayer signs
+        assert_eq!(key(&infos[0]), relayer);
+        assert!(infos[0].is_signer());
+
+        let parsed = MakeAccounts::try_from(&infos[1..]).unwrap();
+        assert_eq!(key(parsed.maker), maker);
+        assert!(!parsed.maker.is_signer());
+        assert_eq!(key(parsed.escrow), escrow_address(&maker, 7));
+    }
+
+    #[test]
+    fn take_round_trips_through_the_parser() {
+        let (taker, maker, mint_a, mint_b) =
+            (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let (relayer, fee_account, extra_mint) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let escrow = escrow_address(&maker, 7);
+
+        let instruction = take(
+            &taker, &maker, &mint_a, &mint_b, &TOKEN_PROGRAM, 7,
+            Some(&relayer), Some(&fee_account), &[extra_mint], 250
+        );
+        let mut accounts = accounts(&instruction);
+        let infos = accounts.infos();
+
+        assert_eq!(instruction.data[0], Take::DISCRIMINATOR);
+        let data = TakeData::try_from(&instruction.data[1..]).unwrap();
+        assert_eq!(data.expected_receive, 250);
+
+        let parsed = TakeAccounts::try_from((infos.as_slice(), data.recipients)).unwrap();
+        assert_eq!(
+            keys(&[
+                parsed.taker.clone(), parsed.maker.clone(), parsed.escrow.clone(), parsed.mint_a.clone(),
+                parsed.mint_b.clone(), parsed.vault.clone(), parsed.taker_ata_a.clone(),
+                parsed.taker_ata_b.clone(), parsed.maker_ata_b.clone(), parsed.token_program.clone(),
+                parsed.close_to.clone(), parsed.vault_rent_to.clone()
+            ]),
+            [
+                taker, maker, escrow, mint_a, mint_b,
+                associated_token_address(&escrow, &mint_a, &TOKEN_PROGRAM),
+                associated_token_address(&taker, &mint_a, &TOKEN_PROGRAM),
+                associated_token_address(&taker, &mint_b, &TOKEN_PROGRAM),
+                associated_token_address(&maker, &mint_b, &TOKEN_PROGRAM),
+                TOKEN_PROGRAM, relayer, fee_account
+            ]
+        );
+
+        // The rent payer signs to send the vault rent elsewhere
+        assert!(parsed.close_to.is_signer());
+
+        assert_eq!(
+            keys(parsed.extra),
+            [
+                associated_token_address(&escrow, &extra_mint, &TOKEN_PROGRAM),
+                extra_mint,
+                associated_token_address(&taker, &extra_mint, &TOKEN_PROGRAM)
+            ]
+        );
+    }
+
+    #[test]
+    fn take_defaults_the_rent_recipients_to_the_maker() {
+        let (taker, maker, mint_a, mint_b) =
+            (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+
+        let instruction = take(&taker, &maker, &mint_a, &mint_b, &TOKEN_PROGRAM, 7, None, None, &[], 250);
+        let mut accounts = accounts(&instruction);
+        let infos = accounts.infos();
+
+        let data = TakeData::try_from(&instruction.data[1..]).unwrap();
+        let parsed = TakeAccounts::try_from((infos.as_slice(), data.recipients)).unwrap();
+
+        assert_eq!((key(parsed.close_to), key(parsed.vault_rent_to)), (maker, maker));
+        assert!(!parsed.maker.is_signer());
+        assert!(parsed.extra.is_empty());
+    }
+
+    #[test]
+    fn refund_round_trips_through_the_parser() {
+        let (maker, mint_a, fee_account) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let escrow = escrow_address(&maker, 7);
+
+        // The maker paid for the escrow and sends the vault rent to a fee account
+        let instruction = refund(&maker, &mint_a, &TOKEN_PROGRAM, 7, None, Some(&fee_account), &[]);
+        let mut accounts = accounts(&instruction);
+        let infos = accounts.infos();
+
+        assert_eq!(instruction.data[0], Refund::DISCRIMINATOR);
+        let data = RefundData::try_from(&instruction.data[1..]).unwrap();
+
+        let parsed = RefundAccounts::try_from((infos.as_slice(), data.recipients)).unwrap();
+        assert_eq!(
+            keys(&[
+                parsed.maker.clone(), parsed.escrow.clone(), parsed.mint_a.clone(), parsed.vault.clone(),
+                parsed.maker_ata_a.clone(), parsed.token_program.clone(), parsed.close_to.clone(),
+                parsed.vault_rent_to.clone()
+            ]),
+            [
+                maker, escrow, mint_a,
+                associated_token_address(&escrow, &mint_a, &TOKEN_PROGRAM),
+                associated_token_address(&maker, &mint_a, &TOKEN_PROGRAM),
+                TOKEN_PROGRAM, maker, fee_account
+            ]
+        );
+        assert!(parsed.close_to.is_signer());
+        assert!(parsed.extra.is_empty());
+    }
+
+    fn escrow_bytes<const N: usize>() -> [u8; N] {
+        let mut data = [0u8; N];
+
+        data[..8].copy_from_slice(&42u64.to_le_bytes());
+        data[8..40].copy_from_slice(&[1; 32]);
+        data[40..72].copy_from_slice(&[2; 32]);
+        data[72..104].copy_from_slice(&[3; 32]);
+        data[104..112].copy_from_slice(&500u64.to_le_bytes());
+        data[112] = 254;
+        data[113..145].copy_from_slice(&[4; 32]);
+
+        data
+    }
+
+    fn expected_view() -> EscrowView {
+        EscrowView {
+            seed: 42,
+            maker: Pubkey::new_from_array([1; 32]),
+            mint_a: Pubkey::new_from_array([2; 32]),
+            mint_b: Pubkey::new_from_array([3; 32]),
+            receive: 500,
+            rent_payer: Pubkey::new_from_array([4; 32]),
+            bump: 254,
+        }
+    }
+
+    #[test]
+    fn decode_escrow_reads_the_fields() {
+        let data = escrow_bytes::<{ Escrow::LEN }>();
+
+        assert_eq!(decode_escrow(&data).unwrap(), expected_view());
+
+        // Unaligned account data decodes the same
+        let mut shifted = [0u8; Escrow::LEN + 1];
+        shifted[1..].copy_from_slice(&data);
+        assert_eq!(decode_escrow(&shifted[1..]).unwrap(), expected_view());
+    }
+
+    #[test]
+    fn decode_escrow_skips_the_extra_mints() {
+        let mut data = escrow_bytes::<{ Escrow::space(2) }>();
+        data[Escrow::LEN..].fill(9);
+
+        assert_eq!(decode_escrow(&data).unwrap(), expected_view());
+    }
+
+    #[test]
+    fn decode_escrow_rejects_short_data() {
+        let data = escrow_bytes::<{ Escrow::LEN }>();
+
+        // An escrow not migrated yet still has the legacy length
+        assert_eq!(decode_escrow(&data[..Escrow::LEGACY_LEN]), Err(ProgramError::InvalidAccountData));
+        assert_eq!(decode_escrow(&data[..Escrow::LEN - 1]), Err(ProgramError::InvalidAccountData));
+        assert_eq!(decode_escrow(&[]), Err(ProgramError::InvalidAccountData));
+    }
+}
@@ -0,0 +1,63 @@
+use pinocchio::program_error::ProgramError;
+
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EscrowError {
+    // Both sides of the escrow use the same mint
+    SameMint,
+    // The maker does not match the one stored in the escrow
+    InvalidMaker,
+    // The escrow or its mints do not match the accounts passed in
+    EscrowMismatch,
+    // The deposit or the expected amount is zero
+    ZeroAmount,
+    // The relayer is not approved as delegate for the deposit
+    InsufficientDelegation,
+    // The rent is not being returned to the account that paid it
+    InvalidRentPayer,
+    // The taker's paying account is not owned by the taker
+    InvalidTakerAccount,
+    // More accounts were passed than the instruction takes
+    TooManyAccounts,
+    // A created account holds less than the rent exempt minimum
+    NotRentExempt,
+    // The batch holds more entries than `MAX_BATCH`
+    BatchTooLarge,
+    // The account to create already holds lamports, data or belongs to the program
+    AccountInUse,
+    // A token account the escrow moves tokens from or to is frozen
+    AccountFrozen,
+    // The vault for the escrow is already a token account, usually from a reused seed
+    VaultAlreadyExists,
+    // The extra assets passed are not exactly the ones recorded in the escrow
+    ExtraAssetsMismatch,
+    // The escrow asks for a different amount than the taker agreed to pay
+    ReceiveMismatch,
+}
+
+impl From<EscrowError> for ProgramError {
+    fn from(error: EscrowError) -> Self {
+        ProgramError::Custom(error as u32)
+    }
+}
+
+// The error for an account list of the wrong length, too few and too many are told apart
+#[inline(always)]
+pub fn account_count_error(passed: usize, expected: usize) -> ProgramError {
+    match passed < expected {
+        true => ProgramError::NotEnoughAccountKeys,
+        false => EscrowError::TooManyAccounts.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn too_few_and_too_many_accounts_are_told_apart() {
+        assert_eq!(account_count_error(8, 9), ProgramError::NotEnoughAccountKeys);
+        assert_eq!(account_count_error(0, 9), ProgramError::NotEnoughAccountKeys);
+        assert_eq!(account_count_error(10, 9), EscrowError::TooManyAccounts.into());
+    }
+}
@@ -0,0 +1,88 @@
+use heapless::String;
+
+// Long enough for "0." followed by the most fractional digits a u8 of decimals allows
+pub const FORMATTED_AMOUNT_LEN: usize = 2 + u8::MAX as usize;
+
+// Renders a raw token amount with its decimals for logs, trailing zeros
+// of the fractional part are dropped so 1_500_000 with 6 decimals is "1.5"
+pub fn format_amount(raw: u64, decimals: u8) -> String<FORMATTED_AMOUNT_LEN> {
+    // A u64 has at most 20 decimal digits
+    let mut digits = [0u8; 20];
+    let mut len = 0;
+    let mut value = raw;
+
+    loop {
+        digits[len] = b'0' + (value % 10) as u8;
+        len += 1;
+        value /= 10;
+
+        if value == 0 {
+            break;
+        }
+    }
+
+    // The digits were produced from the least significant one
+    digits[..len].reverse();
+
+    let decimals = decimals as usize;
+
+    let mut formatted = String::new();
+
+    // The pushes cannot fail since the capacity covers the longest output
+    let (integer, fraction) = match len > decimals {
+        true => (&digits[..len - decimals], &digits[len - decimals..len]),
+        false => (&b"0"[..], &digits[..len]),
+    };
+
+    for digit in integer {
+        let _ = formatted.push(*digit as char);
+    }
+
+    // Only the significant part of the fraction is shown
+    let leading_zeros = decimals.saturating_sub(len);
+    let fraction = match fraction.iter().rposition(|digit| *digit != b'0') {
+        Some(last) => &fraction[..=last],
+        None => return formatted,
+    };
+
+    let _ = formatted.push('.');
+
+    for _ in 0..leading_zeros {
+        let _ = formatted.push('0');
+    }
+
+    for digit in fraction {
+        let _ = formatted.push(*digit as char);
+    }
+
+    formatted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_amount_places_the_decimal_point() {
+        assert_eq!(format_amount(1_500_000, 6).as_str(), "1.5");
+        assert_eq!(format_amount(123_456, 2).as_str(), "1234.56");
+        assert_eq!(format_amount(5, 3).as_str(), "0.005");
+    }
+
+    #[test]
+    fn format_amount_drops_trailing_zeros() {
+        assert_eq!(format_amount(1_000_000, 6).as_str(), "1");
+        assert_eq!(format_amount(0, 0).as_str(), "0");
+        assert_eq!(format_amount(0, 9).as_str(), "0");
+        assert_eq!(format_amount(120, 0).as_str(), "120");
+    }
+
+    #[test]
+    fn format_amount_fits_the_extremes() {
+        assert_eq!(format_amount(u64::MAX, 0).as_str(), "18446744073709551615");
+
+        let formatted = format_amount(1, u8::MAX);
+        assert_eq!(formatted.len(), FORMATTED_AMOUNT_LEN);
+        assert!(formatted.ends_with("01"));
+    }
+}
@@ -0,0 +1,186 @@
+use pinocchio::{
+    account_info::AccountInfo, program_error::ProgramError,
+    pubkey::{create_program_address, Pubkey},
+    ProgramResult
+};
+
+use core::mem::size_of;
+
+use basic_helpers::{
+    ProgramAccount, SignerAccount
+};
+use associated_token_helpers::{
+    AssociatedTokenAccount
+};
+use token_interface_helpers::{
+    TokenAccountInterface,
+    MintInterface
+};
+
+use crate::{account_count_error, check_not_frozen, check_token_account, realloc_program_account, Escrow, EscrowError, MAX_BATCH};
+
+// Adds another asset to an escrow, recorded in the escrow so Take and Refund have to
+// move it along with mint_a
+pub struct AddExtra<'info>{
+    accounts:AddExtraAccounts<'info>,
+    data:AddExtraData
+}
+
+impl<'info> TryFrom<(&'info[AccountInfo], &[u8])> for AddExtra<'info>{
+
+    #[inline]
+    fn try_from(value: (&'info[AccountInfo], &[u8])) -> Result<Self, Self::Error> {
+        let accounts = AddExtraAccounts::try_from(value.0)?;
+        let data = AddExtraData::try_from(value.1)?;
+
+        Ok(AddExtra{
+            accounts,
+            data
+        })
+    }
+
+    type Error = ProgramError;
+}
+
+impl<'info> AddExtra<'info>{
+    pub const DISCRIMINATOR:u8 = 6;
+
+    pub fn check(&self)->ProgramResult{
+        // Check if the maker signed
+        SignerAccount::check(self.accounts.maker)?;
+        // Check if the mint is valid
+        MintInterface::check(self.accounts.mint)?;
+        // Check that the funding ATA belongs to the maker
+        check_token_account(self.accounts.maker_ata, self.accounts.mint.key(), self.accounts.maker.key())?;
+
+        if self.data.amount.eq(&0) {
+            return Err(EscrowError::ZeroAmount.into());
+        }
+
+        // Check that the escrow is valid and belongs to the program, its length is
+        // checked by the load below
+        ProgramAccount::check(self.accounts.escrow, self.accounts.escrow.data_len(), &crate::ID)?;
+
+        let escrow_ref = self.accounts.escrow.try_borrow_data()?;
+        let escrow = Escrow::load(&escrow_ref)?;
+
+        if escrow.maker() != self.accounts.maker.key() {
+            return Err(EscrowError::InvalidMaker.into());
+        }
+
+        // The escrow's own mints are not extra assets
+        if escrow.mint_a() == self.accounts.mint.key() || escrow.mint_b() == self.accounts.mint.key() {
+            return Err(EscrowError::EscrowMismatch.into());
+        }
+
+        // A mint is recorded once, its vault is the escrow's only ATA for it
+        let extra_mints = Escrow::extra_mints(&escrow_ref)?;
+
+        if extra_mints.contains(self.accounts.mint.key()) {
+            return Err(EscrowError::ExtraAssetsMismatch.into());
+        }
+
+        // Take and Refund move every extra asset in one instruction
+        if extra_mints.len() >= MAX_BATCH {
+            return Err(EscrowError::BatchTooLarge.into());
+        }
+
+        let escrow_pda = create_program_address( // This check also binds the maker to the escrow
+            &[b"escrow", self.accounts.maker.key().as_ref(),
+            escrow.seed().as_ref(), escrow.bump().as_ref()],
+            &crate::ID
+        ).map_err(|_| EscrowError::EscrowMismatch)?;
+
+        if self.accounts.escrow.key() != &escrow_pda {
+            return Err(EscrowError::EscrowMismatch.into());
+        }
+
+        AssociatedTokenAccount::check(
+            self.accounts.vault,
+            self.accounts.escrow,
+            self.accounts.mint,
+            self.accounts.token_program,
+            false
+        )
+    }
+
+    pub fn process(&self)->ProgramResult{
+
+        // Perform the checks
+        self.check()?;
+
+        // Record the mint past the ones already held
+        let len = self.accounts.escrow.data_len();
+
+        realloc_program_account(self.accounts.escrow, self.accounts.maker, len + size_of::<Pubkey>())?;
+
+        self.accounts.escrow.try_borrow_mut_data()?[len..].copy_from_slice(self.accounts.mint.key());
+
+        // Anyone can create the escrow's ATA ahead of time, so an existing one is reused
+        AssociatedTokenAccount::init_if_needed(
+            self.accounts.vault,
+            self.accounts.mint,
+            self.accounts.maker,
+            self.accounts.escrow,
+            self.accounts.system_program,
+            self.accounts.token_program
+        )?;
+
+        check_not_frozen(self.accounts.maker_ata)?;
+
+        TokenAccountInterface::transfer(
+            self.accounts.maker_ata,
+            self.accounts.vault,
+            self.accounts.maker,
+            self.data.amount,
+            self.accounts.token_program,
+            &[]
+        )
+    }
+}
+
+pub struct AddExtraAccounts<'a> {
+    pub maker: &'a AccountInfo,
+    pub escrow: &'a AccountInfo,
+    pub mint: &'a AccountInfo,
+    pub maker_ata: &'a AccountInfo,
+    pub vault: &'a AccountInfo,
+    pub system_program: &'a AccountInfo,
+    pub token_program: &'a AccountInfo
+}
+
+impl<'a> TryFrom<&'a[AccountInfo]> for AddExtraAccounts<'a> {
+    type Error = ProgramError;
+
+    #[inline]
+    fn try_from(accounts: &'a[AccountInfo]) -> Result<AddExtraAccounts<'a>, Self::Error> {
+        let [maker, escrow, mint, maker_ata, vault,
+            system_program, token_program, _] = accounts else {
+        return Err(account_count_error(accounts.len(), 8));
+        };
+
+        Ok(AddExtraAccounts {
+            maker,
+            escrow,
+            mint,
+            maker_ata,
+            vault,
+            system_program,
+            token_program
+        })
+    }
+}
+
+pub struct AddExtraData{
+    pub amount:u64
+}
+
+impl TryFrom<&[u8]> for AddExtraData {
+    type Error = ProgramError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        let amount_bytes: [u8; 8] = value.try_into().map_err(|_| ProgramError::InvalidInstructionData)?;
+
+        Ok(AddExtraData { amount: u64::from_le_bytes(amount_bytes) })
+    }
+}
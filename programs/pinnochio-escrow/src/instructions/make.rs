@@ -13,7 +13,9 @@ use token_interface_helpers::{
     MintInterface
 };
 
-use crate::Escrow;
+use core::fmt::Write;
+
+use crate::{account_count_error, assert_untouched, assert_vault_absent, check_token_account, delegated_amount, format_amount, mint_decimals, Escrow, EscrowError, ProgramAccountRent, FORMATTED_AMOUNT_LEN};
 
 pub struct Make<'info>{
     accounts:MakeAccounts<'info>,
@@ -39,31 +41,56 @@ impl<'info> TryFrom<(&'info[AccountInfo], &[u8])> for Make<'info>{
 impl<'info> Make<'info>{
     pub const DISCRIMINATOR:u8 = 0;
 
+    // Hands the signing and funding of the escrow to `payer`, a relayer the maker
+    // approved as delegate of its ATA for the deposit
+    #[inline(always)]
+    pub fn with_payer(mut self, payer: &'info AccountInfo) -> Self {
+        self.accounts.payer = payer;
+        self
+    }
+
     pub fn check(&mut self)->ProgramResult{
-        // Check if the maker signed
-        SignerAccount::check(self.accounts.maker)?;
+        // Check if the payer signed, the maker itself unless a relayer pays
+        SignerAccount::check(self.accounts.payer)?;
         // Check if the mints are valid
         MintInterface::check(self.accounts.mint_a)?;
         MintInterface::check(self.accounts.mint_b)?;
+        // Check that the escrow exchanges two different mints
+        if self.accounts.mint_a.key() == self.accounts.mint_b.key() {
+            return Err(EscrowError::SameMint.into());
+        }
         // Check if the maker's ATA is valid
         TokenAccountInterface::check(self.accounts.maker_ata_a)?;
         // Check that the vault and escrow are yet to exist
         UninitializedAccount::check(self.accounts.escrow)?;
+        assert_vault_absent(self.accounts.vault)?;
         UninitializedAccount::check(self.accounts.vault)?;
+        // A pre-funded or pre-assigned escrow address is rejected instead of reused
+        assert_untouched(self.accounts.escrow)?;
 
         // Check that the exchange is reasonable
         if self.data.recieve.eq(&0) || self.data.amount.eq(&0){
-            return Err(ProgramError::InvalidInstructionData);
+            return Err(EscrowError::ZeroAmount.into());
         }
 
-        // Check that the accounts are derived correctly
+        // A relayer moves the deposit as delegate of the maker's ATA
+        if self.accounts.payer.key() != self.accounts.maker.key() {
+            check_token_account(self.accounts.maker_ata_a, self.accounts.mint_a.key(), self.accounts.maker.key())?;
+
+            if delegated_amount(self.accounts.maker_ata_a, self.accounts.payer.key())?.lt(&self.data.amount) {
+                return Err(EscrowError::InsufficientDelegation.into());
+            }
+        }
+
+        // Check that the accounts are derived correctly, the escrow belongs to the maker
+        // whoever pays for it, so only the maker can refund it
         let (escrow_pda, bump) = find_program_address( // Ensure the maker is bound to the escrow
             &[b"escrow", self.accounts.maker.key().as_ref(), // Not really necessary since we can check the escrow fields
             self.data.seed.as_ref()],
             &crate::ID
         );
         if self.accounts.escrow.key() != &escrow_pda {
-            return Err(ProgramError::InvalidSeeds);
+            return Err(EscrowError::EscrowMismatch.into());
         }
 
         self.accounts.escrow_bump[0] = bump;
@@ -77,9 +104,23 @@ impl<'info> Make<'info>{
         )
     }
 
+    // Writes the escrow fields, the payer funds the escrow so it gets the rent back on close
+    #[inline(always)]
+    pub fn record(&self, escrow:&mut Escrow){
+        escrow.set_inner(
+            self.data.seed, 
+            *self.accounts.maker.key(), 
+            *self.accounts.mint_a.key(), 
+            *self.accounts.mint_b.key(), 
+            self.data.recieve, 
+            *self.accounts.payer.key(), 
+            self.accounts.escrow_bump
+        );
+    }
+
     pub fn init(& mut self)->ProgramResult{
         ProgramAccount::init::<Escrow>(
-            self.accounts.maker,
+            self.accounts.payer,
              self.accounts.escrow, 
             &[
             Seed::from(b"escrow"), 
@@ -90,19 +131,12 @@ impl<'info> Make<'info>{
             Escrow::LEN, 
             &crate::ID)?;
 
+        ProgramAccount::assert_rent_exempt(self.accounts.escrow, Escrow::LEN)?;
+
         // Set the data
         let mut data_ref = self.accounts.escrow.try_borrow_mut_data()?;
 
-        let escrow_data = Escrow::load_mut(&mut data_ref)?;
-
-        escrow_data.set_inner(
-            self.data.seed, 
-            *self.accounts.maker.key(), 
-            *self.accounts.mint_a.key(), 
-            *self.accounts.mint_b.key(), 
-            self.data.recieve, 
-            self.accounts.escrow_bump
-        );
+        self.record(Escrow::load_mut(&mut data_ref)?);
 
         //core::mem::drop(data_ref);
 
@@ -110,7 +144,7 @@ impl<'info> Make<'info>{
         AssociatedTokenAccount::init(
             self.accounts.vault,
             self.accounts.mint_a,
-            self.accounts.maker,
+            self.accounts.payer,
             self.accounts.escrow,
             self.accounts.system_program,
             self.accounts.token_program
@@ -127,13 +161,18 @@ impl<'info> Make<'info>{
 
         
 
-        msg!("Ready to transfer tokens");
+        let decimals = mint_decimals(self.accounts.mint_a)?;
+
+        let mut line: heapless::String<{ FORMATTED_AMOUNT_LEN + 32 }> = heapless::String::new();
+        let _ = write!(line, "Ready to transfer {} tokens", format_amount(self.data.amount, decimals));
+
+        msg!(line.as_str());
         
-        // Transfer the tokens
+        // Transfer the tokens, a relayer moves them as delegate
         TokenAccountInterface::transfer(
             self.accounts.maker_ata_a,
             self.accounts.vault,
-            self.accounts.maker,
+            self.accounts.payer,
             self.data.amount,
             self.accounts.token_program,
             &[]
@@ -143,6 +182,8 @@ impl<'info> Make<'info>{
 
 pub struct MakeAccounts<'a> {
   pub maker: &'a AccountInfo,
+  // Signs and funds the escrow, the maker unless set by `Make::with_payer`
+  pub payer: &'a AccountInfo,
   pub escrow: &'a AccountInfo,
   pub mint_a: &'a AccountInfo,
   pub mint_b: &'a AccountInfo,
@@ -161,11 +202,12 @@ impl<'a> TryFrom<&'a[AccountInfo]> for MakeAccounts<'a> {
         let [maker, escrow, mint_a, mint_b,
             maker_ata_a, vault, system_program, 
             token_program, _] = accounts else{
-            return Err(ProgramError::InvalidArgument);
+            return Err(account_count_error(accounts.len(), 9));
         };
 
         Ok(MakeAccounts {
             maker,
+            payer: maker,
             escrow,
             mint_a,
             mint_b,
@@ -201,4 +243,73 @@ impl TryFrom<&[u8]> for MakeData {
 
         Ok(MakeData { amount, recieve, seed:seed_bytes })
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pinocchio::pubkey::Pubkey;
+    use crate::testing::TestAccounts;
+
+    #[test]
+    fn make_takes_exactly_its_accounts() {
+        assert!(MakeAccounts::try_from(TestAccounts::blank(9).infos().as_slice()).is_ok());
+
+        assert_eq!(
+            MakeAccounts::try_from(TestAccounts::blank(8).infos().as_slice()).err(),
+            Some(ProgramError::NotEnoughAccountKeys)
+        );
+        assert_eq!(
+            MakeAccounts::try_from(TestAccounts::blank(10).infos().as_slice()).err(),
+            Some(EscrowError::TooManyAccounts.into())
+        );
+    }
+
+    #[test]
+    fn a_delegated_escrow_is_refunded_by_the_maker() {
+        let (relayer, maker, escrow_key, mint_a) = ([0xaa; 32], [1; 32], [2; 32], [3; 32]);
+
+        // The relayer ahead of the accounts of `Make`, the maker does not sign
+        let mut accounts = TestAccounts::default();
+        accounts.add(relayer, [0; 32], true, 0, &[]);
+        for key in [maker, escrow_key, mint_a, [4; 32], [5; 32], [6; 32], [7; 32], [8; 32], [9; 32]] {
+            accounts.add(key, [0; 32], false, 0, &[]);
+        }
+        let accounts = accounts.infos();
+
+        let mut data = [0u8; 24];
+        data[8..16].copy_from_slice(&250u64.to_le_bytes());
+        data[16..24].copy_from_slice(&100u64.to_le_bytes());
+
+        // As `MakeDelegated` parses them
+        let mut make = Make::try_from((&accounts[1..], data.as_slice())).unwrap().with_payer(&accounts[0]);
+        assert_eq!(make.accounts.payer.key(), &relayer);
+        assert_eq!(make.accounts.maker.key(), &maker);
+        make.accounts.escrow_bump = [254];
+
+        let mut escrow = Escrow {
+            seed: [0; 8], maker: [0; 32], mint_a: [0; 32], mint_b: [0; 32],
+            receive: 0, bump: [0], rent_payer: [0; 32]
+        };
+        make.record(&mut escrow);
+
+        // The escrow is the maker's, the relayer paid for it
+        assert_eq!((escrow.maker, escrow.rent_payer, escrow.receive), (maker, relayer, 250));
+
+        // The maker refunds it and the rent goes back to the relayer
+        let refund = |signer: Pubkey, close_to: Pubkey| {
+            let mut accounts = TestAccounts::default();
+            for key in [signer, escrow_key, mint_a, [6; 32], [10; 32], [8; 32], [9; 32], [11; 32]] {
+                accounts.add(key, [0; 32], key == signer, 0, &[]);
+            }
+            accounts.add(close_to, [0; 32], false, 0, &[]);
+
+            let accounts = accounts.infos();
+            let data = [crate::RentRecipients::CLOSE_TO];
+            crate::Refund::try_from((accounts.as_slice(), data.as_slice()))?.check_escrow(&escrow)
+        };
+
+        assert_eq!(refund(maker, relayer), Ok(()));
+        assert_eq!(refund(maker, maker), Err(EscrowError::InvalidRentPayer.into()));
+        assert_eq!(refund(relayer, relayer), Err(EscrowError::InvalidMaker.into()));
+    }
+}
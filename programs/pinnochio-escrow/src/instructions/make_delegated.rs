@@ -0,0 +1,34 @@
+use pinocchio::{
+    account_info::AccountInfo, program_error::ProgramError, ProgramResult
+};
+
+use crate::Make;
+
+// Same as `Make` with a relayer signing and paying on behalf of the maker, the
+// maker approves the relayer as delegate of their ATA for at least the deposit.
+// The relayer comes ahead of the accounts of `Make`
+pub struct MakeDelegated<'info>{
+    make:Make<'info>
+}
+
+impl<'info> TryFrom<(&'info[AccountInfo], &[u8])> for MakeDelegated<'info>{
+
+    #[inline]
+    fn try_from(value: (&'info[AccountInfo], &[u8])) -> Result<Self, Self::Error> {
+        let (relayer, accounts) = value.0.split_first().ok_or(ProgramError::NotEnoughAccountKeys)?;
+
+        Ok(MakeDelegated{
+            make: Make::try_from((accounts, value.1))?.with_payer(relayer)
+        })
+    }
+
+    type Error = ProgramError;
+}
+
+impl<'info> MakeDelegated<'info>{
+    pub const DISCRIMINATOR:u8 = 5;
+
+    pub fn process(&mut self)->ProgramResult{
+        self.make.process()
+    }
+}
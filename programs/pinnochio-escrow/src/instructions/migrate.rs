@@ -0,0 +1,84 @@
+use pinocchio::{
+    account_info::AccountInfo, program_error::ProgramError, ProgramResult
+};
+
+use basic_helpers::{
+    SignerAccount
+};
+
+use crate::{account_count_error, realloc_program_account, Escrow};
+
+// Grows an escrow made before the rent payer was recorded to the current layout, so
+// Take and Refund can load it again. Anyone can pay for the extra rent, it goes to the
+// maker with the rest of the escrow's rent when it is closed
+pub struct Migrate<'info>{
+    accounts:MigrateAccounts<'info>,
+}
+
+impl<'info> TryFrom<&'info[AccountInfo]> for Migrate<'info>{
+    #[inline(always)]
+    fn try_from(value: &'info[AccountInfo]) -> Result<Self, Self::Error> {
+        let accounts = MigrateAccounts::try_from(value)?;
+
+        Ok(Migrate{
+            accounts,
+        })
+    }
+
+    type Error = ProgramError;
+}
+
+impl<'info> Migrate<'info>{
+    pub const DISCRIMINATOR:u8 = 7;
+
+    pub fn check(&self)->ProgramResult{
+        // Check if the payer signed
+        SignerAccount::check(self.accounts.payer)?;
+
+        // The program only ever created escrows, so one of its accounts at the old
+        // length is an escrow yet to be migrated
+        if !self.accounts.escrow.is_owned_by(&crate::ID) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        if self.accounts.escrow.data_len() != Escrow::LEGACY_LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Ok(())
+    }
+
+    pub fn process(&self)->ProgramResult{
+
+        // Perform the checks
+        self.check()?;
+
+        // The new bytes are zeroed, the old fields stay where they were
+        realloc_program_account(self.accounts.escrow, self.accounts.payer, Escrow::LEN)?;
+
+        Escrow::migrate(&mut self.accounts.escrow.try_borrow_mut_data()?)
+    }
+}
+
+pub struct MigrateAccounts<'a> {
+    pub payer: &'a AccountInfo,
+    pub escrow: &'a AccountInfo,
+    pub system_program: &'a AccountInfo
+}
+
+impl<'a> TryFrom<&'a[AccountInfo]> for MigrateAccounts<'a> {
+    type Error = ProgramError;
+
+    #[inline]
+    fn try_from(accounts: &'a[AccountInfo]) -> Result<MigrateAccounts<'a>, Self::Error> {
+        let [payer, escrow, system_program] = accounts else {
+        return Err(account_count_error(accounts.len(), 3));
+        };
+
+        Ok(MigrateAccounts {
+            payer,
+            escrow,
+            system_program
+        })
+    }
+}
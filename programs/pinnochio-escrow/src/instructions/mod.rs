@@ -1,8 +1,23 @@
 pub mod make;
 pub use make::*;
 
+pub mod make_delegated;
+pub use make_delegated::*;
+
 pub mod take;
 pub use take::*;
 
 pub mod refund;
-pub use refund::*;
\ No newline at end of file
+pub use refund::*;
+
+pub mod refund_many;
+pub use refund_many::*;
+
+pub mod top_up;
+pub use top_up::*;
+
+pub mod add_extra;
+pub use add_extra::*;
+
+pub mod migrate;
+pub use migrate::*;
@@ -10,22 +10,22 @@ use basic_helpers::{
 use associated_token_helpers::{
     AssociatedTokenAccount
 };
-use pinocchio_token::state::TokenAccount;
 use token_interface_helpers::{
     TokenAccountInterface,
     MintInterface
 };
 
-use crate::Escrow;
+use crate::{check_extra_assets, check_not_frozen, check_token_account, check_vault_rent_to, split_extra_accounts, Escrow, EscrowError, RentRecipients, TokenAccountBalance};
 
 pub struct Refund<'info>{
     accounts:RefundAccounts<'info>,
 }
 
-impl<'info> TryFrom<&'info[AccountInfo]> for Refund<'info>{
+impl<'info> TryFrom<(&'info[AccountInfo], &'info[u8])> for Refund<'info>{
     #[inline(always)]
-    fn try_from(value: &'info[AccountInfo]) -> Result<Self, Self::Error> {
-        let accounts = RefundAccounts::try_from(value)?;
+    fn try_from(value: (&'info[AccountInfo], &'info[u8])) -> Result<Self, Self::Error> {
+        let data = RefundData::try_from(value.1)?;
+        let accounts = RefundAccounts::try_from((value.0, data.recipients))?;
         
         Ok(Refund{
             accounts,
@@ -51,63 +51,156 @@ impl<'info> Refund<'info>{
             self.accounts.token_program,
             true
         )?;
-        // Check that the escrow is valid and belonsgs to the program
-        ProgramAccount::check(self.accounts.escrow, Escrow::LEN, &crate::ID)?;
+        // Check that the escrow is valid and belonsgs to the program, extra assets grow
+        // it past `Escrow::LEN` so its length is checked by the load below
+        ProgramAccount::check(self.accounts.escrow, self.accounts.escrow.data_len(), &crate::ID)?;
         // Check that the accounts are derived correctly
         let escrow_ref = self.accounts.escrow.try_borrow_data()?;
         let escrow = Escrow::load(&escrow_ref)?;
 
+        self.check_escrow(escrow)?;
+
         let escrow_pda = create_program_address( // This check also binds the maker to the escrow
             &[b"escrow", self.accounts.maker.key().as_ref(), 
-            escrow.seed.as_ref(), escrow.bump.as_ref()],
+            escrow.seed().as_ref(), escrow.bump().as_ref()],
             &crate::ID
-        )?;
+        ).map_err(|_| EscrowError::EscrowMismatch)?;
 
         if self.accounts.escrow.key() != &escrow_pda {
-            return Err(ProgramError::InvalidAccountOwner);
+            return Err(EscrowError::EscrowMismatch.into());
         }
 
         Ok(())
     }
 
+    // Checks the escrow against the accounts passed, it belongs to the maker whoever
+    // paid for it, and its rent goes back to whoever did
     #[inline(always)]
-    pub fn init(&self)->ProgramResult{
-        // Initialize the maker's ATA if necessary
+    pub fn check_escrow(&self, escrow:&Escrow)->ProgramResult{
+        if escrow.maker() != self.accounts.maker.key() {
+            return Err(EscrowError::InvalidMaker.into());
+        }
+
+        escrow.check_close_to(self.accounts.close_to.key())?;
+
+        check_vault_rent_to(
+            self.accounts.close_to.key(),
+            self.accounts.close_to.is_signer(),
+            self.accounts.vault_rent_to.key()
+        )?;
+
+        if escrow.mint_a() != self.accounts.mint_a.key() {
+            return Err(EscrowError::EscrowMismatch.into());
+        }
+
+        Ok(())
+    }
+
+    // Creates the maker's token accounts for the extra assets, checked like the
+    // mint_a one before anything moves
+    pub fn init_extra(&self, pairs:&[AccountInfo], maker_atas:&[AccountInfo])->ProgramResult{
+        for (pair, maker_ata) in pairs.chunks_exact(2).zip(maker_atas) {
+            let [_, mint] = pair else {
+                return Err(ProgramError::NotEnoughAccountKeys);
+            };
+
+            self.init_maker_ata(maker_ata, mint)?;
+            check_not_frozen(maker_ata)?;
+        }
+
+        Ok(())
+    }
+
+    // Returns the extra assets of a multi-asset escrow to the maker
+    pub fn refund_extra(&self, pairs:&[AccountInfo], maker_atas:&[AccountInfo], seeds:&[Seed])->ProgramResult{
+        for (pair, maker_ata) in pairs.chunks_exact(2).zip(maker_atas) {
+            let [vault, _] = pair else {
+                return Err(ProgramError::NotEnoughAccountKeys);
+            };
+
+            TokenAccountInterface::transfer(
+                vault,
+                maker_ata,
+                self.accounts.escrow,
+                TokenAccountInterface::balance(vault, self.accounts.token_program)?,
+                self.accounts.token_program,
+                seeds
+            )?;
+
+            TokenAccountInterface::close(
+                vault,
+                self.accounts.maker,
+                self.accounts.escrow,
+                self.accounts.token_program,
+                seeds
+            )?;
+        }
+
+        Ok(())
+    }
+
+    // Whether `maker_ata` is already a token account of the maker for `mint`
+    #[inline(always)]
+    pub fn holds_maker_ata(&self, maker_ata:&AccountInfo, mint:&AccountInfo)->bool{
+        maker_ata.owner() == self.accounts.token_program.key()
+            && check_token_account(maker_ata, mint.key(), self.accounts.maker.key()).is_ok()
+    }
+
+    // Creates the maker's token account for `mint` unless it already holds the mint
+    // for the maker, in which case the ATA program CPI is skipped altogether
+    #[inline(always)]
+    pub fn init_maker_ata(&self, maker_ata:&AccountInfo, mint:&AccountInfo)->ProgramResult{
+        if self.holds_maker_ata(maker_ata, mint) {
+            return Ok(());
+        }
+
         AssociatedTokenAccount::init_if_needed(
-        self.accounts.maker_ata_a,
-        self.accounts.mint_a,
+        maker_ata,
+        mint,
         self.accounts.maker,
         self.accounts.maker,
         self.accounts.system_program,
         self.accounts.token_program,
         )
     }
+
+    #[inline(always)]
+    pub fn init(&self)->ProgramResult{
+        // Initialize the maker's ATA if necessary
+        self.init_maker_ata(self.accounts.maker_ata_a, self.accounts.mint_a)
+    }
    
     pub fn process(&self)->ProgramResult{
 
         // Perform the checks
         self.check()?;
 
+        let (pairs, maker_atas) = check_extra_assets(
+            self.accounts.escrow,
+            self.accounts.extra,
+            self.accounts.token_program
+        )?;
+
         // Initialize accounts if necessary
         self.init()?;
+        self.init_extra(pairs, maker_atas)?;
+
+        check_not_frozen(self.accounts.vault)?;
+        check_not_frozen(self.accounts.maker_ata_a)?;
 
         // Transfer the tokens to the maker's ATA
         let escrow_ref = self.accounts.escrow.try_borrow_data()?;
         let escrow = Escrow::load(&escrow_ref)?;
 
         // Transfer the tokens from the vault to the taker's ATA
-        let amount_to_recieve = 
-            unsafe{
-                TokenAccount::from_bytes_unchecked(
-                    &self.accounts.vault.try_borrow_data()?).amount()
-            };
+        let amount_to_recieve = TokenAccountInterface::balance(self.accounts.vault, self.accounts.token_program)?;
         
         
         let seeds = [
             Seed::from(b"escrow"),
-            Seed::from(escrow.maker.as_ref()),
-            Seed::from(escrow.seed.as_ref()),
-            Seed::from(escrow.bump.as_ref())
+            Seed::from(escrow.maker().as_ref()),
+            Seed::from(escrow.seed().as_ref()),
+            Seed::from(escrow.bump().as_ref())
         ];
 
         TokenAccountInterface::transfer(
@@ -119,10 +212,12 @@ impl<'info> Refund<'info>{
             &seeds
         )?;
 
+        self.refund_extra(pairs, maker_atas, &seeds)?;
+
         // Close the vault account
         TokenAccountInterface::close(
             self.accounts.vault, 
-            self.accounts.maker, 
+            self.accounts.vault_rent_to, 
             self.accounts.escrow, 
             self.accounts.token_program, 
             &seeds
@@ -133,7 +228,7 @@ impl<'info> Refund<'info>{
         // Close the escrow account
         ProgramAccount::close(
             self.accounts.escrow,
-            self.accounts.maker
+            self.accounts.close_to
         )
     }}
 
@@ -144,20 +239,29 @@ pub struct RefundAccounts<'a> {
     pub maker_ata_a: &'a AccountInfo,
     pub vault: &'a AccountInfo,
     pub system_program: &'a AccountInfo,
-    pub token_program: &'a AccountInfo
+    pub token_program: &'a AccountInfo,
+    // Receives the rent of the escrow and its vault, the maker if not flagged
+    pub close_to: &'a AccountInfo,
+    // Receives the rent of the vault instead when flagged
+    pub vault_rent_to: &'a AccountInfo,
+    // The (vault, mint) pairs of a multi-asset escrow followed by a maker ATA for each
+    pub extra: &'a [AccountInfo]
 }
 
-impl<'a> TryFrom<&'a[AccountInfo]> for RefundAccounts<'a> {
+impl<'a> TryFrom<(&'a[AccountInfo], RentRecipients)> for RefundAccounts<'a> {
     type Error = ProgramError;
 
     #[inline]
-    fn try_from(accounts: &'a[AccountInfo]) -> Result<RefundAccounts<'a>, Self::Error> {
+    fn try_from((accounts, recipients): (&'a[AccountInfo], RentRecipients)) -> Result<RefundAccounts<'a>, Self::Error> {
         let [maker, escrow, mint_a, 
                 vault, maker_ata_a, 
-                system_program, token_program, _] = accounts else {
+                system_program, token_program, _, rest @ ..] = accounts else {
         return Err(ProgramError::NotEnoughAccountKeys);
         };
 
+        // The flagged rent recipients come first, then three accounts per extra asset
+        let (close_to, vault_rent_to, extra) = split_extra_accounts(recipients, maker, rest)?;
+
         Ok(RefundAccounts {
             maker,
             escrow,
@@ -165,7 +269,92 @@ impl<'a> TryFrom<&'a[AccountInfo]> for RefundAccounts<'a> {
             maker_ata_a,
             vault,
             system_program,
-            token_program
+            token_program,
+            close_to,
+            vault_rent_to,
+            extra
         })
     }
 }
+
+pub struct RefundData{
+    // The optional rent recipients passed after the fixed accounts
+    pub recipients:RentRecipients
+}
+
+impl TryFrom<&[u8]> for RefundData {
+    type Error = ProgramError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        // Nothing but the rent recipients flag byte, which is optional
+        if value.len() > 1 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        Ok(RefundData { recipients: RentRecipients::try_from(value.first())? })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestAccounts;
+
+    fn parse(count: usize, flags: u8) -> Result<(), ProgramError> {
+        let recipients = RentRecipients::try_from(Some(&flags))?;
+        RefundAccounts::try_from((TestAccounts::blank(count).infos().as_slice(), recipients)).map(|_| ())
+    }
+
+    #[test]
+    fn refund_rejects_missing_and_stray_accounts() {
+        assert_eq!(parse(7, 0), Err(ProgramError::NotEnoughAccountKeys));
+        assert_eq!(parse(8, RentRecipients::VAULT_RENT_TO), Err(ProgramError::NotEnoughAccountKeys));
+
+        assert_eq!(parse(9, 0), Err(EscrowError::TooManyAccounts.into()));
+        assert_eq!(parse(8 + 1 + 3 + 2, RentRecipients::CLOSE_TO), Err(EscrowError::TooManyAccounts.into()));
+    }
+
+    #[test]
+    fn refund_accepts_the_flagged_recipients_and_whole_extra_assets() {
+        assert_eq!(parse(8, 0), Ok(()));
+        assert_eq!(parse(8 + 3, 0), Ok(()));
+        assert_eq!(parse(9, RentRecipients::VAULT_RENT_TO), Ok(()));
+    }
+
+    #[test]
+    fn refund_rejects_more_extra_assets_than_max_batch() {
+        assert_eq!(parse(8 + 3 * crate::MAX_BATCH, 0), Ok(()));
+        assert_eq!(parse(8 + 3 * (crate::MAX_BATCH + 1), 0), Err(EscrowError::BatchTooLarge.into()));
+    }
+
+    #[test]
+    fn an_existing_maker_ata_is_not_created_again() {
+        let (maker, mint_a) = ([0; 32], [2; 32]);
+
+        let token_account = |owner: &[u8; 32]| {
+            let mut data = [0; pinocchio_token::state::TokenAccount::LEN];
+            data[0..32].copy_from_slice(&mint_a);
+            data[32..64].copy_from_slice(owner);
+            data
+        };
+
+        let mut accounts = TestAccounts::blank(6);
+        accounts.add(pinocchio_token::ID, [0; 32], false, 0, &[]);
+        accounts.add([7; 32], [0; 32], false, 0, &[]);
+        accounts.add([8; 32], pinocchio_token::ID, false, 0, &token_account(&maker));
+        // Not created yet, still held by the system program
+        accounts.add([9; 32], [0; 32], false, 0, &[]);
+        accounts.add([10; 32], pinocchio_token::ID, false, 0, &token_account(&[1; 32]));
+        let accounts = accounts.infos();
+
+        let refund = Refund::try_from((&accounts[..8], &[][..])).unwrap();
+        let mint_a = &accounts[2];
+
+        // The ATA program is only called when the maker lacks the account
+        assert!(refund.holds_maker_ata(&accounts[8], mint_a));
+        assert_eq!(refund.init_maker_ata(&accounts[8], mint_a), Ok(()));
+
+        assert!(!refund.holds_maker_ata(&accounts[9], mint_a));
+        assert!(!refund.holds_maker_ata(&accounts[10], mint_a));
+    }
+}
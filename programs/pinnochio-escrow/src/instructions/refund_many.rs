@@ -0,0 +1,275 @@
+use pinocchio::{
+    account_info::AccountInfo, instruction::Seed, msg, program_error::ProgramError,
+    pubkey::create_program_address,
+    ProgramResult
+};
+
+use basic_helpers::{
+    ProgramAccount, SignerAccount
+};
+use token_interface_helpers::{
+    TokenAccountInterface
+};
+
+use crate::{check_token_account, Escrow, EscrowError, TokenAccountBalance, MAX_BATCH};
+
+pub struct RefundMany<'info>{
+    accounts:RefundManyAccounts<'info>,
+}
+
+impl<'info> TryFrom<&'info[AccountInfo]> for RefundMany<'info>{
+    #[inline(always)]
+    fn try_from(value: &'info[AccountInfo]) -> Result<Self, Self::Error> {
+        let accounts = RefundManyAccounts::try_from(value)?;
+
+        Ok(RefundMany{
+            accounts,
+        })
+    }
+
+    type Error = ProgramError;
+}
+
+impl<'info> RefundMany<'info>{
+    pub const DISCRIMINATOR:u8 = 3;
+
+    // Returns the seed and bump of the escrow if the triple can be refunded
+    pub fn check(&self, escrow:&AccountInfo, vault:&AccountInfo, maker_ata:&AccountInfo)->Result<([u8;8], [u8;1]), ProgramError>{
+        // Escrows holding extra assets are refunded one at a time through `Refund`
+        if escrow.data_len() != Escrow::LEN {
+            return Err(EscrowError::ExtraAssetsMismatch.into());
+        }
+
+        // Check that the escrow is valid and belongs to the program
+        ProgramAccount::check(escrow, Escrow::LEN, &crate::ID)?;
+
+        let escrow_ref = escrow.try_borrow_data()?;
+        let escrow_data = Escrow::load(&escrow_ref)?;
+
+        self.check_escrow(escrow_data)?;
+
+        let escrow_pda = create_program_address( // This check also binds the maker to the escrow
+            &[b"escrow", self.accounts.maker.key().as_ref(),
+            escrow_data.seed().as_ref(), escrow_data.bump().as_ref()],
+            &crate::ID
+        ).map_err(|_| EscrowError::EscrowMismatch)?;
+
+        if escrow.key() != &escrow_pda {
+            return Err(EscrowError::EscrowMismatch.into());
+        }
+
+        // The vault has to be held by the escrow and the refund go to the maker
+        if vault.owner() != self.accounts.token_program.key() {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        check_token_account(vault, escrow_data.mint_a(), escrow.key())?;
+        check_token_account(maker_ata, escrow_data.mint_a(), self.accounts.maker.key())?;
+
+        Ok((*escrow_data.seed(), *escrow_data.bump()))
+    }
+
+    // Checks that the escrow is the maker's and was paid for by the maker
+    #[inline(always)]
+    pub fn check_escrow(&self, escrow:&Escrow)->ProgramResult{
+        if escrow.maker() != self.accounts.maker.key() {
+            return Err(EscrowError::InvalidMaker.into());
+        }
+
+        // Sponsored escrows return their rent through `Refund` with the rent payer passed in
+        if escrow.rent_payer() != self.accounts.maker.key() {
+            return Err(EscrowError::InvalidRentPayer.into());
+        }
+
+        Ok(())
+    }
+
+    // Refunds each (escrow, vault, maker_ata) triple `check` accepts. An invalid triple
+    // is skipped so the rest of the batch still goes through
+    #[inline(always)]
+    fn refund_valid<T, S>(
+        refunds:&[T],
+        check:impl Fn(&T, &T, &T)->Result<S, ProgramError>,
+        mut refund:impl FnMut(&T, &T, &T, S)->ProgramResult
+    )->ProgramResult{
+        for triple in refunds.chunks_exact(3) {
+            let [escrow, vault, maker_ata] = triple else {
+                return Err(ProgramError::NotEnoughAccountKeys);
+            };
+
+            let Ok(state) = check(escrow, vault, maker_ata) else {
+                msg!("Skipping an escrow that could not be refunded");
+                continue;
+            };
+
+            refund(escrow, vault, maker_ata, state)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn refund(&self, escrow:&AccountInfo, vault:&AccountInfo, maker_ata:&AccountInfo, seed:[u8;8], bump:[u8;1])->ProgramResult{
+        let amount_to_recieve = TokenAccountInterface::balance(vault, self.accounts.token_program)?;
+
+        let seeds = [
+            Seed::from(b"escrow"),
+            Seed::from(self.accounts.maker.key().as_ref()),
+            Seed::from(seed.as_ref()),
+            Seed::from(bump.as_ref())
+        ];
+
+        TokenAccountInterface::transfer(
+            vault,
+            maker_ata,
+            escrow,
+            amount_to_recieve,
+            self.accounts.token_program,
+            &seeds
+        )?;
+
+        // Close the vault account
+        TokenAccountInterface::close(
+            vault,
+            self.accounts.maker,
+            escrow,
+            self.accounts.token_program,
+            &seeds
+        )?;
+
+        // Close the escrow account
+        ProgramAccount::close(
+            escrow,
+            self.accounts.maker
+        )
+    }
+
+    pub fn process(&self)->ProgramResult{
+        // Check if the maker signed
+        SignerAccount::check(self.accounts.maker)?;
+
+        Self::refund_valid(
+            self.accounts.refunds,
+            |escrow, vault, maker_ata| self.check(escrow, vault, maker_ata),
+            |escrow, vault, maker_ata, (seed, bump)| self.refund(escrow, vault, maker_ata, seed, bump)
+        )
+    }
+}
+
+pub struct RefundManyAccounts<'a> {
+    pub maker: &'a AccountInfo,
+    pub system_program: &'a AccountInfo,
+    pub token_program: &'a AccountInfo,
+    // Repeated (escrow, vault, maker_ata) triples
+    pub refunds: &'a [AccountInfo]
+}
+
+impl<'a> TryFrom<&'a[AccountInfo]> for RefundManyAccounts<'a> {
+    type Error = ProgramError;
+
+    #[inline]
+    fn try_from(accounts: &'a[AccountInfo]) -> Result<RefundManyAccounts<'a>, Self::Error> {
+        let [maker, system_program, token_program, refunds @ ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        if refunds.is_empty() || refunds.len() % 3 != 0 {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+
+        if refunds.len() / 3 > MAX_BATCH {
+            return Err(EscrowError::BatchTooLarge.into());
+        }
+
+        Ok(RefundManyAccounts {
+            maker,
+            system_program,
+            token_program,
+            refunds
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::vec::Vec;
+    use pinocchio::pubkey::Pubkey;
+    use crate::testing::TestAccounts;
+
+    fn escrow_bytes(seed: u64, maker: Pubkey) -> [u8; Escrow::LEN] {
+        let mut data = [0; Escrow::LEN];
+        data[0..8].copy_from_slice(&seed.to_le_bytes());
+        data[8..40].copy_from_slice(&maker);
+        data[113..145].copy_from_slice(&maker);
+        data
+    }
+
+    #[test]
+    fn a_batch_of_three_refunds_all_but_the_invalid_escrow() {
+        let (maker, other) = ([1; 32], [2; 32]);
+
+        let mut accounts = TestAccounts::default();
+        accounts.add(maker, [0; 32], true, 0, &[]);
+        accounts.add([3; 32], [0; 32], false, 0, &[]);
+        accounts.add(pinocchio_token::ID, [0; 32], false, 0, &[]);
+
+        // The second escrow belongs to someone else
+        for (seed, owner) in [(10, maker), (20, other), (30, maker)] {
+            accounts.add([seed as u8; 32], crate::ID, false, 0, &escrow_bytes(seed, owner));
+            accounts.add([seed as u8 + 1; 32], [0; 32], false, 0, &[]);
+            accounts.add([seed as u8 + 2; 32], [0; 32], false, 0, &[]);
+        }
+        let accounts = accounts.infos();
+
+        let refund_many = RefundMany::try_from(accounts.as_slice()).unwrap();
+
+        let mut refunded = Vec::new();
+        let result = RefundMany::refund_valid(
+            refund_many.accounts.refunds,
+            |escrow, _, _| {
+                let data = escrow.try_borrow_data()?;
+                let escrow = Escrow::load(&data)?;
+                refund_many.check_escrow(escrow)?;
+                Ok(u64::from_le_bytes(*escrow.seed()))
+            },
+            |escrow, vault, maker_ata, seed| {
+                refunded.push((seed, *escrow.key(), *vault.key(), *maker_ata.key()));
+                Ok(())
+            }
+        );
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(refunded, [(10, [10; 32], [11; 32], [12; 32]), (30, [30; 32], [31; 32], [32; 32])]);
+    }
+
+    #[test]
+    fn a_sponsored_escrow_is_left_to_refund() {
+        // `blank` gives the maker the key [0; 32], a relayer paid for its escrow
+        let mut data = escrow_bytes(10, [0; 32]);
+        data[113..145].copy_from_slice(&[9; 32]);
+
+        let mut accounts = TestAccounts::blank(6);
+        accounts.add([10; 32], crate::ID, false, 0, &data);
+        let accounts = accounts.infos();
+
+        let refund_many = RefundMany::try_from(&accounts[..6]).unwrap();
+        let data = accounts[6].try_borrow_data().unwrap();
+
+        assert_eq!(
+            refund_many.check_escrow(Escrow::load(&data).unwrap()),
+            Err(EscrowError::InvalidRentPayer.into())
+        );
+    }
+
+    #[test]
+    fn a_batch_past_max_batch_is_rejected_when_parsed() {
+        let mut accounts = TestAccounts::blank(3 + 3 * (MAX_BATCH + 1));
+        let accounts = accounts.infos();
+
+        assert!(RefundManyAccounts::try_from(&accounts[..3 + 3 * MAX_BATCH]).is_ok());
+        assert_eq!(
+            RefundManyAccounts::try_from(accounts.as_slice()).err(),
+            Some(EscrowError::BatchTooLarge.into())
+        );
+    }
+}
@@ -8,25 +8,27 @@ use basic_helpers::{
 use associated_token_helpers::{
     AssociatedTokenAccount
 };
-use pinocchio_token::state::TokenAccount;
 use token_interface_helpers::{
     TokenAccountInterface,
     MintInterface
 };
 
-use crate::Escrow;
+use crate::{check_extra_assets, check_not_frozen, check_token_account, check_vault_rent_to, split_extra_accounts, Escrow, EscrowError, RentRecipients, TokenAccountBalance};
 
 pub struct Take<'info>{
     accounts:TakeAccounts<'info>,
+    data:TakeData
 }
 
-impl<'info> TryFrom<&'info[AccountInfo]> for Take<'info>{
+impl<'info> TryFrom<(&'info[AccountInfo], &[u8])> for Take<'info>{
     #[inline(always)]
-    fn try_from(value: &'info[AccountInfo]) -> Result<Self, Self::Error> {
-        let accounts = TakeAccounts::try_from(value)?;
+    fn try_from(value: (&'info[AccountInfo], &[u8])) -> Result<Self, Self::Error> {
+        let data = TakeData::try_from(value.1)?;
+        let accounts = TakeAccounts::try_from((value.0, data.recipients))?;
         
         Ok(Take{
             accounts,
+            data
         })
     }
 
@@ -44,6 +46,7 @@ impl<'info> Take<'info>{
         MintInterface::check(self.accounts.mint_b)?;
         // Check if the ATAs is valid
         TokenAccountInterface::check(self.accounts.taker_ata_b)?;
+        self.check_taker_ata_b()?;
         
         AssociatedTokenAccount::check(
             self.accounts.vault,
@@ -53,21 +56,110 @@ impl<'info> Take<'info>{
             true
         )?;
 
-        // Check that the escrow is valid and belongs to the program
-        ProgramAccount::check(self.accounts.escrow, Escrow::LEN, &crate::ID)?;
+        // Check that the escrow is valid and belongs to the program, extra assets grow it
+        // past `Escrow::LEN` so its length is checked by the load below
+        ProgramAccount::check(self.accounts.escrow, self.accounts.escrow.data_len(), &crate::ID)?;
 
         // Check that the accounts are derived correctly
         let escrow_ref = self.accounts.escrow.try_borrow_data()?;
         let escrow = Escrow::load(&escrow_ref)?;
 
+        if escrow.maker() != self.accounts.maker.key() {
+            return Err(EscrowError::InvalidMaker.into());
+        }
+
+        escrow.check_close_to(self.accounts.close_to.key())?;
+
+        check_vault_rent_to(
+            self.accounts.close_to.key(),
+            self.accounts.close_to.is_signer(),
+            self.accounts.vault_rent_to.key()
+        )?;
+
+        if escrow.mint_a() != self.accounts.mint_a.key() || escrow.mint_b() != self.accounts.mint_b.key() {
+            return Err(EscrowError::EscrowMismatch.into());
+        }
+
+        self.check_receive(escrow)?;
+
         let escrow_pda = create_program_address( // This check also binds the maker to the escrow
             &[b"escrow", self.accounts.maker.key().as_ref(), // Though we could also check the escrow fields for the maker
-            escrow.seed.as_ref(), escrow.bump.as_ref()],
+            escrow.seed().as_ref(), escrow.bump().as_ref()],
             &crate::ID
-        )?;
+        ).map_err(|_| EscrowError::EscrowMismatch)?;
 
         if self.accounts.escrow.key() != &escrow_pda {
-            return Err(ProgramError::InvalidAccountOwner);
+            return Err(EscrowError::EscrowMismatch.into());
+        }
+
+        Ok(())
+    }
+
+    // Passing the maker's ATA here would pay the maker from their own account
+    #[inline(always)]
+    pub fn check_taker_ata_b(&self)->ProgramResult{
+        check_token_account(self.accounts.taker_ata_b, self.accounts.mint_b.key(), self.accounts.taker.key())
+            .map_err(|_| EscrowError::InvalidTakerAccount.into())
+    }
+
+    // The maker can raise the amount through `TopUp`, so the taker states what they
+    // agreed to pay and a change landing before the take fails it
+    #[inline(always)]
+    pub fn check_receive(&self, escrow:&Escrow)->ProgramResult{
+        if escrow.receive != self.data.expected_receive {
+            return Err(EscrowError::ReceiveMismatch.into());
+        }
+
+        Ok(())
+    }
+
+    // Creates the taker's token accounts for the extra assets, checked like the
+    // mint_a one before anything moves
+    pub fn init_extra(&self, pairs:&[AccountInfo], taker_atas:&[AccountInfo])->ProgramResult{
+        for (pair, taker_ata) in pairs.chunks_exact(2).zip(taker_atas) {
+            let [_, mint] = pair else {
+                return Err(ProgramError::NotEnoughAccountKeys);
+            };
+
+            AssociatedTokenAccount::init_if_needed(
+            taker_ata,
+            mint,
+            self.accounts.taker,
+            self.accounts.taker,
+            self.accounts.system_program,
+            self.accounts.token_program,
+            )?;
+
+            check_not_frozen(taker_ata)?;
+        }
+
+        Ok(())
+    }
+
+    // Releases the extra assets of a multi-asset escrow, each held by an escrow
+    // owned vault funded by the maker through `AddExtra`
+    pub fn release_extra(&self, pairs:&[AccountInfo], taker_atas:&[AccountInfo], seeds:&[Seed])->ProgramResult{
+        for (pair, taker_ata) in pairs.chunks_exact(2).zip(taker_atas) {
+            let [vault, _] = pair else {
+                return Err(ProgramError::NotEnoughAccountKeys);
+            };
+
+            TokenAccountInterface::transfer(
+                vault,
+                taker_ata,
+                self.accounts.escrow,
+                TokenAccountInterface::balance(vault, self.accounts.token_program)?,
+                self.accounts.token_program,
+                seeds
+            )?;
+
+            TokenAccountInterface::close(
+                vault,
+                self.accounts.maker,
+                self.accounts.escrow,
+                self.accounts.token_program,
+                seeds
+            )?;
         }
 
         Ok(())
@@ -99,9 +191,22 @@ impl<'info> Take<'info>{
 
         // Perform the checks
         self.check()?;
+
+        let (pairs, taker_atas) = check_extra_assets(
+            self.accounts.escrow,
+            self.accounts.extra,
+            self.accounts.token_program
+        )?;
         
         // Initialize accounts if necessary
         self.init()?;
+        self.init_extra(pairs, taker_atas)?;
+
+        // Every account of the trade is checked before any of it moves
+        check_not_frozen(self.accounts.taker_ata_b)?;
+        check_not_frozen(self.accounts.maker_ata_b)?;
+        check_not_frozen(self.accounts.vault)?;
+        check_not_frozen(self.accounts.taker_ata_a)?;
         
         // Transfer the tokens to the maker's ATA
         let escrow_ref = self.accounts.escrow.try_borrow_data()?;
@@ -118,19 +223,14 @@ impl<'info> Take<'info>{
         )?;
 
         // Transfer the tokens from the vault to the taker's ATA
-        let amount_to_recieve = 
-        unsafe{
-            let amount = TokenAccount::from_bytes_unchecked(
-                    &self.accounts.vault.try_borrow_data()?).amount();
-            amount
-        };
+        let amount_to_recieve = TokenAccountInterface::balance(self.accounts.vault, self.accounts.token_program)?;
                 
                 
         let seeds = [
             Seed::from(b"escrow"),
-            Seed::from(escrow.maker.as_ref()),
-            Seed::from(escrow.seed.as_ref()),
-            Seed::from(escrow.bump.as_ref())
+            Seed::from(escrow.maker().as_ref()),
+            Seed::from(escrow.seed().as_ref()),
+            Seed::from(escrow.bump().as_ref())
             ];
             
             TokenAccountInterface::transfer(
@@ -141,13 +241,15 @@ impl<'info> Take<'info>{
                 self.accounts.token_program,
                 &seeds
             )?;
+
+        self.release_extra(pairs, taker_atas, &seeds)?;
                     
         msg!("About to close, thanks for coming to the party!");
 
         // Close the vault account
         TokenAccountInterface::close(
             self.accounts.vault, 
-            self.accounts.maker, 
+            self.accounts.vault_rent_to, 
             self.accounts.escrow, 
             self.accounts.token_program, 
             &seeds
@@ -158,7 +260,7 @@ impl<'info> Take<'info>{
         // Close the escrow account
         ProgramAccount::close(
             self.accounts.escrow,
-            self.accounts.maker
+            self.accounts.close_to
         )
     }
 }
@@ -174,21 +276,30 @@ pub struct TakeAccounts<'a> {
     pub taker_ata_b: &'a AccountInfo,
     pub vault: &'a AccountInfo,
     pub system_program: &'a AccountInfo,
-    pub token_program: &'a AccountInfo
+    pub token_program: &'a AccountInfo,
+    // Receives the rent of the escrow and its vault, the maker if not flagged
+    pub close_to: &'a AccountInfo,
+    // Receives the rent of the vault instead when flagged
+    pub vault_rent_to: &'a AccountInfo,
+    // The (vault, mint) pairs of a multi-asset escrow followed by a taker ATA for each
+    pub extra: &'a [AccountInfo]
 }
 
-impl<'a> TryFrom<&'a[AccountInfo]> for TakeAccounts<'a> {
+impl<'a> TryFrom<(&'a[AccountInfo], RentRecipients)> for TakeAccounts<'a> {
     type Error = ProgramError;
 
     #[inline]
-    fn try_from(accounts: &'a[AccountInfo]) -> Result<TakeAccounts<'a>, Self::Error> {
+    fn try_from((accounts, recipients): (&'a[AccountInfo], RentRecipients)) -> Result<TakeAccounts<'a>, Self::Error> {
         let [taker, maker, escrow, mint_a, 
                 mint_b, vault, taker_ata_a, 
                 taker_ata_b, maker_ata_b, system_program, 
-                token_program, _] = accounts else {
+                token_program, _, rest @ ..] = accounts else {
         return Err(ProgramError::NotEnoughAccountKeys);
         };
 
+        // The flagged rent recipients come first, then three accounts per extra asset
+        let (close_to, vault_rent_to, extra) = split_extra_accounts(recipients, maker, rest)?;
+
         Ok(TakeAccounts {
             taker,
             maker,
@@ -200,7 +311,130 @@ impl<'a> TryFrom<&'a[AccountInfo]> for TakeAccounts<'a> {
             maker_ata_b,
             vault,
             system_program,
-            token_program
+            token_program,
+            close_to,
+            vault_rent_to,
+            extra
+        })
+    }
+}
+
+// Take carried no data before `TopUp`, callers built against that have to pass the
+// expected amount now
+pub struct TakeData{
+    // The amount of mint_b the taker agreed to pay
+    pub expected_receive:u64,
+    // The optional rent recipients passed after the fixed accounts
+    pub recipients:RentRecipients
+}
+
+impl TryFrom<&[u8]> for TakeData {
+    type Error = ProgramError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        // The rent recipients flag byte is optional
+        if value.len() != 8 && value.len() != 9 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let receive_bytes: [u8; 8] = value[0..8].try_into().map_err(|_| ProgramError::InvalidInstructionData)?;
+
+        Ok(TakeData {
+            expected_receive: u64::from_le_bytes(receive_bytes),
+            recipients: RentRecipients::try_from(value.get(8))?
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestAccounts;
+
+    fn parse(count: usize, flags: u8) -> Result<(), ProgramError> {
+        let recipients = RentRecipients::try_from(Some(&flags))?;
+        TakeAccounts::try_from((TestAccounts::blank(count).infos().as_slice(), recipients)).map(|_| ())
+    }
+
+    #[test]
+    fn take_rejects_missing_and_stray_accounts() {
+        assert_eq!(parse(11, 0), Err(ProgramError::NotEnoughAccountKeys));
+        assert_eq!(parse(12, RentRecipients::CLOSE_TO), Err(ProgramError::NotEnoughAccountKeys));
+
+        // An account nobody flagged is not read as a rent recipient
+        assert_eq!(parse(13, 0), Err(EscrowError::TooManyAccounts.into()));
+        assert_eq!(parse(12 + 3 + 1, 0), Err(EscrowError::TooManyAccounts.into()));
+    }
+
+    #[test]
+    fn take_accepts_the_flagged_recipients_and_whole_extra_assets() {
+        assert_eq!(parse(12, 0), Ok(()));
+        assert_eq!(parse(13, RentRecipients::CLOSE_TO), Ok(()));
+        assert_eq!(parse(14 + 3, RentRecipients::CLOSE_TO | RentRecipients::VAULT_RENT_TO), Ok(()));
+    }
+
+    #[test]
+    fn take_rejects_more_extra_assets_than_max_batch() {
+        assert_eq!(parse(12 + 3 * crate::MAX_BATCH, 0), Ok(()));
+        assert_eq!(parse(12 + 3 * (crate::MAX_BATCH + 1), 0), Err(EscrowError::BatchTooLarge.into()));
+    }
+
+    #[test]
+    fn a_two_asset_escrow_is_taken_with_its_extra_asset() {
+        // The escrow as `AddExtra` leaves it, the extra mint recorded past the state
+        let mut data = [0; Escrow::space(1)];
+        data[Escrow::LEN..].copy_from_slice(&[0xe1; 32]);
+
+        let mut accounts = TestAccounts::blank(12);
+        accounts.add([0xe0; 32], [0; 32], false, 0, &[]);
+        accounts.add([0xe1; 32], [0; 32], false, 0, &[]);
+        accounts.add([0xe2; 32], [0; 32], false, 0, &[]);
+        accounts.add([0xee; 32], crate::ID, false, 0, &data);
+        let accounts = accounts.infos();
+
+        let take = Take::try_from((&accounts[..15], &250u64.to_le_bytes()[..])).unwrap();
+        let data = accounts[15].try_borrow_data().unwrap();
+        let mints = Escrow::extra_mints(&data).unwrap();
+
+        let (pairs, recipients) = crate::match_extra_assets(mints, take.accounts.extra).unwrap();
+        assert_eq!([*pairs[0].key(), *pairs[1].key(), *recipients[0].key()], [[0xe0; 32], [0xe1; 32], [0xe2; 32]]);
+
+        // Another mint's vault cannot stand in for the recorded one
+        assert_eq!(
+            crate::match_extra_assets(&[[0xe3; 32]], take.accounts.extra).err(),
+            Some(EscrowError::ExtraAssetsMismatch.into())
+        );
+
+        // Nor can the extra asset be left out, its vault would be stranded under the
+        // closed escrow
+        let take = Take::try_from((&accounts[..12], &250u64.to_le_bytes()[..])).unwrap();
+        assert_eq!(
+            crate::match_extra_assets(mints, take.accounts.extra).err(),
+            Some(ProgramError::NotEnoughAccountKeys)
+        );
+    }
+
+    #[test]
+    fn the_maker_cannot_pay_themselves_as_the_taker() {
+        let (taker, maker, mint_b) = ([0; 32], [1; 32], [4; 32]);
+
+        // Checks a take whose taker_ata_b, the eighth account, is owned by `owner`
+        let check = |owner: &[u8; 32]| {
+            let mut data = [0; pinocchio_token::state::TokenAccount::LEN];
+            data[0..32].copy_from_slice(&mint_b);
+            data[32..64].copy_from_slice(owner);
+
+            let mut accounts = TestAccounts::blank(7);
+            accounts.add([7; 32], pinocchio_token::ID, false, 0, &data);
+            for key in 8..12 {
+                accounts.add([key; 32], [0; 32], false, 0, &[]);
+            }
+            let accounts = accounts.infos();
+
+            Take::try_from((accounts.as_slice(), &250u64.to_le_bytes()[..])).unwrap().check_taker_ata_b()
+        };
+
+        assert_eq!(check(&taker), Ok(()));
+        assert_eq!(check(&maker), Err(EscrowError::InvalidTakerAccount.into()));
+    }
+}
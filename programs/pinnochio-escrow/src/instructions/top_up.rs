@@ -0,0 +1,230 @@
+use pinocchio::{
+    account_info::AccountInfo, program_error::ProgramError,
+    pubkey::create_program_address,
+    ProgramResult
+};
+
+use basic_helpers::{
+    ProgramAccount, SignerAccount
+};
+use associated_token_helpers::{
+    AssociatedTokenAccount
+};
+use token_interface_helpers::{
+    TokenAccountInterface,
+    MintInterface
+};
+
+use crate::{account_count_error, Escrow, EscrowError};
+
+pub struct TopUp<'info>{
+    accounts:TopUpAccounts<'info>,
+    data:TopUpData
+}
+
+impl<'info> TryFrom<(&'info[AccountInfo], &[u8])> for TopUp<'info>{
+
+    #[inline]
+    fn try_from(value: (&'info[AccountInfo], &[u8])) -> Result<Self, Self::Error> {
+        let accounts = TopUpAccounts::try_from(value.0)?;
+        let data = TopUpData::try_from(value.1)?;
+
+        Ok(TopUp{
+            accounts,
+            data
+        })
+    }
+
+    type Error = ProgramError;
+}
+
+impl<'info> TopUp<'info>{
+    pub const DISCRIMINATOR:u8 = 4;
+
+    pub fn check(&self)->ProgramResult{
+        // Check if the maker signed
+        SignerAccount::check(self.accounts.maker)?;
+        // Check if the mint is valid
+        MintInterface::check(self.accounts.mint_a)?;
+        // Check if the maker's ATA is valid
+        TokenAccountInterface::check(self.accounts.maker_ata_a)?;
+
+        if self.data.amount.eq(&0) {
+            return Err(EscrowError::ZeroAmount.into());
+        }
+
+        AssociatedTokenAccount::check(
+            self.accounts.vault,
+            self.accounts.escrow,
+            self.accounts.mint_a,
+            self.accounts.token_program,
+            true
+        )?;
+
+        // Check that the escrow is valid and belongs to the program, extra assets grow it
+        // past `Escrow::LEN` so its length is checked by the load below
+        ProgramAccount::check(self.accounts.escrow, self.accounts.escrow.data_len(), &crate::ID)?;
+
+        // Check that the accounts are derived correctly
+        let escrow_ref = self.accounts.escrow.try_borrow_data()?;
+        let escrow = Escrow::load(&escrow_ref)?;
+
+        if escrow.maker() != self.accounts.maker.key() {
+            return Err(EscrowError::InvalidMaker.into());
+        }
+
+        if escrow.mint_a() != self.accounts.mint_a.key() {
+            return Err(EscrowError::EscrowMismatch.into());
+        }
+
+        let escrow_pda = create_program_address( // This check also binds the maker to the escrow
+            &[b"escrow", self.accounts.maker.key().as_ref(),
+            escrow.seed().as_ref(), escrow.bump().as_ref()],
+            &crate::ID
+        ).map_err(|_| EscrowError::EscrowMismatch)?;
+
+        if self.accounts.escrow.key() != &escrow_pda {
+            return Err(EscrowError::EscrowMismatch.into());
+        }
+
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub fn raise_receive(&self, escrow:&mut Escrow)->ProgramResult{
+        let receive = escrow.receive.checked_add(self.data.receive_increase)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+
+        escrow.set_receive(receive);
+
+        Ok(())
+    }
+
+    pub fn process(&self)->ProgramResult{
+
+        // Perform the checks
+        self.check()?;
+
+        // Raise the expected amount if asked to
+        if self.data.receive_increase.gt(&0) {
+            let mut escrow_ref = self.accounts.escrow.try_borrow_mut_data()?;
+            self.raise_receive(Escrow::load_mut(&mut escrow_ref)?)?;
+        }
+
+        // Transfer the additional tokens into the vault
+        TokenAccountInterface::transfer(
+            self.accounts.maker_ata_a,
+            self.accounts.vault,
+            self.accounts.maker,
+            self.data.amount,
+            self.accounts.token_program,
+            &[]
+        )
+    }
+}
+
+pub struct TopUpAccounts<'a> {
+    pub maker: &'a AccountInfo,
+    pub escrow: &'a AccountInfo,
+    pub mint_a: &'a AccountInfo,
+    pub maker_ata_a: &'a AccountInfo,
+    pub vault: &'a AccountInfo,
+    pub token_program: &'a AccountInfo
+}
+
+impl<'a> TryFrom<&'a[AccountInfo]> for TopUpAccounts<'a> {
+    type Error = ProgramError;
+
+    #[inline]
+    fn try_from(accounts: &'a[AccountInfo]) -> Result<TopUpAccounts<'a>, Self::Error> {
+        let [maker, escrow, mint_a,
+            maker_ata_a, vault, token_program] = accounts else {
+        return Err(account_count_error(accounts.len(), 6));
+        };
+
+        Ok(TopUpAccounts {
+            maker,
+            escrow,
+            mint_a,
+            maker_ata_a,
+            vault,
+            token_program
+        })
+    }
+}
+
+pub struct TopUpData{
+    pub amount:u64,
+    // Added to the expected amount, zero leaves it unchanged
+    pub receive_increase:u64
+}
+
+impl TryFrom<&[u8]> for TopUpData {
+    type Error = ProgramError;
+
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        // The receive increase is optional
+        let (amount_bytes, receive_bytes) = match value.len() {
+            8 => (&value[0..8], None),
+            16 => (&value[0..8], Some(&value[8..16])),
+            _ => return Err(ProgramError::InvalidInstructionData),
+        };
+
+        let amount = u64::from_le_bytes(amount_bytes.try_into().map_err(|_| ProgramError::InvalidInstructionData)?);
+
+        let receive_increase = match receive_bytes {
+            Some(bytes) => u64::from_le_bytes(bytes.try_into().map_err(|_| ProgramError::InvalidInstructionData)?),
+            None => 0,
+        };
+
+        Ok(TopUpData { amount, receive_increase })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pinocchio_token::state::TokenAccount;
+    use crate::{testing::TestAccounts, Take, TokenAccountBalance};
+
+    fn instruction_data(first: u64, second: Option<u64>) -> std::vec::Vec<u8> {
+        let mut data = first.to_le_bytes().to_vec();
+        data.extend(second.iter().flat_map(|second| second.to_le_bytes()));
+        data
+    }
+
+    #[test]
+    fn a_take_after_a_top_up_moves_the_larger_amount() {
+        let mut escrow = [0; Escrow::LEN];
+        escrow[104..112].copy_from_slice(&250u64.to_le_bytes());
+
+        let mut vault = [0; TokenAccount::LEN];
+        vault[64..72].copy_from_slice(&100u64.to_le_bytes());
+
+        let mut accounts = TestAccounts::blank(12);
+        accounts.add([0xee; 32], crate::ID, false, 0, &escrow);
+        accounts.add([0xaa; 32], pinocchio_token::ID, false, 0, &vault);
+        accounts.add(pinocchio_token::ID, [0; 32], false, 0, &[]);
+        let accounts = accounts.infos();
+        let (escrow, vault, token_program) = (&accounts[12], &accounts[13], &accounts[14]);
+
+        // Adds 50 to the vault and raises the price by 30
+        let top_up = TopUp::try_from((&accounts[..6], instruction_data(50, Some(30)).as_slice())).unwrap();
+        top_up.raise_receive(Escrow::load_mut(&mut escrow.try_borrow_mut_data().unwrap()).unwrap()).unwrap();
+
+        // Standing in for the token transfer of the top-up
+        vault.try_borrow_mut_data().unwrap()[64..72].copy_from_slice(&150u64.to_le_bytes());
+
+        let escrow_ref = escrow.try_borrow_data().unwrap();
+        let escrow = Escrow::load(&escrow_ref).unwrap();
+
+        // A taker who agreed to the old price is turned away
+        let take = Take::try_from((&accounts[..12], instruction_data(250, None).as_slice())).unwrap();
+        assert_eq!(take.check_receive(escrow), Err(EscrowError::ReceiveMismatch.into()));
+
+        // At the new price the take goes through and moves the whole vault
+        let take = Take::try_from((&accounts[..12], instruction_data(280, None).as_slice())).unwrap();
+        assert_eq!(take.check_receive(escrow), Ok(()));
+        assert_eq!(TokenAccountInterface::balance(vault, token_program), Ok(150));
+    }
+}
@@ -10,6 +10,21 @@ pub use state::*;
 pub mod instructions;
 pub use instructions::*;
 
+#[cfg(any(test, feature = "client"))]
+extern crate std;
+
+#[cfg(feature = "client")]
+pub mod client;
+
+pub mod errors;
+pub use errors::*;
+
+pub mod format;
+pub use format::*;
+
+#[cfg(test)]
+mod testing;
+
 nostd_panic_handler!();
 
 entrypoint!(process_instructions);
@@ -21,8 +36,10 @@ pub const ID: Pubkey = [
     0x8e, 0xf8, 0xaf, 0x70, 0x47, 0xdc, 0x11, 0xf7,
 ];
 
+// The most escrows or extra assets one instruction handles, each costs a few
+// token CPIs so a larger batch could run out of the default compute budget
+pub const MAX_BATCH: usize = 8;
 
- 
 
 pub fn process_instructions(_program_id:&Pubkey, accounts:&[AccountInfo], 
         instruction_data:&[u8])->ProgramResult{
@@ -30,11 +47,26 @@ pub fn process_instructions(_program_id:&Pubkey, accounts:&[AccountInfo],
                 Some((&Make::DISCRIMINATOR, other))=>{
                     Make::try_from((accounts, other))?.process()
                 },
-                Some((&Take::DISCRIMINATOR, _other))=>{
-                    Take::try_from(accounts)?.process()
+                Some((&Take::DISCRIMINATOR, other))=>{
+                    Take::try_from((accounts, other))?.process()
+                },
+                Some((&Refund::DISCRIMINATOR, other))=>{
+                    Refund::try_from((accounts, other))?.process()
+                },
+                Some((&RefundMany::DISCRIMINATOR, _other))=>{
+                    RefundMany::try_from(accounts)?.process()
+                },
+                Some((&TopUp::DISCRIMINATOR, other))=>{
+                    TopUp::try_from((accounts, other))?.process()
+                },
+                Some((&MakeDelegated::DISCRIMINATOR, other))=>{
+                    MakeDelegated::try_from((accounts, other))?.process()
+                },
+                Some((&AddExtra::DISCRIMINATOR, other))=>{
+                    AddExtra::try_from((accounts, other))?.process()
                 },
-                Some((&Refund::DISCRIMINATOR, _other))=>{
-                    Refund::try_from(accounts)?.process()
+                Some((&Migrate::DISCRIMINATOR, _other))=>{
+                    Migrate::try_from(accounts)?.process()
                 },
                 _ =>{
                     Err(ProgramError::InvalidInstructionData)
@@ -0,0 +1,132 @@
+use pinocchio::{program_error::ProgramError, pubkey::Pubkey, ProgramResult};
+
+use crate::EscrowError;
+
+// Which of the optional rent recipients follow the fixed accounts of a Take or Refund,
+// sent as a flag byte at the end of the instruction data. Left out, both rents go to
+// the maker
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RentRecipients {
+    // Receives the rent of the escrow, and of its vault unless `vault_rent_to` is passed
+    pub close_to: bool,
+    // Receives the rent of the vault
+    pub vault_rent_to: bool,
+}
+
+impl RentRecipients {
+    pub const CLOSE_TO: u8 = 1;
+    pub const VAULT_RENT_TO: u8 = 2;
+
+    #[inline(always)]
+    pub fn to_byte(self) -> u8 {
+        (self.close_to as u8 * Self::CLOSE_TO) | (self.vault_rent_to as u8 * Self::VAULT_RENT_TO)
+    }
+
+    // Splits the recipients the flags ask for off `rest`, the accounts past the fixed
+    // ones, and returns them with the accounts left after them
+    #[inline(always)]
+    pub fn split<'a, T>(self, maker: &'a T, rest: &'a [T]) -> Result<(&'a T, &'a T, &'a [T]), ProgramError> {
+        let (close_to, rest) = match self.close_to {
+            true => rest.split_first().ok_or(ProgramError::NotEnoughAccountKeys)?,
+            false => (maker, rest),
+        };
+
+        let (vault_rent_to, rest) = match self.vault_rent_to {
+            true => rest.split_first().ok_or(ProgramError::NotEnoughAccountKeys)?,
+            false => (close_to, rest),
+        };
+
+        Ok((close_to, vault_rent_to, rest))
+    }
+}
+
+impl TryFrom<Option<&u8>> for RentRecipients {
+    type Error = ProgramError;
+
+    fn try_from(flags: Option<&u8>) -> Result<Self, Self::Error> {
+        let flags = flags.copied().unwrap_or(0);
+
+        if flags & !(Self::CLOSE_TO | Self::VAULT_RENT_TO) != 0 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        Ok(Self {
+            close_to: flags & Self::CLOSE_TO != 0,
+            vault_rent_to: flags & Self::VAULT_RENT_TO != 0,
+        })
+    }
+}
+
+// Only the rent payer can send the vault rent elsewhere than the escrow rent, e.g. to a
+// fee account, so `close_to` has to sign for that
+#[inline(always)]
+pub fn check_vault_rent_to(close_to: &Pubkey, close_to_signed: bool, vault_rent_to: &Pubkey) -> ProgramResult {
+    if vault_rent_to.ne(close_to) && !close_to_signed {
+        return Err(EscrowError::InvalidRentPayer.into());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_flag_byte_round_trips() {
+        for byte in 0..=3 {
+            let recipients = RentRecipients::try_from(Some(&byte)).unwrap();
+            assert_eq!(recipients.to_byte(), byte);
+        }
+
+        // Left out, nothing is flagged
+        assert_eq!(RentRecipients::try_from(None).unwrap(), RentRecipients::default());
+        assert_eq!(RentRecipients::try_from(Some(&4)), Err(ProgramError::InvalidInstructionData));
+    }
+
+    #[test]
+    fn the_recipients_are_split_off_by_flag_not_by_count() {
+        let maker = 0;
+        // Three accounts of an extra asset, which the old encoding read by length
+        let extra = [10, 11, 12];
+
+        let (close_to, vault_rent_to, rest) = RentRecipients::default().split(&maker, &extra).unwrap();
+        assert_eq!((*close_to, *vault_rent_to, rest), (0, 0, &extra[..]));
+
+        let flagged = [1, 10, 11, 12];
+        let recipients = RentRecipients { close_to: true, vault_rent_to: false };
+        let (close_to, vault_rent_to, rest) = recipients.split(&maker, &flagged).unwrap();
+        assert_eq!((*close_to, *vault_rent_to, rest), (1, 1, &extra[..]));
+
+        let flagged = [2, 10, 11, 12];
+        let recipients = RentRecipients { close_to: false, vault_rent_to: true };
+        let (close_to, vault_rent_to, rest) = recipients.split(&maker, &flagged).unwrap();
+        assert_eq!((*close_to, *vault_rent_to, rest), (0, 2, &extra[..]));
+
+        let recipients = RentRecipients { close_to: true, vault_rent_to: true };
+        assert_eq!(recipients.split(&maker, &[1]), Err(ProgramError::NotEnoughAccountKeys));
+    }
+
+    #[test]
+    fn the_vault_rent_goes_to_a_fee_account() {
+        let maker = [1; 32];
+        let fee_account = [9; 32];
+
+        // A refund by the maker flagging only `vault_rent_to`, the escrow rent stays with the maker
+        let recipients = RentRecipients::try_from(Some(&RentRecipients::VAULT_RENT_TO)).unwrap();
+        let rest = [fee_account];
+        let (close_to, vault_rent_to, rest) = recipients.split(&maker, &rest).unwrap();
+        assert_eq!((close_to, vault_rent_to), (&maker, &fee_account));
+        assert!(rest.is_empty());
+
+        // The maker signs the refund, so it can redirect the vault rent
+        assert_eq!(check_vault_rent_to(close_to, true, vault_rent_to), Ok(()));
+
+        // Nobody else can redirect it for the rent payer
+        assert_eq!(
+            check_vault_rent_to(close_to, false, vault_rent_to),
+            Err(EscrowError::InvalidRentPayer.into())
+        );
+        assert_eq!(check_vault_rent_to(close_to, false, close_to), Ok(()));
+    }
+}
@@ -0,0 +1,119 @@
+use pinocchio::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
+
+use associated_token_helpers::{
+    AssociatedTokenAccount
+};
+use token_interface_helpers::{
+    MintInterface
+};
+
+use crate::{account_count_error, check_not_frozen, Escrow, EscrowError, RentRecipients, MAX_BATCH};
+
+// Splits the accounts past the fixed ones of a take or refund into the flagged rent
+// recipients and the extra assets, three accounts each. Accounts left over past the
+// last whole asset were not asked for
+#[inline(always)]
+pub fn split_extra_accounts<'a, T>(
+    recipients: RentRecipients,
+    maker: &'a T,
+    rest: &'a [T]
+) -> Result<(&'a T, &'a T, &'a [T]), ProgramError> {
+    let (close_to, vault_rent_to, extra) = recipients.split(maker, rest)?;
+
+    if extra.len() % 3 != 0 {
+        return Err(EscrowError::TooManyAccounts.into());
+    }
+
+    // Checked up front so an oversized escrow fails before any transfer
+    if extra.len() / 3 > MAX_BATCH {
+        return Err(EscrowError::BatchTooLarge.into());
+    }
+
+    Ok((close_to, vault_rent_to, extra))
+}
+
+// Matches the extra assets passed to a take or refund against the mints recorded in
+// the escrow. They come as a (vault, mint) pair per recorded mint in the recorded order,
+// followed by the token account receiving each, and the pairs and recipients are
+// returned. Every one has to be passed, a left out vault would be stranded under the
+// closed escrow
+#[inline(always)]
+pub fn match_extra_assets<'a>(
+    mints: &[Pubkey],
+    extra: &'a [AccountInfo]
+) -> Result<(&'a [AccountInfo], &'a [AccountInfo]), ProgramError> {
+    if extra.len() != mints.len() * 3 {
+        return Err(account_count_error(extra.len(), mints.len() * 3));
+    }
+
+    let (pairs, recipients) = extra.split_at(mints.len() * 2);
+
+    for (pair, expected) in pairs.chunks_exact(2).zip(mints) {
+        let [_, mint] = pair else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        if mint.key() != expected {
+            return Err(EscrowError::ExtraAssetsMismatch.into());
+        }
+    }
+
+    Ok((pairs, recipients))
+}
+
+// Checks the extra assets passed to a take or refund, matched by `match_extra_assets`,
+// and that each vault is the escrow's unfrozen ATA for its mint
+pub fn check_extra_assets<'a>(
+    escrow: &AccountInfo,
+    extra: &'a [AccountInfo],
+    token_program: &AccountInfo
+) -> Result<(&'a [AccountInfo], &'a [AccountInfo]), ProgramError> {
+    let escrow_ref = escrow.try_borrow_data()?;
+    let (pairs, recipients) = match_extra_assets(Escrow::extra_mints(&escrow_ref)?, extra)?;
+
+    for pair in pairs.chunks_exact(2) {
+        let [vault, mint] = pair else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        MintInterface::check(mint)?;
+
+        AssociatedTokenAccount::check(
+            vault,
+            escrow,
+            mint,
+            token_program,
+            true
+        )?;
+
+        check_not_frozen(vault)?;
+    }
+
+    Ok((pairs, recipients))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestAccounts;
+
+    #[test]
+    fn the_extra_assets_must_match_the_recorded_count() {
+        // An escrow holding one extra asset
+        let mut escrow = TestAccounts::default();
+        escrow.add([0xee; 32], crate::ID, false, 0, &[0; Escrow::space(1)]);
+        let mut others = TestAccounts::blank(7);
+
+        let (escrow, others) = (escrow.infos(), others.infos());
+        let (token_program, extra) = others.split_first().unwrap();
+
+        assert_eq!(
+            check_extra_assets(&escrow[0], &extra[..0], token_program).err(),
+            Some(ProgramError::NotEnoughAccountKeys)
+        );
+        assert_eq!(
+            check_extra_assets(&escrow[0], extra, token_program).err(),
+            Some(EscrowError::TooManyAccounts.into())
+        );
+    }
+}
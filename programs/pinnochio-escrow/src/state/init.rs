@@ -0,0 +1,61 @@
+use pinocchio::{account_info::AccountInfo, ProgramResult};
+
+use crate::EscrowError;
+
+// Stricter than `UninitializedAccount::check` for accounts the program creates at a PDA.
+// Anyone can send lamports to the address ahead of time or have it assigned, so the
+// account has to be untouched for `ProgramAccount::init` to start from a known state
+#[inline(always)]
+pub fn assert_untouched(account: &AccountInfo) -> ProgramResult {
+    if account.owner() == &crate::ID || account.data_len() != 0 || account.lamports() != 0 {
+        return Err(EscrowError::AccountInUse.into());
+    }
+
+    Ok(())
+}
+
+// The vault is an ATA of the escrow, so it already holding data means an escrow with
+// the same seed was made before. Checked ahead of `UninitializedAccount::check` to
+// report that case on its own. Lamports alone are fine, the ATA program tops them up
+#[inline(always)]
+pub fn assert_vault_absent(vault: &AccountInfo) -> ProgramResult {
+    if vault.data_len() != 0 {
+        return Err(EscrowError::VaultAlreadyExists.into());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestAccounts;
+
+    #[test]
+    fn a_pre_funded_escrow_pda_is_in_use() {
+        let mut accounts = TestAccounts::default();
+        accounts.add([1; 32], [0; 32], false, 0, &[]);
+        // Someone sent lamports to the escrow address ahead of the make
+        accounts.add([2; 32], [0; 32], false, 890_880, &[]);
+        accounts.add([3; 32], crate::ID, false, 0, &[]);
+        let accounts = accounts.infos();
+
+        assert_eq!(assert_untouched(&accounts[0]), Ok(()));
+        assert_eq!(assert_untouched(&accounts[1]), Err(EscrowError::AccountInUse.into()));
+        assert_eq!(assert_untouched(&accounts[2]), Err(EscrowError::AccountInUse.into()));
+    }
+
+    #[test]
+    fn a_vault_left_by_an_earlier_escrow_is_reported() {
+        let mut accounts = TestAccounts::default();
+        accounts.add([1; 32], [0; 32], false, 0, &[]);
+        // Lamports sent ahead of the make are topped up by the ATA program
+        accounts.add([2; 32], [0; 32], false, 890_880, &[]);
+        accounts.add([3; 32], pinocchio_token::ID, false, 2_039_280, &[0; pinocchio_token::state::TokenAccount::LEN]);
+        let accounts = accounts.infos();
+
+        assert_eq!(assert_vault_absent(&accounts[0]), Ok(()));
+        assert_eq!(assert_vault_absent(&accounts[1]), Ok(()));
+        assert_eq!(assert_vault_absent(&accounts[2]), Err(EscrowError::VaultAlreadyExists.into()));
+    }
+}
@@ -1,2 +1,20 @@
 pub mod state;
-pub use state::*;
\ No newline at end of file
+pub use state::*;
+
+pub mod vault;
+pub use vault::*;
+
+pub mod realloc;
+pub use realloc::*;
+
+pub mod rent;
+pub use rent::*;
+
+pub mod init;
+pub use init::*;
+
+pub mod extra;
+pub use extra::*;
+
+pub mod close;
+pub use close::*;
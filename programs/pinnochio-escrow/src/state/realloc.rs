@@ -0,0 +1,106 @@
+use pinocchio::{
+    account_info::AccountInfo,
+    program_error::ProgramError,
+    sysvars::{rent::Rent, Sysvar},
+    ProgramResult
+};
+use pinocchio_system::instructions::Transfer;
+
+// Grows an account owned by this program to `new_len`, the payer tops up
+// the rent so the account stays rent exempt at the new size.
+//
+// Lets the escrow state gain fields while accounts created at the old
+// length are migrated in place
+pub fn realloc_program_account(
+    account: &AccountInfo,
+    payer: &AccountInfo,
+    new_len: usize
+) -> ProgramResult {
+    realloc_with_rent(account, payer, new_len, Rent::get()?.minimum_balance(new_len))
+}
+
+// Grows the account once `required`, the rent exempt minimum at `new_len`, is known
+#[inline(always)]
+fn realloc_with_rent(
+    account: &AccountInfo,
+    payer: &AccountInfo,
+    new_len: usize,
+    required: u64
+) -> ProgramResult {
+    if !account.is_owned_by(&crate::ID) {
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    // Shrinking would drop state, there is nothing to do at the same length
+    if new_len.le(&account.data_len()) {
+        return Err(ProgramError::InvalidRealloc);
+    }
+
+    if required.gt(&account.lamports()) {
+        Transfer {
+            from: payer,
+            to: account,
+            lamports: required - account.lamports()
+        }.invoke()?;
+    }
+
+    // The new bytes are zeroed
+    account.resize(new_len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{testing::TestAccounts, Escrow};
+
+    // An escrow as it was stored before the rent payer was recorded
+    fn legacy_escrow() -> [u8; Escrow::LEGACY_LEN] {
+        let mut data = [0; Escrow::LEGACY_LEN];
+        data[0..8].copy_from_slice(&7u64.to_le_bytes());
+        data[8..40].copy_from_slice(&[1; 32]);
+        data[40..72].copy_from_slice(&[2; 32]);
+        data[72..104].copy_from_slice(&[3; 32]);
+        data[104..112].copy_from_slice(&250u64.to_le_bytes());
+        data[112] = 254;
+        data
+    }
+
+    #[test]
+    fn a_legacy_escrow_keeps_its_fields_once_grown() {
+        // Already holding the rent at the new length, so nothing is transferred
+        let mut accounts = TestAccounts::default();
+        accounts.add([0xee; 32], crate::ID, false, 2_000_000, &legacy_escrow());
+        accounts.add([0xaa; 32], [0; 32], true, 0, &[]);
+        let accounts = accounts.infos();
+        let (escrow, payer) = (&accounts[0], &accounts[1]);
+
+        assert_eq!(realloc_with_rent(escrow, payer, Escrow::LEN, 2_000_000), Ok(()));
+        assert_eq!(escrow.data_len(), Escrow::LEN);
+
+        let mut data = escrow.try_borrow_mut_data().unwrap();
+        Escrow::migrate(&mut data).unwrap();
+        let escrow = Escrow::load(&data).unwrap();
+
+        assert_eq!(u64::from_le_bytes(*escrow.seed()), 7);
+        assert_eq!((escrow.maker(), escrow.mint_a(), escrow.mint_b()), (&[1; 32], &[2; 32], &[3; 32]));
+        assert_eq!((escrow.receive, escrow.bump()), (250, &[254]));
+        assert_eq!(escrow.rent_payer(), escrow.maker());
+    }
+
+    #[test]
+    fn only_program_accounts_are_grown() {
+        let mut accounts = TestAccounts::default();
+        accounts.add([0xee; 32], crate::ID, false, 2_000_000, &legacy_escrow());
+        accounts.add([0xdd; 32], [0; 32], false, 2_000_000, &legacy_escrow());
+        let accounts = accounts.infos();
+
+        assert_eq!(
+            realloc_with_rent(&accounts[0], &accounts[1], Escrow::LEGACY_LEN, 0),
+            Err(ProgramError::InvalidRealloc)
+        );
+        assert_eq!(
+            realloc_with_rent(&accounts[1], &accounts[0], Escrow::LEN, 0),
+            Err(ProgramError::InvalidAccountOwner)
+        );
+    }
+}
@@ -0,0 +1,49 @@
+use pinocchio::{
+    account_info::AccountInfo,
+    sysvars::{rent::Rent, Sysvar},
+    ProgramResult
+};
+use basic_helpers::ProgramAccount;
+
+use crate::EscrowError;
+
+// The rent check `ProgramAccount` lacks, basic_helpers is not part of this
+// tree so it is added from here
+pub trait ProgramAccountRent {
+    // Post-condition for account creation, catches an account left below the
+    // rent exempt minimum for `data_len` bytes, e.g. by an underfunded client
+    fn assert_rent_exempt(account: &AccountInfo, data_len: usize) -> ProgramResult;
+}
+
+impl ProgramAccountRent for ProgramAccount {
+    #[inline(always)]
+    fn assert_rent_exempt(account: &AccountInfo, data_len: usize) -> ProgramResult {
+        holds_minimum_balance(account, Rent::get()?.minimum_balance(data_len))
+    }
+}
+
+// The check once `minimum`, the rent exempt minimum of the account, is known
+#[inline(always)]
+fn holds_minimum_balance(account: &AccountInfo, minimum: u64) -> ProgramResult {
+    if account.lamports().lt(&minimum) {
+        return Err(EscrowError::NotRentExempt.into());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestAccounts;
+
+    #[test]
+    fn an_underfunded_account_is_not_rent_exempt() {
+        let mut accounts = TestAccounts::default();
+        accounts.add([1; 32], crate::ID, false, 1_000, &[]);
+        let accounts = accounts.infos();
+
+        assert_eq!(holds_minimum_balance(&accounts[0], 1_000), Ok(()));
+        assert_eq!(holds_minimum_balance(&accounts[0], 1_001), Err(EscrowError::NotRentExempt.into()));
+    }
+}
@@ -1,6 +1,8 @@
 use pinocchio::{program_error::ProgramError, pubkey::Pubkey};
 use core::mem::size_of;
 
+use crate::EscrowError;
+
 #[derive(Debug)]
 #[repr(C)]
 pub struct Escrow {
@@ -9,7 +11,10 @@ pub struct Escrow {
     pub mint_a: Pubkey, 
     pub mint_b: Pubkey, 
     pub receive: u64,   
-    pub bump: [u8;1]  
+    pub bump: [u8;1],
+    // Gets the rent back when the escrow and its vault are closed. Appended after
+    // the original fields, escrows made before it are grown by `Migrate`
+    pub rent_payer: Pubkey
 }
 
 impl Escrow{
@@ -19,13 +24,30 @@ impl Escrow{
                         size_of::<Pubkey>() + 
                         size_of::<Pubkey>() + 
                         size_of::<u64>() +    
-                        size_of::<[u8;1]>(); 
+                        size_of::<[u8;1]>() + 
+                        size_of::<Pubkey>(); 
+
+    // The length of the escrows made before the rent payer was recorded
+    pub const LEGACY_LEN: usize = Self::LEN - size_of::<Pubkey>();
 
+    // The account length of an escrow holding `extra` extra assets, their mints are
+    // recorded one after the other past the escrow fields
     #[inline(always)]
-    pub fn load_mut(data: &mut [u8]) -> Result<&mut Self, ProgramError> {
-        if data.len() != Self::LEN {
+    pub const fn space(extra: usize) -> usize {
+        Self::LEN + extra * size_of::<Pubkey>()
+    }
+
+    #[inline(always)]
+    fn check_len(len: usize) -> Result<(), ProgramError> {
+        if len < Self::LEN || (len - Self::LEN) % size_of::<Pubkey>() != 0 {
             return Err(ProgramError::InvalidAccountData);
         }
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub fn load_mut(data: &mut [u8]) -> Result<&mut Self, ProgramError> {
+        Self::check_len(data.len())?;
         let escrow = unsafe { &mut *core::mem::transmute::<*mut u8, *mut Self>(
             data.as_mut_ptr()) };
         Ok(escrow)
@@ -34,16 +56,69 @@ impl Escrow{
 
     #[inline(always)]
     pub fn load(data: &[u8]) -> Result<&Self, ProgramError> {
-        if data.len() != Self::LEN {
-            return Err(ProgramError::InvalidAccountData);
-        }
+        Self::check_len(data.len())?;
         let escrow = unsafe { &mut *core::mem::transmute::<*const u8, *mut Self>(
             data.as_ptr()) };
         Ok(escrow)
     }
 
-     #[inline(always)]
-    pub fn seed(&mut self, seed: [u8;8]) {
+    // Records the maker as the rent payer of an escrow grown from `LEGACY_LEN`, the
+    // maker funded every escrow made before the rent payer was recorded
+    #[inline(always)]
+    pub fn migrate(data: &mut [u8]) -> Result<(), ProgramError> {
+        if data.len() != Self::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let escrow = Self::load_mut(data)?;
+        escrow.rent_payer = escrow.maker;
+
+        Ok(())
+    }
+
+    // The mints of the extra assets the escrow holds, in the order they were added
+    #[inline(always)]
+    pub fn extra_mints(data: &[u8]) -> Result<&[Pubkey], ProgramError> {
+        Self::check_len(data.len())?;
+
+        let extra = &data[Self::LEN..];
+
+        // Safe because `Pubkey` is a byte array and the length is a multiple of it
+        Ok(unsafe {
+            core::slice::from_raw_parts(extra.as_ptr() as *const Pubkey, extra.len() / size_of::<Pubkey>())
+        })
+    }
+
+    #[inline(always)]
+    pub fn seed(&self) -> &[u8;8] { &self.seed }
+
+    #[inline(always)]
+    pub fn maker(&self) -> &Pubkey { &self.maker }
+
+    #[inline(always)]
+    pub fn mint_a(&self) -> &Pubkey { &self.mint_a }
+
+    #[inline(always)]
+    pub fn mint_b(&self) -> &Pubkey { &self.mint_b }
+
+    #[inline(always)]
+    pub fn rent_payer(&self) -> &Pubkey { &self.rent_payer }
+
+    #[inline(always)]
+    pub fn bump(&self) -> &[u8;1] { &self.bump }
+
+    // The rent goes back to whoever funded the escrow, the maker unless it was sponsored
+    #[inline(always)]
+    pub fn check_close_to(&self, close_to: &Pubkey) -> Result<(), ProgramError> {
+        if self.rent_payer.ne(close_to) {
+            return Err(EscrowError::InvalidRentPayer.into());
+        }
+
+        Ok(())
+    }
+
+    #[inline(always)]
+    pub fn set_seed(&mut self, seed: [u8;8]) {
         self.seed = seed;
     }
  
@@ -67,17 +142,76 @@ impl Escrow{
         self.receive = receive;
     }
  
+    #[inline(always)]
+    pub fn set_rent_payer(&mut self, rent_payer: Pubkey) {
+        self.rent_payer = rent_payer;
+    }
+ 
     #[inline(always)]
     pub fn set_bump(&mut self, bump: [u8;1]) {
         self.bump = bump;
     }
  
-    pub fn set_inner(&mut self, seed:[u8;8], maker: Pubkey, mint_a: Pubkey, mint_b: Pubkey, receive: u64, bump: [u8;1]){
+    pub fn set_inner(&mut self, seed:[u8;8], maker: Pubkey, mint_a: Pubkey, mint_b: Pubkey, receive: u64, rent_payer: Pubkey, bump: [u8;1]){
         self.seed = seed;
         self.maker = maker;
         self.mint_a = mint_a;
         self.mint_b = mint_b;
         self.receive = receive;
+        self.rent_payer = rent_payer;
         self.bump = bump;
     }
-}
\ No newline at end of file
+}
+// What a take of the escrow settles, as (mint_b the taker pays, mint_a the taker gets).
+// Takes are all or nothing, so the taker pays the full amount asked for and gets the
+// whole vault, `vault_amount` being its current balance
+#[inline(always)]
+pub fn preview_take(escrow: &Escrow, vault_amount: u64) -> (u64, u64) {
+    (escrow.receive, vault_amount)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A relayer sponsored escrow of maker [1; 32] trading [2; 32] for [3; 32]
+    fn escrow(rent_payer: Pubkey) -> Escrow {
+        Escrow {
+            seed: [0; 8],
+            maker: [1; 32],
+            mint_a: [2; 32],
+            mint_b: [3; 32],
+            receive: 250,
+            bump: [255],
+            rent_payer,
+        }
+    }
+
+    #[test]
+    fn the_rent_of_a_sponsored_escrow_goes_to_the_relayer() {
+        let relayer = [9; 32];
+        let escrow = escrow(relayer);
+
+        assert_eq!(escrow.check_close_to(&relayer), Ok(()));
+
+        // The maker did not pay for it
+        assert_eq!(escrow.check_close_to(escrow.maker()), Err(EscrowError::InvalidRentPayer.into()));
+    }
+
+    #[test]
+    fn the_rent_payer_is_appended_after_the_original_fields() {
+        assert_eq!(Escrow::LEGACY_LEN, 113);
+        assert_eq!(Escrow::LEN, 145);
+
+        assert_eq!(core::mem::offset_of!(Escrow, bump), 112);
+        assert_eq!(core::mem::offset_of!(Escrow, rent_payer), Escrow::LEGACY_LEN);
+    }
+
+    #[test]
+    fn preview_take_settles_the_whole_escrow() {
+        let escrow = escrow([1; 32]);
+
+        assert_eq!(preview_take(&escrow, 1_000), (250, 1_000));
+        assert_eq!(preview_take(&escrow, 0), (250, 0));
+    }
+}
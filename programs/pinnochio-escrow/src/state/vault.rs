@@ -0,0 +1,180 @@
+use pinocchio::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey, ProgramResult};
+use pinocchio_token::state::{Mint, TokenAccount};
+use token_interface_helpers::TokenAccountInterface;
+
+use crate::EscrowError;
+
+// Reads an account of either token program through `read`, the one place its owner and
+// length are checked. Token-2022 accounts can carry extensions past the base layout,
+// the base fields sit at the same offsets for both
+#[inline(always)]
+fn read_token_program_account<T>(
+    account: &AccountInfo,
+    len: usize,
+    read: impl FnOnce(&[u8]) -> T,
+) -> Result<T, ProgramError> {
+    if account.owner().ne(&pinocchio_token::ID) && account.owner().ne(&pinocchio_token_2022::ID) {
+        return Err(ProgramError::InvalidAccountOwner);
+    }
+
+    let data = account.try_borrow_data()?;
+
+    if data.len() < len {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    Ok(read(&data))
+}
+
+#[inline(always)]
+fn read_token_account<T>(account: &AccountInfo, read: impl FnOnce(&TokenAccount) -> T) -> Result<T, ProgramError> {
+    // Safe because the length is checked by the reader
+    read_token_program_account(account, TokenAccount::LEN, |data| read(unsafe { TokenAccount::from_bytes_unchecked(data) }))
+}
+
+// The balance read `TokenAccountInterface` lacks, token_interface_helpers is
+// not part of this tree so it is added from here
+pub trait TokenAccountBalance {
+    // Reads the amount held by a token account of `token_program`, which has to be
+    // one of the two token programs and own the account
+    fn balance(account: &AccountInfo, token_program: &AccountInfo) -> Result<u64, ProgramError>;
+}
+
+impl TokenAccountBalance for TokenAccountInterface {
+    #[inline(always)]
+    fn balance(account: &AccountInfo, token_program: &AccountInfo) -> Result<u64, ProgramError> {
+        if token_program.key().ne(&pinocchio_token::ID) && token_program.key().ne(&pinocchio_token_2022::ID) {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        if account.owner().ne(token_program.key()) {
+            return Err(ProgramError::InvalidAccountOwner);
+        }
+
+        read_token_account(account, |token_account| token_account.amount())
+    }
+}
+
+// Checks that a token account of either token program holds `mint` and is owned by `owner`
+#[inline(always)]
+pub fn check_token_account(account: &AccountInfo, mint: &Pubkey, owner: &Pubkey) -> ProgramResult {
+    match read_token_account(account, |token_account| {
+        token_account.mint().eq(mint) && token_account.owner().eq(owner)
+    })? {
+        true => Ok(()),
+        false => Err(ProgramError::InvalidAccountData),
+    }
+}
+
+// Reads the decimals of a mint of either token program
+#[inline(always)]
+pub fn mint_decimals(mint: &AccountInfo) -> Result<u8, ProgramError> {
+    // Safe because the length is checked by the reader
+    read_token_program_account(mint, Mint::LEN, |data| unsafe { Mint::from_bytes_unchecked(data) }.decimals())
+}
+
+// Reads how much of a token account `delegate` may move, zero if it is not the delegate
+#[inline(always)]
+pub fn delegated_amount(account: &AccountInfo, delegate: &Pubkey) -> Result<u64, ProgramError> {
+    read_token_account(account, |token_account| match token_account.delegate() {
+        Some(key) if key.eq(delegate) => token_account.delegated_amount(),
+        _ => 0,
+    })
+}
+
+// Fails early on a frozen token account of either token program, which the
+// token program would otherwise reject mid transfer with its own error
+#[inline(always)]
+pub fn check_not_frozen(account: &AccountInfo) -> ProgramResult {
+    if read_token_account(account, |token_account| token_account.is_frozen())? {
+        return Err(EscrowError::AccountFrozen.into());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TestAccounts;
+
+    // `len` bytes of a token account holding `amount`
+    fn token_account(amount: u64, len: usize) -> std::vec::Vec<u8> {
+        let mut data = std::vec![0; len];
+        data[64..72].copy_from_slice(&amount.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn balance_rejects_a_short_account() {
+        let mut accounts = TestAccounts::default();
+        accounts.add(pinocchio_token::ID, [0; 32], false, 0, &[]);
+        accounts.add([1; 32], pinocchio_token::ID, false, 0, &token_account(500, TokenAccount::LEN));
+        accounts.add([2; 32], pinocchio_token::ID, false, 0, &token_account(500, TokenAccount::LEN)[..72]);
+        let accounts = accounts.infos();
+        let token_program = &accounts[0];
+
+        assert_eq!(TokenAccountInterface::balance(&accounts[1], token_program), Ok(500));
+
+        // Long enough to hold the amount but not a whole token account
+        assert_eq!(TokenAccountInterface::balance(&accounts[2], token_program), Err(ProgramError::InvalidAccountData));
+    }
+
+    #[test]
+    fn balance_rejects_other_programs() {
+        let mut accounts = TestAccounts::default();
+        accounts.add(pinocchio_token::ID, [0; 32], false, 0, &[]);
+        accounts.add([1; 32], [9; 32], false, 0, &token_account(500, TokenAccount::LEN));
+        let accounts = accounts.infos();
+
+        // Not owned by the token program
+        assert_eq!(TokenAccountInterface::balance(&accounts[1], &accounts[0]), Err(ProgramError::InvalidAccountOwner));
+
+        // Not a token program at all
+        assert_eq!(TokenAccountInterface::balance(&accounts[0], &accounts[1]), Err(ProgramError::IncorrectProgramId));
+    }
+
+    #[test]
+    fn balance_reads_both_token_programs() {
+        // A Token-2022 account carries its account type and extensions past the base layout
+        let mut extended = token_account(700, TokenAccount::LEN + 1 + 12);
+        extended[TokenAccount::LEN] = 2;
+
+        let mut accounts = TestAccounts::default();
+        accounts.add(pinocchio_token::ID, [0; 32], false, 0, &[]);
+        accounts.add(pinocchio_token_2022::ID, [0; 32], false, 0, &[]);
+        accounts.add([1; 32], pinocchio_token::ID, false, 0, &token_account(500, TokenAccount::LEN));
+        accounts.add([2; 32], pinocchio_token_2022::ID, false, 0, &extended);
+        let accounts = accounts.infos();
+        let (token_program, token_2022_program) = (&accounts[0], &accounts[1]);
+
+        assert_eq!(TokenAccountInterface::balance(&accounts[2], token_program), Ok(500));
+        assert_eq!(TokenAccountInterface::balance(&accounts[3], token_2022_program), Ok(700));
+
+        // Each account is read through its own program only
+        assert_eq!(
+            TokenAccountInterface::balance(&accounts[3], token_program),
+            Err(ProgramError::InvalidAccountOwner)
+        );
+    }
+
+    #[test]
+    fn a_frozen_taker_ata_is_rejected_before_the_transfer() {
+        // The account state byte, 2 once the mint's freeze authority froze it
+        let mut frozen = token_account(500, TokenAccount::LEN);
+        frozen[108] = 2;
+
+        let mut initialized = token_account(500, TokenAccount::LEN);
+        initialized[108] = 1;
+
+        let mut accounts = TestAccounts::default();
+        accounts.add([1; 32], pinocchio_token::ID, false, 0, &initialized);
+        accounts.add([2; 32], pinocchio_token::ID, false, 0, &frozen);
+        accounts.add([3; 32], pinocchio_token_2022::ID, false, 0, &frozen);
+        let accounts = accounts.infos();
+
+        assert_eq!(check_not_frozen(&accounts[0]), Ok(()));
+        assert_eq!(check_not_frozen(&accounts[1]), Err(EscrowError::AccountFrozen.into()));
+        assert_eq!(check_not_frozen(&accounts[2]), Err(EscrowError::AccountFrozen.into()));
+    }
+}
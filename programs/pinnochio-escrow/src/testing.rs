@@ -0,0 +1,98 @@
+use core::{marker::PhantomData, mem::{size_of, transmute}, ops::Deref};
+use std::{vec, vec::Vec};
+
+use pinocchio::{account_info::AccountInfo, pubkey::Pubkey};
+
+// The header the runtime serializes ahead of each account's data, laid out like
+// pinocchio's `Account` so an `AccountInfo` can point at it
+#[repr(C)]
+struct Header {
+    borrow_state: u8,
+    is_signer: u8,
+    is_writable: u8,
+    executable: u8,
+    resize_delta: i32,
+    key: Pubkey,
+    owner: Pubkey,
+    lamports: u64,
+    data_len: u64,
+}
+
+// Not borrowed and not a duplicate
+const NOT_BORROWED: u8 = u8::MAX;
+
+// Room past the data for tests that grow an account, as the runtime leaves
+const REALLOC_ROOM: usize = 10 * 1024;
+
+// Backs the accounts of an instruction under test, each one a header followed by its
+// data in a buffer of its own. The buffers are u64 so the data is aligned like the
+// runtime input, and stay put while the `AccountInfo`s pointing at them are in use
+#[derive(Default)]
+pub struct TestAccounts {
+    buffers: Vec<Vec<u64>>,
+}
+
+impl TestAccounts {
+    // `count` empty accounts with distinct keys, enough for the parsers which only
+    // look at how many accounts are passed
+    pub fn blank(count: usize) -> Self {
+        let mut accounts = Self::default();
+
+        for index in 0..count {
+            accounts.add([index as u8; 32], [0; 32], false, 0, &[]);
+        }
+
+        accounts
+    }
+
+    pub fn add(&mut self, key: Pubkey, owner: Pubkey, is_signer: bool, lamports: u64, data: &[u8]) -> &mut Self {
+        let mut buffer = vec![0u64; (size_of::<Header>() + data.len() + REALLOC_ROOM).div_ceil(8)];
+
+        // Safe because the buffer is 8 byte aligned and longer than the header
+        unsafe {
+            (buffer.as_mut_ptr() as *mut Header).write(Header {
+                borrow_state: NOT_BORROWED,
+                is_signer: is_signer as u8,
+                is_writable: 1,
+                executable: 0,
+                resize_delta: 0,
+                key,
+                owner,
+                lamports,
+                data_len: data.len() as u64,
+            });
+
+            core::slice::from_raw_parts_mut((buffer.as_mut_ptr() as *mut u8).add(size_of::<Header>()), data.len())
+                .copy_from_slice(data);
+        }
+
+        self.buffers.push(buffer);
+        self
+    }
+
+    pub fn infos(&mut self) -> Infos<'_> {
+        Infos {
+            infos: self.buffers
+                .iter_mut()
+                // Safe because `AccountInfo` is a single pointer to the header
+                .map(|buffer| unsafe { transmute::<*mut u64, AccountInfo>(buffer.as_mut_ptr()) })
+                .collect(),
+            _accounts: PhantomData,
+        }
+    }
+}
+
+// The `AccountInfo`s of some `TestAccounts`, which they point into, so they
+// borrow them to keep the accounts from being dropped first
+pub struct Infos<'a> {
+    infos: Vec<AccountInfo>,
+    _accounts: PhantomData<&'a mut TestAccounts>,
+}
+
+impl Deref for Infos<'_> {
+    type Target = Vec<AccountInfo>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.infos
+    }
+}
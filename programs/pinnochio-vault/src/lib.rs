@@ -2,9 +2,10 @@
 use pinocchio::{
     account_info::AccountInfo, entrypoint, instruction::{Seed, Signer}, 
     nostd_panic_handler, program_error::ProgramError, pubkey::{find_program_address, Pubkey}, 
+    sysvars::{clock::Clock, rent::Rent, Sysvar},
     ProgramResult
 };
-use pinocchio_system::instructions::Transfer;
+use pinocchio_system::instructions::{CreateAccount, Transfer};
 
 nostd_panic_handler!();
 
@@ -17,6 +18,62 @@ pub const ID: Pubkey = [
     0x8e, 0xf8, 0xaf, 0x70, 0x47, 0xdc, 0x11, 0xf7,
 ];
 
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VaultError {
+    // The vault is locked until its unlock time
+    VaultLocked,
+}
+
+impl From<VaultError> for ProgramError {
+    fn from(error: VaultError) -> Self {
+        ProgramError::Custom(error as u32)
+    }
+}
+
+// Holds the unlock time of an owner's vault, the vault itself stays a plain
+// system account so the state lives in its own PDA. Missing means unlocked
+pub struct VaultState;
+
+impl VaultState {
+    pub const LEN:usize = 8;
+
+    pub fn unlock_ts(state:&AccountInfo)->Result<i64, ProgramError>{
+        let data = state.try_borrow_data()?;
+
+        let unlock_ts:[u8;8] = data.get(..Self::LEN).ok_or(ProgramError::InvalidAccountData)?
+            .try_into().unwrap();
+
+        Ok(i64::from_le_bytes(unlock_ts))
+    }
+
+    // Withdrawing is allowed from the unlock time on
+    #[inline(always)]
+    pub fn check_unlocked(unlock_ts:i64, now:i64)->ProgramResult{
+        if now.lt(&unlock_ts) {
+            return Err(VaultError::VaultLocked.into());
+        }
+
+        Ok(())
+    }
+
+    // Returns whether the state account has been created, after checking its address
+    pub fn check(state:&AccountInfo, owner:&AccountInfo)->Result<(bool, u8), ProgramError>{
+        let (expected_state, bump) = 
+            find_program_address(&[b"vault_state", owner.key()], &ID);
+
+        if expected_state.ne(state.key()){
+            return Err(ProgramError::InvalidSeeds);
+        }
+
+        match state.is_owned_by(&ID) {
+            true if state.data_len().eq(&Self::LEN) => Ok((true, bump)),
+            false if state.data_is_empty() => Ok((false, bump)),
+            _ => Err(ProgramError::InvalidAccountData)
+        }
+    }
+}
+
 pub fn process_instructions(_program_id:&Pubkey, accounts:&[AccountInfo], 
         instruction_data:&[u8])->ProgramResult{
             match instruction_data.split_first(){
@@ -34,12 +91,18 @@ pub fn process_instructions(_program_id:&Pubkey, accounts:&[AccountInfo],
 
 pub struct DepositAccounts<'info>{
     pub owner:&'info AccountInfo,
-    pub vault:&'info AccountInfo
+    pub vault:&'info AccountInfo,
+    // Only required when the deposit carries an unlock time
+    pub vault_state:Option<&'info AccountInfo>
 }
 
 pub struct Deposit<'info>{
     accounts:DepositAccounts<'info>,
-    amount:u64
+    amount:u64,
+    // Lets the vault be funded after a client has already created the PDA
+    allow_funded:bool,
+    // Optional, the vault can't be withdrawn before this unix timestamp
+    unlock_ts:Option<i64>
 }
 
 impl<'info> TryFrom<(&'info[AccountInfo], &[u8])> for Deposit<'info>{
@@ -47,22 +110,42 @@ impl<'info> TryFrom<(&'info[AccountInfo], &[u8])> for Deposit<'info>{
         let accounts = value.0;
         let amount = value.1;
 
-        let [owner, vault, _] = accounts else{
+        let [owner, vault, _, remaining @ ..] = accounts else{
             return Err(ProgramError::InvalidArgument);
         };
 
         let accounts = DepositAccounts{
                                 owner, 
-                                vault
+                                vault,
+                                vault_state: remaining.first()
                             };
 
+        // The unlock time follows the mode byte, both are optional
+        let unlock_ts = match amount.len() {
+            17 => Some(i64::from_le_bytes(amount[9..17].try_into().unwrap())),
+            _ => None
+        };
+
+        // The mode byte is optional, the strict create path is the default
+        let (amount, allow_funded) = match amount.len() {
+            8 => (amount, false),
+            9 | 17 => match amount[8] {
+                0 => (&amount[..8], false),
+                1 => (&amount[..8], true),
+                _ => return Err(ProgramError::InvalidInstructionData)
+            },
+            _ => return Err(ProgramError::InvalidInstructionData)
+        };
+
         let amount_bytes:[u8;8] = amount.try_into().map_err(|_| ProgramError::InvalidInstructionData)?;
 
         let amount = u64::from_le_bytes(amount_bytes);
 
         Ok(Deposit{
             accounts,
-            amount
+            amount,
+            allow_funded,
+            unlock_ts
         })
     }
 
@@ -82,9 +165,7 @@ impl<'info> Deposit<'info>{
             return Err(ProgramError::InvalidAccountOwner);
         }
 
-        if self.accounts.vault.lamports().ne(&0){
-            return Err(ProgramError::InvalidAccountData);
-        }
+        Self::check_vault_balance(self.allow_funded, self.accounts.vault.lamports())?;
 
         if !self.accounts.vault.data_is_empty(){
             return Err(ProgramError::InvalidAccountData);
@@ -104,10 +185,59 @@ impl<'info> Deposit<'info>{
         Ok(())
     }
 
+    // A fresh vault holds nothing yet, `allow_funded` also takes one a client already
+    // created by sending it lamports. A pre-created vault is still required to be a
+    // plain system account, which `check` enforces either way
+    #[inline(always)]
+    pub fn check_vault_balance(allow_funded:bool, lamports:u64)->ProgramResult{
+        if !allow_funded && lamports.ne(&0){
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Ok(())
+    }
+
+    // Records the unlock time, a later deposit can only push it further out
+    pub fn lock(&self, unlock_ts:i64)->ProgramResult{
+        let vault_state = self.accounts.vault_state.ok_or(ProgramError::NotEnoughAccountKeys)?;
+
+        let (initialized, bump) = VaultState::check(vault_state, self.accounts.owner)?;
+
+        let unlock_ts = match initialized {
+            true => unlock_ts.max(VaultState::unlock_ts(vault_state)?),
+            false => {
+                let bump = [bump];
+
+                let seeds = [Seed::from(b"vault_state"),
+                        Seed::from(&self.accounts.owner.key()[..]),
+                        Seed::from(&bump)
+                ];
+
+                CreateAccount{
+                    from: self.accounts.owner,
+                    to: vault_state,
+                    lamports: Rent::get()?.minimum_balance(VaultState::LEN),
+                    space: VaultState::LEN as u64,
+                    owner: &ID
+                }.invoke_signed(&[Signer::from(&seeds)])?;
+
+                unlock_ts
+            }
+        };
+
+        vault_state.try_borrow_mut_data()?.copy_from_slice(&unlock_ts.to_le_bytes());
+
+        Ok(())
+    }
+
     pub fn process(&self)->ProgramResult{
 
         self.check()?;
 
+        if let Some(unlock_ts) = self.unlock_ts {
+            self.lock(unlock_ts)?;
+        }
+
         Transfer{
             from: self.accounts.owner,
             to: self.accounts.vault,
@@ -119,6 +249,8 @@ impl<'info> Deposit<'info>{
 pub struct WithdrawAccounts<'info>{
     pub owner:&'info AccountInfo,
     pub vault:&'info AccountInfo,
+    // Always required so a lock can't be skipped by leaving it out
+    pub vault_state:&'info AccountInfo,
     pub bump:[u8;1]
 }
 
@@ -129,13 +261,14 @@ pub struct Withdraw<'info>{
 impl<'info> TryFrom<&'info[AccountInfo]> for Withdraw<'info>{
     fn try_from(accounts: &'info[AccountInfo]) -> Result<Self, Self::Error> {
 
-        let [owner, vault, _] = accounts else{
+        let [owner, vault, _, vault_state] = accounts else{
             return Err(ProgramError::InvalidArgument);
         };
 
         let accounts = WithdrawAccounts{
                                 owner, 
                                 vault,
+                                vault_state,
                                 bump:[0] // Temporary, bump would be placed in later
                             };
 
@@ -177,6 +310,12 @@ impl<'info> Withdraw<'info>{
             return Err(ProgramError::InvalidAccountOwner);
         }
 
+        let (has_lock, _) = VaultState::check(self.accounts.vault_state, self.accounts.owner)?;
+
+        if has_lock {
+            VaultState::check_unlocked(VaultState::unlock_ts(self.accounts.vault_state)?, Clock::get()?.unix_timestamp)?;
+        }
+
         Ok(())
     }
 
@@ -195,9 +334,45 @@ impl<'info> Withdraw<'info>{
             from: self.accounts.vault,
             to: self.accounts.owner,
             lamports: self.accounts.vault.lamports()
-        }.invoke_signed(&[signer])
+        }.invoke_signed(&[signer])?;
+
+        // The lock is spent with the vault, its rent goes back to the owner
+        if self.accounts.vault_state.is_owned_by(&ID) {
+            *self.accounts.owner.try_borrow_mut_lamports()? += self.accounts.vault_state.lamports();
+            self.accounts.vault_state.close()?;
+        }
+
+        Ok(())
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
 
+    #[test]
+    fn a_locked_vault_is_withdrawn_from_the_unlock_time_on() {
+        let unlock_ts = 1_700_000_000;
 
+        // An early withdraw is turned away
+        assert_eq!(VaultState::check_unlocked(unlock_ts, unlock_ts - 1), Err(VaultError::VaultLocked.into()));
+
+        assert_eq!(VaultState::check_unlocked(unlock_ts, unlock_ts), Ok(()));
+        assert_eq!(VaultState::check_unlocked(unlock_ts, unlock_ts + 86_400), Ok(()));
+    }
+
+    #[test]
+    fn a_fresh_vault_is_funded_in_either_mode() {
+        assert_eq!(Deposit::check_vault_balance(false, 0), Ok(()));
+        assert_eq!(Deposit::check_vault_balance(true, 0), Ok(()));
+    }
+
+    #[test]
+    fn a_pre_created_vault_is_funded_only_when_allowed() {
+        // A client created the vault PDA by sending it the rent exempt minimum
+        let lamports = 890_880;
+
+        assert_eq!(Deposit::check_vault_balance(false, lamports), Err(ProgramError::InvalidAccountData));
+        assert_eq!(Deposit::check_vault_balance(true, lamports), Ok(()));
+    }
+}